@@ -0,0 +1,184 @@
+//! A writer for single-frame Aseprite sprites (`.ase` / `.aseprite`).
+//!
+//! Only what a flat pixel-art document needs is written: the header, one
+//! frame with a palette chunk, and a layer and raw cel per layer. All
+//! values are little-endian.
+
+use std::fs;
+
+/// An image layer with its cel cropped to `width` by `height` pixels at
+/// (`x`, `y`) on the canvas. `rgba` holds the cel row by row from the top.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub rgba: Vec<u8>,
+}
+
+/// A one-frame RGBA sprite. The first layer is drawn at the bottom.
+#[derive(Debug, Clone, Default)]
+pub struct Sprite {
+    pub width: u16,
+    pub height: u16,
+    /// Named swatches for the sprite's palette.
+    pub palette: Vec<(String, [u8; 3])>,
+    pub layers: Vec<Layer>,
+}
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const PALETTE_CHUNK: u16 = 0x2019;
+/// Frame duration in milliseconds; irrelevant for a still but required.
+const FRAME_DURATION: u16 = 100;
+
+impl Sprite {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut chunks: Vec<Vec<u8>> = Vec::new();
+        if !self.palette.is_empty() {
+            chunks.push(chunk(PALETTE_CHUNK, &self.palette_chunk()));
+        }
+        for layer in &self.layers {
+            let mut data = Vec::new();
+            put_u16(&mut data, 3); // Visible and editable.
+            put_u16(&mut data, 0); // Normal image layer.
+            put_u16(&mut data, 0); // Child level.
+            put_u16(&mut data, 0); // Default width, ignored.
+            put_u16(&mut data, 0); // Default height, ignored.
+            put_u16(&mut data, 0); // Normal blend mode.
+            data.extend([255, 0, 0, 0]); // Opacity and reserved bytes.
+            put_string(&mut data, &layer.name);
+            chunks.push(chunk(LAYER_CHUNK, &data));
+        }
+        for (index, layer) in self.layers.iter().enumerate() {
+            let mut data = Vec::new();
+            put_u16(&mut data, index as u16);
+            put_u16(&mut data, layer.x);
+            put_u16(&mut data, layer.y);
+            data.push(255); // Opacity.
+            put_u16(&mut data, 0); // Raw, uncompressed cel.
+            put_u16(&mut data, 0); // Z-index.
+            data.extend([0; 5]);
+            put_u16(&mut data, layer.width);
+            put_u16(&mut data, layer.height);
+            data.extend(&layer.rgba);
+            chunks.push(chunk(CEL_CHUNK, &data));
+        }
+
+        let body: Vec<u8> = chunks.concat();
+        let mut frame = Vec::new();
+        put_u32(&mut frame, 16 + body.len() as u32);
+        put_u16(&mut frame, FRAME_MAGIC);
+        put_u16(&mut frame, chunks.len().min(0xFFFF) as u16);
+        put_u16(&mut frame, FRAME_DURATION);
+        frame.extend([0; 2]);
+        put_u32(&mut frame, chunks.len() as u32);
+        frame.extend(body);
+
+        let mut out = Vec::new();
+        put_u32(&mut out, 128 + frame.len() as u32);
+        put_u16(&mut out, HEADER_MAGIC);
+        put_u16(&mut out, 1); // Frames.
+        put_u16(&mut out, self.width);
+        put_u16(&mut out, self.height);
+        put_u16(&mut out, 32); // Bits per pixel.
+        put_u32(&mut out, 1); // Layer opacity is valid.
+        put_u16(&mut out, FRAME_DURATION);
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 0);
+        out.push(0); // Transparent palette index, unused in RGBA.
+        out.extend([0; 3]);
+        put_u16(&mut out, self.palette.len() as u16);
+        out.extend([1, 1]); // Square pixels.
+        put_u16(&mut out, 0); // Grid position and size.
+        put_u16(&mut out, 0);
+        put_u16(&mut out, 16);
+        put_u16(&mut out, 16);
+        out.extend([0; 84]);
+        out.extend(frame);
+        out
+    }
+
+    pub fn save(&self, path: &str) {
+        fs::write(path, self.to_bytes()).expect("Unable to write Aseprite file.");
+    }
+
+    fn palette_chunk(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        put_u32(&mut data, self.palette.len() as u32);
+        put_u32(&mut data, 0);
+        put_u32(&mut data, self.palette.len() as u32 - 1);
+        data.extend([0; 8]);
+        for (name, [r, g, b]) in &self.palette {
+            put_u16(&mut data, 1); // Has a name.
+            data.extend([*r, *g, *b, 255]);
+            put_string(&mut data, name);
+        }
+        data
+    }
+}
+
+fn chunk(kind: u16, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_u32(&mut out, 6 + data.len() as u32);
+    put_u16(&mut out, kind);
+    out.extend(data);
+    out
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_string(out: &mut Vec<u8>, value: &str) {
+    put_u16(out, value.len() as u16);
+    out.extend(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_and_magic_numbers_line_up() {
+        let sprite = Sprite {
+            width: 4,
+            height: 3,
+            palette: vec![("Red".to_string(), [255, 0, 0])],
+            layers: vec![Layer {
+                name: "Red".to_string(),
+                x: 1,
+                y: 2,
+                width: 1,
+                height: 1,
+                rgba: vec![255, 0, 0, 255],
+            }],
+        };
+        let bytes = sprite.to_bytes();
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        assert_eq!(u32_at(0) as usize, bytes.len());
+        assert_eq!(u16_at(4), HEADER_MAGIC);
+        assert_eq!((u16_at(8), u16_at(10)), (4, 3));
+        assert_eq!(u32_at(128) as usize, bytes.len() - 128);
+        assert_eq!(u16_at(132), FRAME_MAGIC);
+        assert_eq!(u32_at(140), 3);
+        // Walk the chunks to check every size field.
+        let mut at = 144;
+        let mut kinds = Vec::new();
+        while at < bytes.len() {
+            kinds.push(u16_at(at + 4));
+            at += u32_at(at) as usize;
+        }
+        assert_eq!(at, bytes.len());
+        assert_eq!(kinds, [PALETTE_CHUNK, LAYER_CHUNK, CEL_CHUNK]);
+    }
+}
@@ -0,0 +1,1723 @@
+use crate::metric::{self, ColorMetric};
+use crate::palette::{ColorConfig, ColorConfigs};
+use crate::segment;
+use crate::status::{self, Failure};
+use crate::{Color, X_SIZE, Y_SIZE};
+use nannou::rand::rngs::StdRng;
+use nannou::rand::seq::SliceRandom;
+use nannou::rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The assignment of a tile `--key-color` left empty: it takes no piece and
+/// is drawn as a gap.
+pub const HOLE: usize = usize::MAX;
+
+/// Channel weights applied before measuring distance, roughly matching how
+/// bright each primary appears.
+const CHANNEL_WEIGHTS: [f32; 3] = [0.3, 0.59, 0.11];
+
+/// Converts an sRGB color into the space distances are measured in.
+pub fn working_color(r: u8, g: u8, b: u8) -> [f32; 3] {
+    [
+        r as f32 * CHANNEL_WEIGHTS[0],
+        g as f32 * CHANNEL_WEIGHTS[1],
+        b as f32 * CHANNEL_WEIGHTS[2],
+    ]
+}
+
+/// Shuffles `tiles`, by `weights` if given, and assigns them greedily,
+/// returning the palette index chosen for each tile in raster order. Tiles
+/// left out keep index 0.
+pub fn solve(
+    reference: &[Color],
+    tiles: &[usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    weights: Option<&[f32]>,
+    rng: &mut impl Rng,
+) -> Vec<usize> {
+    let order = shuffled(tiles, weights, rng);
+    assign_colors(reference, &order, color_configs, emphasis, texture, false)
+}
+
+/// `tiles` in random order. With `weights`, indexed by tile, heavier tiles
+/// tend to come first: each is sorted by a uniform draw raised to one over
+/// its weight, largest first, as in Efraimidis and Spirakis' weighted
+/// sampling, so a tile twice as heavy is twice as likely to be next.
+pub fn shuffled(tiles: &[usize], weights: Option<&[f32]>, rng: &mut impl Rng) -> Vec<usize> {
+    let mut order = tiles.to_vec();
+    match weights {
+        None => order.shuffle(rng),
+        Some(weights) => {
+            let mut keyed: Vec<(f32, usize)> = order
+                .iter()
+                .map(|&tile| (rng.gen::<f32>().powf(1.0 / weights[tile]), tile))
+                .collect();
+            keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+            order = keyed.into_iter().map(|(_, tile)| tile).collect();
+        }
+    }
+    order
+}
+
+/// How tiles are ordered for the greedy solve, from `--shuffle-strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShuffleStrategy {
+    /// A plain shuffle, or a weighted one with `--weighted-shuffle`.
+    #[default]
+    Random,
+    /// Tiles whose nearest color has the fewest pieces go first, so the
+    /// scarce colors reach the tiles that want them before they run out.
+    WeightedCount,
+}
+
+impl FromStr for ShuffleStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(ShuffleStrategy::Random),
+            "weighted-count" => Ok(ShuffleStrategy::WeightedCount),
+            _ => Err(format!(
+                "Unknown shuffle strategy '{s}'; use random or weighted-count"
+            )),
+        }
+    }
+}
+
+/// `order` sorted most constrained first for `--shuffle-strategy
+/// weighted-count`: by the count of each tile's nearest palette color,
+/// stock aside, fewest first. Ties keep their order in `order`, so they
+/// stay shuffled.
+pub fn most_constrained_first(
+    order: Vec<usize>,
+    reference: &[Color],
+    color_configs: &ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) -> Vec<usize> {
+    let nearest: Vec<u64> = order
+        .par_iter()
+        .map(|&tile| {
+            let color = &reference[tile];
+            closest_in_stock(color_configs, color, emphasis, 0, metric::for_tile(color))
+                .map_or(u64::MAX, |index| color_configs.colors[index].count)
+        })
+        .collect();
+    let mut keyed: Vec<(u64, usize)> = nearest.into_iter().zip(order).collect();
+    keyed.sort_by_key(|&(count, _)| count);
+    keyed.into_iter().map(|(_, tile)| tile).collect()
+}
+
+/// Every tile's weight for `--weighted-shuffle`: the Sobel gradient
+/// magnitude of the picture's luma around it, plus one so that tiles in
+/// flat areas still shuffle among themselves. Edges of the grid repeat
+/// their outermost tiles.
+pub fn edge_weights(reference: &[Color]) -> Vec<f32> {
+    let luma = |x: i64, y: i64| {
+        let x = x.clamp(0, X_SIZE as i64 - 1) as u64;
+        let y = y.clamp(0, Y_SIZE as i64 - 1) as u64;
+        let color = &reference[(y * X_SIZE + x) as usize];
+        0.299 * color.r as f32 + 0.587 * color.g as f32 + 0.114 * color.b as f32
+    };
+    (0..Y_SIZE as i64)
+        .flat_map(|y| (0..X_SIZE as i64).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let gx = luma(x + 1, y - 1) + 2.0 * luma(x + 1, y) + luma(x + 1, y + 1)
+                - luma(x - 1, y - 1)
+                - 2.0 * luma(x - 1, y)
+                - luma(x - 1, y + 1);
+            let gy = luma(x - 1, y + 1) + 2.0 * luma(x, y + 1) + luma(x + 1, y + 1)
+                - luma(x - 1, y - 1)
+                - 2.0 * luma(x, y - 1)
+                - luma(x + 1, y - 1);
+            (gx * gx + gy * gy).sqrt() + 1.0
+        })
+        .collect()
+}
+
+/// Samples among the nearest few colors instead of always taking the single
+/// closest, for a less uniform look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Texture {
+    /// How many of the nearest colors with inventory left are candidates.
+    pub k: usize,
+    /// Temperature of the draw. At 0 the nearest color always wins; higher
+    /// values pick the farther candidates more often.
+    pub strength: f32,
+    /// Seeds each tile's draw so runs can be reproduced.
+    pub seed: u64,
+}
+
+/// One greedy run from `restarts`.
+#[derive(Debug, Clone)]
+pub struct Restart {
+    pub assignment: Vec<usize>,
+    /// Inventory left after this run.
+    pub palette: ColorConfigs,
+    /// Sum of every tile's distance to its assigned color.
+    pub total_error: f32,
+}
+
+/// Runs the greedy solve once per seed, each with its own shuffle and a fresh
+/// copy of the palette, in parallel. Results come back in seed order.
+pub fn restarts(
+    reference: &[Color],
+    tiles: &[usize],
+    color_configs: &ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    weights: Option<&[f32]>,
+    seeds: &[u64],
+) -> Vec<Restart> {
+    seeds
+        .par_iter()
+        .map(|&seed| {
+            let mut palette = color_configs.clone();
+            let assignment = solve(
+                reference,
+                tiles,
+                &mut palette,
+                emphasis,
+                texture,
+                weights,
+                &mut StdRng::seed_from_u64(seed),
+            );
+            let stats = statistics(reference, &assignment, &palette, emphasis);
+            Restart {
+                total_error: stats.average_error * reference.len() as f32,
+                assignment,
+                palette,
+            }
+        })
+        .collect()
+}
+
+/// Assigns every tile, visiting them in `order`, its closest palette color
+/// with inventory left. The result is indexed by raster position. With
+/// `no_overlap`, for `--no-overlap`, a tile passes over the colors of its
+/// neighbors assigned before it; `order` should then be raster order, so
+/// every tile sees the ones to its left and below.
+pub fn assign_colors(
+    reference: &[Color],
+    order: &[usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    no_overlap: bool,
+) -> Vec<usize> {
+    let mut assignment = vec![0; reference.len()];
+    let mut placed = no_overlap.then(|| vec![None; reference.len()]);
+    assign_into(
+        reference,
+        order,
+        &mut assignment,
+        color_configs,
+        emphasis,
+        texture,
+        placed.as_deref_mut(),
+    );
+    assignment
+}
+
+/// Like `assign_colors`, but hands each grid row to `row_done` as soon as
+/// every tile in it has been assigned.
+pub fn assign_colors_by_row(
+    reference: &[Color],
+    order: &[usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    no_overlap: bool,
+    mut row_done: impl FnMut(Vec<Color>),
+) -> Vec<usize> {
+    let mut assignment = vec![0; reference.len()];
+    let mut placed = no_overlap.then(|| vec![None; reference.len()]);
+    let mut remaining_in_row = vec![X_SIZE; Y_SIZE as usize];
+    for &tile in order {
+        assign_into(
+            reference,
+            &[tile],
+            &mut assignment,
+            color_configs,
+            emphasis,
+            texture,
+            placed.as_deref_mut(),
+        );
+        let y = reference[tile].y;
+        remaining_in_row[y as usize] -= 1;
+        if remaining_in_row[y as usize] == 0 {
+            let row = (y * X_SIZE) as usize..((y + 1) * X_SIZE) as usize;
+            row_done(assigned_pixels(
+                &reference[row.clone()],
+                &assignment[row],
+                color_configs,
+            ));
+        }
+    }
+    assignment
+}
+
+/// How well an assignment reproduces the source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    /// Mean distance between each tile's source and assigned color.
+    pub average_error: f32,
+    /// Tiles that did not get their first-choice color because it ran out.
+    pub fallbacks: usize,
+}
+
+pub fn statistics(
+    reference: &[Color],
+    assignment: &[usize],
+    color_configs: &ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) -> Stats {
+    let mut total_error = 0.0;
+    let mut fallbacks = 0;
+    let mut tiles = 0;
+    for (original, &index) in reference.iter().zip(assignment) {
+        if index == HOLE {
+            continue;
+        }
+        tiles += 1;
+        let target = working_color(original.r, original.g, original.b);
+        total_error += squared_distance(&color_configs.colors[index].working, &target).sqrt();
+        let first_choice = color_configs
+            .colors
+            .iter()
+            .enumerate()
+            .map(|(i, config)| (i, distance(config, original, emphasis)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+        if first_choice != Some(index) {
+            fallbacks += 1;
+        }
+    }
+    Stats {
+        average_error: total_error / tiles.max(1) as f32,
+        fallbacks,
+    }
+}
+
+/// Logs every tile's color, the color it got, the nearest palette color
+/// whether or not it had pieces left, and the distance to the one it got.
+pub fn log_tiles(
+    reference: &[Color],
+    assignment: &[usize],
+    color_configs: &ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) {
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
+    for (tile, (original, &index)) in reference.iter().zip(assignment).enumerate() {
+        if index == HOLE {
+            continue;
+        }
+        let nearest = color_configs
+            .colors
+            .iter()
+            .min_by(|a, b| {
+                distance(a, original, emphasis).total_cmp(&distance(b, original, emphasis))
+            })
+            .map_or("none", |config| config.name.as_str());
+        let assigned = &color_configs.colors[index];
+        let target = working_color(original.r, original.g, original.b);
+        log::debug!(
+            "Tile {tile} at ({}, {}): rgb({}, {}, {}) got {}, nearest {nearest}, distance {:.1}.",
+            original.x,
+            original.y,
+            original.r,
+            original.g,
+            original.b,
+            assigned.name,
+            squared_distance(&assigned.working, &target).sqrt()
+        );
+    }
+}
+
+/// Number of tiles assigned to each palette color.
+pub fn usage(assignment: &[usize], color_count: usize) -> Vec<u64> {
+    let mut used = vec![0u64; color_count];
+    for &index in assignment.iter().filter(|&&index| index != HOLE) {
+        used[index] += 1;
+    }
+    used
+}
+
+/// How near, in tiles, two tiles are for `affinity` to count them.
+pub const AFFINITY_RADIUS: u64 = 3;
+
+/// For each pair of palette colors, how many pairs of tiles in them lie
+/// within `AFFINITY_RADIUS` tiles of each other, counted once per pair of
+/// tiles. Only entries `[i][j]` with `i <= j` are filled; the rest would
+/// repeat them.
+pub fn affinity(assignment: &[usize], color_count: usize) -> Vec<Vec<u64>> {
+    let radius = AFFINITY_RADIUS as i64;
+    // Offsets to the neighbours ahead of a tile in raster order, so that
+    // each pair of tiles is seen from one end only.
+    let ahead: Vec<(i64, i64)> = (0..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| (dy > 0 || dx > 0) && dx * dx + dy * dy <= radius * radius)
+        .collect();
+    let mut counts = vec![vec![0u64; color_count]; color_count];
+    for (tile, &color) in assignment.iter().enumerate() {
+        if color == HOLE {
+            continue;
+        }
+        let (x, y) = (tile as i64 % X_SIZE as i64, tile as i64 / X_SIZE as i64);
+        for &(dx, dy) in &ahead {
+            let (nx, ny) = (x + dx, y + dy);
+            if !(0..X_SIZE as i64).contains(&nx) || ny >= Y_SIZE as i64 {
+                continue;
+            }
+            let other = assignment[(ny * X_SIZE as i64 + nx) as usize];
+            if other != HOLE {
+                counts[color.min(other)][color.max(other)] += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Empties the keyed-out `holes` of a solve, which gave them no pieces.
+pub fn punch_holes(assignment: &mut [usize], holes: &[usize]) {
+    for &tile in holes {
+        assignment[tile] = HOLE;
+    }
+}
+
+/// Cost of the pieces used, or `None` when no color has a price.
+pub fn total_cost(assignment: &[usize], color_configs: &ColorConfigs) -> Option<f64> {
+    if color_configs
+        .colors
+        .iter()
+        .all(|config| config.price.is_none())
+    {
+        return None;
+    }
+    let used = usage(assignment, color_configs.colors.len());
+    Some(
+        color_configs
+            .colors
+            .iter()
+            .zip(used)
+            .map(|(config, used)| config.price.unwrap_or(0.0) * used as f64)
+            .sum(),
+    )
+}
+
+/// Builds the displayed tiles from an assignment. Holes keep their source
+/// color.
+pub fn assigned_pixels(
+    reference: &[Color],
+    assignment: &[usize],
+    color_configs: &ColorConfigs,
+) -> Vec<Color> {
+    reference
+        .iter()
+        .zip(assignment)
+        .map(|(original, &index)| {
+            if index == HOLE {
+                return original.clone();
+            }
+            let config = &color_configs.colors[index];
+            Color {
+                r: config.r,
+                g: config.g,
+                b: config.b,
+                x: original.x,
+                y: original.y,
+            }
+        })
+        .collect()
+}
+
+/// Applies a palette whose counts changed without re-solving from scratch.
+///
+/// `color_configs` holds the remaining inventory of the current assignment and
+/// `updated` the newly declared counts. Colors reduced below their usage give
+/// up their worst-fitting tiles, which are reassigned; with `upgrade` set, the
+/// worst tiles overall then move to any closer color that has inventory left.
+/// Returns the number of tiles that changed, or `None` when colors were added,
+/// removed, or recolored and a full solve is needed instead.
+pub fn resolve_incremental(
+    reference: &[Color],
+    assignment: &mut [usize],
+    color_configs: &mut ColorConfigs,
+    updated: ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    upgrade: bool,
+) -> Option<usize> {
+    let same_structure = color_configs.colors.len() == updated.colors.len()
+        && color_configs
+            .colors
+            .iter()
+            .zip(&updated.colors)
+            .all(|(a, b)| a.name == b.name && (a.r, a.g, a.b) == (b.r, b.g, b.b));
+    if !same_structure {
+        return None;
+    }
+
+    let before = assignment.to_vec();
+    let used = usage(assignment, updated.colors.len());
+    *color_configs = updated;
+
+    let mut freed: Vec<usize> = Vec::new();
+    for (index, config) in color_configs.colors.iter_mut().enumerate() {
+        if used[index] <= config.count {
+            config.count -= used[index];
+            continue;
+        }
+        let excess = (used[index] - config.count) as usize;
+        config.count = 0;
+        let mut by_error: Vec<(usize, f32)> = (0..assignment.len())
+            .filter(|&tile| assignment[tile] == index)
+            .map(|tile| (tile, distance(config, &reference[tile], emphasis)))
+            .collect();
+        by_error.sort_by(|a, b| b.1.total_cmp(&a.1));
+        freed.extend(by_error.into_iter().take(excess).map(|(tile, _)| tile));
+    }
+    freed.sort_by(|&a, &b| {
+        let error = |tile: usize| {
+            distance(
+                &color_configs.colors[assignment[tile]],
+                &reference[tile],
+                emphasis,
+            )
+        };
+        error(b).total_cmp(&error(a))
+    });
+    assign_into(
+        reference,
+        &freed,
+        assignment,
+        color_configs,
+        emphasis,
+        None,
+        None,
+    );
+
+    if upgrade {
+        let mut tiles: Vec<(usize, f32)> = (0..assignment.len())
+            .filter(|&tile| assignment[tile] != HOLE)
+            .map(|tile| {
+                let config = &color_configs.colors[assignment[tile]];
+                (tile, distance(config, &reference[tile], emphasis))
+            })
+            .collect();
+        tiles.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (tile, current_error) in tiles {
+            if color_configs.colors.iter().all(|config| config.count == 0) {
+                break;
+            }
+            let best = calculate_closest_color(
+                color_configs,
+                &reference[tile],
+                emphasis,
+                metric::for_tile(&reference[tile]),
+            );
+            if distance(&color_configs.colors[best], &reference[tile], emphasis) < current_error {
+                color_configs.colors[assignment[tile]].count += 1;
+                color_configs.colors[best].decrement();
+                assignment[tile] = best;
+            }
+        }
+    }
+
+    Some(
+        before
+            .iter()
+            .zip(assignment.iter())
+            .filter(|(a, b)| a != b)
+            .count(),
+    )
+}
+
+/// Smooths out speckle: each pass moves every tile whose neighbors all
+/// differ from it to the color most of them share, breaking ties by distance
+/// to the source. Tiles stay put when no tied color has inventory left.
+/// Neighbors are read as they were at the start of the pass. Returns how many
+/// tiles each pass reassigned.
+pub fn merge_small_regions(
+    reference: &[Color],
+    assignment: &mut [usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    passes: u32,
+) -> Vec<usize> {
+    let mut reassigned = Vec::new();
+    for _ in 0..passes {
+        let before = assignment.to_vec();
+        let mut changed = 0;
+        for (tile, original) in reference.iter().enumerate() {
+            if before[tile] == HOLE {
+                continue;
+            }
+            let (x, y) = (original.x, original.y);
+            let neighbors: Vec<usize> = [
+                (x > 0).then(|| tile - 1),
+                (x + 1 < X_SIZE).then(|| tile + 1),
+                (y > 0).then(|| tile - X_SIZE as usize),
+                (y + 1 < Y_SIZE).then(|| tile + X_SIZE as usize),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|neighbor| before[neighbor])
+            .filter(|&color| color != HOLE)
+            .collect();
+            if neighbors.iter().any(|&color| color == before[tile]) {
+                continue;
+            }
+            let mut votes: HashMap<usize, usize> = HashMap::new();
+            for &color in &neighbors {
+                *votes.entry(color).or_default() += 1;
+            }
+            let most = votes.values().copied().max().unwrap_or(0);
+            let best = votes
+                .iter()
+                .filter(|&(&color, &count)| count == most && color_configs.colors[color].count > 0)
+                .map(|(&color, _)| color)
+                .min_by(|&a, &b| {
+                    let error =
+                        |color: usize| distance(&color_configs.colors[color], original, emphasis);
+                    error(a).total_cmp(&error(b)).then(a.cmp(&b))
+                });
+            if let Some(best) = best {
+                color_configs.colors[assignment[tile]].count += 1;
+                color_configs.colors[best].decrement();
+                assignment[tile] = best;
+                changed += 1;
+            }
+        }
+        reassigned.push(changed);
+    }
+    reassigned
+}
+
+/// Recolors every connected same-color region of fewer than `min_size`
+/// tiles, smallest first, to the color of the touching region it shares the
+/// longest border with, breaking ties by distance to the source. A region
+/// moves only when that color has a piece left for each of its tiles;
+/// otherwise the next longest border is tried, and failing all it stays.
+/// Returns how many regions were merged and how many tiles they held.
+pub fn merge_tiny_regions(
+    reference: &[Color],
+    assignment: &mut [usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    min_size: usize,
+) -> (usize, usize) {
+    let (mut merged, mut moved) = (0, 0);
+    // Regions no neighbour could take, by their first tile.
+    let mut stuck = Vec::new();
+    loop {
+        let regions = segment::connected(assignment);
+        let small = regions
+            .iter()
+            .filter(|tiles| {
+                tiles.len() < min_size && assignment[tiles[0]] != HOLE && !stuck.contains(&tiles[0])
+            })
+            .min_by_key(|tiles| (tiles.len(), tiles[0]));
+        let small = match small {
+            Some(small) => small,
+            None => break,
+        };
+        let mut labels = vec![0; assignment.len()];
+        for (region, tiles) in regions.iter().enumerate() {
+            for &tile in tiles {
+                labels[tile] = region;
+            }
+        }
+        let own = labels[small[0]];
+        let mut borders: HashMap<usize, usize> = HashMap::new();
+        for &tile in small {
+            for neighbour in segment::neighbours(tile) {
+                if labels[neighbour] != own && assignment[neighbour] != HOLE {
+                    *borders.entry(labels[neighbour]).or_default() += 1;
+                }
+            }
+        }
+        let error = |color: usize| -> f32 {
+            small
+                .iter()
+                .map(|&tile| distance(&color_configs.colors[color], &reference[tile], emphasis))
+                .sum()
+        };
+        let best = borders
+            .iter()
+            .map(|(&region, &border)| (border, assignment[regions[region][0]]))
+            .filter(|&(_, color)| color_configs.colors[color].count >= small.len() as u64)
+            .min_by(|&(a_border, a), &(b_border, b)| {
+                b_border
+                    .cmp(&a_border)
+                    .then(error(a).total_cmp(&error(b)))
+                    .then(a.cmp(&b))
+            });
+        let color = match best {
+            Some((_, color)) => color,
+            None => {
+                stuck.push(small[0]);
+                continue;
+            }
+        };
+        for &tile in small {
+            color_configs.colors[assignment[tile]].count += 1;
+            color_configs.colors[color].decrement();
+            assignment[tile] = color;
+        }
+        merged += 1;
+        moved += small.len();
+    }
+    (merged, moved)
+}
+
+/// Moves every tile of color `from` that `movable` allows to color `to`,
+/// returning their pieces to `from`, and reports how many moved. When `to`
+/// has too few pieces left nothing moves and the shortfall is the error,
+/// unless `partial` is set: then as many move as there are pieces, the
+/// tiles `from` fits worst first.
+pub fn replace_color(
+    reference: &[Color],
+    assignment: &mut [usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    (from, to): (usize, usize),
+    movable: impl Fn(usize) -> bool,
+    partial: bool,
+) -> Result<usize, u64> {
+    let mut tiles: Vec<(usize, f32)> = (0..assignment.len())
+        .filter(|&tile| assignment[tile] == from && from != to && movable(tile))
+        .map(|tile| {
+            let error = distance(&color_configs.colors[from], &reference[tile], emphasis);
+            (tile, error)
+        })
+        .collect();
+    let available = color_configs.colors[to].count;
+    if tiles.len() as u64 > available {
+        if !partial {
+            return Err(tiles.len() as u64 - available);
+        }
+        tiles.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        tiles.truncate(available as usize);
+    }
+    for &(tile, _) in &tiles {
+        assignment[tile] = to;
+    }
+    color_configs.colors[from].count += tiles.len() as u64;
+    color_configs.colors[to].count -= tiles.len() as u64;
+    Ok(tiles.len())
+}
+
+/// Assigns `tiles` in order. `placed`, when given, holds the palette color
+/// of every tile assigned so far by raster position, and keeps tiles apart
+/// from those colors.
+fn assign_into(
+    reference: &[Color],
+    tiles: &[usize],
+    assignment: &mut [usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    mut placed: Option<&mut [Option<Color>]>,
+) {
+    for &tile in tiles {
+        let original_color = &reference[tile];
+        let nearest_color = match (&placed, texture) {
+            (Some(placed), _) => closest_apart(color_configs, original_color, emphasis, placed),
+            (None, Some(texture)) if texture.strength > 0.0 && texture.k > 1 => {
+                textured_choice(color_configs, tile, original_color, emphasis, texture)
+            }
+            _ => calculate_closest_color(
+                color_configs,
+                original_color,
+                emphasis,
+                metric::for_tile(original_color),
+            ),
+        };
+        let config = color_configs
+            .colors
+            .get_mut(nearest_color)
+            .expect("Color configs should have value within index range");
+        config.decrement();
+        if let Some(placed) = placed.as_deref_mut() {
+            placed[tile] = Some(Color {
+                r: config.r,
+                g: config.g,
+                b: config.b,
+                x: original_color.x,
+                y: original_color.y,
+            });
+        }
+        assignment[tile] = nearest_color;
+    }
+}
+
+/// The 4-connected neighbors of tile `(x, y)` on a grid `w` tiles wide, as
+/// far as they are on the grid: left, right, below, and above. Each is
+/// `None` until it is assigned.
+pub fn get_neighbors(pixels: &[Option<Color>], x: u64, y: u64, w: u64) -> Vec<Option<&Color>> {
+    let h = pixels.len() as u64 / w;
+    let mut neighbors = Vec::with_capacity(4);
+    let mut push = |x: u64, y: u64| neighbors.push(pixels[(y * w + x) as usize].as_ref());
+    if x > 0 {
+        push(x - 1, y);
+    }
+    if x + 1 < w {
+        push(x + 1, y);
+    }
+    if y > 0 {
+        push(x, y - 1);
+    }
+    if y + 1 < h {
+        push(x, y + 1);
+    }
+    neighbors
+}
+
+/// Like `calculate_closest_color`, but moving down the list of colors with
+/// inventory, nearest first, past any an assigned neighbor in `placed`
+/// already has. When the neighbors have every such color between them, the
+/// closest is used after all.
+fn closest_apart(
+    color_configs: &ColorConfigs,
+    original_color: &Color,
+    emphasis: &HashMap<String, f32>,
+    placed: &[Option<Color>],
+) -> usize {
+    let neighbors: Vec<&Color> = get_neighbors(placed, original_color.x, original_color.y, X_SIZE)
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut candidates: Vec<(usize, f32)> = color_configs
+        .colors
+        .iter()
+        .enumerate()
+        .filter(|(_, config)| config.count > 0)
+        .map(|(index, config)| (index, distance(config, original_color, emphasis)))
+        .collect();
+    // Stable, so equally distant colors keep palette order.
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let apart = candidates.iter().find(|&&(index, _)| {
+        let config = &color_configs.colors[index];
+        !neighbors
+            .iter()
+            .any(|neighbor| (neighbor.r, neighbor.g, neighbor.b) == (config.r, config.g, config.b))
+    });
+    match apart.or(candidates.first()) {
+        Some(&(index, _)) => index,
+        None => {
+            status::classify(Failure::Inventory, Value::Null);
+            panic!("Invalid configuration of colors.  Not enough colors present.")
+        }
+    }
+}
+
+/// Index of the closest palette color by `metric` that still has
+/// inventory. The palette side comes precomputed from
+/// `ColorConfigs::prepare`, so only the pixel is converted here.
+pub fn calculate_closest_color(
+    color_configs: &ColorConfigs,
+    original_color: &Color,
+    emphasis: &HashMap<String, f32>,
+    metric: ColorMetric,
+) -> usize {
+    closest_in_stock(color_configs, original_color, emphasis, 1, metric).unwrap_or_else(|| {
+        status::classify(Failure::Inventory, Value::Null);
+        panic!("Invalid configuration of colors.  Not enough colors present.")
+    })
+}
+
+/// How many of the tiles of `reference` are closest to each palette color,
+/// counts aside.
+pub fn nearest_counts(
+    reference: &[Color],
+    color_configs: &ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) -> Vec<u64> {
+    let mut counts = vec![0; color_configs.colors.len()];
+    for color in reference {
+        if let Some(index) =
+            closest_in_stock(color_configs, color, emphasis, 0, metric::for_tile(color))
+        {
+            counts[index] += 1;
+        }
+    }
+    counts
+}
+
+/// Index of the closest palette color by `metric` with at least `needed`
+/// pieces left.
+pub fn closest_in_stock(
+    color_configs: &ColorConfigs,
+    original_color: &Color,
+    emphasis: &HashMap<String, f32>,
+    needed: u64,
+    metric: ColorMetric,
+) -> Option<usize> {
+    let target = measured(original_color, metric);
+    let mut closest_dist: f32 = f32::MAX;
+    let mut closest_index = None;
+    for (index, color_config) in color_configs.colors.iter().enumerate() {
+        if color_config.count < needed {
+            continue;
+        }
+        let dist = emphasized(
+            color_config,
+            squared_distance(palette_side(color_config, metric), &target),
+            emphasis,
+        );
+        if dist < closest_dist {
+            closest_dist = dist;
+            closest_index = Some(index);
+        }
+    }
+    closest_index
+}
+
+/// Index of the palette color with a piece left for each of `tiles` whose
+/// summed distance to them is smallest.
+pub fn closest_for_tiles(
+    color_configs: &ColorConfigs,
+    reference: &[Color],
+    tiles: &[usize],
+    emphasis: &HashMap<String, f32>,
+) -> Option<usize> {
+    color_configs
+        .colors
+        .iter()
+        .enumerate()
+        .filter(|(_, config)| config.count >= tiles.len() as u64)
+        .map(|(index, config)| {
+            let total: f32 = tiles
+                .iter()
+                .map(|&tile| distance(config, &reference[tile], emphasis))
+                .sum();
+            (index, total)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// The `k` palette colors nearest to a pixel as the matcher sees them,
+/// nearest first, with their distances. Inventory is not considered.
+pub fn candidates(
+    color_configs: &ColorConfigs,
+    original_color: &Color,
+    emphasis: &HashMap<String, f32>,
+    k: usize,
+) -> Vec<(usize, f32)> {
+    let mut candidates: Vec<(usize, f32)> = color_configs
+        .colors
+        .iter()
+        .enumerate()
+        .map(|(index, config)| (index, distance(config, original_color, emphasis).sqrt()))
+        .collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    candidates.truncate(k);
+    candidates
+}
+
+/// Draws one of the `texture.k` nearest colors with inventory, weighting each
+/// by its inverse distance raised to `1 / strength`. An exact match always
+/// wins.
+fn textured_choice(
+    color_configs: &ColorConfigs,
+    tile: usize,
+    original_color: &Color,
+    emphasis: &HashMap<String, f32>,
+    texture: Texture,
+) -> usize {
+    let mut candidates: Vec<(usize, f32)> = color_configs
+        .colors
+        .iter()
+        .enumerate()
+        .filter(|(_, config)| config.count > 0)
+        .map(|(index, config)| (index, distance(config, original_color, emphasis).sqrt()))
+        .collect();
+    if candidates.is_empty() {
+        status::classify(Failure::Inventory, Value::Null);
+        panic!("Invalid configuration of colors.  Not enough colors present.")
+    }
+    // Stable, so equally distant colors keep palette order as in the
+    // deterministic matcher.
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    candidates.truncate(texture.k);
+    let nearest = candidates[0].1;
+    if nearest == 0.0 {
+        return candidates[0].0;
+    }
+    let weights: Vec<f32> = candidates
+        .iter()
+        .map(|&(_, dist)| (nearest / dist).powf(1.0 / texture.strength))
+        .collect();
+    // Each tile draws from its own stream so the result does not depend on
+    // how many draws came before it.
+    let mut rng =
+        StdRng::seed_from_u64(texture.seed ^ (tile as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let mut pick = rng.gen::<f32>() * weights.iter().sum::<f32>();
+    for (&(index, _), weight) in candidates.iter().zip(&weights) {
+        if pick < *weight {
+            return index;
+        }
+        pick -= weight;
+    }
+    candidates[candidates.len() - 1].0
+}
+
+/// Distance between a palette color and a pixel as the matcher sees it,
+/// by the metric of the pixel's region.
+fn distance(config: &ColorConfig, original: &Color, emphasis: &HashMap<String, f32>) -> f32 {
+    let metric = metric::for_tile(original);
+    let target = measured(original, metric);
+    emphasized(
+        config,
+        squared_distance(palette_side(config, metric), &target),
+        emphasis,
+    )
+}
+
+/// A pixel in the space `metric` measures distances in.
+fn measured(color: &Color, metric: ColorMetric) -> [f32; 3] {
+    match metric {
+        ColorMetric::Rgb => working_color(color.r, color.g, color.b),
+        ColorMetric::Lab => metric::lab(color.r, color.g, color.b),
+    }
+}
+
+fn palette_side(config: &ColorConfig, metric: ColorMetric) -> &[f32; 3] {
+    match metric {
+        ColorMetric::Rgb => &config.working,
+        ColorMetric::Lab => &config.lab,
+    }
+}
+
+fn emphasized(config: &ColorConfig, dist: f32, emphasis: &HashMap<String, f32>) -> f32 {
+    match emphasis.get(&config.name) {
+        Some(factor) => dist * factor,
+        None => dist,
+    }
+}
+
+fn squared_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    let r_dist = a[0] - b[0];
+    let g_dist = a[1] - b[1];
+    let b_dist = a[2] - b[2];
+    r_dist * r_dist + g_dist * g_dist + b_dist * b_dist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// The per-pixel conversion the matcher used before palettes were
+    /// precomputed.
+    fn naive_closest(color_configs: &ColorConfigs, original: &Color) -> usize {
+        let mut closest = (f32::MAX, usize::MAX);
+        for (index, config) in color_configs.colors.iter().enumerate() {
+            let r = (config.r as f32 - original.r as f32) * 0.3;
+            let g = (config.g as f32 - original.g as f32) * 0.59;
+            let b = (config.b as f32 - original.b as f32) * 0.11;
+            let dist = r * r + g * g + b * b;
+            if dist < closest.0 {
+                closest = (dist, index);
+            }
+        }
+        closest.1
+    }
+
+    fn palette(colors: &[(&str, u8, u8, u8, u64)]) -> ColorConfigs {
+        let mut color_configs = ColorConfigs {
+            colors: colors
+                .iter()
+                .map(|&(name, r, g, b, count)| ColorConfig::new(name.to_string(), r, g, b, count))
+                .collect(),
+        };
+        color_configs.prepare();
+        color_configs
+    }
+
+    fn pixel(r: u8, g: u8, b: u8) -> Color {
+        Color {
+            r,
+            g,
+            b,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    #[test]
+    fn single_color_palette_returns_first_index() {
+        let palette = palette(&[("Gray", 128, 128, 128, 1)]);
+        assert_eq!(
+            calculate_closest_color(
+                &palette,
+                &pixel(10, 200, 30),
+                &HashMap::new(),
+                ColorMetric::Rgb
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn exact_match_preferred_over_approximate() {
+        let palette = palette(&[("Near", 101, 99, 100, 5), ("Exact", 100, 100, 100, 5)]);
+        assert_eq!(
+            calculate_closest_color(
+                &palette,
+                &pixel(100, 100, 100),
+                &HashMap::new(),
+                ColorMetric::Rgb
+            ),
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough colors present")]
+    fn all_counts_zero_panics() {
+        let palette = palette(&[("Black", 0, 0, 0, 0), ("White", 255, 255, 255, 0)]);
+        calculate_closest_color(&palette, &pixel(0, 0, 0), &HashMap::new(), ColorMetric::Rgb);
+    }
+
+    #[test]
+    fn channel_weights_are_applied() {
+        let working = working_color(100, 100, 100);
+        for (channel, expected) in working.iter().zip([30.0, 59.0, 11.0]) {
+            assert!((channel - expected).abs() < 1e-4);
+        }
+        // Each candidate is 30 away on a single channel, so only the weights
+        // separate them: blue differences matter least, green the most.
+        let palette = palette(&[
+            ("Red shift", 130, 100, 100, 1),
+            ("Green shift", 100, 130, 100, 1),
+            ("Blue shift", 100, 100, 130, 1),
+        ]);
+        assert_eq!(
+            calculate_closest_color(
+                &palette,
+                &pixel(100, 100, 100),
+                &HashMap::new(),
+                ColorMetric::Rgb
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn exhausted_color_is_skipped_after_decrement() {
+        let mut palette = palette(&[("Exact", 50, 50, 50, 1), ("Close", 60, 60, 60, 1)]);
+        let target = pixel(50, 50, 50);
+        let first = calculate_closest_color(&palette, &target, &HashMap::new(), ColorMetric::Rgb);
+        assert_eq!(first, 0);
+        palette.colors[first].decrement();
+        assert_eq!(
+            calculate_closest_color(&palette, &target, &HashMap::new(), ColorMetric::Rgb),
+            1
+        );
+    }
+
+    #[test]
+    fn tie_selects_lower_index() {
+        let palette = palette(&[("First", 20, 40, 60, 1), ("Second", 20, 40, 60, 1)]);
+        assert_eq!(
+            calculate_closest_color(
+                &palette,
+                &pixel(20, 40, 60),
+                &HashMap::new(),
+                ColorMetric::Rgb
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn black_pixel_picks_black_over_white() {
+        let palette = palette(&[("White", 255, 255, 255, 1), ("Black", 0, 0, 0, 1)]);
+        assert_eq!(
+            calculate_closest_color(&palette, &pixel(0, 0, 0), &HashMap::new(), ColorMetric::Rgb),
+            1
+        );
+    }
+
+    #[test]
+    fn dark_red_pixel_against_green_and_blue() {
+        // This case was written down as expecting green, but under
+        // `CHANNEL_WEIGHTS` green carries the largest weight, so a full green
+        // channel costs far more than a full blue one: the red difference is
+        // the same for both, and (0.59 * 255)^2 = 22,633 for green against
+        // (0.11 * 255)^2 = 787 for blue. Blue is the closer color.
+        let palette = palette(&[("Green", 0, 255, 0, 1), ("Blue", 0, 0, 255, 1)]);
+        assert_eq!(
+            calculate_closest_color(
+                &palette,
+                &pixel(128, 0, 0),
+                &HashMap::new(),
+                ColorMetric::Rgb
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn emphasis_factor_scales_distance() {
+        let palette = palette(&[("Near", 100, 100, 100, 1), ("Far", 110, 110, 110, 1)]);
+        let emphasis = HashMap::from([("Far".to_string(), 0.01)]);
+        assert_eq!(
+            calculate_closest_color(&palette, &pixel(100, 100, 100), &emphasis, ColorMetric::Rgb),
+            0
+        );
+        assert_eq!(
+            calculate_closest_color(&palette, &pixel(102, 102, 102), &emphasis, ColorMetric::Rgb),
+            1
+        );
+    }
+
+    #[test]
+    fn incremental_resolve_frees_worst_tiles_of_reduced_color() {
+        let reference = vec![pixel(0, 0, 0), pixel(10, 10, 10), pixel(40, 40, 40)];
+        let declared = palette(&[("Black", 0, 0, 0, 3), ("Gray", 50, 50, 50, 3)]);
+        let mut remaining = declared.clone();
+        let mut assignment = assign_colors(
+            &reference,
+            &[0, 1, 2],
+            &mut remaining,
+            &HashMap::new(),
+            None,
+            false,
+        );
+        assert_eq!(assignment, vec![0, 0, 1]);
+
+        let reduced = palette(&[("Black", 0, 0, 0, 1), ("Gray", 50, 50, 50, 3)]);
+        let changed = resolve_incremental(
+            &reference,
+            &mut assignment,
+            &mut remaining,
+            reduced,
+            &HashMap::new(),
+            false,
+        );
+        assert_eq!(changed, Some(1));
+        assert_eq!(assignment, vec![0, 1, 1]);
+        assert_eq!(remaining.colors[0].count, 0);
+        assert_eq!(remaining.colors[1].count, 1);
+    }
+
+    #[test]
+    fn incremental_resolve_upgrades_into_new_inventory() {
+        let reference = vec![pixel(0, 0, 0), pixel(5, 5, 5)];
+        let mut remaining = palette(&[("Black", 0, 0, 0, 1), ("Gray", 50, 50, 50, 1)]);
+        let mut assignment = assign_colors(
+            &reference,
+            &[0, 1],
+            &mut remaining,
+            &HashMap::new(),
+            None,
+            false,
+        );
+        assert_eq!(assignment, vec![0, 1]);
+
+        let increased = palette(&[("Black", 0, 0, 0, 2), ("Gray", 50, 50, 50, 1)]);
+        let changed = resolve_incremental(
+            &reference,
+            &mut assignment,
+            &mut remaining,
+            increased,
+            &HashMap::new(),
+            true,
+        );
+        assert_eq!(changed, Some(1));
+        assert_eq!(assignment, vec![0, 0]);
+    }
+
+    #[test]
+    fn incremental_resolve_rejects_structural_changes() {
+        let reference = vec![pixel(0, 0, 0)];
+        let mut remaining = palette(&[("Black", 0, 0, 0, 1)]);
+        let mut assignment = assign_colors(
+            &reference,
+            &[0],
+            &mut remaining,
+            &HashMap::new(),
+            None,
+            false,
+        );
+        let recolored = palette(&[("Black", 5, 5, 5, 1)]);
+        assert_eq!(
+            resolve_incremental(
+                &reference,
+                &mut assignment,
+                &mut remaining,
+                recolored,
+                &HashMap::new(),
+                true,
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn precomputed_palette_matches_naive_distance() {
+        let palette = ColorConfigs::load("sample/colors.json", false);
+        let emphasis = HashMap::new();
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let pixel = Color {
+                        r,
+                        g,
+                        b,
+                        x: 0,
+                        y: 0,
+                    };
+                    assert_eq!(
+                        calculate_closest_color(&palette, &pixel, &emphasis, ColorMetric::Rgb),
+                        naive_closest(&palette, &pixel),
+                        "mismatch for rgb({r}, {g}, {b})"
+                    );
+                }
+            }
+        }
+    }
+
+    /// `closest_in_stock` converting each palette color to the working
+    /// space on every call, as it did before palettes were precomputed.
+    fn converting_in_stock(
+        color_configs: &ColorConfigs,
+        original: &Color,
+        needed: u64,
+    ) -> Option<usize> {
+        let target = working_color(original.r, original.g, original.b);
+        let mut closest = (f32::MAX, None);
+        for (index, config) in color_configs.colors.iter().enumerate() {
+            if config.count < needed {
+                continue;
+            }
+            let dist = squared_distance(&working_color(config.r, config.g, config.b), &target);
+            if dist < closest.0 {
+                closest = (dist, Some(index));
+            }
+        }
+        closest.1
+    }
+
+    /// Times matching every tile of a full grid against the sample palette,
+    /// and a refinement pass matching them again against the stock a solve
+    /// left, with the palette's working colors precomputed and converted on
+    /// every call.
+    #[test]
+    #[ignore]
+    fn precomputed_palette_outpaces_converting_per_call() {
+        const ROUNDS: u32 = 50;
+        let palette = ColorConfigs::load("sample/colors.json", false);
+        let reference: Vec<Color> = (0..X_SIZE * Y_SIZE)
+            .map(|i| Color {
+                r: (i * 7 % 256) as u8,
+                g: (i / 3 % 256) as u8,
+                b: (i * 13 / 5 % 256) as u8,
+                x: i % X_SIZE,
+                y: i / X_SIZE,
+            })
+            .collect();
+        let emphasis = HashMap::new();
+        let tiles: Vec<usize> = (0..reference.len()).collect();
+        let mut solved = palette.clone();
+        assign_colors(&reference, &tiles, &mut solved, &emphasis, None, false);
+        for (pass, needed, stock) in [("Full grid", 0, &palette), ("Refinement", 1, &solved)] {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for color in &reference {
+                    std::hint::black_box(closest_in_stock(
+                        stock,
+                        color,
+                        &emphasis,
+                        needed,
+                        ColorMetric::Rgb,
+                    ));
+                }
+            }
+            let precomputed = start.elapsed() / ROUNDS;
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for color in &reference {
+                    std::hint::black_box(converting_in_stock(stock, color, needed));
+                }
+            }
+            let converting = start.elapsed() / ROUNDS;
+            println!(
+                "{pass}: {precomputed:?} per pass precomputed, {converting:?} converting per call"
+            );
+        }
+    }
+
+    #[test]
+    fn rows_are_reported_once_complete_and_match_full_solve() {
+        let reference: Vec<Color> = (0..X_SIZE * Y_SIZE)
+            .map(|i| Color {
+                r: (i % 256) as u8,
+                g: (i / 7 % 256) as u8,
+                b: (i / 13 % 256) as u8,
+                x: i % X_SIZE,
+                y: i / X_SIZE,
+            })
+            .collect();
+        let order: Vec<usize> = (0..reference.len()).rev().collect();
+        let counts = [("Black", 0, 0, 0, 2000), ("White", 255, 255, 255, 2000)];
+        let emphasis = HashMap::new();
+
+        let mut expected_palette = palette(&counts);
+        let expected = assign_colors(
+            &reference,
+            &order,
+            &mut expected_palette,
+            &emphasis,
+            None,
+            false,
+        );
+
+        let mut rows = Vec::new();
+        let mut by_row_palette = palette(&counts);
+        let assignment = assign_colors_by_row(
+            &reference,
+            &order,
+            &mut by_row_palette,
+            &emphasis,
+            None,
+            false,
+            |row| rows.push(row),
+        );
+        assert_eq!(assignment, expected);
+        assert_eq!(rows.len(), Y_SIZE as usize);
+        // The order runs backwards, so the top row finishes first.
+        assert!(rows[0].iter().all(|tile| tile.y == Y_SIZE - 1));
+        assert_eq!(rows.iter().map(Vec::len).sum::<usize>(), reference.len());
+    }
+
+    #[test]
+    fn merge_small_regions_absorbs_isolated_tile() {
+        let reference: Vec<Color> = (0..X_SIZE * Y_SIZE)
+            .map(|i| Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                x: i % X_SIZE,
+                y: i / X_SIZE,
+            })
+            .collect();
+        let mut color_configs = palette(&[("Black", 0, 0, 0, 1), ("Red", 255, 0, 0, 0)]);
+        let speck = (5 * X_SIZE + 5) as usize;
+        let mut assignment = vec![1; reference.len()];
+        assignment[speck] = 0;
+        color_configs.colors[1].count = 1;
+
+        let reassigned = merge_small_regions(
+            &reference,
+            &mut assignment,
+            &mut color_configs,
+            &HashMap::new(),
+            2,
+        );
+        assert_eq!(reassigned, [1, 0]);
+        assert_eq!(assignment[speck], 1);
+        assert_eq!(color_configs.colors[0].count, 2);
+        assert_eq!(color_configs.colors[1].count, 0);
+    }
+
+    #[test]
+    fn tiny_regions_join_their_longest_border() {
+        let reference: Vec<Color> = (0..X_SIZE * Y_SIZE)
+            .map(|i| Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                x: i % X_SIZE,
+                y: i / X_SIZE,
+            })
+            .collect();
+        let mut color_configs = palette(&[
+            ("Black", 0, 0, 0, 0),
+            ("Red", 255, 0, 0, 0),
+            ("Blue", 0, 0, 255, 0),
+        ]);
+        // Blue fills the bottom two rows and red the rest. A two-tile black
+        // region sits on the border, four of its six sides touching red.
+        let mut assignment: Vec<usize> = (0..reference.len())
+            .map(|tile| if tile < 2 * X_SIZE as usize { 2 } else { 1 })
+            .collect();
+        let speck = [(2 * X_SIZE + 5) as usize, (2 * X_SIZE + 6) as usize];
+        for tile in speck {
+            assignment[tile] = 0;
+        }
+        color_configs.colors[1].count = 2;
+
+        let reassigned = merge_tiny_regions(
+            &reference,
+            &mut assignment,
+            &mut color_configs,
+            &HashMap::new(),
+            3,
+        );
+        assert_eq!(reassigned, (1, 2));
+        assert!(speck.iter().all(|&tile| assignment[tile] == 1));
+        let counts: Vec<u64> = color_configs
+            .colors
+            .iter()
+            .map(|config| config.count)
+            .collect();
+        assert_eq!(counts, [2, 0, 0]);
+
+        // At size 1 nothing is small enough to merge.
+        assert_eq!(
+            merge_tiny_regions(
+                &reference,
+                &mut assignment,
+                &mut color_configs,
+                &HashMap::new(),
+                1
+            ),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn replacing_a_color_checks_inventory_or_moves_worst_fits() {
+        let reference = vec![pixel(250, 0, 0), pixel(120, 0, 0), pixel(200, 0, 0)];
+        let mut color_configs = palette(&[("Red", 255, 0, 0, 0), ("Maroon", 128, 0, 0, 2)]);
+        let mut assignment = vec![0, 0, 0];
+
+        let short = replace_color(
+            &reference,
+            &mut assignment,
+            &mut color_configs,
+            &HashMap::new(),
+            (0, 1),
+            |_| true,
+            false,
+        );
+        assert_eq!(short, Err(1));
+        assert_eq!(assignment, [0, 0, 0]);
+
+        let moved = replace_color(
+            &reference,
+            &mut assignment,
+            &mut color_configs,
+            &HashMap::new(),
+            (0, 1),
+            |_| true,
+            true,
+        );
+        assert_eq!(moved, Ok(2));
+        // The darkest tiles suit red least.
+        assert_eq!(assignment, [0, 1, 1]);
+        let counts: Vec<u64> = color_configs
+            .colors
+            .iter()
+            .map(|config| config.count)
+            .collect();
+        assert_eq!(counts, [2, 0]);
+    }
+
+    #[test]
+    fn restarts_are_reproducible_per_seed() {
+        let reference: Vec<Color> = (0..50).map(|i| pixel(i * 5, 100, 255 - i * 5)).collect();
+        let color_configs = palette(&[("Red", 255, 0, 0, 25), ("Blue", 0, 0, 255, 25)]);
+        let emphasis = HashMap::new();
+        let tiles: Vec<usize> = (0..reference.len()).collect();
+        let first = restarts(
+            &reference,
+            &tiles,
+            &color_configs,
+            &emphasis,
+            None,
+            None,
+            &[1, 2, 3],
+        );
+        let second = restarts(
+            &reference,
+            &tiles,
+            &color_configs,
+            &emphasis,
+            None,
+            None,
+            &[3, 2, 1],
+        );
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0].assignment, second[2].assignment);
+        assert_eq!(first[2].total_error, second[0].total_error);
+        for run in &first {
+            assert!(run.palette.colors.iter().all(|config| config.count == 0));
+        }
+    }
+
+    #[test]
+    fn scarce_colors_go_first() {
+        let palette = palette(&[("Black", 0, 0, 0, 50), ("White", 255, 255, 255, 2)]);
+        let reference = vec![
+            pixel(10, 10, 10),
+            pixel(250, 250, 250),
+            pixel(0, 0, 0),
+            pixel(240, 240, 240),
+        ];
+        let order = most_constrained_first(vec![0, 1, 2, 3], &reference, &palette, &HashMap::new());
+        assert_eq!(order, [1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn weighted_shuffles_favor_edges() {
+        // A white right half on black: only the columns either side of the
+        // border have a gradient.
+        let reference: Vec<Color> = (0..X_SIZE * Y_SIZE)
+            .map(|tile| {
+                let level = if tile % X_SIZE < X_SIZE / 2 { 0 } else { 255 };
+                Color {
+                    x: tile % X_SIZE,
+                    y: tile / X_SIZE,
+                    ..pixel(level, level, level)
+                }
+            })
+            .collect();
+        let weights = edge_weights(&reference);
+        assert_eq!(weights[0], 1.0);
+        assert!(weights[(X_SIZE / 2) as usize] > 1000.0);
+        assert_eq!(
+            weights[(X_SIZE / 2 - 1) as usize],
+            weights[(X_SIZE / 2) as usize]
+        );
+
+        let heavy = [1000.0, 1.0, 1.0, 1.0];
+        let mut rng = StdRng::seed_from_u64(7);
+        let firsts = (0..100)
+            .filter(|_| shuffled(&[0, 1, 2, 3], Some(&heavy), &mut rng)[0] == 0)
+            .count();
+        assert!(firsts > 90, "{firsts}");
+    }
+
+    #[test]
+    fn texture_strength_zero_matches_nearest_and_seed_reproduces() {
+        let reference: Vec<Color> = (0..60).map(|i| pixel(i * 4, 120, 200 - i * 3)).collect();
+        let counts = [
+            ("Red", 255, 0, 0, 30),
+            ("Green", 0, 255, 0, 30),
+            ("Blue", 0, 0, 255, 30),
+        ];
+        let order: Vec<usize> = (0..reference.len()).collect();
+        let emphasis = HashMap::new();
+        let solve_with = |texture| {
+            assign_colors(
+                &reference,
+                &order,
+                &mut palette(&counts),
+                &emphasis,
+                texture,
+                false,
+            )
+        };
+        let texture = |strength, seed| {
+            Some(Texture {
+                k: 3,
+                strength,
+                seed,
+            })
+        };
+
+        let deterministic = solve_with(None);
+        assert_eq!(solve_with(texture(0.0, 1)), deterministic);
+        let adventurous = solve_with(texture(2.0, 1));
+        assert_eq!(solve_with(texture(2.0, 1)), adventurous);
+        assert_ne!(adventurous, deterministic);
+    }
+
+    #[test]
+    fn candidates_ignore_inventory_and_sort_nearest_first() {
+        let palette = palette(&[
+            ("Black", 0, 0, 0, 0),
+            ("Gray", 128, 128, 128, 5),
+            ("White", 255, 255, 255, 5),
+        ]);
+        let found = candidates(&palette, &pixel(20, 20, 20), &HashMap::new(), 2);
+        let order: Vec<usize> = found.iter().map(|&(index, _)| index).collect();
+        assert_eq!(order, [0, 1]);
+        assert!(found[0].1 < found[1].1);
+    }
+
+    #[test]
+    fn holes_take_no_pieces_and_keep_their_source() {
+        let reference: Vec<Color> = (0..4).map(|i| pixel(i * 60, 0, 0)).collect();
+        let mut palette = palette(&[("Red", 200, 0, 0, 3)]);
+        let mut assignment = assign_colors(
+            &reference,
+            &[0, 1, 3],
+            &mut palette,
+            &HashMap::new(),
+            None,
+            false,
+        );
+        punch_holes(&mut assignment, &[2]);
+        assert_eq!(usage(&assignment, 1), [3]);
+        assert_eq!(palette.colors[0].count, 0);
+        let pixels = assigned_pixels(&reference, &assignment, &palette);
+        assert_eq!((pixels[2].r, pixels[3].r), (120, 200));
+    }
+
+    #[test]
+    fn affinity_counts_pairs_within_the_radius_once() {
+        let mut assignment = vec![HOLE; (X_SIZE * Y_SIZE) as usize];
+        let at = |x: u64, y: u64| (y * X_SIZE + x) as usize;
+        assignment[at(0, 0)] = 0;
+        assignment[at(3, 0)] = 1;
+        // Just over three tiles from the first.
+        assignment[at(3, 1)] = 1;
+        assignment[at(X_SIZE - 1, 0)] = 0;
+        let counts = affinity(&assignment, 2);
+        assert_eq!(counts, [[0, 1], [0, 1]]);
+    }
+
+    #[test]
+    fn nearest_counts_ignore_stock() {
+        let reference: Vec<Color> = [10, 20, 240].iter().map(|&r| pixel(r, 0, 0)).collect();
+        let palette = palette(&[("Dark", 0, 0, 0, 0), ("Red", 250, 0, 0, 9)]);
+        assert_eq!(
+            nearest_counts(&reference, &palette, &HashMap::new()),
+            [2, 1]
+        );
+    }
+
+    #[test]
+    fn no_overlap_passes_over_neighbors_colors() {
+        // One full row, so every tile has its neighbors on the grid.
+        let reference: Vec<Color> = (0..X_SIZE)
+            .map(|x| Color {
+                x,
+                ..pixel(200, 0, 0)
+            })
+            .collect();
+        let colors = [("Red", 200, 0, 0, 3), ("Dark red", 150, 0, 0, 3)];
+        let run = |no_overlap| {
+            assign_colors(
+                &reference,
+                &[0, 1, 2],
+                &mut palette(&colors),
+                &HashMap::new(),
+                None,
+                no_overlap,
+            )[..3]
+                .to_vec()
+        };
+        assert_eq!(run(false), [0, 0, 0]);
+        assert_eq!(run(true), [0, 1, 0]);
+
+        let mut placed = vec![None; (X_SIZE * 2) as usize];
+        placed[1] = Some(pixel(1, 2, 3));
+        let neighbors = get_neighbors(&placed, 0, 0, X_SIZE);
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].map(|color| color.r), Some(1));
+        assert!(neighbors[1].is_none());
+    }
+}
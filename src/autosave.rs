@@ -0,0 +1,173 @@
+//! Periodic copies of the working session, so edits survive a crash.
+//!
+//! Auto-saves rotate through a few numbered files next to the `--session`
+//! path, `<stem>.autosave1.json` and so on. Each is written to a temporary
+//! file and renamed into place, so a crash mid-write leaves the older
+//! copies intact.
+
+use crate::cli::Options;
+use crate::session::SavedAssignment;
+use crate::Model;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+pub struct Autosave {
+    session: String,
+    /// The slot the next auto-save goes to.
+    next: usize,
+    last_write: Instant,
+    /// Assignment and remaining counts as of the last write, to skip
+    /// writing when nothing changed.
+    saved: Option<(Vec<usize>, Vec<u64>)>,
+}
+
+impl Autosave {
+    pub fn new(options: &Options) -> Option<Autosave> {
+        let session = options.session.clone()?;
+        // Continue after the newest existing copy rather than overwrite it.
+        let next = newest(&session, options.autosave_backups)
+            .map(|(slot, _)| (slot + 1) % options.autosave_backups)
+            .unwrap_or(0);
+        Some(Autosave {
+            session,
+            next,
+            last_write: Instant::now(),
+            saved: None,
+        })
+    }
+}
+
+fn slot_path(session: &str, slot: usize) -> PathBuf {
+    let path = Path::new(session);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.autosave{}.json", slot + 1))
+}
+
+/// The most recently modified auto-save slot and its time.
+fn newest(session: &str, slots: usize) -> Option<(usize, SystemTime)> {
+    (0..slots)
+        .filter_map(|slot| {
+            let modified = fs::metadata(slot_path(session, slot))
+                .ok()?
+                .modified()
+                .ok()?;
+            Some((slot, modified))
+        })
+        .max_by_key(|&(_, modified)| modified)
+}
+
+/// Writes `saved` to `path` through a temporary file and a rename.
+pub fn write_atomic(saved: &SavedAssignment, path: &Path) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(saved).expect("Assignment should serialize.");
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, json)?;
+    fs::rename(&temporary, path)
+}
+
+/// Auto-saves once the interval has passed, if anything changed.
+pub fn tick(model: &mut Model) {
+    let due = match &model.autosave {
+        Some(autosave) => autosave.last_write.elapsed() >= model.options.autosave_interval.0,
+        None => false,
+    };
+    if due {
+        save_now(model);
+    }
+}
+
+/// Auto-saves right away, e.g. after a re-solve, unless nothing changed.
+pub fn save_now(model: &mut Model) {
+    let state = (
+        model.assignment.clone(),
+        model
+            .palette
+            .colors
+            .iter()
+            .map(|config| config.count)
+            .collect(),
+    );
+    let saved = SavedAssignment::from_model(model);
+    let slots = model.options.autosave_backups;
+    let autosave = match &mut model.autosave {
+        Some(autosave) => autosave,
+        None => return,
+    };
+    autosave.last_write = Instant::now();
+    if autosave.saved.as_ref() == Some(&state) {
+        return;
+    }
+    let path = slot_path(&autosave.session, autosave.next);
+    match write_atomic(&saved, &path) {
+        Ok(()) => {
+            log::info!("Auto-saved to {}.", path.display());
+            autosave.next = (autosave.next + 1) % slots;
+            autosave.saved = Some(state);
+        }
+        Err(err) => log::error!("Auto-save to {} failed: {err}", path.display()),
+    }
+}
+
+/// The session to resume from at startup: the newest auto-save when it is
+/// newer than the session file and the user agrees (or passed
+/// `--restore-autosave`), otherwise the session file if it exists.
+pub fn startup_session(options: &Options) -> Option<SavedAssignment> {
+    let session = options.session.as_deref()?;
+    let session_time = fs::metadata(session)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    if let Some((slot, modified)) = newest(session, options.autosave_backups) {
+        if session_time.is_none_or(|time| modified > time) {
+            let path = slot_path(session, slot);
+            if options.restore_autosave || confirm(&path) {
+                log::info!("Restoring {}.", path.display());
+                return Some(SavedAssignment::load(&path.to_string_lossy()));
+            }
+        }
+    }
+    session_time?;
+    log::info!("Resuming {session}.");
+    Some(SavedAssignment::load(session))
+}
+
+fn confirm(path: &Path) -> bool {
+    eprint!(
+        "{} is newer than the session. Restore it? [y/N] ",
+        path.display()
+    );
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok();
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfigs;
+
+    #[test]
+    fn atomic_writes_land_in_rotating_slots() {
+        let dir = std::env::temp_dir().join(format!("autosave-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let session = dir.join("mosaic.json").to_string_lossy().into_owned();
+        assert_eq!(slot_path(&session, 1), dir.join("mosaic.autosave2.json"));
+        assert!(newest(&session, 2).is_none());
+
+        let saved = SavedAssignment {
+            width: 1,
+            height: 1,
+            source: String::new(),
+            palette: ColorConfigs { colors: Vec::new() },
+            tiles: Vec::new(),
+            forced: Vec::new(),
+            legend_sort: Default::default(),
+            numbers: Vec::new(),
+        };
+        write_atomic(&saved, &slot_path(&session, 1)).unwrap();
+        assert_eq!(newest(&session, 2).map(|(slot, _)| slot), Some(1));
+        assert!(!slot_path(&session, 1).with_extension("json.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Covering a finished mosaic with larger plates for `--export-bricks`.
+//!
+//! The assignment is left as it is; this only works out which neighbouring
+//! tiles of the same color could be one bigger part. Parts may be turned
+//! either way, so `1x2` also covers two tiles stacked vertically.
+
+use crate::{assign, X_SIZE, Y_SIZE};
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+/// A plate size in tiles, as placed: `width` across and `height` up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Brick {
+    pub width: u64,
+    pub height: u64,
+}
+
+impl Brick {
+    pub fn area(&self) -> u64 {
+        self.width * self.height
+    }
+
+    /// The size with the short side first, the way parts are sold.
+    pub fn upright(&self) -> Brick {
+        Brick {
+            width: self.width.min(self.height),
+            height: self.width.max(self.height),
+        }
+    }
+
+    fn turned(&self) -> Brick {
+        Brick {
+            width: self.height,
+            height: self.width,
+        }
+    }
+}
+
+impl fmt::Display for Brick {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl FromStr for Brick {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .ok_or_else(|| format!("expected a size like 2x4, got '{s}'"))?;
+        let side = |side: &str| match side.trim().parse::<u64>() {
+            Ok(side) if side > 0 => Ok(side),
+            _ => Err(format!("expected a size like 2x4, got '{s}'")),
+        };
+        Ok(Brick {
+            width: side(width)?,
+            height: side(height)?,
+        })
+    }
+}
+
+/// The part sizes allowed, from `--brick-sizes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrickSizes(pub Vec<Brick>);
+
+impl Default for BrickSizes {
+    fn default() -> BrickSizes {
+        "1x1,1x2,1x4,2x2,2x4"
+            .parse()
+            .expect("Default brick sizes should parse.")
+    }
+}
+
+impl FromStr for BrickSizes {
+    type Err = String;
+
+    /// A comma-separated list, which must include `1x1` so that every tile
+    /// can be covered.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sizes: Vec<Brick> = s.split(',').map(str::parse).collect::<Result<_, _>>()?;
+        let single = Brick {
+            width: 1,
+            height: 1,
+        };
+        if !sizes.contains(&single) {
+            return Err(format!(
+                "'{s}' must include 1x1 so every tile can be covered"
+            ));
+        }
+        Ok(BrickSizes(sizes))
+    }
+}
+
+/// One part on the grid: its bottom-left tile, size as placed, and palette
+/// color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub x: u64,
+    pub y: u64,
+    pub brick: Brick,
+    pub color: usize,
+}
+
+impl Placement {
+    /// Raster indices of the tiles the part covers.
+    pub fn tiles(&self) -> impl Iterator<Item = usize> + '_ {
+        (self.y..self.y + self.brick.height).flat_map(move |y| {
+            (self.x..self.x + self.brick.width).map(move |x| (y * X_SIZE + x) as usize)
+        })
+    }
+}
+
+/// Covers every tile of `assignment` but the keyed-out holes exactly once. Tiles are visited in
+/// raster order and each one not yet covered starts the largest allowed
+/// part, in either orientation, that fits over uncovered tiles of its own
+/// color; wider beats taller between parts of the same area.
+pub fn cover(assignment: &[usize], sizes: &BrickSizes) -> Vec<Placement> {
+    let mut candidates: Vec<Brick> = sizes
+        .0
+        .iter()
+        .flat_map(|brick| [*brick, brick.turned()])
+        .collect();
+    candidates.sort_by_key(|brick| (Reverse(brick.area()), Reverse(brick.width)));
+    candidates.dedup();
+
+    let mut covered = vec![false; assignment.len()];
+    let mut placements = Vec::new();
+    for tile in 0..assignment.len() {
+        if covered[tile] || assignment[tile] == assign::HOLE {
+            continue;
+        }
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        let color = assignment[tile];
+        let placement = candidates
+            .iter()
+            .map(|&brick| Placement { x, y, brick, color })
+            .find(|placement| {
+                placement.x + placement.brick.width <= X_SIZE
+                    && placement.y + placement.brick.height <= Y_SIZE
+                    && placement
+                        .tiles()
+                        .all(|tile| !covered[tile] && assignment[tile] == color)
+            })
+            .expect("A 1x1 part always fits.");
+        for tile in placement.tiles() {
+            covered[tile] = true;
+        }
+        placements.push(placement);
+    }
+    placements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cover_is_exact_and_keeps_colors() {
+        // Color 0 everywhere except a 3-tile-wide stripe of color 1.
+        let assignment: Vec<usize> = (0..X_SIZE * Y_SIZE)
+            .map(|tile| usize::from((5..8).contains(&(tile % X_SIZE))))
+            .collect();
+        let placements = cover(&assignment, &BrickSizes::default());
+        let area: u64 = placements
+            .iter()
+            .map(|placement| placement.brick.area())
+            .sum();
+        assert_eq!(area, X_SIZE * Y_SIZE);
+        let mut seen = vec![false; assignment.len()];
+        for placement in &placements {
+            for tile in placement.tiles() {
+                assert!(!seen[tile]);
+                seen[tile] = true;
+                assert_eq!(assignment[tile], placement.color);
+            }
+        }
+        // The stripe is 3 wide: a 2x4 stood on end beside a 1x4.
+        let stripe: Vec<Brick> = placements
+            .iter()
+            .filter(|placement| placement.color == 1)
+            .map(|placement| placement.brick)
+            .collect();
+        assert!(stripe.iter().all(|brick| brick.height == 4));
+        assert_eq!(stripe.len() as u64, 2 * Y_SIZE / 4);
+
+        assert!("2x2,1x2".parse::<BrickSizes>().is_err());
+        assert_eq!(
+            "1x1,3x2".parse::<BrickSizes>().unwrap().0[1].upright(),
+            Brick {
+                width: 2,
+                height: 3
+            }
+        );
+    }
+}
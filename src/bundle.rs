@@ -0,0 +1,235 @@
+//! Project bundles: one `.zip` holding the picture, the palette, the
+//! settings file, the command line, and the working assignment, so a
+//! project opens again from the bundle alone.
+//!
+//! `manifest.json` names the other entries and carries the bundle format.
+//! Readers ignore fields they do not know, so later versions may add some
+//! without raising `FORMAT`; only a change older builds would misread does.
+
+use crate::session::SavedAssignment;
+use crate::{zip, Model};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// The bundle format this build writes and the newest it opens.
+const FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Flags not carried into a bundle, with whether each takes a value. The
+/// bundle brings its own settings file and session, and `--seed` is
+/// replaced by the seed the mosaic was solved with.
+const DROPPED_FLAGS: &[(&str, bool)] = &[
+    ("--config", true),
+    ("--session", true),
+    ("--seed", true),
+    ("--save-bundle", true),
+    ("--open-recent", true),
+    ("--headless", false),
+    ("--summary-json", false),
+    ("--restore-autosave", false),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format: u32,
+    /// The version of the program that wrote the bundle.
+    #[serde(default)]
+    written_by: String,
+    /// Entry names of the files the project was opened with.
+    picture: String,
+    #[serde(default)]
+    palette: Option<String>,
+    #[serde(default)]
+    config: Option<String>,
+    #[serde(default)]
+    session: Option<String>,
+    /// The command line's flags, without the files above.
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+/// Just the format, read first so that a newer bundle is refused before
+/// the rest of its manifest is trusted.
+#[derive(Deserialize)]
+struct Format {
+    format: u32,
+}
+
+/// The bundle `args` open, when they are a lone `.zip` path.
+pub fn opened(args: &[String]) -> Option<&str> {
+    match args {
+        [path] if path.to_ascii_lowercase().ends_with(".zip") => Some(path),
+        _ => None,
+    }
+}
+
+/// Writes the project on screen to `path`.
+pub fn save(model: &Model, path: &str) {
+    if model.slideshow.is_some() {
+        log::info!("A bundle keeps one picture; not available during a slideshow.");
+        return;
+    }
+    let options = &model.options;
+    let mut parts = Vec::new();
+    // Each file the project was opened with, as its entry name.
+    let mut pack = |stem: &str, file: Option<&String>| -> Result<Option<String>, ()> {
+        let file = match file {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let bytes = fs::read(file).map_err(|err| {
+            log::error!("Could not bundle {file}: {err}");
+        })?;
+        let name = match Path::new(file).extension() {
+            Some(extension) => format!("{stem}.{}", extension.to_string_lossy()),
+            None => stem.to_string(),
+        };
+        parts.push((name.clone(), bytes));
+        Ok(Some(name))
+    };
+    let (Ok(Some(picture)), Ok(palette), Ok(config)) = (
+        pack("picture", Some(&options.picture_path)),
+        pack("palette", options.color_data.as_ref()),
+        pack("settings", options.config.as_ref()),
+    ) else {
+        return;
+    };
+    // Keyed-out tiles have no palette index to record, so the assignment is
+    // solved again on opening.
+    let session = model.holes.is_empty().then(|| {
+        let saved = SavedAssignment::from_model(model);
+        let json = serde_json::to_vec_pretty(&saved).expect("Assignment should serialize.");
+        parts.push(("session.json".to_string(), json));
+        "session.json".to_string()
+    });
+    let mut args = kept_flags(&options.flags);
+    args.extend(["--seed".to_string(), model.seed.to_string()]);
+    let manifest = Manifest {
+        format: FORMAT,
+        written_by: env!("CARGO_PKG_VERSION").to_string(),
+        picture,
+        palette,
+        config,
+        session,
+        args,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).expect("Manifest should serialize.");
+    parts.insert(0, (MANIFEST.to_string(), json));
+
+    match fs::write(path, zip::write(&parts)) {
+        Ok(()) => log::info!("Saved the project to {path}."),
+        Err(err) => log::error!("Could not save {path}: {err}"),
+    }
+}
+
+/// `flags` without those in `DROPPED_FLAGS`.
+fn kept_flags(flags: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match DROPPED_FLAGS.iter().find(|(name, _)| name == flag) {
+            Some((_, true)) => {
+                flags.next();
+            }
+            Some((_, false)) => {}
+            None => kept.push(flag.clone()),
+        }
+    }
+    kept
+}
+
+/// Unpacks the bundle at `path` into a temporary folder and returns the
+/// arguments that open its project from there.
+pub fn open(path: &str) -> Vec<String> {
+    let archive =
+        fs::read(path).unwrap_or_else(|err| panic!("Unable to read bundle {path}: {err}"));
+    let files = zip::read(&archive).unwrap_or_else(|err| panic!("Bundle {path} is damaged: {err}"));
+    let manifest = manifest(path, &files);
+
+    let stem = Path::new(path)
+        .file_stem()
+        .map_or("bundle".into(), |stem| stem.to_string_lossy());
+    let dir = env::temp_dir().join(env!("CARGO_PKG_NAME")).join(&*stem);
+    // Leftovers of an earlier opening, auto-saves included, are stale.
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap_or_else(|err| panic!("Unable to unpack {path}: {err}"));
+    let unpack = |name: &str| {
+        let (_, bytes) = files
+            .iter()
+            .find(|(file, _)| file == name)
+            .unwrap_or_else(|| panic!("Bundle {path} is missing {name}"));
+        let target = dir.join(name);
+        fs::write(&target, bytes).unwrap_or_else(|err| panic!("Unable to unpack {path}: {err}"));
+        target.to_string_lossy().into_owned()
+    };
+
+    let mut args = vec![unpack(&manifest.picture)];
+    args.extend(manifest.palette.as_deref().map(unpack));
+    args.extend(manifest.args.iter().cloned());
+    if let Some(config) = &manifest.config {
+        args.extend(["--config".to_string(), unpack(config)]);
+    }
+    if let Some(session) = &manifest.session {
+        args.extend(["--session".to_string(), unpack(session)]);
+    }
+    log::info!("Opened {path}, unpacked to {}.", dir.display());
+    args
+}
+
+fn manifest(path: &str, files: &[(String, Vec<u8>)]) -> Manifest {
+    let bytes = files
+        .iter()
+        .find(|(name, _)| name == MANIFEST)
+        .map(|(_, bytes)| bytes)
+        .unwrap_or_else(|| panic!("{path} is not a project bundle; it has no {MANIFEST}"));
+    let Format { format } = serde_json::from_slice(bytes)
+        .unwrap_or_else(|err| panic!("Bundle {path} has an unreadable {MANIFEST}: {err}"));
+    if format > FORMAT {
+        panic!(
+            "{path} was saved by a newer version (bundle format {format}); this build opens \
+             format {FORMAT} and older. Update to open it."
+        )
+    }
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|err| panic!("Bundle {path} has an unreadable {MANIFEST}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn bundles_keep_settings_and_read_older_manifests() {
+        let flags = strings(&[
+            "--headless",
+            "--seed",
+            "7",
+            "--restarts",
+            "3",
+            "--config",
+            "a.json",
+            "--cel",
+        ]);
+        assert_eq!(kept_flags(&flags), strings(&["--restarts", "3", "--cel"]));
+        assert_eq!(opened(&strings(&["project.ZIP"])), Some("project.ZIP"));
+        assert_eq!(opened(&strings(&["me.png", "colors.json"])), None);
+
+        let current = br#"{"format": 1, "picture": "picture.png", "unknown": true}"#.to_vec();
+        let files = vec![(MANIFEST.to_string(), current)];
+        assert_eq!(manifest("old.zip", &files).picture, "picture.png");
+    }
+
+    #[test]
+    #[should_panic(expected = "saved by a newer version (bundle format 2)")]
+    fn newer_bundles_are_refused() {
+        let newer = br#"{"format": 2, "layers": []}"#.to_vec();
+        manifest("new.zip", &[(MANIFEST.to_string(), newer)]);
+    }
+}
@@ -0,0 +1,1594 @@
+use crate::assign::ShuffleStrategy;
+use crate::bricks::BrickSizes;
+use crate::bundle;
+use crate::dither::DitherMode;
+use crate::export::{
+    BeforeAfterLayout, IcsDate, PackingListFormat, SectionAxis, SvgFilters, TextileChartStyle,
+    TextileRowDirection,
+};
+use crate::hex::HexOrientation;
+use crate::legend::LegendSort;
+use crate::logging::LogLevel;
+use crate::metric::{ColorMetric, RegionMetrics};
+use crate::minecraft::MinecraftVersion;
+use crate::palette;
+use crate::recent;
+use crate::render::{ExactSize, HexColor, DEFAULT_TILE_PX};
+use crate::selection::Symmetry;
+use crate::source::{ResizeAnchor, ScaleMode, UpscaleFilter};
+use crate::status::ErrorFormat;
+use crate::table::ReportFormat;
+use crate::text::TextPosition;
+use crate::tile_mesh::FoldCorner;
+use crate::{X_SIZE, Y_SIZE};
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Settings collected from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// The arguments as given, for recording how a run was made.
+    pub args: Vec<String>,
+    /// `args` without the picture and palette paths.
+    pub flags: Vec<String>,
+    /// The project bundle the arguments were unpacked from.
+    pub bundle: Option<String>,
+    pub picture_path: String,
+    pub color_data: Option<String>,
+    /// Skip ICC profile handling and treat the decoded pixels as sRGB.
+    pub assume_srgb: bool,
+    pub scale_mode: ScaleMode,
+    pub resize_anchor: ResizeAnchor,
+    /// Enlarge the picture this many times before fitting it to the grid.
+    pub super_resolution: Option<u32>,
+    /// How `super_resolution` enlarges the picture.
+    pub upscale_filter: UpscaleFilter,
+    /// Source color whose tiles are left empty, like a green screen.
+    pub key_color: Option<HexColor>,
+    /// How far in RGB a tile may be from `key_color` and still be keyed out.
+    pub key_tolerance: f32,
+    /// Pieces of each color in an imported `.gpl` or `.aco` palette.
+    pub count_per_color: Option<u64>,
+    /// JSON map of color name to count for an imported palette.
+    pub count_overlay: Option<String>,
+    /// Generate this many random colors instead of reading a palette file.
+    pub random_palette: Option<u64>,
+    /// Average this many equal areas of the picture into the palette
+    /// instead of reading a palette file.
+    pub palette_from_image: Option<u64>,
+    /// Build the palette as a gradient of this many colors from
+    /// `gradient_from` to `gradient_to`.
+    pub palette_gradient: Option<u64>,
+    /// Take the palette from the color literals of this stylesheet instead
+    /// of reading a palette file.
+    pub palette_from_css: Option<String>,
+    /// Take the palette from the distinct pixels of this swatch image
+    /// instead of reading a palette file.
+    pub palette_from_png: Option<String>,
+    /// Most colors a `palette_from_png` image may have.
+    pub max_palette_colors: usize,
+    /// The gradient's first color, as `rrggbb` or a palette color's name.
+    pub gradient_from: Option<String>,
+    /// The gradient's last color, as `gradient_from`.
+    pub gradient_to: Option<String>,
+    /// The color space the gradient is spaced evenly in.
+    pub gradient_space: ColorMetric,
+    /// Seed for every random choice, so runs can be reproduced.
+    pub seed: Option<u64>,
+    /// Temperature for sampling among the nearest colors; off when unset.
+    pub texture_strength: Option<f32>,
+    /// How many nearest colors `texture_strength` samples from.
+    pub texture_k: usize,
+    /// Solve this many times with different shuffles and keep the best.
+    pub restarts: Option<u32>,
+    /// Shuffle tiles so those on strong edges of the picture tend to be
+    /// solved first.
+    pub weighted_shuffle: bool,
+    /// Whether tiles are shuffled or solved most constrained first.
+    pub shuffle_strategy: ShuffleStrategy,
+    /// Dither the picture's tiles before solving them.
+    pub dither_mode: Option<DitherMode>,
+    /// Side of `dither_mode`'s Bayer matrix, 4 or 8.
+    pub bayer_size: u32,
+    /// Solve in raster order, never giving a tile the color of a
+    /// 4-connected neighbor solved before it while another is in stock.
+    pub no_overlap: bool,
+    /// Color spread above which an adaptive cell is split; uniform tiles
+    /// when unset.
+    pub adaptive_cells: Option<f32>,
+    /// Side in tiles of the largest adaptive cell.
+    pub adaptive_max_cell: u64,
+    /// Cut the picture into regions of similar color and give each one
+    /// palette color.
+    pub cel: bool,
+    /// Roughly how many regions `cel` starts from.
+    pub cel_regions: usize,
+    /// Smallest region `cel` keeps, in tiles; smaller ones are merged into
+    /// a neighbour.
+    pub cel_min_size: usize,
+    /// Write the `cel` regions and their boundaries as a PNG here.
+    pub export_segments: Option<String>,
+    /// Lay the tiles out as hexagons in offset rows or columns.
+    pub hex_grid: Option<HexOrientation>,
+    /// Palette color names whose distance is scaled by the paired factor.
+    pub emphasis: Vec<(String, f32)>,
+    /// Which color distance each part of the grid is matched by.
+    pub region_palette_override: RegionMetrics,
+    /// The color distance tiles outside `region_palette_override` are matched
+    /// by, and `color_distance_table` measures.
+    pub color_metric: ColorMetric,
+    /// Scale palette counts so they add up to the grid's tile count.
+    pub normalize_counts: bool,
+    /// Gamma-correct the palette's colors by this exponent on loading.
+    pub gamma_palette: Option<f32>,
+    /// Warn about palette colors whose measured values in this `name,r,g,b`
+    /// CSV are off.
+    pub palette_validate_physical: Option<String>,
+    /// How far in RGB a measured color may be from the palette's before
+    /// `palette_validate_physical` warns.
+    pub measurement_tolerance: f32,
+    /// Count each color by how many tiles are nearest it, ignoring the
+    /// palette's counts.
+    pub auto_count: bool,
+    /// Merge the palette down to at most this many colors.
+    pub color_reduce: Option<usize>,
+    /// Round palette channels to this many levels, merging colors that
+    /// become equal.
+    pub quantize_palette: Option<u32>,
+    /// Save the palette in use, after any reduction, here.
+    pub palette_out: Option<String>,
+    /// Print how much of the color cube the palette covers.
+    pub palette_diversity_score: bool,
+    /// Print the distance between every two palette colors as TSV.
+    pub color_distance_table: bool,
+    /// List only the pairs of `color_distance_table` closer than this.
+    pub distance_threshold: Option<f32>,
+    /// Print how many tiles each color got, and how many pieces are left.
+    pub stats: bool,
+    /// Print how often each pair of colors lies near each other.
+    pub color_affinity_map: bool,
+    /// Text, JSON, or CSV for `stats`, `color_affinity_map`,
+    /// `palette_diversity_score`, and `diff`.
+    pub color_report_format: ReportFormat,
+    /// Write the palette as an HTML page of swatches here before solving.
+    pub palette_html_preview: Option<String>,
+    /// Edge palette whose pieces replace the border after solving.
+    pub corner_tiles: Option<String>,
+    /// Passes of isolated-tile smoothing after each solve.
+    pub merge_small_regions: u32,
+    /// Connected same-color regions smaller than this are recolored to
+    /// their neighbour's color after each solve.
+    pub min_region_size: usize,
+    /// Keep the window title on the tile under the mouse.
+    pub show_grid_coords: bool,
+    /// Turn the hues of the window's tile colors continuously.
+    pub color_cycle_animation: bool,
+    /// Turns of the color wheel per second for `color_cycle_animation`.
+    pub cycle_speed: f32,
+    /// Zoom the window onto the tiles of this color once solved.
+    pub interactive_zoom_to_color: Option<String>,
+    /// Open the window with build batches marked in tile corners.
+    pub tile_corner_indicator: bool,
+    /// Tiles per build batch for `tile_corner_indicator`.
+    pub batch_size: u64,
+    /// Open the window with each tile's raster index written on it.
+    pub tile_debug_index: bool,
+    /// Open the window with the tiles colored by their color's remaining
+    /// count.
+    pub tile_count_heatmap: bool,
+    /// Draw the window's tiles with this corner folded under.
+    pub tile_corner_fold: Option<FoldCorner>,
+    /// Pixels along each edge that `tile_corner_fold` cuts.
+    pub fold_size: f32,
+    /// Report extra detail on stderr.
+    pub verbose: bool,
+    /// The least severe messages printed on stderr.
+    pub log_level: LogLevel,
+    /// Aseprite sprite with a layer per palette color.
+    pub output_aseprite: Option<String>,
+    /// Write the mosaic as an indexed GIMP image with a layer per color here.
+    pub export_xcf: Option<String>,
+    /// Write the build as a calendar of daily events here.
+    pub export_ics: Option<String>,
+    /// Tiles each of `export_ics`'s days places.
+    pub tiles_per_day: u64,
+    /// First day of `export_ics`'s schedule; today when unset.
+    pub start_date: Option<IcsDate>,
+    /// Write a Discord webhook message showing the mosaic here.
+    pub export_discord_embed: Option<String>,
+    /// Post `export_discord_embed`'s message to this webhook too.
+    pub webhook_url: Option<String>,
+    /// Title of the window, and of `export_discord_embed`'s embed.
+    pub window_title: Option<String>,
+    /// Write the mosaic as a TikZ picture here.
+    pub export_latex: Option<String>,
+    /// Also write a figure with this caption around `export_latex`'s
+    /// picture.
+    pub latex_caption: Option<String>,
+    /// Write the mosaic's red, green, and blue channels and their
+    /// composite as PNGs into this folder.
+    pub export_png_split_rgb: Option<String>,
+    /// Write the mosaic with a faint labelled grid over it here.
+    pub export_png_watermarked_grid: Option<String>,
+    /// Tiles between `export_png_watermarked_grid`'s lines.
+    pub grid_interval: u32,
+    /// OpenRaster image with a layer per palette color.
+    pub export_openraster: Option<String>,
+    /// Save the project as a bundle here.
+    pub save_bundle: Option<String>,
+    /// Write the mosaic as a palette PNG of the palette's colors here.
+    pub export_png_indexed: Option<String>,
+    /// Write the tiles alone, stretched to exactly this size, to the path
+    /// paired with it.
+    pub export_png_zoom: Option<(ExactSize, String)>,
+    /// Write the palette as a one-pixel-tall strip PNG in hue order here.
+    pub export_png_band: Option<String>,
+    /// Draw `export_png_band` as named swatches instead of a pixel each.
+    pub band_with_names: bool,
+    /// Write the colors the mosaic uses as a GIMP palette here.
+    pub export_gimp_palette: Option<String>,
+    /// Write the palette as CSS custom properties here.
+    pub export_css_variables: Option<String>,
+    /// What the CSS property names start with instead of `color`.
+    pub css_prefix: Option<String>,
+    /// Write the palette as Android color resources into this `res` folder.
+    pub export_android_theme: Option<String>,
+    /// Write the tile colors as Rust constants for `include!` here.
+    pub export_game_asset: Option<String>,
+    /// Store the game asset's colors as runs with a decoding function.
+    pub compress_rle: bool,
+    /// Write the mosaic as an Excel workbook, one filled cell per tile.
+    pub export_xls: Option<String>,
+    /// Write a parts list covering the mosaic with larger plates here, with
+    /// a placement diagram beside it.
+    pub export_bricks: Option<String>,
+    /// Plate sizes `export_bricks` may use.
+    pub brick_sizes: BrickSizes,
+    /// Write an OpenSCAD script building the mosaic in 3-D here.
+    pub export_openscad: Option<String>,
+    /// Write a Blender Python script building the mosaic in 3-D here.
+    pub export_blender: Option<String>,
+    /// Write the mosaic as one binary STL mesh here.
+    pub export_stl: Option<String>,
+    /// Write the mosaic as an OBJ with an object per color here.
+    pub export_obj: Option<String>,
+    /// Write a Fritzing part SVG with an LED per tile here.
+    pub export_fritzing: Option<String>,
+    /// Write a KiCad footprint with a pad per tile here.
+    pub export_kicad: Option<String>,
+    /// Millimetres between the pads of `export_kicad`.
+    pub kicad_pitch: f64,
+    /// Write a DST embroidery design, a thread per color, here.
+    pub export_embroidery: Option<String>,
+    /// Satin stitches per millimetre in `export_embroidery`.
+    pub stitch_density: f32,
+    /// Tile side in millimetres for the 3-D exports.
+    pub stud_pitch: f64,
+    /// Millimetre height of tiles whose color has no `height_mm`.
+    pub tile_height: f64,
+    /// Put a round stud on every tile of the mesh exports.
+    pub studs: bool,
+    /// Minecraft map item file showing the mosaic.
+    pub export_minecraft_map: Option<String>,
+    /// Release whose map colors and data version the map file uses.
+    pub minecraft_version: MinecraftVersion,
+    /// Folder for intermediate images and per-tile candidate lists.
+    pub debug_dump: Option<String>,
+    /// Settings file with export presets.
+    pub config: Option<String>,
+    /// Export preset from the settings file to run once solved.
+    pub export_preset: Option<String>,
+    /// Extra palettes solved against the same source for comparison.
+    pub compare: Vec<String>,
+    /// Cycle through the pictures in this folder instead of showing one.
+    pub slideshow: Option<String>,
+    /// How long each slideshow picture stays up once solved.
+    pub interval: Interval,
+    /// Show the slideshow pictures in random order.
+    pub shuffle: bool,
+    /// Record the window's solving animation as JSON Lines.
+    pub export_timelapse_json: Option<String>,
+    /// Play back a recorded solving animation instead of solving.
+    pub replay_timelapse: Option<String>,
+    /// Recorded frames played back per window frame.
+    pub replay_speed: f32,
+    /// Session file to resume from and save to with `S`.
+    pub session: Option<String>,
+    /// How often the session is auto-saved while edits are pending.
+    pub autosave_interval: Interval,
+    /// How many rotating auto-save files are kept.
+    pub autosave_backups: usize,
+    /// Restore a newer auto-save without asking.
+    pub restore_autosave: bool,
+    /// Write exports and exit without opening a window.
+    pub headless: bool,
+    /// How failures are printed on stderr. The panic hook reads the flag
+    /// from the arguments itself; this only validates it.
+    pub error_format: ErrorFormat,
+    /// Print a JSON summary of a headless run on stdout.
+    pub summary_json: bool,
+    /// Open a second window for editing palette counts live.
+    pub interactive_palette_editor: bool,
+    /// Open a window plotting palette and tile colors in the RGB cube.
+    pub palette_cluster_visualization: bool,
+    /// Mirroring applied to recolors in the window.
+    pub symmetry: Symmetry,
+    /// Order of the legend, and so of color numbers in exports.
+    pub legend_sort: LegendSort,
+    /// Open the window showing the source colors instead of the assignment.
+    pub show_original_colors: bool,
+    /// Draw square tiles sized to fit the window instead of stretching them.
+    pub tile_size_from_window: bool,
+    /// TOML file rebinding the window's keyboard shortcuts.
+    pub keybindings: Option<String>,
+    /// List every shortcut with its current keys and exit.
+    pub print_keys: bool,
+    /// Print the JSON Schema of palette files and exit.
+    pub color_config_schema: bool,
+    /// Reject palette fields the schema does not describe.
+    pub parse_strict: bool,
+    /// List the recently opened projects and exit.
+    pub recent: bool,
+    /// Empty the recent projects list and exit.
+    pub forget_recent: bool,
+    /// Write the rendered mosaic (or comparison sheet) as a PNG here.
+    pub output: Option<String>,
+    /// Write the `output` image as WebP here.
+    pub export_webp: Option<String>,
+    /// Quality of `export_webp` from 0 to 100. Only lossless, 100, is
+    /// written, so lower values are ignored.
+    pub webp_quality: u8,
+    /// Number each tile of `output` with its palette position.
+    pub tile_label: bool,
+    /// TrueType font for tile labels instead of the built-in one.
+    pub tile_label_font: Option<String>,
+    /// Leave the background of `output` transparent.
+    pub transparent_bg: bool,
+    /// Lay a bar of the tile, grid, and color counts over the bottom of
+    /// `output`.
+    pub progress_bar: bool,
+    /// Height of the `progress_bar` in pixels.
+    pub bar_height_px: u32,
+    /// Pixels per tile in exported images.
+    pub output_scale: u32,
+    /// Largest brightness change, as a fraction, that tiles are shaded by
+    /// for a hand-made look; off at 0.
+    pub brightness_jitter: f32,
+    /// Blank border around exported images, in pixels.
+    pub margin: u32,
+    pub margin_color: HexColor,
+    /// Words spelled out in tiles on the grid.
+    pub text: Option<String>,
+    /// Where on the grid the text goes.
+    pub text_position: TextPosition,
+    /// Palette color of the text's tiles.
+    pub text_color: Option<String>,
+    /// Palette color of a box behind the text, if any.
+    pub text_background: Option<String>,
+    /// PNG or JSON file forcing tiles to chosen colors.
+    pub force_mask: Option<String>,
+    /// Thickness in tiles of a frame around the grid.
+    pub frame: u32,
+    /// Palette color the frame is built from.
+    pub frame_color: Option<String>,
+    /// Text written below the grid in exported images.
+    pub caption: Option<String>,
+    /// Write a Markdown build report here.
+    pub report: Option<String>,
+    /// Save the finished assignment as JSON here.
+    pub export_json: Option<String>,
+    /// Check `export_json`'s palette against the palette schema before
+    /// writing it.
+    pub export_json_schema_validated: bool,
+    /// List the most used colors first in exported palettes.
+    pub palette_sort_by_usage: bool,
+    /// Compare two saved assignments instead of building a mosaic.
+    pub diff: Option<(String, String)>,
+    /// With `diff`, also write a visual diff image here.
+    pub diff_image: Option<String>,
+    /// Render the second of two saved assignments to the PNG named third,
+    /// outlining the tiles that changed by whether they turned warmer.
+    pub export_png_diff: Option<(String, String, String)>,
+    /// Write a printable PDF with one placement sheet per color here.
+    pub export_pdf_color_sheets: Option<String>,
+    /// Write a PDF of legend numbers in place, with a color legend, here.
+    pub export_label_sheet: Option<String>,
+    /// Write a fuse bead guide PDF, a page per peg board, here.
+    pub export_fuse_beads: Option<String>,
+    /// Pegs along each side of the boards of `export_fuse_beads`.
+    pub board_size: u64,
+    /// Write a PDF of round stickers, as many of each color as it has
+    /// tiles, here.
+    pub export_stickersheet: Option<String>,
+    /// Diameter of the `export_stickersheet` stickers in millimetres.
+    pub sticker_diameter_mm: f32,
+    /// Write a cross-stitch chart PDF, with a legend of symbols and DMC
+    /// threads and a shopping list of skeins, here.
+    pub export_cross_stitch: Option<String>,
+    /// Write a PDF with a page on each palette color, for kit builders,
+    /// here.
+    pub export_color_book: Option<String>,
+    /// Write a PDF shopping list of the colors used, with purchase links.
+    pub export_pdf_bom: Option<String>,
+    /// Write a knitting and weaving chart of the grid here.
+    pub export_textile_chart: Option<String>,
+    /// Which way `export_textile_chart` numbers its rows.
+    pub textile_row_direction: TextileRowDirection,
+    /// Whether `export_textile_chart` fills cells with color or symbols.
+    pub textile_chart_style: TextileChartStyle,
+    /// Write each color's tiles per grid row here, for packing kits.
+    pub export_packing_list: Option<String>,
+    /// Text or JSON for `export_packing_list`.
+    pub packing_list_format: PackingListFormat,
+    /// Write a PNG of each grid row into this folder.
+    pub export_png_sections: Option<String>,
+    /// Whether `export_png_sections` cuts rows or columns.
+    pub section_axis: SectionAxis,
+    /// Write an animated PNG of the tiles being placed one by one here.
+    pub export_sprite_animation: Option<String>,
+    /// Frames a second of `export_sprite_animation`.
+    pub fps: u16,
+    /// How long `export_sprite_animation` holds the finished mosaic.
+    pub pause_ms: u16,
+    /// Write a Lottie animation of the tiles fading in one by one here.
+    pub export_lottie: Option<String>,
+    /// How long `export_lottie` takes to place every tile.
+    pub duration_ms: u32,
+    /// Write the mosaic as Braille characters here.
+    pub export_braille: Option<String>,
+    /// Luminance, from 0 to 1, above which `export_braille` raises a dot.
+    pub braille_threshold: f32,
+    /// Color each `export_braille` character with ANSI escapes.
+    pub ansi_color: bool,
+    /// Write a gapless thumbnail of the mosaic here.
+    pub export_png_thumbnail: Option<String>,
+    /// Width and height of `export_png_thumbnail` in pixels.
+    pub thumb_size: u32,
+    /// Write the tiles as an SVG drawing here.
+    pub export_svg: Option<String>,
+    /// Artistic filters applied to each color's tiles in `export_svg`.
+    pub svg_filters: SvgFilters,
+    /// Write the build as an animated SVG here.
+    pub export_svg_animation: Option<String>,
+    /// Seconds `export_svg_animation` takes to place every tile.
+    pub duration_s: f64,
+    /// Write the mosaic as Google Earth polygons, one per tile, here.
+    pub export_kml: Option<String>,
+    /// Latitude and longitude `export_kml` centers the mosaic on.
+    pub center_lat: Option<f64>,
+    pub center_lon: Option<f64>,
+    /// Side of each of `export_kml`'s tiles on the ground, in metres.
+    pub tile_size_m: f64,
+    /// Clockwise turn of `export_kml`'s layout from facing north.
+    pub rotate_degrees: f64,
+    /// Write the mosaic blended over the downscaled source here.
+    pub export_png_composited: Option<String>,
+    /// Share of each of `export_png_composited`'s pixels that is mosaic.
+    pub blend_alpha: f32,
+    /// Write the mosaic with numbered row and column headers here.
+    pub export_png_numbered_grid: Option<String>,
+    /// Thickness of `export_png_numbered_grid`'s headers; a tile's size
+    /// when unset.
+    pub number_cell_size: Option<u32>,
+    /// Write the mosaic with spreadsheet-like headers numbered from 0 here.
+    pub export_png_column_labels: Option<String>,
+    /// Write color-by-color build steps as images into this folder.
+    pub export_color_steps: Option<String>,
+    /// Write every color's stencil into one PNG here, with a JSON map of
+    /// their positions beside it.
+    pub export_png_atlas: Option<String>,
+    /// Write a Stardew Valley style tileset of the colors in use here, with
+    /// a JSON config beside it.
+    pub export_stardew: Option<String>,
+    /// PNG of the picture beside the mosaic.
+    pub export_before_after: Option<String>,
+    /// Side by side or stacked for `export_before_after`.
+    pub before_after_layout: BeforeAfterLayout,
+    /// Mosaic height side by side, or width stacked, in pixels; the tiles
+    /// at `output_scale` when unset.
+    pub before_after_size: Option<u32>,
+    /// Caption `export_before_after` with the grid size and piece count.
+    pub before_after_caption: bool,
+    /// Draw studs on the tiles of `export_before_after`.
+    pub before_after_studs: bool,
+    /// Stencils per row of `export_png_atlas`; about square when unset.
+    pub atlas_layout: Option<usize>,
+    /// Write the palette as a QR code image to this path.
+    pub export_qrcode_palette: Option<String>,
+}
+
+/// A duration written as `30s`, `2m`, `500ms`, or a bare number of seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval(pub Duration);
+
+impl Default for Interval {
+    fn default() -> Self {
+        Interval(Duration::from_secs(30))
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(split) => s.split_at(split),
+            None => (s, "s"),
+        };
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("expected a duration such as 30s, got '{s}'"))?;
+        let seconds = match unit {
+            "ms" => number / 1000.0,
+            "s" => number,
+            "m" => number * 60.0,
+            _ => return Err(format!("unknown unit '{unit}' in '{s}'")),
+        };
+        Duration::try_from_secs_f64(seconds)
+            .map(Interval)
+            .map_err(|err| format!("{err} in '{s}'"))
+    }
+}
+
+/// Candidates considered by `--texture-strength` unless `--texture-k` is given.
+const DEFAULT_TEXTURE_K: usize = 3;
+
+/// Largest `--adaptive-cells` cell unless `--adaptive-max-cell` is given.
+const DEFAULT_ADAPTIVE_MAX_CELL: u64 = 4;
+
+/// Largest `--super-resolution`; the upscale is meant for small pictures.
+const MAX_SUPER_RESOLUTION: u32 = 16;
+
+/// `--dither-mode ordered` matrix side unless `--bayer-size` is given.
+const DEFAULT_BAYER_SIZE: u32 = 4;
+
+/// Regions `--cel` starts from unless `--cel-regions` is given.
+const DEFAULT_CEL_REGIONS: usize = 96;
+
+/// Smallest `--cel` region unless `--cel-min-size` is given.
+const DEFAULT_CEL_MIN_SIZE: usize = 6;
+
+/// Tile side of the 3-D exports unless `--stud-pitch` is given, as on
+/// common building bricks.
+const DEFAULT_STUD_PITCH: f64 = 8.0;
+/// Pegs a side of `--export-fuse-beads` boards unless `--board-size` is
+/// given, as on large square Perler boards.
+const DEFAULT_BOARD_SIZE: u64 = 29;
+/// Sticker size of `--export-stickersheet` unless `--sticker-diameter-mm`
+/// is given, that of common round sticker sheets.
+const DEFAULT_STICKER_DIAMETER_MM: f32 = 10.0;
+/// Pad spacing of `--export-kicad` unless `--kicad-pitch` is given.
+const DEFAULT_KICAD_PITCH: f64 = 5.0;
+/// Satin stitches a millimetre in `--export-embroidery` unless
+/// `--stitch-density` is given.
+const DEFAULT_STITCH_DENSITY: f32 = 4.0;
+/// WebP quality unless `--webp-quality` is given: lossless, the only
+/// quality the built-in encoder writes.
+const DEFAULT_WEBP_QUALITY: u8 = 100;
+/// Height of 3-D tiles unless `--tile-height` or the color says otherwise.
+const DEFAULT_TILE_HEIGHT: f64 = 3.2;
+
+/// RGB distance `--key-color` reaches unless `--key-tolerance` is given;
+/// enough to take the anti-aliased fringe of a flat background.
+const DEFAULT_KEY_TOLERANCE: f32 = 40.0;
+
+/// RGB distance a measured color may be off unless `--measurement-tolerance`
+/// is given; about what two lots of one bead color differ by.
+const DEFAULT_MEASUREMENT_TOLERANCE: f32 = 20.0;
+
+/// Smallest region kept unless `--min-region-size` says otherwise; every
+/// region has at least one tile, so none are merged.
+const DEFAULT_MIN_REGION_SIZE: usize = 1;
+
+/// Tiles per build batch unless `--batch-size` is given.
+const DEFAULT_BATCH_SIZE: u64 = 50;
+/// Pixels `--tile-corner-fold` cuts unless `--fold-size` is given.
+const DEFAULT_FOLD_SIZE: f32 = 4.0;
+
+/// Colors a `--palette-from-png` swatch may have unless
+/// `--max-palette-colors` says otherwise.
+const DEFAULT_MAX_PALETTE_COLORS: usize = 256;
+
+/// Playback at the speed it was recorded unless `--replay-speed` is given.
+const DEFAULT_REPLAY_SPEED: f32 = 1.0;
+
+/// `--export-sprite-animation` timing unless `--fps` and `--pause-ms` are
+/// given.
+const DEFAULT_FPS: u16 = 30;
+const DEFAULT_PAUSE_MS: u16 = 2000;
+
+/// How long `--export-lottie` takes unless `--duration-ms` is given.
+const DEFAULT_DURATION_MS: u32 = 5000;
+
+/// Tiles brighter than half raise their Braille dot unless
+/// `--braille-threshold` says otherwise.
+const DEFAULT_BRAILLE_THRESHOLD: f32 = 0.5;
+
+/// `--export-png-thumbnail` size unless `--thumb-size` is given.
+const DEFAULT_THUMB_SIZE: u32 = 256;
+
+/// `--export-png-progress-bar` height unless `--bar-height-px` is given.
+const DEFAULT_BAR_HEIGHT_PX: u32 = 24;
+
+/// `--export-svg-animation` length unless `--duration-s` is given.
+const DEFAULT_DURATION_S: f64 = 10.0;
+
+/// `--export-kml` tile size unless `--tile-size-m` is given.
+const DEFAULT_TILE_SIZE_M: f64 = 10.0;
+
+/// `--export-ics` tiles a day unless `--tiles-per-day` is given.
+const DEFAULT_TILES_PER_DAY: u64 = 50;
+
+/// `--export-png-watermarked-grid` spacing unless `--grid-interval` is
+/// given.
+const DEFAULT_GRID_INTERVAL: u32 = 8;
+
+/// `--export-png-composited` opacity unless `--blend-alpha` is given.
+const DEFAULT_BLEND_ALPHA: f32 = 0.7;
+
+/// `--color-cycle-animation` turns a tenth of the wheel a second unless
+/// `--cycle-speed` is given.
+const DEFAULT_CYCLE_SPEED: f32 = 0.1;
+
+/// Auto-save period unless `--autosave-interval` says otherwise.
+const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// A rotating pair, so one copy is always complete.
+const DEFAULT_AUTOSAVE_BACKUPS: usize = 2;
+
+/// Factor used when `--emphasize-palette-color` is not followed by
+/// `--emphasis-factor`.
+const DEFAULT_EMPHASIS_FACTOR: f32 = 0.5;
+
+impl Options {
+    /// Reads the process arguments; none at all reopen the most recent
+    /// project, `--open-recent N` stands for the arguments of the Nth, and
+    /// a lone `.zip` opens the project bundled in it.
+    pub fn from_env() -> Options {
+        let args = recent::expand(env::args().skip(1).collect());
+        match bundle::opened(&args) {
+            Some(path) => {
+                let mut options = Options::parse(bundle::open(path));
+                options.bundle = Some(path.to_string());
+                options
+            }
+            None => Options::parse(args),
+        }
+    }
+
+    /// Parses the arguments that follow the program name.
+    pub fn parse(args: Vec<String>) -> Options {
+        let mut options = Options {
+            args: args.clone(),
+            texture_k: DEFAULT_TEXTURE_K,
+            key_tolerance: DEFAULT_KEY_TOLERANCE,
+            measurement_tolerance: DEFAULT_MEASUREMENT_TOLERANCE,
+            min_region_size: DEFAULT_MIN_REGION_SIZE,
+            adaptive_max_cell: DEFAULT_ADAPTIVE_MAX_CELL,
+            cel_regions: DEFAULT_CEL_REGIONS,
+            bayer_size: DEFAULT_BAYER_SIZE,
+            cel_min_size: DEFAULT_CEL_MIN_SIZE,
+            batch_size: DEFAULT_BATCH_SIZE,
+            fold_size: DEFAULT_FOLD_SIZE,
+            stud_pitch: DEFAULT_STUD_PITCH,
+            kicad_pitch: DEFAULT_KICAD_PITCH,
+            stitch_density: DEFAULT_STITCH_DENSITY,
+            board_size: DEFAULT_BOARD_SIZE,
+            sticker_diameter_mm: DEFAULT_STICKER_DIAMETER_MM,
+            export_cross_stitch: None,
+            export_color_book: None,
+            tile_height: DEFAULT_TILE_HEIGHT,
+            output_scale: DEFAULT_TILE_PX,
+            webp_quality: DEFAULT_WEBP_QUALITY,
+            replay_speed: DEFAULT_REPLAY_SPEED,
+            max_palette_colors: DEFAULT_MAX_PALETTE_COLORS,
+            fps: DEFAULT_FPS,
+            pause_ms: DEFAULT_PAUSE_MS,
+            duration_ms: DEFAULT_DURATION_MS,
+            braille_threshold: DEFAULT_BRAILLE_THRESHOLD,
+            thumb_size: DEFAULT_THUMB_SIZE,
+            bar_height_px: DEFAULT_BAR_HEIGHT_PX,
+            tile_size_m: DEFAULT_TILE_SIZE_M,
+            duration_s: DEFAULT_DURATION_S,
+            blend_alpha: DEFAULT_BLEND_ALPHA,
+            tiles_per_day: DEFAULT_TILES_PER_DAY,
+            grid_interval: DEFAULT_GRID_INTERVAL,
+            cycle_speed: DEFAULT_CYCLE_SPEED,
+            autosave_interval: Interval(DEFAULT_AUTOSAVE_INTERVAL),
+            autosave_backups: DEFAULT_AUTOSAVE_BACKUPS,
+            ..Options::default()
+        };
+        let mut positional: Vec<String> = Vec::new();
+        let mut positions = Vec::new();
+        let total = args.len();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--assume-srgb" => options.assume_srgb = true,
+                "--scale-mode" => options.scale_mode = parse_value(&mut args, &arg),
+                "--resize-anchor" => options.resize_anchor = parse_value(&mut args, &arg),
+                "--super-resolution" => {
+                    options.super_resolution = Some(parse_value(&mut args, &arg))
+                }
+                "--upscale-filter" => options.upscale_filter = parse_value(&mut args, &arg),
+                "--key-color" => options.key_color = Some(parse_value(&mut args, &arg)),
+                "--key-tolerance" => options.key_tolerance = parse_value(&mut args, &arg),
+                "--count-per-color" => options.count_per_color = Some(parse_value(&mut args, &arg)),
+                "--count-overlay" => options.count_overlay = Some(next_value(&mut args, &arg)),
+                "--random-palette" => options.random_palette = Some(parse_value(&mut args, &arg)),
+                "--palette-from-image" => {
+                    options.palette_from_image = Some(parse_value(&mut args, &arg))
+                }
+                "--palette-gradient" => {
+                    options.palette_gradient = Some(parse_value(&mut args, &arg))
+                }
+                "--palette-from-css" => {
+                    options.palette_from_css = Some(next_value(&mut args, &arg))
+                }
+                "--palette-from-png" => {
+                    options.palette_from_png = Some(next_value(&mut args, &arg))
+                }
+                "--max-palette-colors" => options.max_palette_colors = parse_value(&mut args, &arg),
+                "--from" => options.gradient_from = Some(next_value(&mut args, &arg)),
+                "--to" => options.gradient_to = Some(next_value(&mut args, &arg)),
+                "--gradient-space" => options.gradient_space = parse_value(&mut args, &arg),
+                "--seed" => options.seed = Some(parse_value(&mut args, &arg)),
+                "--corner-tiles" => options.corner_tiles = Some(next_value(&mut args, &arg)),
+                "--merge-small-regions" => {
+                    options.merge_small_regions = parse_value(&mut args, &arg)
+                }
+                "--min-region-size" => options.min_region_size = parse_value(&mut args, &arg),
+                "--normalize-counts" => options.normalize_counts = true,
+                "--auto-count" => options.auto_count = true,
+                "--gamma-palette" => options.gamma_palette = Some(parse_value(&mut args, &arg)),
+                "--palette-validate-physical" => {
+                    options.palette_validate_physical = Some(next_value(&mut args, &arg))
+                }
+                "--measurement-tolerance" => {
+                    options.measurement_tolerance = parse_value(&mut args, &arg)
+                }
+                "--color-reduce" => options.color_reduce = Some(parse_value(&mut args, &arg)),
+                "--quantize-palette" => {
+                    options.quantize_palette = Some(parse_value(&mut args, &arg))
+                }
+                "--palette-out" => options.palette_out = Some(next_value(&mut args, &arg)),
+                "--palette-diversity-score" => options.palette_diversity_score = true,
+                "--color-distance-table" => options.color_distance_table = true,
+                "--threshold" => options.distance_threshold = Some(parse_value(&mut args, &arg)),
+                "--stats" => options.stats = true,
+                "--color-affinity-map" => options.color_affinity_map = true,
+                "--color-report-format" => {
+                    options.color_report_format = parse_value(&mut args, &arg)
+                }
+                "--palette-html-preview" => {
+                    options.palette_html_preview = Some(next_value(&mut args, &arg))
+                }
+                "--verbose" => options.verbose = true,
+                "--log-level" => options.log_level = parse_value(&mut args, &arg),
+                "--show-grid-coords" => options.show_grid_coords = true,
+                "--color-cycle-animation" => options.color_cycle_animation = true,
+                "--cycle-speed" => options.cycle_speed = parse_value(&mut args, &arg),
+                "--interactive-zoom-to-color" => {
+                    options.interactive_zoom_to_color = Some(next_value(&mut args, &arg))
+                }
+                "--tile-corner-indicator" => options.tile_corner_indicator = true,
+                "--tile-debug-index" => options.tile_debug_index = true,
+                "--tile-count-heatmap" => options.tile_count_heatmap = true,
+                "--tile-corner-fold" => {
+                    options.tile_corner_fold = Some(parse_value(&mut args, &arg))
+                }
+                "--fold-size" => options.fold_size = parse_value(&mut args, &arg),
+                "--batch-size" => options.batch_size = parse_value(&mut args, &arg),
+                "--texture-strength" => {
+                    options.texture_strength = Some(parse_value(&mut args, &arg))
+                }
+                "--texture-k" => options.texture_k = parse_value(&mut args, &arg),
+                "--restarts" => options.restarts = Some(parse_value(&mut args, &arg)),
+                "--weighted-shuffle" => options.weighted_shuffle = true,
+                "--shuffle-strategy" => options.shuffle_strategy = parse_value(&mut args, &arg),
+                "--dither-mode" => options.dither_mode = Some(parse_value(&mut args, &arg)),
+                "--bayer-size" => options.bayer_size = parse_value(&mut args, &arg),
+                "--no-overlap" => options.no_overlap = true,
+                "--adaptive-cells" => options.adaptive_cells = Some(parse_value(&mut args, &arg)),
+                "--hex-grid" => options.hex_grid = Some(parse_value(&mut args, &arg)),
+                "--adaptive-max-cell" => options.adaptive_max_cell = parse_value(&mut args, &arg),
+                "--cel" => options.cel = true,
+                "--cel-regions" => options.cel_regions = parse_value(&mut args, &arg),
+                "--cel-min-size" => options.cel_min_size = parse_value(&mut args, &arg),
+                "--export-segments" => options.export_segments = Some(next_value(&mut args, &arg)),
+                "--compare" => options.compare.push(next_value(&mut args, &arg)),
+                "--slideshow" => options.slideshow = Some(next_value(&mut args, &arg)),
+                "--interval" => options.interval = parse_value(&mut args, &arg),
+                "--shuffle" => options.shuffle = true,
+                "--export-timelapse-json" => {
+                    options.export_timelapse_json = Some(next_value(&mut args, &arg))
+                }
+                "--replay-timelapse" => {
+                    options.replay_timelapse = Some(next_value(&mut args, &arg))
+                }
+                "--replay-speed" => options.replay_speed = parse_value(&mut args, &arg),
+                "--session" => options.session = Some(next_value(&mut args, &arg)),
+                "--autosave-interval" => options.autosave_interval = parse_value(&mut args, &arg),
+                "--autosave-backups" => options.autosave_backups = parse_value(&mut args, &arg),
+                "--restore-autosave" => options.restore_autosave = true,
+                "--headless" => options.headless = true,
+                "--error-format" => options.error_format = parse_value(&mut args, &arg),
+                "--summary-json" => options.summary_json = true,
+                "--interactive-palette-editor" => options.interactive_palette_editor = true,
+                "--palette-cluster-visualization" => options.palette_cluster_visualization = true,
+                "--symmetry" => options.symmetry = parse_value(&mut args, &arg),
+                "--legend-sort" => options.legend_sort = parse_value(&mut args, &arg),
+                "--show-original-colors" => options.show_original_colors = true,
+                "--tile-size-from-window" => options.tile_size_from_window = true,
+                "--keybindings" => options.keybindings = Some(next_value(&mut args, &arg)),
+                "--print-keys" => options.print_keys = true,
+                "--color-config-schema" => options.color_config_schema = true,
+                "--parse-strict" => options.parse_strict = true,
+                "--recent" => options.recent = true,
+                "--forget-recent" => options.forget_recent = true,
+                "--output" => options.output = Some(next_value(&mut args, &arg)),
+                "--export-webp" => options.export_webp = Some(next_value(&mut args, &arg)),
+                "--webp-quality" => options.webp_quality = parse_value(&mut args, &arg),
+                "--tile-label" => options.tile_label = true,
+                "--tile-label-font" => {
+                    options.tile_label = true;
+                    options.tile_label_font = Some(next_value(&mut args, &arg))
+                }
+                "--transparent-bg" => options.transparent_bg = true,
+                "--export-png-progress-bar" => options.progress_bar = true,
+                "--bar-height-px" => options.bar_height_px = parse_value(&mut args, &arg),
+                "--output-scale" => options.output_scale = parse_value(&mut args, &arg),
+                "--brightness-jitter" => options.brightness_jitter = parse_value(&mut args, &arg),
+                "--margin" => options.margin = parse_value(&mut args, &arg),
+                "--margin-color" => options.margin_color = parse_value(&mut args, &arg),
+                "--text" => options.text = Some(next_value(&mut args, &arg)),
+                "--text-pos" => options.text_position = parse_value(&mut args, &arg),
+                "--text-color" => options.text_color = Some(next_value(&mut args, &arg)),
+                "--text-bg" => options.text_background = Some(next_value(&mut args, &arg)),
+                "--force-mask" => options.force_mask = Some(next_value(&mut args, &arg)),
+                "--frame" => options.frame = parse_value(&mut args, &arg),
+                "--frame-color" => options.frame_color = Some(next_value(&mut args, &arg)),
+                "--caption" => options.caption = Some(next_value(&mut args, &arg)),
+                "--export-gimp-palette" => {
+                    options.export_gimp_palette = Some(next_value(&mut args, &arg))
+                }
+                "--export-css-variables" => {
+                    options.export_css_variables = Some(next_value(&mut args, &arg))
+                }
+                "--css-prefix" => options.css_prefix = Some(next_value(&mut args, &arg)),
+                "--export-android-theme" => {
+                    options.export_android_theme = Some(next_value(&mut args, &arg))
+                }
+                "--export-game-asset" => {
+                    options.export_game_asset = Some(next_value(&mut args, &arg))
+                }
+                "--compress-rle" => options.compress_rle = true,
+                "--export-xls" => options.export_xls = Some(next_value(&mut args, &arg)),
+                "--export-bricks" => options.export_bricks = Some(next_value(&mut args, &arg)),
+                "--export-openscad" => options.export_openscad = Some(next_value(&mut args, &arg)),
+                "--export-blender" => options.export_blender = Some(next_value(&mut args, &arg)),
+                "--export-stl" => options.export_stl = Some(next_value(&mut args, &arg)),
+                "--export-obj" => options.export_obj = Some(next_value(&mut args, &arg)),
+                "--export-fritzing" => options.export_fritzing = Some(next_value(&mut args, &arg)),
+                "--export-kicad" => options.export_kicad = Some(next_value(&mut args, &arg)),
+                "--kicad-pitch" => options.kicad_pitch = parse_value(&mut args, &arg),
+                "--export-embroidery" => {
+                    options.export_embroidery = Some(next_value(&mut args, &arg))
+                }
+                "--stitch-density" => options.stitch_density = parse_value(&mut args, &arg),
+                "--save-bundle" => options.save_bundle = Some(next_value(&mut args, &arg)),
+                "--export-png-indexed" => {
+                    options.export_png_indexed = Some(next_value(&mut args, &arg))
+                }
+                "--export-png-zoom" => {
+                    let size = parse_value(&mut args, &arg);
+                    options.export_png_zoom = Some((size, next_value(&mut args, &arg)));
+                }
+                "--export-png-band" => options.export_png_band = Some(next_value(&mut args, &arg)),
+                "--band-with-names" => options.band_with_names = true,
+                "--stud-pitch" => options.stud_pitch = parse_value(&mut args, &arg),
+                "--tile-height" => options.tile_height = parse_value(&mut args, &arg),
+                "--studs" => options.studs = true,
+                "--brick-sizes" => options.brick_sizes = parse_value(&mut args, &arg),
+                "--output-aseprite" => options.output_aseprite = Some(next_value(&mut args, &arg)),
+                "--export-xcf" => options.export_xcf = Some(next_value(&mut args, &arg)),
+                "--export-ics" => options.export_ics = Some(next_value(&mut args, &arg)),
+                "--tiles-per-day" => options.tiles_per_day = parse_value(&mut args, &arg),
+                "--start-date" => options.start_date = Some(parse_value(&mut args, &arg)),
+                "--export-discord-embed" => {
+                    options.export_discord_embed = Some(next_value(&mut args, &arg))
+                }
+                "--webhook-url" => options.webhook_url = Some(next_value(&mut args, &arg)),
+                "--window-title" => options.window_title = Some(next_value(&mut args, &arg)),
+                "--export-latex" => options.export_latex = Some(next_value(&mut args, &arg)),
+                "--latex-caption" => options.latex_caption = Some(next_value(&mut args, &arg)),
+                "--export-png-split-rgb" => {
+                    options.export_png_split_rgb = Some(next_value(&mut args, &arg))
+                }
+                "--export-png-watermarked-grid" => {
+                    options.export_png_watermarked_grid = Some(next_value(&mut args, &arg))
+                }
+                "--grid-interval" => options.grid_interval = parse_value(&mut args, &arg),
+                "--export-openraster" => {
+                    options.export_openraster = Some(next_value(&mut args, &arg))
+                }
+                "--export-minecraft-map" => {
+                    options.export_minecraft_map = Some(next_value(&mut args, &arg))
+                }
+                "--minecraft-version" => options.minecraft_version = parse_value(&mut args, &arg),
+                "--debug-dump" => options.debug_dump = Some(next_value(&mut args, &arg)),
+                "--config" => options.config = Some(next_value(&mut args, &arg)),
+                "--export" => options.export_preset = Some(next_value(&mut args, &arg)),
+                "--report" => options.report = Some(next_value(&mut args, &arg)),
+                "--export-json" => options.export_json = Some(next_value(&mut args, &arg)),
+                "--export-json-schema-validated" => options.export_json_schema_validated = true,
+                "--palette-sort-by-usage" => options.palette_sort_by_usage = true,
+                "--diff" => {
+                    let before = next_value(&mut args, &arg);
+                    options.diff = Some((before, next_value(&mut args, &arg)));
+                }
+                "--diff-image" => options.diff_image = Some(next_value(&mut args, &arg)),
+                "--export-png-diff" => {
+                    let before = next_value(&mut args, &arg);
+                    let after = next_value(&mut args, &arg);
+                    options.export_png_diff = Some((before, after, next_value(&mut args, &arg)));
+                }
+                "--export-pdf-color-sheets" => {
+                    options.export_pdf_color_sheets = Some(next_value(&mut args, &arg))
+                }
+                "--export-label-sheet" => {
+                    options.export_label_sheet = Some(next_value(&mut args, &arg))
+                }
+                "--export-fuse-beads" => {
+                    options.export_fuse_beads = Some(next_value(&mut args, &arg))
+                }
+                "--board-size" => options.board_size = parse_value(&mut args, &arg),
+                "--export-stickersheet" => {
+                    options.export_stickersheet = Some(next_value(&mut args, &arg))
+                }
+                "--sticker-diameter-mm" => {
+                    options.sticker_diameter_mm = parse_value(&mut args, &arg)
+                }
+                "--export-cross-stitch" => {
+                    options.export_cross_stitch = Some(next_value(&mut args, &arg))
+                }
+                "--export-color-book" => {
+                    options.export_color_book = Some(next_value(&mut args, &arg))
+                }
+                "--export-pdf-bom" => options.export_pdf_bom = Some(next_value(&mut args, &arg)),
+                "--export-textile-chart" => {
+                    options.export_textile_chart = Some(next_value(&mut args, &arg))
+                }
+                "--textile-row-direction" => {
+                    options.textile_row_direction = parse_value(&mut args, &arg)
+                }
+                "--textile-chart-style" => {
+                    options.textile_chart_style = parse_value(&mut args, &arg)
+                }
+                "--export-packing-list" => {
+                    options.export_packing_list = Some(next_value(&mut args, &arg))
+                }
+                "--format" => options.packing_list_format = parse_value(&mut args, &arg),
+                "--export-png-sections" => {
+                    options.export_png_sections = Some(next_value(&mut args, &arg))
+                }
+                "--section-axis" => options.section_axis = parse_value(&mut args, &arg),
+                "--export-sprite-animation" => {
+                    options.export_sprite_animation = Some(next_value(&mut args, &arg))
+                }
+                "--fps" => options.fps = parse_value(&mut args, &arg),
+                "--pause-ms" => options.pause_ms = parse_value(&mut args, &arg),
+                "--export-lottie" => options.export_lottie = Some(next_value(&mut args, &arg)),
+                "--duration-ms" => options.duration_ms = parse_value(&mut args, &arg),
+                "--export-braille" => options.export_braille = Some(next_value(&mut args, &arg)),
+                "--braille-threshold" => options.braille_threshold = parse_value(&mut args, &arg),
+                "--ansi-color" => options.ansi_color = true,
+                "--export-png-thumbnail" => {
+                    options.export_png_thumbnail = Some(next_value(&mut args, &arg))
+                }
+                "--thumb-size" => options.thumb_size = parse_value(&mut args, &arg),
+                "--export-svg" => options.export_svg = Some(next_value(&mut args, &arg)),
+                "--export-svg-animation" => {
+                    options.export_svg_animation = Some(next_value(&mut args, &arg))
+                }
+                "--duration-s" => options.duration_s = parse_value(&mut args, &arg),
+                "--export-svg-filters" => options.svg_filters = parse_value(&mut args, &arg),
+                "--export-kml" => options.export_kml = Some(next_value(&mut args, &arg)),
+                "--center-lat" => options.center_lat = Some(parse_value(&mut args, &arg)),
+                "--center-lon" => options.center_lon = Some(parse_value(&mut args, &arg)),
+                "--tile-size-m" => options.tile_size_m = parse_value(&mut args, &arg),
+                "--rotate-degrees" => options.rotate_degrees = parse_value(&mut args, &arg),
+                "--export-png-composited" => {
+                    options.export_png_composited = Some(next_value(&mut args, &arg))
+                }
+                "--blend-alpha" => options.blend_alpha = parse_value(&mut args, &arg),
+                "--export-png-numbered-grid" => {
+                    options.export_png_numbered_grid = Some(next_value(&mut args, &arg))
+                }
+                "--export-png-column-labels" => {
+                    options.export_png_column_labels = Some(next_value(&mut args, &arg))
+                }
+                "--number-cell-size" => {
+                    options.number_cell_size = Some(parse_value(&mut args, &arg))
+                }
+                "--export-color-steps" => {
+                    options.export_color_steps = Some(next_value(&mut args, &arg))
+                }
+                "--export-png-atlas" => {
+                    options.export_png_atlas = Some(next_value(&mut args, &arg))
+                }
+                "--atlas-layout" => options.atlas_layout = Some(parse_value(&mut args, &arg)),
+                "--export-stardew" => options.export_stardew = Some(next_value(&mut args, &arg)),
+                "--export-before-after" => {
+                    options.export_before_after = Some(next_value(&mut args, &arg))
+                }
+                "--before-after-layout" => {
+                    options.before_after_layout = parse_value(&mut args, &arg)
+                }
+                "--before-after-size" => {
+                    options.before_after_size = Some(parse_value(&mut args, &arg))
+                }
+                "--before-after-caption" => options.before_after_caption = true,
+                "--before-after-studs" => options.before_after_studs = true,
+                "--export-qrcode-palette" => {
+                    options.export_qrcode_palette = Some(next_value(&mut args, &arg))
+                }
+                "--region-palette-override" => {
+                    options.region_palette_override = parse_value(&mut args, &arg)
+                }
+                "--color-metric" => options.color_metric = parse_value(&mut args, &arg),
+                "--emphasize-palette-color" => options
+                    .emphasis
+                    .push((next_value(&mut args, &arg), DEFAULT_EMPHASIS_FACTOR)),
+                "--emphasis-factor" => {
+                    let factor: f32 = parse_value(&mut args, &arg);
+                    let (_, last) = options
+                        .emphasis
+                        .last_mut()
+                        .expect("--emphasis-factor must follow --emphasize-palette-color");
+                    *last = factor;
+                }
+                flag if flag.starts_with("--") => panic!("Unknown flag {flag}"),
+                _ => {
+                    positions.push(total - args.len() - 1);
+                    positional.push(arg)
+                }
+            }
+        }
+        options.flags = options
+            .args
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !positions.contains(index))
+            .map(|(_, arg)| arg.clone())
+            .collect();
+
+        if options.min_region_size == 0 {
+            panic!("--min-region-size must be at least 1")
+        }
+        if options.webp_quality > 100 {
+            panic!("--webp-quality must be from 0 to 100")
+        }
+        if options.output_scale == 0 {
+            panic!("--output-scale must be at least 1")
+        }
+        if !(0.0..=1.0).contains(&options.brightness_jitter) {
+            panic!("--brightness-jitter must be between 0 and 1")
+        }
+        if options
+            .texture_strength
+            .is_some_and(|strength| strength < 0.0)
+        {
+            panic!("--texture-strength cannot be negative")
+        }
+        if options.texture_k == 0 {
+            panic!("--texture-k must be at least 1")
+        }
+        if options.restarts == Some(0) {
+            panic!("--restarts must be at least 1")
+        }
+        if options.adaptive_cells.is_some() {
+            let size = options.adaptive_max_cell;
+            if !size.is_power_of_two()
+                || !X_SIZE.is_multiple_of(size)
+                || !Y_SIZE.is_multiple_of(size)
+            {
+                panic!("--adaptive-max-cell must be a power of two dividing {X_SIZE}x{Y_SIZE}")
+            }
+            if options.hex_grid.is_some() {
+                panic!("--adaptive-cells needs square tiles; drop --hex-grid")
+            }
+            if options.restarts.is_some() || options.texture_strength.is_some() {
+                panic!("--adaptive-cells cannot be combined with --restarts or --texture-strength")
+            }
+        }
+        if options.cel {
+            if options.cel_regions == 0 {
+                panic!("--cel-regions must be at least 1")
+            }
+            if options.adaptive_cells.is_some()
+                || options.restarts.is_some()
+                || options.texture_strength.is_some()
+            {
+                panic!("--cel cannot be combined with --adaptive-cells, --restarts, or --texture-strength")
+            }
+        } else if options.export_segments.is_some() {
+            panic!("--export-segments needs --cel")
+        }
+        if options.compress_rle && options.export_game_asset.is_none() {
+            panic!("--compress-rle needs --export-game-asset")
+        }
+        if options.key_color.is_some() {
+            if options.key_tolerance < 0.0 {
+                panic!("--key-tolerance cannot be negative")
+            }
+            if options.adaptive_cells.is_some() || options.cel {
+                panic!("--key-color cannot be combined with --adaptive-cells or --cel")
+            }
+            if options.session.is_some() || options.export_json.is_some() {
+                panic!("--key-color leaves holes that --session and --export-json cannot record")
+            }
+        }
+        if options.shuffle_strategy == ShuffleStrategy::WeightedCount
+            && (options.restarts.is_some() || options.no_overlap || options.cel)
+        {
+            panic!(
+                "--shuffle-strategy weighted-count orders the tiles itself; drop --restarts, \
+                 --no-overlap, and --cel"
+            )
+        }
+        if !options
+            .super_resolution
+            .is_none_or(|factor| (2..=MAX_SUPER_RESOLUTION).contains(&factor))
+        {
+            panic!("--super-resolution must be between 2 and {MAX_SUPER_RESOLUTION}")
+        }
+        if options.upscale_filter != UpscaleFilter::default() && options.super_resolution.is_none()
+        {
+            panic!("--upscale-filter needs --super-resolution")
+        }
+        if options.bayer_size != DEFAULT_BAYER_SIZE && options.dither_mode.is_none() {
+            panic!("--bayer-size needs --dither-mode ordered")
+        }
+        if ![4, 8].contains(&options.bayer_size) {
+            panic!("--bayer-size must be 4 or 8")
+        }
+        // Cells and regions are solved from their mean color, which would
+        // average the dither away.
+        if options.dither_mode.is_some() && (options.adaptive_cells.is_some() || options.cel) {
+            panic!("--dither-mode dithers single tiles; drop --adaptive-cells and --cel")
+        }
+        if options.no_overlap
+            && (options.restarts.is_some()
+                || options.texture_strength.is_some()
+                || options.weighted_shuffle
+                || options.adaptive_cells.is_some()
+                || options.cel)
+        {
+            panic!(
+                "--no-overlap solves in raster order; drop --restarts, --texture-strength, \
+                 --weighted-shuffle, --adaptive-cells, and --cel"
+            )
+        }
+        if options.batch_size == 0 {
+            panic!("--batch-size must be at least 1")
+        }
+        if options.tile_corner_indicator && options.hex_grid.is_some() {
+            panic!("--tile-corner-indicator needs square tiles; drop --hex-grid")
+        }
+        if options.tile_corner_fold.is_some() && options.hex_grid.is_some() {
+            panic!("--tile-corner-fold needs square tiles; drop --hex-grid")
+        }
+        if !(options.fold_size > 0.0 && options.fold_size.is_finite()) {
+            panic!("--fold-size must be positive")
+        }
+        if options.before_after_size == Some(0) {
+            panic!("--before-after-size must be at least 1")
+        }
+        if options.before_after_studs && options.hex_grid.is_some() {
+            panic!("--before-after-studs needs square tiles; drop --hex-grid")
+        }
+        if options.atlas_layout == Some(0) {
+            panic!("--atlas-layout must be at least 1")
+        }
+        if options.autosave_backups == 0 {
+            panic!("--autosave-backups must be at least 1")
+        }
+        if options.frame > 0 && options.frame_color.is_none() {
+            panic!("--frame needs --frame-color")
+        }
+        if options.export_bricks.is_some() && options.hex_grid.is_some() {
+            panic!("--export-bricks needs square tiles; drop --hex-grid")
+        }
+        if options.export_png_zoom.is_some() && options.hex_grid.is_some() {
+            panic!("--export-png-zoom needs square tiles; drop --hex-grid")
+        }
+        if options.export_openscad.is_some() && options.hex_grid.is_some() {
+            panic!("--export-openscad needs square tiles; drop --hex-grid")
+        }
+        if options.export_blender.is_some() && options.hex_grid.is_some() {
+            panic!("--export-blender needs square tiles; drop --hex-grid")
+        }
+        if (options.export_stl.is_some() || options.export_obj.is_some())
+            && options.hex_grid.is_some()
+        {
+            panic!("--export-stl and --export-obj need square tiles; drop --hex-grid")
+        }
+        if options.board_size == 0 {
+            panic!("--board-size must be at least 1")
+        }
+        if options.export_fuse_beads.is_some() && options.hex_grid.is_some() {
+            panic!("--export-fuse-beads needs square tiles; drop --hex-grid")
+        }
+        if options.export_cross_stitch.is_some() && options.hex_grid.is_some() {
+            panic!("--export-cross-stitch needs square tiles; drop --hex-grid")
+        }
+        if options.packing_list_format != PackingListFormat::default()
+            && options.export_packing_list.is_none()
+        {
+            panic!("--format needs --export-packing-list")
+        }
+        if options.distance_threshold.is_some() && !options.color_distance_table {
+            panic!("--threshold needs --color-distance-table")
+        }
+        if options.fps == 0 {
+            panic!("--fps must be positive")
+        }
+        if !options.svg_filters.is_empty() && options.export_svg.is_none() {
+            panic!("--export-svg-filters needs --export-svg")
+        }
+        if options.export_svg.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-svg needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        if options.export_svg_animation.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!(
+                "--export-svg-animation needs square tiles; drop --hex-grid and --adaptive-cells"
+            )
+        }
+        if options.duration_s != DEFAULT_DURATION_S && options.export_svg_animation.is_none() {
+            panic!("--duration-s needs --export-svg-animation")
+        }
+        if !(options.duration_s > 0.0 && options.duration_s.is_finite()) {
+            panic!("--duration-s must be positive")
+        }
+        if options.export_kml.is_some()
+            && (options.center_lat.is_none() || options.center_lon.is_none())
+        {
+            panic!("--export-kml needs --center-lat and --center-lon")
+        }
+        if options.export_kml.is_none()
+            && (options.center_lat.is_some()
+                || options.center_lon.is_some()
+                || options.tile_size_m != DEFAULT_TILE_SIZE_M
+                || options.rotate_degrees != 0.0)
+        {
+            panic!(
+                "--center-lat, --center-lon, --tile-size-m, and --rotate-degrees need --export-kml"
+            )
+        }
+        // Longitude degrees shrink to nothing at the poles.
+        if !options.center_lat.is_none_or(|lat| lat.abs() < 89.0) {
+            panic!("--center-lat must be between -89 and 89")
+        }
+        if !options
+            .center_lon
+            .is_none_or(|lon| (-180.0..=180.0).contains(&lon))
+        {
+            panic!("--center-lon must be between -180 and 180")
+        }
+        if !(options.tile_size_m > 0.0 && options.tile_size_m.is_finite()) {
+            panic!("--tile-size-m must be positive")
+        }
+        if !options.rotate_degrees.is_finite() {
+            panic!("--rotate-degrees must be a number")
+        }
+        if options.export_kml.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-kml needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        if !(0.0..=1.0).contains(&options.blend_alpha) {
+            panic!("--blend-alpha must be between 0 and 1")
+        }
+        if options.blend_alpha != DEFAULT_BLEND_ALPHA && options.export_png_composited.is_none() {
+            panic!("--blend-alpha needs --export-png-composited")
+        }
+        if options.export_png_composited.is_some() && options.hex_grid.is_some() {
+            panic!("--export-png-composited needs square tiles; drop --hex-grid")
+        }
+        if options.number_cell_size.is_some()
+            && options.export_png_numbered_grid.is_none()
+            && options.export_png_column_labels.is_none()
+        {
+            panic!(
+                "--number-cell-size needs --export-png-numbered-grid or --export-png-column-labels"
+            )
+        }
+        if options.export_png_column_labels.is_some() && options.hex_grid.is_some() {
+            panic!("--export-png-column-labels needs square tiles; drop --hex-grid")
+        }
+        if options.export_png_numbered_grid.is_some() && options.hex_grid.is_some() {
+            panic!("--export-png-numbered-grid needs square tiles; drop --hex-grid")
+        }
+        if options.export_ics.is_none()
+            && (options.tiles_per_day != DEFAULT_TILES_PER_DAY || options.start_date.is_some())
+        {
+            panic!("--tiles-per-day and --start-date need --export-ics")
+        }
+        if options.tiles_per_day == 0 {
+            panic!("--tiles-per-day must be at least 1")
+        }
+        if options.export_ics.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-ics needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        if options.webhook_url.is_some() && options.export_discord_embed.is_none() {
+            panic!("--webhook-url needs --export-discord-embed")
+        }
+        if !options
+            .webhook_url
+            .as_ref()
+            .is_none_or(|url| url.starts_with("https://"))
+        {
+            panic!("--webhook-url must be an https:// address")
+        }
+        if options.export_json_schema_validated && options.export_json.is_none() {
+            panic!("--export-json-schema-validated needs --export-json")
+        }
+        if options.grid_interval != DEFAULT_GRID_INTERVAL
+            && options.export_png_watermarked_grid.is_none()
+        {
+            panic!("--grid-interval needs --export-png-watermarked-grid")
+        }
+        if options.grid_interval == 0 {
+            panic!("--grid-interval must be at least 1")
+        }
+        if options.export_png_watermarked_grid.is_some() && options.hex_grid.is_some() {
+            panic!("--export-png-watermarked-grid needs square tiles; drop --hex-grid")
+        }
+        if options.latex_caption.is_some() && options.export_latex.is_none() {
+            panic!("--latex-caption needs --export-latex")
+        }
+        if options.export_latex.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-latex needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        // The bitmap font is 7 pixels tall, plus a pixel above and below.
+        if options.bar_height_px < 9 {
+            panic!("--bar-height-px must be at least 9")
+        }
+        if options.thumb_size == 0 {
+            panic!("--thumb-size must be positive")
+        }
+        if !(0.0..=1.0).contains(&options.braille_threshold) {
+            panic!("--braille-threshold must be between 0 and 1")
+        }
+        if options.ansi_color && options.export_braille.is_none() {
+            panic!("--ansi-color needs --export-braille")
+        }
+        if options.export_braille.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-braille needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        if options.duration_ms == 0 {
+            panic!("--duration-ms must be positive")
+        }
+        if options.export_lottie.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-lottie needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        if options.export_sprite_animation.is_some()
+            && (options.hex_grid.is_some() || options.adaptive_cells.is_some())
+        {
+            panic!("--export-sprite-animation needs square tiles; drop --hex-grid and --adaptive-cells")
+        }
+        if options.export_png_sections.is_some() && options.hex_grid.is_some() {
+            panic!("--export-png-sections needs square tiles; drop --hex-grid")
+        }
+        if options.export_textile_chart.is_some() && options.hex_grid.is_some() {
+            panic!("--export-textile-chart needs square tiles; drop --hex-grid")
+        }
+        // A4 is 210mm wide, less the sheets' margins.
+        if !(options.sticker_diameter_mm > 0.0 && options.sticker_diameter_mm <= 180.0) {
+            panic!("--sticker-diameter-mm must be more than 0 and at most 180")
+        }
+        if options.kicad_pitch <= 0.0 {
+            panic!("--kicad-pitch must be positive")
+        }
+        if !(options.stitch_density > 0.0 && options.stitch_density.is_finite()) {
+            panic!("--stitch-density must be positive")
+        }
+        if options.export_embroidery.is_some() && options.hex_grid.is_some() {
+            panic!("--export-embroidery needs square tiles; drop --hex-grid")
+        }
+        if options.stud_pitch <= 0.0 || options.tile_height <= 0.0 {
+            panic!("--stud-pitch and --tile-height must be positive")
+        }
+        if options.text.is_some() && options.text_color.is_none() {
+            panic!("--text needs --text-color")
+        }
+        if (options.text.is_some() || options.force_mask.is_some())
+            && options.adaptive_cells.is_some()
+        {
+            panic!("--text and --force-mask cannot be combined with --adaptive-cells")
+        }
+        if options.diff.is_some()
+            || options.export_png_diff.is_some()
+            || options.print_keys
+            || options.color_config_schema
+            || options.recent
+            || options.forget_recent
+        {
+            return options;
+        }
+
+        if options.summary_json && !options.headless {
+            panic!("--summary-json reports a headless run; add --headless")
+        }
+        if options.slideshow.is_some() && options.headless {
+            panic!("--slideshow needs a window; drop --headless")
+        }
+        if options.tile_debug_index && options.headless {
+            panic!("--tile-debug-index draws in the window; drop --headless")
+        }
+        if options.tile_count_heatmap && options.headless {
+            panic!("--tile-count-heatmap draws in the window; drop --headless")
+        }
+        if options.color_cycle_animation && options.headless {
+            panic!("--color-cycle-animation animates the window; drop --headless")
+        }
+        if options.cycle_speed != DEFAULT_CYCLE_SPEED && !options.color_cycle_animation {
+            panic!("--cycle-speed needs --color-cycle-animation")
+        }
+        if !options.cycle_speed.is_finite() {
+            panic!("--cycle-speed must be a number")
+        }
+        if options.interactive_zoom_to_color.is_some() && options.headless {
+            panic!("--interactive-zoom-to-color zooms the window; drop --headless")
+        }
+        if options.palette_cluster_visualization {
+            if options.headless {
+                panic!("--palette-cluster-visualization needs a window; drop --headless")
+            }
+            if options.slideshow.is_some() {
+                panic!("--palette-cluster-visualization cannot be combined with --slideshow")
+            }
+        }
+        if options.interactive_palette_editor {
+            if options.headless {
+                panic!("--interactive-palette-editor needs a window; drop --headless")
+            }
+            if options.slideshow.is_some() {
+                panic!("--interactive-palette-editor cannot be combined with --slideshow")
+            }
+        }
+        if options.slideshow.is_some() && options.save_bundle.is_some() {
+            panic!("--save-bundle keeps one picture; it cannot be used with --slideshow")
+        }
+        if options.slideshow.is_some() && options.session.is_some() {
+            panic!("--session keeps one picture's edits; it cannot be used with --slideshow")
+        }
+        if options.export_timelapse_json.is_some() {
+            if options.headless {
+                panic!("--export-timelapse-json records the window's animation; drop --headless")
+            }
+            if options.slideshow.is_some() {
+                panic!("--export-timelapse-json records one picture; it cannot be used with --slideshow")
+            }
+        }
+        if !(options.replay_speed > 0.0 && options.replay_speed.is_finite()) {
+            panic!("--replay-speed must be positive")
+        }
+        if let Some(path) = &options.replay_timelapse {
+            if options.headless {
+                panic!("--replay-timelapse plays back in the window; drop --headless")
+            }
+            if options.slideshow.is_some() || options.export_timelapse_json.is_some() {
+                panic!("--replay-timelapse solves nothing; drop --slideshow and --export-timelapse-json")
+            }
+            // The recording holds both the picture and the palette.
+            if !positional.is_empty() {
+                panic!("--replay-timelapse takes no picture or palette")
+            }
+            options.picture_path = path.clone();
+            return options;
+        }
+
+        let generated = [
+            options.random_palette.is_some(),
+            options.palette_from_image.is_some(),
+            options.palette_gradient.is_some(),
+            options.palette_from_css.is_some(),
+            options.palette_from_png.is_some(),
+        ];
+        if generated.iter().filter(|&&made| made).count() > 1 {
+            panic!(
+                "--random-palette, --palette-from-image, --palette-gradient, \
+                 --palette-from-css, and --palette-from-png each make the palette; pick one"
+            )
+        }
+        if options.palette_gradient.is_some()
+            && (options.gradient_from.is_none() || options.gradient_to.is_none())
+        {
+            panic!("--palette-gradient needs both --from and --to")
+        }
+
+        let mut positional = positional.into_iter();
+        // A slideshow picks its own pictures, so only the palette is given.
+        if options.slideshow.is_none() {
+            options.picture_path = positional
+                .next()
+                .expect("Need to provide file paths for picture and color config");
+        }
+        options.color_data = positional.next();
+        match &options.color_data {
+            // A gradient only looks up its ends in the palette file.
+            Some(path)
+                if palette::is_imported(path)
+                    && options.palette_gradient.is_none()
+                    && options.count_per_color.is_none()
+                    && options.count_overlay.is_none() =>
+            {
+                panic!("{path} has no counts; add --count-per-color or --count-overlay")
+            }
+            Some(path) if !path.ends_with(".json") && !palette::is_imported(path) => {
+                panic!("Need to provide filepath for a .json, .gpl, or .aco palette")
+            }
+            None if !generated.contains(&true) => {
+                panic!("Need to provide file paths for picture and color config")
+            }
+            _ => {}
+        }
+        if options.palette_validate_physical.is_some()
+            && (options.color_data.is_none() || generated.contains(&true))
+        {
+            panic!("--palette-validate-physical checks a palette file; drop the made palette")
+        }
+        if options.measurement_tolerance < 0.0 || options.measurement_tolerance.is_nan() {
+            panic!("--measurement-tolerance must not be negative")
+        }
+        options
+    }
+
+    /// How the primary palette is referred to in captions.
+    pub fn palette_label(&self) -> &str {
+        // Made palettes win over the palette file, as when loading.
+        if self.replay_timelapse.is_some() {
+            "recorded palette"
+        } else if self.random_palette.is_some() {
+            "random palette"
+        } else if self.palette_from_image.is_some() {
+            "palette from image"
+        } else if self.palette_gradient.is_some() {
+            "gradient palette"
+        } else if let Some(path) = self
+            .palette_from_css
+            .as_ref()
+            .or(self.palette_from_png.as_ref())
+        {
+            path
+        } else {
+            self.color_data.as_deref().unwrap_or("random palette")
+        }
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| panic!("Missing value for {flag}"))
+}
+
+fn parse_value<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> T {
+    let value = next_value(args, flag);
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid value '{value}' for {flag}"))
+}
@@ -0,0 +1,114 @@
+//! Reading a picture from the system clipboard for `Ctrl+V`.
+//!
+//! The platform's own clipboard tools are asked for the data: `wl-paste` or
+//! `xclip` on Linux, `pngpaste` and `pbpaste` on macOS, and PowerShell on
+//! Windows. When the clipboard holds text naming an image file instead,
+//! that file is loaded.
+
+use image::DynamicImage;
+use std::path::Path;
+use std::process::Command;
+
+/// What the clipboard held.
+pub enum Pasted {
+    Image(DynamicImage),
+    /// A path to a file, maybe a picture, copied as text.
+    File(String),
+}
+
+#[cfg(target_os = "linux")]
+const IMAGE_COMMANDS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline", "--type", "image/png"],
+    &[
+        "xclip",
+        "-selection",
+        "clipboard",
+        "-target",
+        "image/png",
+        "-out",
+    ],
+];
+#[cfg(target_os = "linux")]
+const TEXT_COMMANDS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+];
+
+#[cfg(target_os = "macos")]
+const IMAGE_COMMANDS: &[&[&str]] = &[&["pngpaste", "-"]];
+#[cfg(target_os = "macos")]
+const TEXT_COMMANDS: &[&[&str]] = &[&["pbpaste"]];
+
+#[cfg(target_os = "windows")]
+const IMAGE_COMMANDS: &[&[&str]] = &[&[
+    "powershell",
+    "-NoProfile",
+    "-Command",
+    "Add-Type -AssemblyName System.Windows.Forms; \
+     $image = [Windows.Forms.Clipboard]::GetImage(); \
+     if ($image) { $png = New-Object IO.MemoryStream; \
+     $image.Save($png, [Drawing.Imaging.ImageFormat]::Png); \
+     [Console]::OpenStandardOutput().Write($png.ToArray(), 0, $png.Length) }",
+]];
+#[cfg(target_os = "windows")]
+const TEXT_COMMANDS: &[&[&str]] = &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const IMAGE_COMMANDS: &[&[&str]] = &[];
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const TEXT_COMMANDS: &[&[&str]] = &[];
+
+/// Reads the clipboard, preferring image data over a copied file path.
+/// The message says what was missing otherwise.
+pub fn read() -> Result<Pasted, String> {
+    for command in IMAGE_COMMANDS {
+        if let Some(bytes) = run(command) {
+            if let Ok(img) = image::load_from_memory(&bytes) {
+                return Ok(Pasted::Image(img));
+            }
+        }
+    }
+    let text = TEXT_COMMANDS
+        .iter()
+        .find_map(|command| run(command))
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    match text.as_deref().and_then(path_in_text) {
+        Some(path) => Ok(Pasted::File(path)),
+        None if IMAGE_COMMANDS.is_empty() => {
+            Err("Pasting is not supported on this platform.".to_string())
+        }
+        None => Err("The clipboard holds no image or image file.".to_string()),
+    }
+}
+
+/// Standard output of a command that ran and printed something.
+fn run(command: &[&str]) -> Option<Vec<u8>> {
+    let (program, args) = command.split_first()?;
+    let output = Command::new(program).args(args).output().ok()?;
+    (output.status.success() && !output.stdout.is_empty()).then_some(output.stdout)
+}
+
+/// The existing file named by the first line of `text`, which may be a
+/// `file://` URI as file managers copy them.
+fn path_in_text(text: &str) -> Option<String> {
+    let line = text.lines().next()?.trim();
+    let path = line
+        .strip_prefix("file://")
+        .map_or_else(|| line.to_string(), |uri| uri.replace("%20", " "));
+    Path::new(&path).is_file().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copied_paths_and_uris_name_files() {
+        let readme = concat!(env!("CARGO_MANIFEST_DIR"), "/README.md");
+        assert_eq!(path_in_text(readme).as_deref(), Some(readme));
+        let uri = format!("file://{readme}\nfile:///elsewhere\n");
+        assert_eq!(path_in_text(&uri).as_deref(), Some(readme));
+        assert_eq!(path_in_text("just some words"), None);
+        assert_eq!(path_in_text(""), None);
+    }
+}
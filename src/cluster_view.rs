@@ -0,0 +1,232 @@
+//! The `--palette-cluster-visualization` window: the palette and the
+//! picture's tile colors as points in the RGB cube, to show how well the
+//! palette covers the colors the picture needs.
+//!
+//! Palette colors are large balls and tile colors small dots, each joined
+//! to the palette color its tile was given. Dragging turns the cube. The
+//! points are projected by hand and drawn back to front, since nannou's
+//! shapes are flat and would turn edge-on with the cube.
+
+use crate::{assign, Color, Model};
+use nannou::prelude::*;
+use nannou::window;
+
+const WIDTH: u32 = 560;
+const HEIGHT: u32 = 560;
+/// Radians turned per pixel dragged.
+const DRAG_SPEED: f32 = 0.01;
+/// Eye distance from the cube's center in cube sides; nearer exaggerates
+/// depth.
+const EYE: f32 = 2.5;
+const PALETTE_RADIUS: f32 = 12.0;
+const PIXEL_RADIUS: f32 = 2.0;
+
+pub struct ClusterView {
+    window: window::Id,
+    /// Turn about the vertical axis.
+    yaw: f32,
+    /// Tilt towards the viewer.
+    pitch: f32,
+    /// Where the mouse was when the drag last moved the cube.
+    dragging: Option<Point2>,
+}
+
+/// Opens the window beside the mosaic at a three-quarter view, blue
+/// running up.
+pub fn open(app: &App) -> ClusterView {
+    let window = app
+        .new_window()
+        .title("Palette clusters")
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .mouse_pressed(mouse_pressed)
+        .mouse_moved(mouse_moved)
+        .mouse_released(mouse_released)
+        .build()
+        .expect("Unable to open the palette cluster window.");
+    ClusterView {
+        window,
+        yaw: -PI / 4.0,
+        pitch: 0.5,
+        dragging: None,
+    }
+}
+
+/// Whether `id` is the cluster window, whose events the mosaic ignores.
+pub fn owns(model: &Model, id: window::Id) -> bool {
+    model
+        .cluster_view
+        .as_ref()
+        .is_some_and(|view| view.window == id)
+}
+
+/// Whether the cluster window has focus, so the mouse is over it.
+pub fn has_focus(app: &App, model: &Model) -> bool {
+    model
+        .cluster_view
+        .as_ref()
+        .is_some_and(|view| app.window_id() == view.window)
+}
+
+/// Moves the window over to a model built for another picture.
+pub fn carry_over(from: &mut Model, to: &mut Model) {
+    to.cluster_view = from.cluster_view.take();
+}
+
+/// A point of the cube on screen, with its depth, larger being nearer.
+struct Projected {
+    at: Point2,
+    depth: f32,
+    /// How much nearer things are drawn larger.
+    scale: f32,
+}
+
+impl ClusterView {
+    /// Projects a color, its channels from 0 to 255, into a window of
+    /// `side` pixels.
+    fn project(&self, (r, g, b): (u8, u8, u8), side: f32) -> Projected {
+        // The cube centered on the origin, one unit a side, blue up.
+        let point = vec3(r as f32, b as f32, g as f32) / 255.0 - 0.5;
+        let (sin, cos) = self.yaw.sin_cos();
+        let point = vec3(
+            point.x * cos + point.z * sin,
+            point.y,
+            -point.x * sin + point.z * cos,
+        );
+        let (sin, cos) = self.pitch.sin_cos();
+        let point = vec3(
+            point.x,
+            point.y * cos - point.z * sin,
+            point.y * sin + point.z * cos,
+        );
+        let scale = EYE / (EYE - point.z);
+        Projected {
+            at: point.truncate() * scale * side * 0.45,
+            depth: point.z,
+            scale,
+        }
+    }
+}
+
+fn rgb(color: &Color) -> (u8, u8, u8) {
+    (color.r, color.g, color.b)
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let view = match &model.cluster_view {
+        Some(view) => view,
+        None => return,
+    };
+    let side = frame.rect().w().min(frame.rect().h());
+    let draw = app.draw();
+    draw.background().color(srgb8(24, 24, 24));
+
+    // The cube's edges, the three axes from black brighter. Corner `n` has
+    // red full if bit 0 of `n` is set, green bit 1, and blue bit 2.
+    let corner_color = |n: u8| {
+        let full = |bit: u8| if n & bit != 0 { 255 } else { 0 };
+        (full(1), full(2), full(4))
+    };
+    for corner in 0..8u8 {
+        for bit in [1, 2, 4] {
+            if corner & bit != 0 {
+                continue;
+            }
+            let axis = corner == 0;
+            draw.line()
+                .start(view.project(corner_color(corner), side).at)
+                .end(view.project(corner_color(corner | bit), side).at)
+                .weight(if axis { 2.0 } else { 1.0 })
+                .color(if axis {
+                    srgb8(200, 200, 200)
+                } else {
+                    srgb8(70, 70, 70)
+                });
+        }
+    }
+    for (label, corner) in [("R", 1), ("G", 2), ("B", 4)] {
+        draw.text(label)
+            .xy(view.project(corner_color(corner), side).at * 1.08)
+            .font_size(14)
+            .color(WHITE);
+    }
+
+    // Joining lines below every point, then the points far to near.
+    let colors = &model.palette.colors;
+    let tiles: Vec<(&Color, usize)> = model
+        .reference_pixels
+        .iter()
+        .zip(&model.assignment)
+        .filter(|(_, &index)| index != assign::HOLE && index < colors.len())
+        .map(|(color, &index)| (color, index))
+        .collect();
+    for &(color, index) in &tiles {
+        let config = &colors[index];
+        let rgb_of = (config.r, config.g, config.b);
+        draw.line()
+            .start(view.project(rgb(color), side).at)
+            .end(view.project(rgb_of, side).at)
+            .weight(1.0)
+            .color(srgba8(config.r, config.g, config.b, 60));
+    }
+    let mut points: Vec<(Projected, (u8, u8, u8), f32)> = tiles
+        .iter()
+        .map(|&(color, _)| (view.project(rgb(color), side), rgb(color), PIXEL_RADIUS))
+        .chain(colors.iter().map(|config| {
+            let rgb = (config.r, config.g, config.b);
+            (view.project(rgb, side), rgb, PALETTE_RADIUS)
+        }))
+        .collect();
+    points.sort_by(|a, b| a.0.depth.total_cmp(&b.0.depth));
+    for (point, (r, g, b), radius) in points {
+        let radius = radius * point.scale;
+        draw.ellipse()
+            .xy(point.at)
+            .radius(radius)
+            .color(srgb8(r, g, b));
+        if radius >= PALETTE_RADIUS * 0.5 {
+            // A rim and a highlight so the balls read as round.
+            draw.ellipse()
+                .xy(point.at)
+                .radius(radius)
+                .no_fill()
+                .stroke_weight(1.0)
+                .stroke(srgb8(10, 10, 10));
+            draw.ellipse()
+                .xy(point.at + vec2(-radius, radius) * 0.35)
+                .radius(radius * 0.25)
+                .color(srgba8(255, 255, 255, 110));
+        }
+    }
+    draw.text("Drag to turn")
+        .xy(pt2(0.0, frame.rect().bottom() + 12.0))
+        .font_size(11)
+        .color(srgb8(150, 150, 150));
+    draw.to_frame(app, &frame)
+        .expect("Unable to draw the palette cluster window.");
+}
+
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    if let (MouseButton::Left, Some(view)) = (button, &mut model.cluster_view) {
+        view.dragging = Some(app.mouse.position());
+    }
+}
+
+fn mouse_moved(_app: &App, model: &mut Model, point: Point2) {
+    let view = match &mut model.cluster_view {
+        Some(view) => view,
+        None => return,
+    };
+    if let Some(last) = view.dragging {
+        let moved = point - last;
+        view.yaw -= moved.x * DRAG_SPEED;
+        view.pitch = (view.pitch - moved.y * DRAG_SPEED).clamp(-PI / 2.0, PI / 2.0);
+        view.dragging = Some(point);
+    }
+}
+
+fn mouse_released(_app: &App, model: &mut Model, button: MouseButton) {
+    if let (MouseButton::Left, Some(view)) = (button, &mut model.cluster_view) {
+        view.dragging = None;
+    }
+}
@@ -0,0 +1,208 @@
+//! Comparing two saved assignments.
+
+use crate::palette;
+use crate::render;
+use crate::session::SavedAssignment;
+use crate::table::{ReportFormat, Table};
+use crate::{X_SIZE, Y_SIZE};
+use image::{Rgb, RgbImage};
+use serde_json::json;
+
+/// Prints how two assignments differ and optionally writes an image where
+/// unchanged tiles are dimmed and changed ones are outlined. JSON and CSV
+/// give the changed colors only, as rows of `name`, `before`, `after`, and
+/// `delta`.
+pub fn run(
+    before_path: &str,
+    after_path: &str,
+    image_path: Option<&str>,
+    tile_px: u32,
+    format: ReportFormat,
+) {
+    let (before, after) = load_pair(before_path, after_path);
+    let changed: Vec<bool> = (0..before.tiles.len())
+        .map(|tile| before.pixels_differ(&after, tile))
+        .collect();
+    let changed_count = changed.iter().filter(|&&c| c).count();
+    if format == ReportFormat::Text {
+        println!(
+            "{changed_count} of {} tiles differ ({:.1}%).",
+            changed.len(),
+            changed_count as f64 * 100.0 / changed.len().max(1) as f64
+        );
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    for saved in [&before, &after] {
+        for config in &saved.palette.colors {
+            if !names.contains(&config.name) {
+                names.push(config.name.clone());
+            }
+        }
+    }
+    let usage = |saved: &SavedAssignment, name: &str| {
+        (0..saved.tiles.len())
+            .filter(|&tile| saved.name(tile) == name)
+            .count() as i64
+    };
+    if format == ReportFormat::Text {
+        let width = names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(5)
+            .max(5);
+        println!(
+            "{:<width$}  {:>6}  {:>6}  {:>6}",
+            "Color", "Before", "After", "Delta"
+        );
+        for name in &names {
+            let (was, now) = (usage(&before, name), usage(&after, name));
+            if was != now {
+                println!("{name:<width$}  {was:>6}  {now:>6}  {:>+6}", now - was);
+            }
+        }
+    } else {
+        let mut table = Table::new(&["name", "before", "after", "delta"]);
+        for name in &names {
+            let (was, now) = (usage(&before, name), usage(&after, name));
+            if was != now {
+                table.push(vec![json!(name), json!(was), json!(now), json!(now - was)]);
+            }
+        }
+        table.print(format);
+    }
+
+    if let Some(path) = image_path {
+        check_grid(&after);
+        diff_image(&after, &changed, tile_px)
+            .save(path)
+            .expect("Unable to write diff image.");
+        log::info!("Wrote {path}.");
+    }
+}
+
+/// Writes `after` as `--output` would show it, with each tile that changed
+/// since `before` outlined in red when it turned warmer, blue when cooler,
+/// and white when neither.
+pub fn export_png(before_path: &str, after_path: &str, path: &str, tile_px: u32) {
+    let (before, after) = load_pair(before_path, after_path);
+    check_grid(&after);
+    let pixels = after.pixels();
+    let mut img = render::render_to_image(&pixels, tile_px);
+    let (mut warmer, mut cooler) = (0, 0);
+    for (tile, color) in pixels.iter().enumerate() {
+        if !before.pixels_differ(&after, tile) {
+            continue;
+        }
+        let config = &before.palette.colors[before.tiles[tile]];
+        let was = warmth([config.r, config.g, config.b]);
+        let now = warmth([color.r, color.g, color.b]);
+        let outline = if now > was {
+            warmer += 1;
+            WARMER
+        } else if now < was {
+            cooler += 1;
+            COOLER
+        } else {
+            WHITE
+        };
+        outline_tile(
+            &mut img,
+            color.x,
+            after.height - 1 - color.y,
+            tile_px,
+            outline,
+        );
+    }
+    img.save(path).expect("Unable to write diff image.");
+    log::info!("Wrote {path}: {warmer} tiles turned warmer and {cooler} cooler.");
+}
+
+const WARMER: Rgb<u8> = Rgb([255, 0, 0]);
+const COOLER: Rgb<u8> = Rgb([0, 0, 255]);
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+/// The hue farthest from warm, between cyan and blue.
+const COOLEST_HUE: f32 = 210.0;
+
+/// How warm a color looks, from 0 at `COOLEST_HUE` to 180 at the orange
+/// opposite it, by hue angle. The hue is the same in HSL as in HSV. Grays
+/// have no hue and sit halfway.
+fn warmth(rgb: [u8; 3]) -> f32 {
+    let (hue, saturation, _) = palette::hsv(rgb);
+    if saturation == 0.0 {
+        return 90.0;
+    }
+    let apart = (hue - COOLEST_HUE).rem_euclid(360.0);
+    apart.min(360.0 - apart)
+}
+
+fn load_pair(before_path: &str, after_path: &str) -> (SavedAssignment, SavedAssignment) {
+    let before = SavedAssignment::load(before_path);
+    let after = SavedAssignment::load(after_path);
+    if (before.width, before.height) != (after.width, after.height) {
+        panic!(
+            "Cannot diff a {}x{} assignment against a {}x{} one",
+            before.width, before.height, after.width, after.height
+        )
+    }
+    (before, after)
+}
+
+fn check_grid(saved: &SavedAssignment) {
+    if (saved.width, saved.height) != (X_SIZE, Y_SIZE) {
+        panic!("Diff images need a {X_SIZE}x{Y_SIZE} grid")
+    }
+}
+
+/// Renders `after`, dimming unchanged tiles and outlining changed ones.
+fn diff_image(after: &SavedAssignment, changed: &[bool], tile_px: u32) -> RgbImage {
+    let mut pixels = after.pixels();
+    for (color, &changed) in pixels.iter_mut().zip(changed) {
+        if !changed {
+            color.r /= 4;
+            color.g /= 4;
+            color.b /= 4;
+        }
+    }
+    let mut img = render::render_to_image(&pixels, tile_px);
+    for (color, _) in pixels.iter().zip(changed).filter(|(_, &c)| c) {
+        let row = after.height - 1 - color.y;
+        outline_tile(&mut img, color.x, row, tile_px, WHITE);
+    }
+    img
+}
+
+/// Draws a one-pixel border just inside the tile at `x` and image row `row`.
+fn outline_tile(img: &mut RgbImage, x: u64, row: u64, tile_px: u32, color: Rgb<u8>) {
+    let inner = tile_px.saturating_sub(1).max(1);
+    let (left, top) = (x as u32 * tile_px, row as u32 * tile_px);
+    for i in 0..inner {
+        for (x, y) in [
+            (left + i, top),
+            (left + i, top + inner - 1),
+            (left, top + i),
+            (left + inner - 1, top + i),
+        ] {
+            img.put_pixel(x, y, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmth_follows_the_hue_angle() {
+        let (red, orange, blue, cyan) = (
+            warmth([255, 0, 0]),
+            warmth([255, 128, 0]),
+            warmth([0, 0, 255]),
+            warmth([0, 255, 255]),
+        );
+        assert!(orange > red && red > cyan && red > blue);
+        assert_eq!(warmth([40, 40, 40]), 90.0);
+        assert_eq!(blue, 30.0);
+    }
+}
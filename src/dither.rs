@@ -0,0 +1,133 @@
+//! Ordered dithering of the picture's tiles before they are solved, for
+//! `--dither-mode ordered`.
+//!
+//! Each tile's color is nudged lighter or darker by its cell of a Bayer
+//! matrix tiled over the grid, by up to half the usual gap between palette
+//! colors, so that areas between two palette colors come out as a regular
+//! screen of both rather than all one. No error passes from tile to tile,
+//! so every tile is dithered on its own.
+
+use crate::palette::ColorConfigs;
+use crate::Color;
+use rayon::prelude::*;
+use std::str::FromStr;
+
+/// How the picture is dithered before solving, from `--dither-mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DitherMode {
+    /// A Bayer matrix threshold added to every tile.
+    Ordered,
+}
+
+impl FromStr for DitherMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ordered" => Ok(DitherMode::Ordered),
+            _ => Err(format!("Unknown dither mode '{s}'; use ordered")),
+        }
+    }
+}
+
+/// Bayer matrices, each cell its rank in the order they switch on.
+const BAYER_4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+const BAYER_8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// The threshold of the tile at (`x`, `y`) in the `size` by `size` matrix,
+/// from just above -0.5 to just below 0.5.
+fn threshold(size: u32, x: u64, y: u64) -> f32 {
+    let rank = match size {
+        4 => BAYER_4[(y % 4) as usize][(x % 4) as usize],
+        8 => BAYER_8[(y % 8) as usize][(x % 8) as usize],
+        _ => unreachable!("Bayer sizes are checked when parsing."),
+    };
+    (rank as f32 + 0.5) / (size * size) as f32 - 0.5
+}
+
+/// The mean distance from each palette color to its nearest other one,
+/// the step between neighbouring colors the thresholds are scaled to.
+fn spread(palette: &ColorConfigs) -> f32 {
+    let colors: Vec<[f32; 3]> = palette
+        .colors
+        .iter()
+        .map(|config| [config.r, config.g, config.b].map(f32::from))
+        .collect();
+    if colors.len() < 2 {
+        return 0.0;
+    }
+    let total: f32 = colors
+        .iter()
+        .enumerate()
+        .map(|(index, color)| {
+            colors
+                .iter()
+                .enumerate()
+                .filter(|&(other, _)| other != index)
+                .map(|(_, other)| {
+                    (0..3)
+                        .map(|channel| (color[channel] - other[channel]).powi(2))
+                        .sum::<f32>()
+                        .sqrt()
+                })
+                .fold(f32::INFINITY, f32::min)
+        })
+        .sum();
+    total / colors.len() as f32
+}
+
+/// `colors` with the `size` by `size` Bayer matrix added, scaled to the
+/// spacing of `palette`'s colors.
+pub fn ordered(colors: &[Color], palette: &ColorConfigs, size: u32) -> Vec<Color> {
+    // Shifting all three channels alike moves a color along the gray
+    // diagonal, the square root of 3 times as far as each channel moves.
+    let spread = spread(palette) / 3f32.sqrt();
+    let dithered = colors
+        .par_iter()
+        .map(|color| {
+            let offset = threshold(size, color.x, color.y) * spread;
+            let shift = |channel: u8| (channel as f32 + offset).round().clamp(0.0, 255.0) as u8;
+            Color {
+                r: shift(color.r),
+                g: shift(color.g),
+                b: shift(color.b),
+                ..color.clone()
+            }
+        })
+        .collect();
+    log::info!(
+        "Dithered the tiles with a {size} x {size} Bayer matrix, up to {:.0} levels a channel either way.",
+        spread / 2.0
+    );
+    dithered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_cover_every_rank_around_zero() {
+        for size in [4, 8] {
+            let mut thresholds: Vec<f32> = (0..size as u64)
+                .flat_map(|y| (0..size as u64).map(move |x| threshold(size, x, y)))
+                .collect();
+            thresholds.sort_by(f32::total_cmp);
+            let cells = (size * size) as f32;
+            for (rank, value) in thresholds.iter().enumerate() {
+                assert!((value - ((rank as f32 + 0.5) / cells - 0.5)).abs() < 1e-6);
+            }
+            // The matrix repeats across the grid.
+            assert_eq!(threshold(size, 1, 2), threshold(size, 1 + size as u64, 2));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Special pieces for the border of the grid, from `--corner-tiles`.
+//!
+//! Edge colors come from their own small palette with their own counts and
+//! replace the main assignment along the rows and columns they are given
+//! for. They are appended to the model's palette as separate entries, so a
+//! name shared with the main palette still keeps two inventories.
+
+use crate::assign;
+use crate::metric;
+use crate::palette::{ColorConfig, ColorConfigs};
+use crate::{Color, X_SIZE, Y_SIZE};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+/// Colors allowed at each part of the border. Corners use `corners` when it
+/// has any colors and otherwise whichever row or column they sit on.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EdgeConfig {
+    #[serde(default)]
+    pub top_row: Vec<ColorConfig>,
+    #[serde(default)]
+    pub bottom_row: Vec<ColorConfig>,
+    #[serde(default)]
+    pub left_col: Vec<ColorConfig>,
+    #[serde(default)]
+    pub right_col: Vec<ColorConfig>,
+    #[serde(default)]
+    pub corners: Vec<ColorConfig>,
+}
+
+impl EdgeConfig {
+    pub fn load(path: &str) -> EdgeConfig {
+        let text = fs::read_to_string(path).expect("Could not open corner tiles file.");
+        serde_json::from_str(&text).expect("Corner tiles JSON not parseable.")
+    }
+
+    /// The groups in the order they claim tiles, with the tiles each may
+    /// cover.
+    fn groups(&self) -> [(&[ColorConfig], Vec<usize>); 5] {
+        let tile = |x: u64, y: u64| (y * X_SIZE + x) as usize;
+        let (right, top) = (X_SIZE - 1, Y_SIZE - 1);
+        [
+            (
+                &self.corners[..],
+                vec![tile(0, 0), tile(right, 0), tile(0, top), tile(right, top)],
+            ),
+            (
+                &self.top_row[..],
+                (0..X_SIZE).map(|x| tile(x, top)).collect(),
+            ),
+            (
+                &self.bottom_row[..],
+                (0..X_SIZE).map(|x| tile(x, 0)).collect(),
+            ),
+            (
+                &self.left_col[..],
+                (0..Y_SIZE).map(|y| tile(0, y)).collect(),
+            ),
+            (
+                &self.right_col[..],
+                (0..Y_SIZE).map(|y| tile(right, y)).collect(),
+            ),
+        ]
+    }
+}
+
+/// Appends the edge colors to `palette` and moves every border tile they
+/// cover to the nearest one with pieces left, returning the main palette's
+/// pieces. Tiles whose group has run out keep their main color. Returns how
+/// many tiles were replaced.
+pub fn apply(
+    edges: &EdgeConfig,
+    reference: &[Color],
+    assignment: &mut [usize],
+    palette: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) -> usize {
+    let mut placed = vec![false; assignment.len()];
+    let mut replaced = 0;
+    let mut short = 0;
+    for (colors, tiles) in edges.groups() {
+        if colors.is_empty() {
+            continue;
+        }
+        let first = palette.colors.len();
+        let mut group = ColorConfigs {
+            colors: colors.to_vec(),
+        };
+        group.prepare();
+        for tile in tiles {
+            // Keyed-out border tiles stay empty.
+            if placed[tile] || assignment[tile] == assign::HOLE {
+                continue;
+            }
+            placed[tile] = true;
+            if group.colors.iter().all(|config| config.count == 0) {
+                short += 1;
+                continue;
+            }
+            let index = assign::calculate_closest_color(
+                &group,
+                &reference[tile],
+                emphasis,
+                metric::for_tile(&reference[tile]),
+            );
+            group.colors[index].decrement();
+            palette.colors[assignment[tile]].count += 1;
+            assignment[tile] = first + index;
+            replaced += 1;
+        }
+        palette.colors.extend(group.colors);
+    }
+    if short > 0 {
+        log::warn!("Ran out of edge pieces; {short} border tiles keep their main color.");
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, rgb: u8, count: u64) -> ColorConfig {
+        ColorConfig::new(name.to_string(), rgb, rgb, rgb, count)
+    }
+
+    #[test]
+    fn corners_win_and_counts_stay_separate() {
+        let reference: Vec<Color> = (0..X_SIZE * Y_SIZE)
+            .map(|index| Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                x: index % X_SIZE,
+                y: index / X_SIZE,
+            })
+            .collect();
+        let mut assignment = vec![0; reference.len()];
+        let mut palette = ColorConfigs {
+            colors: vec![config("Black", 0, 0)],
+        };
+        let edges = EdgeConfig {
+            corners: vec![config("Corner", 200, 4)],
+            bottom_row: vec![config("Black", 0, 10)],
+            ..EdgeConfig::default()
+        };
+        assert_eq!(
+            apply(
+                &edges,
+                &reference,
+                &mut assignment,
+                &mut palette,
+                &HashMap::new()
+            ),
+            14
+        );
+        assert_eq!(assignment[0], 1);
+        assert_eq!(assignment[X_SIZE as usize - 1], 1);
+        assert_eq!(assignment[1], 2);
+        assert_eq!(assignment[11], 0);
+        let counts: Vec<u64> = palette.colors.iter().map(|config| config.count).collect();
+        assert_eq!(counts, [14, 0, 0]);
+    }
+}
@@ -0,0 +1,193 @@
+//! The bits of the Tajima DST embroidery format needed to write a design.
+//!
+//! A DST file is a 512-byte text header followed by three-byte records,
+//! each moving the needle by up to 121 units of 0.1 mm along each axis,
+//! with y up. A record's bits spell both offsets in balanced ternary, one
+//! bit for each of +1, -1, +3, -3, up to +81 and -81, and say whether the
+//! move sews a stitch, jumps without one, or stops for the next thread.
+
+/// The farthest one record moves along an axis.
+const MAX_MOVE: i32 = 121;
+const HEADER_LEN: usize = 512;
+/// Marks the end of the header text.
+const HEADER_END: u8 = 0x1a;
+const END: [u8; 3] = [0x00, 0x00, 0xf3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Stitch,
+    Jump,
+    ColorChange,
+}
+
+/// Builds a design from absolute needle positions in 0.1 mm, starting at
+/// the origin.
+#[derive(Debug, Default)]
+pub struct Dst {
+    records: Vec<[u8; 3]>,
+    position: (i32, i32),
+    /// The lowest and highest x and y reached.
+    min: (i32, i32),
+    max: (i32, i32),
+    color_changes: usize,
+}
+
+impl Dst {
+    pub fn new() -> Dst {
+        Dst::default()
+    }
+
+    /// Where the needle is now.
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    /// Sews to `(x, y)`, in several stitches if it is too far for one.
+    pub fn stitch(&mut self, x: i32, y: i32) {
+        self.move_to(x, y, Kind::Stitch);
+    }
+
+    /// Moves to `(x, y)` without sewing.
+    pub fn jump(&mut self, x: i32, y: i32) {
+        self.move_to(x, y, Kind::Jump);
+    }
+
+    /// Stops for the next thread.
+    pub fn color_change(&mut self) {
+        self.records.push(encode(0, 0, Kind::ColorChange));
+        self.color_changes += 1;
+    }
+
+    /// Splits the move into equal steps short enough for a record each.
+    fn move_to(&mut self, x: i32, y: i32, kind: Kind) {
+        let (from_x, from_y) = self.position;
+        let (dx, dy) = (x - from_x, y - from_y);
+        let steps = (dx.abs().max(dy.abs()) + MAX_MOVE - 1) / MAX_MOVE;
+        let mut last = self.position;
+        for step in 1..=steps {
+            let next = (from_x + dx * step / steps, from_y + dy * step / steps);
+            self.records
+                .push(encode(next.0 - last.0, next.1 - last.1, kind));
+            last = next;
+        }
+        self.position = (x, y);
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+
+    /// The header, labeled `label`, the records, and the end record.
+    pub fn into_bytes(self, label: &str) -> Vec<u8> {
+        let label: String = label.chars().filter(char::is_ascii).take(16).collect();
+        let (x, y) = self.position;
+        let header = format!(
+            "LA:{label:<16}\rST:{:>7}\rCO:{:>3}\r+X:{:>5}\r-X:{:>5}\r+Y:{:>5}\r-Y:{:>5}\r\
+             AX:{}{:>5}\rAY:{}{:>5}\rMX:+    0\rMY:+    0\rPD:******\r",
+            self.records.len() + 1,
+            self.color_changes,
+            self.max.0,
+            -self.min.0,
+            self.max.1,
+            -self.min.1,
+            if x < 0 { '-' } else { '+' },
+            x.abs(),
+            if y < 0 { '-' } else { '+' },
+            y.abs(),
+        );
+        let mut bytes = header.into_bytes();
+        bytes.push(HEADER_END);
+        bytes.resize(HEADER_LEN, b' ');
+        bytes.extend(self.records.into_iter().flatten());
+        bytes.extend(END);
+        bytes
+    }
+}
+
+/// One record moving by `(dx, dy)`, each within `MAX_MOVE`.
+fn encode(dx: i32, dy: i32, kind: Kind) -> [u8; 3] {
+    // The byte and bit of each power's plus and minus, for x then y.
+    const X_BITS: [(i32, usize, u8, u8); 5] = [
+        (81, 2, 2, 3),
+        (27, 1, 2, 3),
+        (9, 0, 2, 3),
+        (3, 1, 0, 1),
+        (1, 0, 0, 1),
+    ];
+    const Y_BITS: [(i32, usize, u8, u8); 5] = [
+        (81, 2, 5, 4),
+        (27, 1, 5, 4),
+        (9, 0, 5, 4),
+        (3, 1, 7, 6),
+        (1, 0, 7, 6),
+    ];
+    let mut record = [0, 0, 0x03];
+    for (mut left, bits) in [(dx, X_BITS), (dy, Y_BITS)] {
+        for (power, byte, plus, minus) in bits {
+            // What the smaller powers together can still make up.
+            let rest = (power - 1) / 2;
+            if left > rest {
+                record[byte] |= 1 << plus;
+                left -= power;
+            } else if left < -rest {
+                record[byte] |= 1 << minus;
+                left += power;
+            }
+        }
+    }
+    match kind {
+        Kind::Stitch => {}
+        Kind::Jump => record[2] |= 0x80,
+        Kind::ColorChange => record[2] |= 0xc0,
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The offsets a record moves by, summed from its bits.
+    fn decode(record: [u8; 3]) -> (i32, i32) {
+        let bit = |byte: usize, bit: u8| (record[byte] >> bit & 1) as i32;
+        let x = bit(0, 0) - bit(0, 1)
+            + 3 * (bit(1, 0) - bit(1, 1))
+            + 9 * (bit(0, 2) - bit(0, 3))
+            + 27 * (bit(1, 2) - bit(1, 3))
+            + 81 * (bit(2, 2) - bit(2, 3));
+        let y = bit(0, 7) - bit(0, 6)
+            + 3 * (bit(1, 7) - bit(1, 6))
+            + 9 * (bit(0, 5) - bit(0, 4))
+            + 27 * (bit(1, 5) - bit(1, 4))
+            + 81 * (bit(2, 5) - bit(2, 4));
+        (x, y)
+    }
+
+    #[test]
+    fn records_spell_offsets_in_balanced_ternary() {
+        assert_eq!(encode(1, 0, Kind::Stitch), [0x01, 0x00, 0x03]);
+        assert_eq!(encode(121, 121, Kind::Stitch), [0xa5, 0xa5, 0x27]);
+        assert_eq!(encode(0, 0, Kind::ColorChange), [0x00, 0x00, 0xc3]);
+        for dx in -MAX_MOVE..=MAX_MOVE {
+            for dy in [-MAX_MOVE, -40, -1, 0, 13, MAX_MOVE] {
+                assert_eq!(decode(encode(dx, dy, Kind::Jump)), (dx, dy));
+            }
+        }
+    }
+
+    #[test]
+    fn long_moves_split_and_the_header_counts_them() {
+        let mut dst = Dst::new();
+        dst.stitch(300, -10);
+        assert_eq!(dst.records.len(), 3);
+        let moved = dst.records.iter().fold((0, 0), |(x, y), &record| {
+            let (dx, dy) = decode(record);
+            (x + dx, y + dy)
+        });
+        assert_eq!(moved, (300, -10));
+        let bytes = dst.into_bytes("test");
+        assert_eq!(bytes.len(), HEADER_LEN + 4 * 3);
+        let header = String::from_utf8_lossy(&bytes[..HEADER_LEN]);
+        assert!(header.starts_with("LA:test            \rST:      4\rCO:  0\r+X:  300\r-X:    0"));
+        assert!(header.contains("-Y:   10\rAX:+  300\rAY:-   10\r"));
+        assert_eq!(&bytes[bytes.len() - 3..], END);
+    }
+}
@@ -0,0 +1,82 @@
+//! The palette as Android resources, written by `--export-android-theme`
+//! into a project's `res` folder: `values/colors.xml` with a
+//! `mosaic_<name>` color per palette color, and `drawable/mosaic.xml`, a
+//! shape filled with the color the mosaic uses most.
+
+use super::parts;
+use crate::Model;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const PREFIX: &str = "mosaic";
+
+/// Writes both resource files under `dir`, creating `values` and
+/// `drawable` as needed.
+pub fn export(model: &Model, dir: &str) {
+    let dir = Path::new(dir);
+    let parts = parts::parts(model);
+    let mut taken = HashSet::new();
+    let mut names = Vec::new();
+    let mut colors = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<resources>\n");
+    for part in &parts {
+        let mut name = format!("{PREFIX}_{}", identifier(&part.name));
+        // Names that only differ in punctuation would collide.
+        let base = name.clone();
+        for n in 2.. {
+            if taken.insert(name.clone()) {
+                break;
+            }
+            name = format!("{base}_{n}");
+        }
+        writeln!(
+            colors,
+            "    <color name=\"{name}\">{}</color>",
+            part.hex().to_uppercase()
+        )
+        .unwrap();
+        names.push(name);
+    }
+    colors.push_str("</resources>\n");
+
+    // The first of the most used colors, in palette order.
+    let dominant = (0..parts.len())
+        .rev()
+        .max_by_key(|&index| parts[index].used)
+        .expect("The palette has at least one color.");
+    let drawable = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <shape xmlns:android=\"http://schemas.android.com/apk/res/android\"\n    \
+         android:shape=\"rectangle\">\n    \
+         <solid android:color=\"@color/{}\" />\n\
+         </shape>\n",
+        names[dominant]
+    );
+
+    for (folder, file, contents) in [
+        ("values", "colors.xml", colors),
+        ("drawable", "mosaic.xml", drawable),
+    ] {
+        let folder = dir.join(folder);
+        fs::create_dir_all(&folder).expect("Unable to create Android resource folder.");
+        let path = folder.join(file);
+        fs::write(&path, contents).expect("Unable to write Android resource.");
+        log::info!("Wrote {}.", path.display());
+    }
+}
+
+/// Lowercases `name` and joins its runs of letters and digits with
+/// underscores, the only characters resource names may hold.
+fn identifier(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "unnamed".to_string()
+    } else {
+        words.join("_")
+    }
+}
@@ -0,0 +1,59 @@
+//! The mosaic as an Aseprite sprite with a layer per palette color.
+
+use crate::aseprite::{Layer, Sprite};
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+
+/// Writes a sprite one pixel per tile. Each used color gets a layer holding
+/// just its tiles, in palette order, and the palette carries every color's
+/// name.
+pub fn export(model: &Model, path: &str) {
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let layers = model
+        .palette
+        .colors
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| used[index] > 0)
+        .map(|(index, config)| {
+            // Crop the cel to the color's tiles, in image rows from the top.
+            let cells: Vec<(u64, u64)> = model
+                .assignment
+                .iter()
+                .enumerate()
+                .filter(|&(_, &assigned)| assigned == index)
+                .map(|(tile, _)| (tile as u64 % X_SIZE, Y_SIZE - 1 - tile as u64 / X_SIZE))
+                .collect();
+            let left = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+            let right = cells.iter().map(|&(x, _)| x).max().unwrap_or(0);
+            let top = cells.iter().map(|&(_, row)| row).min().unwrap_or(0);
+            let bottom = cells.iter().map(|&(_, row)| row).max().unwrap_or(0);
+            let (width, height) = (right - left + 1, bottom - top + 1);
+            let mut rgba = vec![0; (width * height * 4) as usize];
+            for (x, row) in cells {
+                let at = (((row - top) * width + x - left) * 4) as usize;
+                rgba[at..at + 4].copy_from_slice(&[config.r, config.g, config.b, 255]);
+            }
+            Layer {
+                name: config.name.clone(),
+                x: left as u16,
+                y: top as u16,
+                width: width as u16,
+                height: height as u16,
+                rgba,
+            }
+        })
+        .collect();
+    let sprite = Sprite {
+        width: X_SIZE as u16,
+        height: Y_SIZE as u16,
+        palette: model
+            .palette
+            .colors
+            .iter()
+            .map(|config| (config.name.clone(), [config.r, config.g, config.b]))
+            .collect(),
+        layers,
+    };
+    sprite.save(path);
+    log::info!("Wrote {path}.");
+}
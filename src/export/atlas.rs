@@ -0,0 +1,108 @@
+//! Every color's tiles as a stencil packed into one sprite sheet, written by
+//! `--export-png-atlas` with a JSON map of where each stencil sits.
+
+use crate::font;
+use crate::legend;
+use crate::render;
+use crate::{assign, Color, Model};
+use image::{imageops, Rgba, RgbaImage};
+use serde_json::{json, Map};
+use std::fs;
+use std::path::Path;
+
+const GUTTER: u32 = 8;
+const LABEL_SCALE: u32 = 2;
+const LABEL_HEIGHT: u32 = (font::GLYPH_HEIGHT + 3) * LABEL_SCALE;
+const LABEL_BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// Writes one stencil per used color in legend order, `columns` to a row
+/// (about square when unset), each under a strip with its name. A stencil
+/// is the color's tiles drawn as in `--output` with everything else left
+/// transparent. The JSON written next to `path` gives each color's stencil
+/// as `x`, `y`, `w`, `h` in atlas pixels, leaving out the name strip.
+pub fn export(model: &Model, path: &str, columns: Option<usize>) {
+    let numbers = legend::numbers(model);
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| numbers[index]);
+    let columns = columns
+        .unwrap_or_else(|| (order.len() as f32).sqrt().ceil() as usize)
+        .max(1);
+    let rows = order.len().div_ceil(columns);
+
+    let scale = model.options.output_scale;
+    let stencils: Vec<RgbaImage> = order
+        .iter()
+        .map(|&color| stencil(model, color, scale))
+        .collect();
+    let (width, height) = stencils
+        .first()
+        .map_or((0, 0), |stencil| stencil.dimensions());
+    let cell = (width + GUTTER, LABEL_HEIGHT + height + GUTTER);
+    let mut atlas = RgbaImage::new(
+        GUTTER + columns.min(order.len()) as u32 * cell.0,
+        GUTTER + rows as u32 * cell.1,
+    );
+    let mut coordinates = Map::new();
+    for (slot, (&color, stencil)) in order.iter().zip(&stencils).enumerate() {
+        let left = GUTTER + (slot % columns) as u32 * cell.0;
+        let top = GUTTER + (slot / columns) as u32 * cell.1;
+        for y in top..top + LABEL_HEIGHT {
+            for x in left..left + width {
+                atlas.put_pixel(x, y, LABEL_BACKGROUND);
+            }
+        }
+        let name = &model.palette.colors[color].name;
+        let mut label = format!("{}. {name}", numbers[color]);
+        while font::text_width(&label, LABEL_SCALE) > width {
+            label.pop();
+        }
+        font::draw_text(
+            &mut atlas,
+            left + 2,
+            top + 2 * LABEL_SCALE,
+            &label,
+            Rgba([255, 255, 255, 255]),
+            LABEL_SCALE,
+        );
+        let y = top + LABEL_HEIGHT;
+        imageops::overlay(&mut atlas, stencil, left as i64, y as i64);
+        coordinates.insert(
+            name.clone(),
+            json!({ "x": left, "y": y, "w": width, "h": height }),
+        );
+    }
+    atlas.save(path).expect("Unable to write atlas image.");
+    let map_path = Path::new(path).with_extension("json");
+    let map = serde_json::to_string_pretty(&coordinates).expect("Atlas map should serialize.");
+    fs::write(&map_path, map).expect("Unable to write atlas map.");
+    log::info!(
+        "Wrote {path} with {} stencils and {}.",
+        order.len(),
+        map_path.display()
+    );
+}
+
+/// The tiles of `color` alone, in whichever layout the mosaic uses.
+fn stencil(model: &Model, color: usize, scale: u32) -> RgbaImage {
+    let pixels: Vec<Color> = model
+        .pixels
+        .iter()
+        .zip(&model.assignment)
+        .filter(|(_, &index)| index == color)
+        .map(|(pixel, _)| pixel.clone())
+        .collect();
+    if let Some(orientation) = model.options.hex_grid {
+        return render::render_hex_to_rgba(&pixels, scale, orientation);
+    }
+    match model.leaves(&model.assignment) {
+        Some(cells) => {
+            let cells: Vec<_> = cells
+                .into_iter()
+                .filter(|cell| model.assignment[cell.first_tile()] == color)
+                .collect();
+            render::render_cells_to_rgba(&model.pixels, &cells, scale)
+        }
+        None => render::render_to_rgba(&pixels, scale),
+    }
+}
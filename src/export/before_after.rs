@@ -0,0 +1,197 @@
+//! The source picture beside the finished mosaic in one image, written by
+//! `--export-before-after` for sharing.
+
+use super::render_grid;
+use crate::font;
+use crate::render::{self, Presentation};
+use crate::{assign, source, Model, X_SIZE, Y_SIZE};
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage, RgbaImage};
+use std::str::FromStr;
+
+const GUTTER: u32 = 16;
+const CAPTION_SCALE: u32 = 2;
+const CAPTION_HEIGHT: u32 = (font::GLYPH_HEIGHT + 6) * CAPTION_SCALE;
+
+/// How the two halves of `--export-before-after` are stacked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// The picture on the left, the mosaic on the right.
+    #[default]
+    Horizontal,
+    /// The picture above the mosaic.
+    Vertical,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "horizontal" => Ok(Layout::Horizontal),
+            "vertical" => Ok(Layout::Vertical),
+            _ => Err(format!(
+                "Unknown before/after layout '{s}'; use horizontal or vertical"
+            )),
+        }
+    }
+}
+
+/// Writes the picture letterboxed to the mosaic's size, a gutter, and the
+/// mosaic with its frame. `--before-after-size` sets the mosaic's height
+/// side by side or its width stacked. The picture is read again from its
+/// file, so a pasted one cannot be shown.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let original = match source::read_source_image(&options.picture_path, options.assume_srgb) {
+        Ok(original) => original,
+        Err(err) => {
+            log::warn!(
+                "Skipping {path}: cannot read the picture {} again ({err}).",
+                options.picture_path
+            );
+            return;
+        }
+    };
+    let mosaic = mosaic(model);
+    let (width, height) = mosaic.dimensions();
+    let mut fitted = RgbImage::from_pixel(width, height, render::BACKGROUND);
+    let scale =
+        (width as f32 / original.width() as f32).min(height as f32 / original.height() as f32);
+    let resized = original
+        .resize_exact(
+            ((original.width() as f32 * scale).round() as u32).clamp(1, width),
+            ((original.height() as f32 * scale).round() as u32).clamp(1, height),
+            FilterType::Triangle,
+        )
+        .to_rgb8();
+    imageops::replace(
+        &mut fitted,
+        &resized,
+        ((width - resized.width()) / 2) as i64,
+        ((height - resized.height()) / 2) as i64,
+    );
+
+    let (across, down) = match options.before_after_layout {
+        Layout::Horizontal => (2 * width + 3 * GUTTER, height + 2 * GUTTER),
+        Layout::Vertical => (width + 2 * GUTTER, 2 * height + 3 * GUTTER),
+    };
+    let caption_height = if options.before_after_caption {
+        CAPTION_HEIGHT
+    } else {
+        0
+    };
+    let mut img = RgbImage::from_pixel(across, down + caption_height, render::BACKGROUND);
+    imageops::replace(&mut img, &fitted, GUTTER as i64, GUTTER as i64);
+    let (left, top) = match options.before_after_layout {
+        Layout::Horizontal => (2 * GUTTER + width, GUTTER),
+        Layout::Vertical => (GUTTER, 2 * GUTTER + height),
+    };
+    imageops::replace(&mut img, &mosaic, left as i64, top as i64);
+    if options.before_after_caption {
+        let mut caption = caption(model);
+        while font::text_width(&caption, CAPTION_SCALE) > across {
+            caption.pop();
+        }
+        font::draw_text(
+            &mut img,
+            (across - font::text_width(&caption, CAPTION_SCALE)) / 2,
+            down,
+            &caption,
+            Rgb([255, 255, 255]),
+            CAPTION_SCALE,
+        );
+    }
+    img.save(path).expect("Unable to write before/after image.");
+    log::info!("Wrote {path}.");
+}
+
+/// The mosaic with its frame, studded if asked. With `--before-after-size`
+/// the tiles get as many whole pixels as fit and the rest is padding.
+fn mosaic(model: &Model) -> RgbImage {
+    let options = &model.options;
+    let border = model.frame.map_or(0, |frame| 2 * frame.thickness);
+    let extent = match options.before_after_layout {
+        Layout::Horizontal => Y_SIZE as u32,
+        Layout::Vertical => X_SIZE as u32,
+    } + border;
+    let scale = options
+        .before_after_size
+        .map_or(options.output_scale, |size| (size / extent).max(1));
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let mut grid = render_grid(model, &pixels, &model.assignment, scale);
+    if options.before_after_studs {
+        add_studs(&mut grid, scale);
+    }
+    let presentation = Presentation {
+        frame: super::presentation(model).frame,
+        ..Presentation::default()
+    };
+    let img = render::flatten(
+        &render::present(&grid, scale, &presentation),
+        render::BACKGROUND,
+    );
+    let size = match options.before_after_size {
+        Some(size) => size,
+        None => return img,
+    };
+    let (width, height) = match options.before_after_layout {
+        Layout::Horizontal => (img.width(), size.max(img.height())),
+        Layout::Vertical => (size.max(img.width()), img.height()),
+    };
+    let mut padded = RgbImage::from_pixel(width, height, render::BACKGROUND);
+    imageops::replace(
+        &mut padded,
+        &img,
+        ((width - img.width()) / 2) as i64,
+        ((height - img.height()) / 2) as i64,
+    );
+    padded
+}
+
+/// Shades a round stud onto every tile, lit from the top left.
+fn add_studs(grid: &mut RgbaImage, scale: u32) {
+    // As in `render_to_rgba`, the last row and column of each tile are gap.
+    let inner = scale.saturating_sub(1).max(1);
+    let radius = 0.3 * inner as f32;
+    let centre = (inner as f32 - 1.0) / 2.0;
+    for top in (0..grid.height()).step_by(scale as usize) {
+        for left in (0..grid.width()).step_by(scale as usize) {
+            let base = *grid.get_pixel(left + centre as u32, top + centre as u32);
+            if base.0[3] == 0 {
+                continue;
+            }
+            for dy in 0..inner {
+                for dx in 0..inner {
+                    let (x, y) = (dx as f32 - centre, dy as f32 - centre);
+                    let distance = (x * x + y * y).sqrt();
+                    if distance > radius {
+                        continue;
+                    }
+                    // Brighter towards the light, darker at the far rim.
+                    let light = -(x + y) / (radius * std::f32::consts::SQRT_2);
+                    let rim = if distance > radius - 1.0 { 0.75 } else { 1.0 };
+                    let factor = (1.0 + 0.3 * light) * rim;
+                    let mut pixel = base;
+                    for channel in &mut pixel.0[..3] {
+                        *channel = (*channel as f32 * factor + 24.0 * light.max(0.0))
+                            .round()
+                            .clamp(0.0, 255.0) as u8;
+                    }
+                    grid.put_pixel(left + dx, top + dy, pixel);
+                }
+            }
+        }
+    }
+}
+
+/// Grid size and piece count, e.g. `48x48 tiles, 2304 pieces, 21 colors`.
+fn caption(model: &Model) -> String {
+    let frame = model.frame.map_or(0, |frame| frame.tile_count());
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    format!(
+        "{X_SIZE}x{Y_SIZE} tiles, {} pieces, {} colors",
+        (model.assignment.len() - model.holes.len()) as u64 + frame,
+        used.iter().filter(|&&count| count > 0).count()
+    )
+}
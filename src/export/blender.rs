@@ -0,0 +1,109 @@
+//! A Blender Python script building the mosaic in 3-D, written by
+//! `--export-blender`.
+//!
+//! Each tile is a cube added with `primitive_cube_add`, `--stud-pitch`
+//! across and as tall as its color's `height_mm`, with an emission
+//! material per palette color shared by all of its tiles.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Writes the script to `path`. Tiles are laid from the bottom-left corner
+/// at the origin, `x` across and `y` up, in metres as Blender measures.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let pitch = options.stud_pitch;
+    let mut script = String::new();
+    writeln!(script, "# Mosaic of {}", options.picture_path).unwrap();
+    writeln!(
+        script,
+        "# Grid: {X_SIZE} x {Y_SIZE} tiles, {pitch} mm apart"
+    )
+    .unwrap();
+    writeln!(script, "# Palette: {}", options.palette_label()).unwrap();
+    let file = Path::new(path)
+        .file_name()
+        .map_or(path.into(), |name| name.to_string_lossy());
+    writeln!(
+        script,
+        "#\n\
+         # Run it with Blender to build the mosaic in the startup scene:\n\
+         #     blender --python {file}\n\
+         # or open it in Blender's Text Editor and press Run Script.\n\n\
+         import bpy\n"
+    )
+    .unwrap();
+    writeln!(script, "PITCH = {}  # metres", pitch / 1000.0).unwrap();
+    script.push_str(
+        "\n\n\
+         def material(name, r, g, b):\n    \
+         \"\"\"An emission material of the linear color r, g, b.\"\"\"\n    \
+         mat = bpy.data.materials.new(name)\n    \
+         mat.use_nodes = True\n    \
+         mat.diffuse_color = (r, g, b, 1.0)\n    \
+         nodes = mat.node_tree.nodes\n    \
+         nodes.clear()\n    \
+         emission = nodes.new(\"ShaderNodeEmission\")\n    \
+         emission.inputs[\"Color\"].default_value = (r, g, b, 1.0)\n    \
+         output = nodes.new(\"ShaderNodeOutputMaterial\")\n    \
+         mat.node_tree.links.new(emission.outputs[\"Emission\"], output.inputs[\"Surface\"])\n    \
+         return mat\n\n\n\
+         def tile(x, y, height, mat):\n    \
+         \"\"\"A cube on the ground filling grid square x, y.\"\"\"\n    \
+         bpy.ops.mesh.primitive_cube_add(\n        \
+         size=1,\n        \
+         location=((x + 0.5) * PITCH, (y + 0.5) * PITCH, height / 2),\n        \
+         scale=(PITCH, PITCH, height),\n    \
+         )\n    \
+         cube = bpy.context.active_object\n    \
+         cube.name = f\"tile {x},{y}\"\n    \
+         cube.data.materials.append(mat)\n\n\n",
+    );
+
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    script.push_str("materials = {\n");
+    for (index, config) in model.palette.colors.iter().enumerate() {
+        if used[index] == 0 {
+            continue;
+        }
+        let [r, g, b] = [config.r, config.g, config.b].map(linear);
+        let name = serde_json::to_string(&config.name).expect("Names should serialize.");
+        writeln!(
+            script,
+            "    {index}: material({name}, {r:.4}, {g:.4}, {b:.4}),"
+        )
+        .unwrap();
+    }
+    script.push_str("}\n\n");
+
+    for (color, &index) in model.pixels.iter().zip(&model.assignment) {
+        if index == assign::HOLE {
+            continue;
+        }
+        let height = model.palette.colors[index]
+            .height_mm
+            .unwrap_or(options.tile_height);
+        writeln!(
+            script,
+            "tile({}, {}, {}, materials[{index}])",
+            color.x,
+            color.y,
+            height / 1000.0
+        )
+        .unwrap();
+    }
+    fs::write(path, script).expect("Unable to write Blender script.");
+    log::info!("Wrote {path}.");
+}
+
+/// An sRGB channel as the linear value Blender's colors take.
+fn linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
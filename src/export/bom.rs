@@ -0,0 +1,197 @@
+//! A printable bill of materials, written by `--export-pdf-bom`: every
+//! color the mosaic uses with its hex code, the pieces it needs, and where
+//! to buy it, with a QR code of the `purchase_url` beside the row for
+//! scanning with a phone. Colors without a URL are flagged `[no source]`.
+//!
+//! Rows continue over as many A4 pages as they need; the last one ends
+//! with the grand total.
+
+use super::color_sheets::MARGIN;
+use super::parts;
+use crate::pdf::{self, Document, Page, A4};
+use crate::Model;
+use qrcode::QrCode;
+
+const ROW_HEIGHT: f32 = 64.0;
+const ROWS_PER_PAGE: usize = 10;
+const QR_SIDE: f32 = 56.0;
+const SWATCH: f32 = 16.0;
+const NO_SOURCE: &str = "[no source]";
+/// Where each column starts, from the left margin.
+const NAME_COLUMN: f32 = 26.0;
+const HEX_COLUMN: f32 = 200.0;
+const COUNT_COLUMN: f32 = 262.0;
+const URL_COLUMN: f32 = 316.0;
+const URL_SIZE: f32 = 8.0;
+/// Lines a long URL is wrapped over before it is cut short.
+const URL_LINES: usize = 3;
+
+/// Writes the table of used colors in palette order.
+pub fn export(model: &Model, path: &str) {
+    let used: Vec<(usize, parts::Part)> = parts::parts(model)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, part)| part.used > 0)
+        .collect();
+    let total: u64 = used.iter().map(|(_, part)| part.used).sum();
+    let unsourced = used
+        .iter()
+        .filter(|&&(index, _)| url(model, index).is_none())
+        .count();
+
+    let mut document = Document::new(A4);
+    let pages = used.len().div_ceil(ROWS_PER_PAGE).max(1);
+    let chunks: Vec<&[(usize, parts::Part)]> = if used.is_empty() {
+        vec![&[]]
+    } else {
+        used.chunks(ROWS_PER_PAGE).collect()
+    };
+    for (sheet, rows) in chunks.into_iter().enumerate() {
+        let mut page = Page::new();
+        let top = A4.1 - MARGIN;
+        page.fill_color(0, 0, 0);
+        let heading = if pages > 1 {
+            format!("Bill of materials ({} of {pages})", sheet + 1)
+        } else {
+            "Bill of materials".to_string()
+        };
+        page.text(MARGIN, top - 18.0, 18.0, &heading);
+        let header = top - 44.0;
+        for (column, label) in [
+            (NAME_COLUMN, "Color"),
+            (HEX_COLUMN, "Hex"),
+            (COUNT_COLUMN, "Count"),
+            (URL_COLUMN, "Purchase"),
+        ] {
+            page.text(MARGIN + column, header, 10.0, label);
+        }
+        page.stroke_color(0, 0, 0);
+        page.line_width(0.8);
+        page.line((MARGIN, header - 6.0), (A4.0 - MARGIN, header - 6.0));
+
+        let mut row_top = header - 10.0;
+        for (index, part) in rows {
+            draw_row(&mut page, model, *index, part, row_top);
+            row_top -= ROW_HEIGHT;
+            page.stroke_color(200, 200, 200);
+            page.line_width(0.5);
+            page.line((MARGIN, row_top), (A4.0 - MARGIN, row_top));
+        }
+        if sheet + 1 == pages {
+            page.fill_color(0, 0, 0);
+            page.text(
+                MARGIN,
+                row_top - 22.0,
+                12.0,
+                &format!("Total: {total} pieces in {} colors", used.len()),
+            );
+            if unsourced > 0 {
+                page.text(
+                    MARGIN,
+                    row_top - 40.0,
+                    10.0,
+                    &format!("{unsourced} colors have no source yet."),
+                );
+            }
+        }
+        document.push(page);
+    }
+    document.save(path);
+    log::info!(
+        "Wrote a bill of materials of {} colors to {path}.",
+        used.len()
+    );
+}
+
+fn url(model: &Model, index: usize) -> Option<&str> {
+    model.palette.colors[index]
+        .purchase_url
+        .as_deref()
+        .filter(|url| !url.trim().is_empty())
+}
+
+fn draw_row(page: &mut Page, model: &Model, index: usize, part: &parts::Part, top: f32) {
+    let baseline = top - ROW_HEIGHT / 2.0 - 4.0;
+    let [r, g, b] = part.rgb;
+    page.fill_color(r, g, b);
+    page.stroke_color(0, 0, 0);
+    page.line_width(0.5);
+    page.rect(MARGIN, baseline - 4.0, SWATCH, SWATCH, true, true);
+    page.fill_color(0, 0, 0);
+    let mut name = part.name.clone();
+    while pdf::text_width(&name, 10.0) > HEX_COLUMN - NAME_COLUMN - 6.0 && name.pop().is_some() {}
+    page.text(MARGIN + NAME_COLUMN, baseline, 10.0, &name);
+    page.text(MARGIN + HEX_COLUMN, baseline, 10.0, &part.hex());
+    page.text(
+        MARGIN + COUNT_COLUMN,
+        baseline,
+        10.0,
+        &part.used.to_string(),
+    );
+
+    let qr_left = A4.0 - MARGIN - QR_SIDE;
+    let url = match url(model, index) {
+        Some(url) => url,
+        None => {
+            page.fill_color(190, 0, 0);
+            page.text(MARGIN + URL_COLUMN, baseline, 10.0, NO_SOURCE);
+            return;
+        }
+    };
+    // Wraps at a fixed number of characters, as the font's are all about
+    // the same width.
+    let per_line =
+        ((qr_left - 8.0 - MARGIN - URL_COLUMN) / pdf::text_width("m", URL_SIZE)) as usize;
+    let chars: Vec<char> = url.chars().collect();
+    let mut lines: Vec<String> = chars
+        .chunks(per_line.max(1))
+        .map(|line| line.iter().collect())
+        .collect();
+    if lines.len() > URL_LINES {
+        lines.truncate(URL_LINES);
+        let last = &mut lines[URL_LINES - 1];
+        for _ in 0..3 {
+            last.pop();
+        }
+        last.push_str("...");
+    }
+    let first = baseline + (lines.len() as f32 - 1.0) * (URL_SIZE + 2.0) / 2.0;
+    for (line, text) in lines.iter().enumerate() {
+        let y = first - line as f32 * (URL_SIZE + 2.0);
+        page.text(MARGIN + URL_COLUMN, y, URL_SIZE, text);
+    }
+
+    match QrCode::new(url.as_bytes()) {
+        Ok(code) => draw_code(page, &code, qr_left, top - (ROW_HEIGHT - QR_SIDE) / 2.0),
+        Err(err) => log::warn!("No QR code for {}: {err}", part.name),
+    }
+}
+
+/// Draws `code` `QR_SIDE` points wide from its top-left corner, each run of
+/// dark modules in a row as one rectangle.
+fn draw_code(page: &mut Page, code: &QrCode, left: f32, top: f32) {
+    let width = code.width();
+    let module = QR_SIDE / width as f32;
+    page.fill_color(0, 0, 0);
+    for y in 0..width {
+        let mut x = 0;
+        while x < width {
+            if code[(x, y)] != qrcode::Color::Dark {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && code[(x, y)] == qrcode::Color::Dark {
+                x += 1;
+            }
+            page.rect(
+                left + start as f32 * module,
+                top - (y + 1) as f32 * module,
+                (x - start) as f32 * module,
+                module,
+                true,
+                false,
+            );
+        }
+    }
+}
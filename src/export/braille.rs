@@ -0,0 +1,62 @@
+//! The mosaic as Braille art, written by `--export-braille` for pasting
+//! into terminals and chats: each character covers two tiles across and
+//! four down, with a dot raised for every tile brighter than
+//! `--braille-threshold`. With `--ansi-color` each character is colored
+//! the average of its tiles.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+
+const BLANK: u32 = 0x2800;
+/// The bit of each dot, by column and then row from the top.
+const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Writes `Y_SIZE / 4` lines of `X_SIZE / 2` characters, top line first.
+/// Keyed-out holes are dark.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let tile = |x: u64, y: u64| {
+        let index = (y * X_SIZE + x) as usize;
+        (model.assignment[index] != assign::HOLE).then(|| &model.pixels[index])
+    };
+    let mut text = String::new();
+    for line in 0..Y_SIZE / 4 {
+        for column in 0..X_SIZE / 2 {
+            let mut bits = 0;
+            let mut sum = [0u32; 3];
+            let mut tiles = 0;
+            for (dx, dots) in DOTS.iter().enumerate() {
+                for (dy, dot) in dots.iter().enumerate() {
+                    // Grid rows count up from the bottom, lines down from
+                    // the top.
+                    let y = Y_SIZE - 1 - (line * 4 + dy as u64);
+                    let Some(color) = tile(column * 2 + dx as u64, y) else {
+                        continue;
+                    };
+                    let luma =
+                        (0.299 * color.r as f32 + 0.587 * color.g as f32 + 0.114 * color.b as f32)
+                            / 255.0;
+                    if luma > options.braille_threshold {
+                        bits |= dot;
+                    }
+                    for (total, channel) in sum.iter_mut().zip([color.r, color.g, color.b]) {
+                        *total += channel as u32;
+                    }
+                    tiles += 1;
+                }
+            }
+            if options.ansi_color && tiles > 0 {
+                let [r, g, b] = sum.map(|total| total / tiles);
+                write!(text, "\x1b[38;2;{r};{g};{b}m").unwrap();
+            }
+            text.push(char::from_u32(BLANK + bits).expect("Braille patterns are characters."));
+        }
+        if options.ansi_color {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
+    fs::write(path, text).expect("Unable to write Braille art.");
+    log::info!("Wrote {path}.");
+}
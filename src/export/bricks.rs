@@ -0,0 +1,57 @@
+//! Parts list and placement diagram for covering the mosaic with larger
+//! plates, written by `--export-bricks`.
+
+use crate::bricks::{self, Brick};
+use crate::render;
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Writes a CSV with one row per part size and color to `path`, and the
+/// diagram of where each part goes beside it as a PNG.
+pub fn export(model: &Model, path: &str) {
+    let diagram = Path::new(path).with_extension("png");
+    if diagram == Path::new(path) {
+        panic!("--export-bricks writes a CSV with a PNG beside it; give a .csv path")
+    }
+    let placements = bricks::cover(&model.assignment, &model.options.brick_sizes);
+
+    let mut counts: BTreeMap<(usize, Reverse<u64>, Brick), u64> = BTreeMap::new();
+    for placement in &placements {
+        let brick = placement.brick.upright();
+        *counts
+            .entry((placement.color, Reverse(brick.area()), brick))
+            .or_default() += 1;
+    }
+    let mut csv = String::from("Part,Color,RGB,Count\n");
+    for (&(color, _, brick), count) in &counts {
+        let config = &model.palette.colors[color];
+        writeln!(
+            csv,
+            "{brick},\"{}\",#{:02X}{:02X}{:02X},{count}",
+            config.name.replace('"', "\"\""),
+            config.r,
+            config.g,
+            config.b
+        )
+        .unwrap();
+    }
+    fs::write(path, csv).expect("Unable to write bricks parts list.");
+
+    let scale = model.options.output_scale;
+    render::flatten(
+        &render::render_bricks_to_rgba(&model.pixels, &placements, scale),
+        render::BACKGROUND,
+    )
+    .save(&diagram)
+    .expect("Unable to write bricks diagram.");
+    log::info!(
+        "Covered the {} tiles with {} parts. Wrote {path} and {}.",
+        X_SIZE * Y_SIZE - model.holes.len() as u64,
+        placements.len(),
+        diagram.display()
+    );
+}
@@ -0,0 +1,116 @@
+//! A color book for a mosaic kit, written by `--export-color-book`: one A4
+//! page per palette color with a large swatch, its values and codes, the
+//! pieces the mosaic needs, and a ruled box for the builder's notes.
+//!
+//! Pages follow the colors' `group`, then their names, with ungrouped
+//! colors last. The Perler code is looked up as `--export-fuse-beads` does;
+//! the BrickLink and DMC codes come only from the palette.
+
+use super::color_sheets::MARGIN;
+use super::fuse_beads::perler_codes;
+use super::parts;
+use crate::pdf::{self, Document, Page, A4};
+use crate::Model;
+
+const SWATCH: f32 = 240.0;
+const ROW_HEIGHT: f32 = 22.0;
+/// Where the values' column starts, from the left margin.
+const VALUE_COLUMN: f32 = 120.0;
+const NOTES_LINE_SPACING: f32 = 24.0;
+/// Group heading of colors the palette files under none.
+const UNGROUPED: &str = "Ungrouped";
+
+/// Writes a page per palette color in group and name order.
+pub fn export(model: &Model, path: &str) {
+    let colors = &model.palette.colors;
+    let parts = parts::parts(model);
+    let perler = perler_codes(colors);
+    let mut order: Vec<usize> = (0..colors.len()).collect();
+    order.sort_by(|&a, &b| {
+        let key = |index: usize| {
+            let config = &colors[index];
+            (
+                config.group.is_none(),
+                config.group.as_deref(),
+                &config.name,
+            )
+        };
+        key(a).cmp(&key(b))
+    });
+
+    let mut document = Document::new(A4);
+    for (number, &index) in order.iter().enumerate() {
+        let config = &colors[index];
+        let part = &parts[index];
+        let mut page = Page::new();
+        let top = A4.1 - MARGIN;
+        page.fill_color(90, 90, 90);
+        page.text(
+            MARGIN,
+            top - 12.0,
+            12.0,
+            config.group.as_deref().unwrap_or(UNGROUPED),
+        );
+        let count = format!("{} of {}", number + 1, order.len());
+        page.text(
+            A4.0 - MARGIN - pdf::text_width(&count, 10.0),
+            top - 12.0,
+            10.0,
+            &count,
+        );
+        page.fill_color(0, 0, 0);
+        let mut name = config.name.clone();
+        while pdf::text_width(&name, 24.0) > A4.0 - 2.0 * MARGIN && name.pop().is_some() {}
+        page.text(MARGIN, top - 40.0, 24.0, &name);
+
+        page.fill_color(config.r, config.g, config.b);
+        page.stroke_color(0, 0, 0);
+        page.line_width(1.0);
+        let swatch_top = top - 60.0;
+        page.rect(MARGIN, swatch_top - SWATCH, SWATCH, SWATCH, true, true);
+
+        let code = |code: Option<&str>| code.unwrap_or("-").to_string();
+        let rows = [
+            ("Hex", part.hex()),
+            ("RGB", format!("{}, {}, {}", config.r, config.g, config.b)),
+            ("Needed", format!("{} pieces", part.used)),
+            ("Left over", format!("{} pieces", part.remaining)),
+            ("Group", code(config.group.as_deref())),
+            ("BrickLink", code(config.bricklink_code.as_deref())),
+            ("Perler", code(perler[index].as_deref())),
+            ("DMC", code(config.dmc_code.as_deref())),
+        ];
+        let mut baseline = swatch_top - SWATCH - 36.0;
+        page.fill_color(0, 0, 0);
+        for (label, value) in rows {
+            page.text(MARGIN, baseline, 12.0, label);
+            page.text(MARGIN + VALUE_COLUMN, baseline, 12.0, &value);
+            baseline -= ROW_HEIGHT;
+        }
+
+        // The notes box fills the rest of the page.
+        let notes_top = baseline - 4.0;
+        page.text(MARGIN, notes_top - 14.0, 12.0, "Notes");
+        let box_top = notes_top - 22.0;
+        page.stroke_color(0, 0, 0);
+        page.line_width(0.8);
+        page.rect(
+            MARGIN,
+            MARGIN,
+            A4.0 - 2.0 * MARGIN,
+            box_top - MARGIN,
+            false,
+            true,
+        );
+        page.stroke_color(190, 190, 190);
+        page.line_width(0.5);
+        let mut line = box_top - NOTES_LINE_SPACING;
+        while line > MARGIN + NOTES_LINE_SPACING / 2.0 {
+            page.line((MARGIN + 8.0, line), (A4.0 - MARGIN - 8.0, line));
+            line -= NOTES_LINE_SPACING;
+        }
+        document.push(page);
+    }
+    document.save(path);
+    log::info!("Wrote a color book of {} colors to {path}.", order.len());
+}
@@ -0,0 +1,147 @@
+//! One printable A4 page per color showing where each of its tiles goes.
+
+use crate::assign;
+use crate::legend;
+use crate::pdf::{self, Document, Page, A4};
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::cmp::Reverse;
+
+pub(super) const MARGIN: f32 = 40.0;
+/// Room left of the grid for row labels and below it for column labels.
+const LABEL_SPACE: f32 = 16.0;
+const LABEL_SIZE: f32 = 6.0;
+/// Cells are about 3.7mm across, and dots a little under 3mm.
+pub(super) const CELL: f32 = (A4.0 - 2.0 * MARGIN - LABEL_SPACE) / X_SIZE as f32;
+const DOT_RADIUS: f32 = CELL * 0.38;
+/// Every this many cells the grid line is drawn darker to help counting.
+const MAJOR_EVERY: u64 = 8;
+/// How far the grid's bottom-left corner is in from the page's, both ways.
+pub(super) const GRID_CORNER: f32 = MARGIN + LABEL_SPACE;
+
+/// Writes the sheets, most used color first, each titled with the color's
+/// legend number. Colors with no tiles are left out.
+pub fn export(model: &Model, path: &str) {
+    let numbers = legend::numbers(model);
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| Reverse(used[index]));
+
+    let mut document = Document::new(A4);
+    for (sheet, &index) in order.iter().enumerate() {
+        let mut page = Page::new();
+        header(&mut page, model, index, numbers[index], used[index]);
+        page.fill_color(0, 0, 0);
+        page.text(
+            A4.0 - MARGIN - pdf::text_width("Sheet 00 of 00", 10.0),
+            A4.1 - MARGIN - 10.0,
+            10.0,
+            &format!("Sheet {} of {}", sheet + 1, order.len()),
+        );
+        grid(&mut page, model, index);
+        document.push(page);
+    }
+    document.save(path);
+    log::info!("Wrote {} color sheets to {path}.", order.len());
+}
+
+fn header(page: &mut Page, model: &Model, index: usize, number: usize, count: u64) {
+    let config = &model.palette.colors[index];
+    let top = A4.1 - MARGIN;
+    let mut name = format!("{number}. {}", config.name);
+    while pdf::text_width(&name, 18.0) > A4.0 - 2.0 * MARGIN - 100.0 && name.pop().is_some() {}
+    page.fill_color(0, 0, 0);
+    page.text(MARGIN, top - 18.0, 18.0, &name);
+
+    let swatch = 50.0;
+    page.fill_color(config.r, config.g, config.b);
+    page.stroke_color(0, 0, 0);
+    page.line_width(1.0);
+    page.rect(MARGIN, top - 40.0 - swatch, swatch, swatch, true, true);
+    page.fill_color(0, 0, 0);
+    let left = MARGIN + swatch + 12.0;
+    page.text(left, top - 58.0, 14.0, &format!("{count} tiles"));
+    page.text(
+        left,
+        top - 76.0,
+        10.0,
+        &format!("rgb({}, {}, {})", config.r, config.g, config.b),
+    );
+    page.text(
+        left,
+        top - 90.0,
+        10.0,
+        "Positions match the window: x from the left, y from the bottom.",
+    );
+}
+
+/// The grid's lines with its rows and columns numbered, in grays and
+/// black.
+pub(super) fn ruled_grid(page: &mut Page) {
+    let (left, bottom) = (GRID_CORNER, GRID_CORNER);
+    let (width, height) = (X_SIZE as f32 * CELL, Y_SIZE as f32 * CELL);
+
+    for x in 0..=X_SIZE {
+        grid_line_style(page, x);
+        let at = left + x as f32 * CELL;
+        page.line((at, bottom), (at, bottom + height));
+    }
+    for y in 0..=Y_SIZE {
+        grid_line_style(page, y);
+        let at = bottom + y as f32 * CELL;
+        page.line((left, at), (left + width, at));
+    }
+
+    page.fill_color(0, 0, 0);
+    for x in 0..X_SIZE {
+        let label = x.to_string();
+        let center = left + (x as f32 + 0.5) * CELL;
+        page.text(
+            center - pdf::text_width(&label, LABEL_SIZE) / 2.0,
+            bottom - LABEL_SIZE - 4.0,
+            LABEL_SIZE,
+            &label,
+        );
+    }
+    for y in 0..Y_SIZE {
+        let label = y.to_string();
+        let middle = bottom + (y as f32 + 0.5) * CELL;
+        page.text(
+            left - pdf::text_width(&label, LABEL_SIZE) - 3.0,
+            middle - LABEL_SIZE / 3.0,
+            LABEL_SIZE,
+            &label,
+        );
+    }
+}
+
+fn grid(page: &mut Page, model: &Model, index: usize) {
+    ruled_grid(page);
+    let (left, bottom) = (GRID_CORNER, GRID_CORNER);
+    let config = &model.palette.colors[index];
+    page.fill_color(config.r, config.g, config.b);
+    page.stroke_color(0, 0, 0);
+    page.line_width(0.8);
+    for (tile, &assigned) in model.assignment.iter().enumerate() {
+        if assigned != index {
+            continue;
+        }
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        page.circle(
+            left + (x as f32 + 0.5) * CELL,
+            bottom + (y as f32 + 0.5) * CELL,
+            DOT_RADIUS,
+            true,
+            true,
+        );
+    }
+}
+
+fn grid_line_style(page: &mut Page, line: u64) {
+    if line.is_multiple_of(MAJOR_EVERY) {
+        page.stroke_color(90, 90, 90);
+        page.line_width(0.7);
+    } else {
+        page.stroke_color(190, 190, 190);
+        page.line_width(0.3);
+    }
+}
@@ -0,0 +1,91 @@
+//! Build instructions that place one color at a time over the whole grid,
+//! written by `--export-color-steps`.
+
+use crate::render;
+use crate::{assign, Color, Model};
+use std::fs;
+use std::path::Path;
+
+/// How much of a tile's own color is kept when it is not the step's color.
+const DIM: u32 = 25;
+/// Where a tile not yet placed is drawn, so the empty plate still shows.
+const EMPTY: [u8; 3] = [48, 48, 48];
+
+/// Writes `step_01.png`, `step_02.png`, ... into `dir`, one per used color
+/// from the least used to the most used, so accents go down while the
+/// plate is still empty. Each step shows that color's tiles on the dimmed
+/// mosaic beside everything placed so far.
+pub fn export(model: &Model, dir: &str) {
+    fs::create_dir_all(dir).expect("Unable to create color steps folder.");
+    let dir = Path::new(dir);
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    // A stable sort keeps palette order between colors used equally often.
+    order.sort_by_key(|&index| used[index]);
+
+    let scale = model.options.output_scale;
+    for (step, &color) in order.iter().enumerate() {
+        let placed = &order[..=step];
+        let highlight: Vec<Color> =
+            tiles(model, |index| if index == color { None } else { Some(DIM) });
+        let so_far: Vec<Color> = tiles(model, |index| {
+            if placed.contains(&index) {
+                None
+            } else {
+                Some(0)
+            }
+        });
+        let config = &model.palette.colors[color];
+        let renders = [
+            (
+                format!(
+                    "Step {} of {}: {}\n{} tiles",
+                    step + 1,
+                    order.len(),
+                    config.name,
+                    used[color]
+                ),
+                render::flatten(
+                    &super::render_grid(model, &highlight, &model.assignment, scale),
+                    render::BACKGROUND,
+                ),
+            ),
+            (
+                format!("Placed so far\n{} colors", step + 1),
+                render::flatten(
+                    &super::render_grid(model, &so_far, &model.assignment, scale),
+                    render::BACKGROUND,
+                ),
+            ),
+        ];
+        let path = dir.join(format!("step_{:02}.png", step + 1));
+        render::contact_sheet(&renders)
+            .save(&path)
+            .expect("Unable to write color step image.");
+    }
+    log::info!("Wrote {} color steps to {}.", order.len(), dir.display());
+}
+
+/// Every tile in its assigned color, except that `fade` may ask for a
+/// percentage of it to be kept over the empty plate color instead.
+fn tiles(model: &Model, fade: impl Fn(usize) -> Option<u32>) -> Vec<Color> {
+    model
+        .pixels
+        .iter()
+        .zip(&model.assignment)
+        .map(|(color, &index)| match fade(index) {
+            None => color.clone(),
+            Some(kept) => {
+                let mix = |c: u8, under: u8| {
+                    ((c as u32 * kept + under as u32 * (100 - kept)) / 100) as u8
+                };
+                Color {
+                    r: mix(color.r, EMPTY[0]),
+                    g: mix(color.g, EMPTY[1]),
+                    b: mix(color.b, EMPTY[2]),
+                    ..color.clone()
+                }
+            }
+        })
+        .collect()
+}
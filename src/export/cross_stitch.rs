@@ -0,0 +1,187 @@
+//! A cross-stitch chart, written by `--export-cross-stitch`: the grid with
+//! a symbol in every stitch, then a legend of the symbols and a shopping
+//! list of DMC threads.
+//!
+//! Each color in use gets its own symbol, the most used color the first
+//! and plainest of `SYMBOLS`. Thread numbers come from the palette's
+//! `dmc_code`, and a skein is counted for every `STITCHES_PER_SKEIN`
+//! stitches or part of it.
+
+use super::color_sheets::{ruled_grid, CELL, GRID_CORNER, MARGIN};
+use crate::assign;
+use crate::palette::ColorConfig;
+use crate::pdf::{self, Document, Page, A4};
+use crate::{Model, X_SIZE};
+use std::cmp::Reverse;
+
+/// Chart symbols, easiest told apart first. Helvetica here only covers
+/// ASCII, so these are all letters, digits, and punctuation.
+const SYMBOLS: [&str; 50] = [
+    "X", "O", "+", "#", "@", "*", "%", "&", "=", "/", "\\", "<", ">", "^", "~", "?", "!", "$", "A",
+    "B", "C", "D", "E", "F", "G", "H", "K", "M", "N", "P", "R", "S", "T", "U", "V", "W", "Y", "Z",
+    "2", "3", "4", "5", "6", "7", "8", "9", "a", "b", "d", "e",
+];
+const STITCHES_PER_SKEIN: u64 = 100;
+const SYMBOL_SIZE: f32 = CELL * 0.7;
+const ROW_HEIGHT: f32 = 18.0;
+const SWATCH: f32 = 13.0;
+/// List rows on a page below its title.
+const ROWS_PER_PAGE: usize = ((A4.1 - 2.0 * MARGIN - 60.0) / ROW_HEIGHT) as usize;
+
+/// The symbol of the `rank`th most used color. Past the fiftieth the
+/// symbols come round again with a prime mark for each lap.
+pub(super) fn symbol(rank: usize) -> String {
+    format!(
+        "{}{}",
+        SYMBOLS[rank % SYMBOLS.len()],
+        "'".repeat(rank / SYMBOLS.len())
+    )
+}
+
+fn dmc(config: &ColorConfig) -> &str {
+    config.dmc_code.as_deref().unwrap_or("-")
+}
+
+/// Writes the chart page, then the legend and the shopping list, both most
+/// used color first.
+pub fn export(model: &Model, path: &str) {
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| Reverse(used[index]));
+    let mut symbols = vec![String::new(); used.len()];
+    for (rank, &index) in order.iter().enumerate() {
+        symbols[index] = symbol(rank);
+    }
+
+    let mut document = Document::new(A4);
+    document.push(chart_page(model, &symbols));
+    list_pages(&mut document, "Legend", &order, |page, index, baseline| {
+        let config = &model.palette.colors[index];
+        page.fill_color(0, 0, 0);
+        page.text(MARGIN + 4.0, baseline, 11.0, &symbols[index]);
+        swatch(page, config, MARGIN + 30.0, baseline);
+        page.fill_color(0, 0, 0);
+        let mut name = config.name.clone();
+        let room = A4.0 - 2.0 * MARGIN - 160.0;
+        while pdf::text_width(&name, 11.0) > room && name.pop().is_some() {}
+        page.text(MARGIN + 52.0, baseline, 11.0, &name);
+        let code = dmc(config);
+        page.text(
+            A4.0 - MARGIN - pdf::text_width(code, 11.0),
+            baseline,
+            11.0,
+            code,
+        );
+    });
+
+    let skeins = |index: usize| used[index].div_ceil(STITCHES_PER_SKEIN);
+    let total: u64 = order.iter().map(|&index| skeins(index)).sum();
+    let title = format!("Shopping list: {total} skeins, one per {STITCHES_PER_SKEIN} stitches");
+    list_pages(&mut document, &title, &order, |page, index, baseline| {
+        let config = &model.palette.colors[index];
+        swatch(page, config, MARGIN, baseline);
+        page.fill_color(0, 0, 0);
+        page.text(MARGIN + 22.0, baseline, 11.0, dmc(config));
+        let mut name = config.name.clone();
+        let room = A4.0 - 2.0 * MARGIN - 260.0;
+        while pdf::text_width(&name, 11.0) > room && name.pop().is_some() {}
+        page.text(MARGIN + 100.0, baseline, 11.0, &name);
+        let skeins = skeins(index);
+        let count = format!(
+            "{} stitches, {skeins} {}",
+            used[index],
+            if skeins == 1 { "skein" } else { "skeins" }
+        );
+        page.text(
+            A4.0 - MARGIN - pdf::text_width(&count, 11.0),
+            baseline,
+            11.0,
+            &count,
+        );
+    });
+    document.save(path);
+    log::info!(
+        "Wrote a cross-stitch chart of {} colors and {total} skeins to {path}.",
+        order.len()
+    );
+}
+
+/// Every stitch's symbol centered in its cell, over a light wash of its
+/// thread color so the chart reads in color or in black and white.
+fn chart_page(model: &Model, symbols: &[String]) -> Page {
+    let mut page = Page::new();
+    page.fill_color(0, 0, 0);
+    page.text(MARGIN, A4.1 - MARGIN - 18.0, 18.0, "Cross-stitch chart");
+    page.text(
+        MARGIN,
+        A4.1 - MARGIN - 36.0,
+        10.0,
+        "One stitch per cell; symbols are on the legend page. x counts from the left, y from the bottom.",
+    );
+    for (tile, &assigned) in model.assignment.iter().enumerate() {
+        if assigned == assign::HOLE {
+            continue;
+        }
+        let config = &model.palette.colors[assigned];
+        let wash = |channel: u8| ((channel as u16 + 2 * 255) / 3) as u8;
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        page.fill_color(wash(config.r), wash(config.g), wash(config.b));
+        page.rect(
+            GRID_CORNER + x as f32 * CELL,
+            GRID_CORNER + y as f32 * CELL,
+            CELL,
+            CELL,
+            true,
+            false,
+        );
+    }
+    ruled_grid(&mut page);
+    page.fill_color(0, 0, 0);
+    for (tile, &assigned) in model.assignment.iter().enumerate() {
+        if assigned == assign::HOLE {
+            continue;
+        }
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        let symbol = &symbols[assigned];
+        page.text(
+            GRID_CORNER + (x as f32 + 0.5) * CELL - pdf::text_width(symbol, SYMBOL_SIZE) / 2.0,
+            GRID_CORNER + (y as f32 + 0.5) * CELL - SYMBOL_SIZE / 3.0,
+            SYMBOL_SIZE,
+            symbol,
+        );
+    }
+    page
+}
+
+fn swatch(page: &mut Page, config: &ColorConfig, x: f32, baseline: f32) {
+    page.fill_color(config.r, config.g, config.b);
+    page.stroke_color(0, 0, 0);
+    page.line_width(0.8);
+    page.rect(x, baseline - 3.0, SWATCH, SWATCH, true, true);
+}
+
+/// Pages listing `entries` under `title`, each row drawn by `row` at its
+/// baseline.
+pub(super) fn list_pages(
+    document: &mut Document,
+    title: &str,
+    entries: &[usize],
+    mut row: impl FnMut(&mut Page, usize, f32),
+) {
+    let pages = entries.len().div_ceil(ROWS_PER_PAGE).max(1);
+    for (sheet, rows) in entries.chunks(ROWS_PER_PAGE).enumerate() {
+        let mut page = Page::new();
+        let heading = if pages > 1 {
+            format!("{title} ({} of {pages})", sheet + 1)
+        } else {
+            title.to_string()
+        };
+        page.fill_color(0, 0, 0);
+        page.text(MARGIN, A4.1 - MARGIN - 18.0, 18.0, &heading);
+        for (line, &index) in rows.iter().enumerate() {
+            let baseline = A4.1 - MARGIN - 60.0 - (line + 1) as f32 * ROW_HEIGHT;
+            row(&mut page, index, baseline);
+        }
+        document.push(page);
+    }
+}
@@ -0,0 +1,59 @@
+//! The palette as CSS custom properties, written by
+//! `--export-css-variables`.
+
+use super::parts;
+use crate::Model;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+
+/// Used when `--css-prefix` is not given.
+const DEFAULT_PREFIX: &str = "color";
+
+/// Writes a `:root` rule with a `--<prefix>-<name>` property per palette
+/// color, its value the color's hex code and a comment with how many tiles
+/// use it.
+pub fn export(model: &Model, path: &str) {
+    let prefix = model
+        .options
+        .css_prefix
+        .as_deref()
+        .unwrap_or(DEFAULT_PREFIX);
+    let mut taken = HashSet::new();
+    let mut css = String::from(":root {\n");
+    for part in parts::parts(model) {
+        let mut name = format!("{prefix}-{}", slug(&part.name));
+        // Names that only differ in punctuation would collide.
+        let base = name.clone();
+        for n in 2.. {
+            if taken.insert(name.clone()) {
+                break;
+            }
+            name = format!("{base}-{n}");
+        }
+        writeln!(
+            css,
+            "  --{name}: {}; /* count: {} */",
+            part.hex(),
+            part.used
+        )
+        .unwrap();
+    }
+    css.push_str("}\n");
+    fs::write(path, css).expect("Unable to write CSS variables.");
+    log::info!("Wrote {path}.");
+}
+
+/// Lowercases `name` and joins its runs of letters and digits with hyphens.
+fn slug(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "unnamed".to_string()
+    } else {
+        words.join("-")
+    }
+}
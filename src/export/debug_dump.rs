@@ -0,0 +1,96 @@
+//! Intermediate pipeline stages written by `--debug-dump`, for telling
+//! whether a bad mosaic comes from the resize or the matching.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Tiles described in `candidates.json`, spread evenly over the grid.
+const SAMPLE_TILES: u64 = 64;
+/// Nearest colors listed for each sampled tile.
+const CANDIDATES_PER_TILE: usize = 5;
+
+#[derive(Serialize)]
+struct SampledTile {
+    tile: usize,
+    x: u64,
+    y: u64,
+    source: [u8; 3],
+    assigned: String,
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Serialize)]
+struct Candidate {
+    name: String,
+    rgb: [u8; 3],
+    /// Distance in the matcher's working space, after emphasis.
+    distance: f32,
+    /// Pieces declared in the palette.
+    declared: u64,
+}
+
+/// Writes `resized.png`, the working image one block per tile, and
+/// `candidates.json` into `dir`, creating it if needed.
+pub fn export(model: &Model, dir: &str) {
+    fs::create_dir_all(dir).expect("Unable to create debug dump folder.");
+    let dir = Path::new(dir);
+
+    let mut resized = RgbImage::new(X_SIZE as u32, Y_SIZE as u32);
+    for color in &model.reference_pixels {
+        let row = (Y_SIZE - 1 - color.y) as u32;
+        resized.put_pixel(color.x as u32, row, Rgb([color.r, color.g, color.b]));
+    }
+    let scale = model.options.output_scale;
+    let resized = imageops::resize(
+        &resized,
+        X_SIZE as u32 * scale,
+        Y_SIZE as u32 * scale,
+        FilterType::Nearest,
+    );
+    let path = dir.join("resized.png");
+    resized
+        .save(&path)
+        .expect("Unable to write debug resized image.");
+    log::info!("Wrote {}.", path.display());
+
+    let declared = model.declared_palette();
+    let total = X_SIZE * Y_SIZE;
+    let sampled: Vec<SampledTile> = (0..SAMPLE_TILES.min(total))
+        .map(|sample| (sample * total / SAMPLE_TILES) as usize)
+        .map(|tile| {
+            let source = &model.reference_pixels[tile];
+            let candidates =
+                assign::candidates(&declared, source, &model.emphasis, CANDIDATES_PER_TILE)
+                    .into_iter()
+                    .map(|(index, distance)| {
+                        let config = &declared.colors[index];
+                        Candidate {
+                            name: config.name.clone(),
+                            rgb: [config.r, config.g, config.b],
+                            distance,
+                            declared: config.count,
+                        }
+                    })
+                    .collect();
+            SampledTile {
+                tile,
+                x: source.x,
+                y: source.y,
+                source: [source.r, source.g, source.b],
+                assigned: match model.assignment[tile] {
+                    assign::HOLE => "empty".to_string(),
+                    index => declared.colors[index].name.clone(),
+                },
+                candidates,
+            }
+        })
+        .collect();
+    let path = dir.join("candidates.json");
+    let json = serde_json::to_string_pretty(&sampled).expect("Candidates should serialize.");
+    fs::write(&path, json).expect("Unable to write debug candidates.");
+    log::info!("Wrote {}.", path.display());
+}
@@ -0,0 +1,109 @@
+//! A Discord webhook message showing the mosaic, written by
+//! `--export-discord-embed`: an embed titled with `--window-title`,
+//! describing the grid and tinted with the most used palette color, whose
+//! image is the `--output` rendering attached as `mosaic.png`.
+//!
+//! With `--webhook-url` the message is also posted, as the multipart form
+//! Discord takes for attachments: the payload as `payload_json` and the
+//! image, rendered in memory, as `files[0]`. There is no HTTP client built
+//! in, so it is sent with `curl`, which must be on the `PATH`.
+
+use super::{output_image, ImageStyle};
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use image::{DynamicImage, ImageFormat};
+use serde_json::json;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Name the image is attached under and its embed refers to.
+const ATTACHMENT: &str = "mosaic.png";
+
+/// Writes the payload, and posts it with the image when a webhook is set.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let tiles: u64 = used.iter().sum();
+    let colors = used.iter().filter(|&&count| count > 0).count();
+    let title = options.window_title.clone().unwrap_or_else(|| {
+        let stem = Path::new(&options.picture_path)
+            .file_stem()
+            .map_or("mosaic".into(), |stem| stem.to_string_lossy());
+        format!("Mosaic of {stem}")
+    });
+    let mut embed = json!({
+        "title": title,
+        "description": format!(
+            "{X_SIZE} x {Y_SIZE} grid, {tiles} tiles in {colors} of {} colors from {}.",
+            model.palette.colors.len(),
+            options.palette_label()
+        ),
+        "image": { "url": format!("attachment://{ATTACHMENT}") },
+    });
+    // The first of the most used colors, if any tile has one.
+    let dominant = (0..used.len())
+        .filter(|&index| used[index] > 0)
+        .max_by_key(|&index| (used[index], std::cmp::Reverse(index)));
+    if let Some(index) = dominant {
+        let config = &model.palette.colors[index];
+        embed["color"] = json!(u32::from_be_bytes([0, config.r, config.g, config.b]));
+        embed["fields"] = json!([{
+            "name": "Most used color",
+            "value": format!("{} ({} tiles)", config.name, used[index]),
+            "inline": true,
+        }]);
+    }
+    let payload = json!({
+        "embeds": [embed],
+        "attachments": [{ "id": 0, "filename": ATTACHMENT }],
+    });
+    let payload = serde_json::to_string_pretty(&payload).expect("Payload is JSON.");
+    fs::write(path, &payload).expect("Unable to write Discord payload.");
+    log::info!("Wrote {path}.");
+
+    if let Some(url) = &options.webhook_url {
+        let img = output_image(model, &ImageStyle::from_options(model));
+        match post(url, path, &png(&img)) {
+            Ok(()) => log::info!("Posted the mosaic to the Discord webhook."),
+            Err(err) => log::warn!("Could not post to the Discord webhook: {err}"),
+        }
+    }
+}
+
+fn png(img: &DynamicImage) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    img.write_to(&mut bytes, ImageFormat::Png)
+        .expect("Unable to encode mosaic image.");
+    bytes.into_inner()
+}
+
+/// Sends the payload written to `payload_path` and the image, which curl
+/// reads from its standard input.
+fn post(url: &str, payload_path: &str, image: &[u8]) -> Result<(), String> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--form"])
+        .arg(format!("payload_json=<{payload_path}"))
+        .arg("--form")
+        .arg(format!("files[0]=@-;filename={ATTACHMENT};type=image/png"))
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("unable to run curl: {err}"))?;
+    child
+        .stdin
+        .take()
+        .expect("Standard input is piped.")
+        .write_all(image)
+        .map_err(|err| format!("unable to send the image to curl: {err}"))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("curl did not finish: {err}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
@@ -0,0 +1,81 @@
+//! A DST embroidery design of the mosaic, written by `--export-embroidery`:
+//! each color in use is one thread, sewn in palette order with a stop to
+//! change thread between them, filling its tiles with satin stitch.
+//!
+//! A tile is a zigzag of stitches from edge to edge, `--stitch-density`
+//! of them a millimetre, running left to right along even rows of the grid
+//! and right to left along odd ones. Side-by-side tiles of one color join
+//! into one band; the needle jumps wherever a color's tiles are apart. The
+//! design is centered on the hoop's middle, where the machine starts.
+
+use crate::embroidery::Dst;
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fs;
+use std::path::Path;
+
+/// Side of a tile in millimetres, which puts the grid in a 130 mm hoop.
+const TILE_MM: f32 = 2.5;
+/// DST positions are in tenths of a millimetre.
+const UNITS_PER_MM: f32 = 10.0;
+
+/// Writes the design and logs its threads in the order the machine asks
+/// for them, since DST files have no colors of their own.
+pub fn export(model: &Model, path: &str) {
+    let tile = TILE_MM * UNITS_PER_MM;
+    let stitches = ((TILE_MM * model.options.stitch_density).round() as u32).max(1);
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let threads: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    let mut dst = Dst::new();
+    for (thread, &color) in threads.iter().enumerate() {
+        if thread > 0 {
+            dst.color_change();
+        }
+        for y in 0..Y_SIZE {
+            let rightwards = y % 2 == 0;
+            for step in 0..X_SIZE {
+                let x = if rightwards { step } else { X_SIZE - 1 - step };
+                if model.assignment[(y * X_SIZE + x) as usize] != color {
+                    continue;
+                }
+                let left = (x as f32 - X_SIZE as f32 / 2.0) * tile;
+                let bottom = (y as f32 - Y_SIZE as f32 / 2.0) * tile;
+                let (start, direction) = if rightwards {
+                    (left, 1.0)
+                } else {
+                    (left + tile, -1.0)
+                };
+                let unit = |value: f32| value.round() as i32;
+                // Carry on from the tile before when it ended on this
+                // tile's edge, else jump to the bottom corner.
+                let top = unit(bottom + tile);
+                let from_top = dst.position() == (unit(start), top);
+                if dst.position() != (unit(start), unit(bottom)) && !from_top {
+                    dst.jump(unit(start), unit(bottom));
+                }
+                for column in 0..=stitches {
+                    let at = start + direction * tile * column as f32 / stitches as f32;
+                    let high = (column % 2 == 1) != from_top;
+                    dst.stitch(unit(at), if high { top } else { unit(bottom) });
+                }
+            }
+        }
+    }
+
+    let label = Path::new(&model.options.picture_path)
+        .file_stem()
+        .map_or("mosaic".into(), |stem| stem.to_string_lossy())
+        .into_owned();
+    fs::write(path, dst.into_bytes(&label)).expect("Unable to write embroidery file.");
+    log::info!("Wrote {path} with {} threads:", threads.len());
+    for (thread, &color) in threads.iter().enumerate() {
+        let config = &model.palette.colors[color];
+        log::info!(
+            "  {}. {} (#{:02x}{:02x}{:02x})",
+            thread + 1,
+            config.name,
+            config.r,
+            config.g,
+            config.b
+        );
+    }
+}
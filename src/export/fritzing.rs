@@ -0,0 +1,191 @@
+//! A Fritzing custom part SVG for building the mosaic as an LED matrix,
+//! written by `--export-fritzing`.
+//!
+//! Every tile is one LED in its palette color. The drawing is in mils,
+//! thousandths of an inch, as Fritzing expects, with one group per view:
+//! `breadboard` draws the LEDs and their leads, `schematic` a diode symbol
+//! per LED, and `pcb` a pair of pads per LED on `copper0` with outlines on
+//! `silkscreen`. LED `n` in raster order has anode connector `2n` and
+//! cathode connector `2n + 1`; the breadboard leads carry the
+//! `connector<k>pin` ids that a part's `.fzp` file refers to. Keyed-out
+//! holes get no LED and are left out of the numbering.
+
+use crate::{assign, legend, Color, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+
+/// LED spacing in mils.
+const PITCH: f32 = 400.0;
+const LED_RADIUS: f32 = 120.0;
+/// Label text height in mils.
+const LABEL_SIZE: f32 = 44.0;
+
+/// Writes the part SVG to `path`. Tiles are placed as in `--output`, with
+/// each LED labeled by its color's name and legend number.
+pub fn export(model: &Model, path: &str) {
+    let (width, height) = match model.options.hex_grid {
+        Some(orientation) => {
+            let width = X_SIZE as f32 * PITCH;
+            (width, width / orientation.aspect())
+        }
+        None => (X_SIZE as f32 * PITCH, Y_SIZE as f32 * PITCH),
+    };
+    let numbers = legend::numbers(model);
+    let leds: Vec<(&Color, usize)> = model
+        .pixels
+        .iter()
+        .zip(model.assignment.iter().copied())
+        .filter(|&(_, index)| index != assign::HOLE)
+        .collect();
+    let centres: Vec<(f32, f32)> = leds
+        .iter()
+        .map(|(color, _)| match model.options.hex_grid {
+            Some(orientation) => {
+                let (fx, fy) = orientation.center(color.x, color.y);
+                (fx * width, (1.0 - fy) * height)
+            }
+            // SVG rows count down from the top, grid rows up from the bottom.
+            None => (
+                (color.x as f32 + 0.5) * PITCH,
+                ((Y_SIZE - 1 - color.y) as f32 + 0.5) * PITCH,
+            ),
+        })
+        .collect();
+
+    let mut svg = String::new();
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+         width=\"{}in\" height=\"{}in\" viewBox=\"0 0 {width} {height}\">",
+        width / 1000.0,
+        height / 1000.0
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<title>LED mosaic of {}</title>",
+        escape(&model.options.picture_path)
+    )
+    .unwrap();
+
+    svg.push_str("<g id=\"breadboard\">\n");
+    writeln!(
+        svg,
+        "<rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" fill=\"#1f3d2b\"/>"
+    )
+    .unwrap();
+    for (led, ((x, y), &(color, index))) in centres.iter().zip(&leds).enumerate() {
+        let name = escape(&model.palette.colors[index].name);
+        let fill = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+        writeln!(svg, "<g id=\"led{led}\"><title>{name}</title>").unwrap();
+        for (side, dx) in [(0, -50.0), (1, 50.0)] {
+            writeln!(
+                svg,
+                "<rect id=\"connector{}pin\" x=\"{}\" y=\"{}\" width=\"20\" height=\"60\" fill=\"#9a9a9a\"/>",
+                2 * led + side,
+                x + dx - 10.0,
+                y + 30.0
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<circle cx=\"{x}\" cy=\"{}\" r=\"{LED_RADIUS}\" fill=\"{fill}\" stroke=\"#202020\" stroke-width=\"10\"/>",
+            y - 60.0
+        )
+        .unwrap();
+        let label = format!("{}. {name}", numbers[index]);
+        writeln!(
+            svg,
+            "<text x=\"{x}\" y=\"{}\" font-family=\"OCRA\" font-size=\"{LABEL_SIZE}\" \
+             fill=\"#ffffff\" text-anchor=\"middle\"{}>{label}</text>",
+            y + 150.0,
+            squeeze(&label)
+        )
+        .unwrap();
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</g>\n");
+
+    svg.push_str("<g id=\"schematic\" display=\"none\">\n");
+    for (led, ((x, y), &(_, index))) in centres.iter().zip(&leds).enumerate() {
+        let name = escape(&model.palette.colors[index].name);
+        // A diode pointing right, anode on the left.
+        writeln!(
+            svg,
+            "<g id=\"schematic{led}\" stroke=\"#000000\" stroke-width=\"10\" fill=\"none\">\
+             <line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\"/>\
+             <path d=\"M {} {} L {} {y} L {} {} Z\"/>\
+             <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/></g>",
+            x - 150.0,
+            x + 150.0,
+            x - 50.0,
+            y - 60.0,
+            x + 50.0,
+            x - 50.0,
+            y + 60.0,
+            x + 50.0,
+            y - 60.0,
+            x + 50.0,
+            y + 60.0
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"{x}\" y=\"{}\" font-family=\"Droid Sans\" font-size=\"{LABEL_SIZE}\" \
+             fill=\"#000000\" text-anchor=\"middle\"{}>{name}</text>",
+            y + 60.0 + LABEL_SIZE + 10.0,
+            squeeze(&name)
+        )
+        .unwrap();
+    }
+    svg.push_str("</g>\n");
+
+    svg.push_str("<g id=\"pcb\" display=\"none\">\n<g id=\"copper0\">\n");
+    for (led, (x, y)) in centres.iter().enumerate() {
+        for (side, dx) in [(0, -50.0), (1, 50.0)] {
+            writeln!(
+                svg,
+                "<circle id=\"connector{}pad\" cx=\"{}\" cy=\"{y}\" r=\"30\" fill=\"none\" \
+                 stroke=\"#f7bd13\" stroke-width=\"20\"/>",
+                2 * led + side,
+                x + dx
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</g>\n<g id=\"silkscreen\">\n");
+    for (x, y) in &centres {
+        writeln!(
+            svg,
+            "<circle cx=\"{x}\" cy=\"{y}\" r=\"{LED_RADIUS}\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"8\"/>"
+        )
+        .unwrap();
+    }
+    svg.push_str("</g>\n</g>\n</svg>\n");
+
+    fs::write(path, svg).expect("Unable to write Fritzing part.");
+    log::info!("Wrote {path} with {} LEDs.", centres.len());
+}
+
+/// Squeezes labels wider than a tile into it.
+fn squeeze(label: &str) -> String {
+    // Roughly the advance of an average glyph.
+    let estimate = label.chars().count() as f32 * LABEL_SIZE * 0.6;
+    if estimate > PITCH * 0.9 {
+        format!(
+            " textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"",
+            PITCH * 0.9
+        )
+    } else {
+        String::new()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,229 @@
+//! A fuse bead placement guide, written by `--export-fuse-beads`: the
+//! mosaic cut into square peg boards of `--board-size` pegs a side, one
+//! A4 page per board, then a shopping list of beads per color.
+//!
+//! Each peg is a circle in its bead's color holding the bead's Perler
+//! code: the color's `perler_code` if it has one, else the code of the
+//! bead in `perler_colors.json` with the color's name, else `??`.
+
+use super::color_sheets::MARGIN;
+use crate::assign;
+use crate::palette::ColorConfig;
+use crate::pdf::{self, Document, Page, A4};
+use crate::{Model, X_SIZE, Y_SIZE};
+use serde::Deserialize;
+use std::cmp::Reverse;
+
+/// Perler bead codes by color name.
+const PERLER_COLORS: &str = include_str!("perler_colors.json");
+/// Shown for colors with no known code.
+const UNKNOWN_CODE: &str = "??";
+/// Room at the top of each board page for its title.
+const HEADER: f32 = 56.0;
+const ROW_HEIGHT: f32 = 20.0;
+/// Shopping list rows on a page below its title.
+const ROWS_PER_PAGE: usize = ((A4.1 - 2.0 * MARGIN - 60.0) / ROW_HEIGHT) as usize;
+
+#[derive(Deserialize)]
+struct Bead {
+    code: String,
+    name: String,
+}
+
+/// Writes the boards from the picture's top-left in reading order, then
+/// the shopping list, most needed color first.
+pub fn export(model: &Model, path: &str) {
+    let board = model.options.board_size;
+    let codes: Vec<String> = perler_codes(&model.palette.colors)
+        .into_iter()
+        .map(|code| code.unwrap_or_else(|| UNKNOWN_CODE.to_string()))
+        .collect();
+
+    let (across, down) = (X_SIZE.div_ceil(board), Y_SIZE.div_ceil(board));
+    let mut document = Document::new(A4);
+    for row in 0..down {
+        for column in 0..across {
+            document.push(board_page(model, &codes, (column, row), (across, down)));
+        }
+    }
+
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| Reverse(used[index]));
+    let total: u64 = used.iter().sum();
+    let pages = order.len().div_ceil(ROWS_PER_PAGE).max(1);
+    for (sheet, rows) in order.chunks(ROWS_PER_PAGE).enumerate() {
+        let mut page = Page::new();
+        let title = if pages > 1 {
+            format!("Shopping list ({} of {pages})", sheet + 1)
+        } else {
+            "Shopping list".to_string()
+        };
+        page.fill_color(0, 0, 0);
+        page.text(MARGIN, A4.1 - MARGIN - 18.0, 18.0, &title);
+        page.text(
+            MARGIN,
+            A4.1 - MARGIN - 36.0,
+            10.0,
+            &format!("{total} beads in {} colors.", order.len()),
+        );
+        for (line, &index) in rows.iter().enumerate() {
+            let config = &model.palette.colors[index];
+            let baseline = A4.1 - MARGIN - 60.0 - (line + 1) as f32 * ROW_HEIGHT;
+            bead(&mut page, config, (MARGIN + 8.0, baseline + 4.0), 7.0, None);
+            page.fill_color(0, 0, 0);
+            page.text(MARGIN + 24.0, baseline, 11.0, &codes[index]);
+            let mut name = config.name.clone();
+            let room = A4.0 - 2.0 * MARGIN - 160.0;
+            while pdf::text_width(&name, 11.0) > room && name.pop().is_some() {}
+            page.text(MARGIN + 64.0, baseline, 11.0, &name);
+            let count = format!("{} beads", used[index]);
+            page.text(
+                A4.0 - MARGIN - pdf::text_width(&count, 11.0),
+                baseline,
+                11.0,
+                &count,
+            );
+        }
+        document.push(page);
+    }
+    document.save(path);
+    log::info!(
+        "Wrote {} boards of {board}x{board} pegs and a list of {} colors to {path}.",
+        across * down,
+        order.len()
+    );
+}
+
+/// The Perler code of each of `colors`, where one is known.
+pub(super) fn perler_codes(colors: &[ColorConfig]) -> Vec<Option<String>> {
+    let beads: Vec<Bead> =
+        serde_json::from_str(PERLER_COLORS).expect("The bundled Perler colors should parse.");
+    colors
+        .iter()
+        .map(|config| perler_code(config, &beads))
+        .collect()
+}
+
+/// The bead code of `config`, its own or else its name's.
+fn perler_code(config: &ColorConfig, beads: &[Bead]) -> Option<String> {
+    if let Some(code) = &config.perler_code {
+        return Some(code.clone());
+    }
+    let key = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    beads
+        .iter()
+        .find(|bead| key(&bead.name) == key(&config.name))
+        .map(|bead| bead.code.clone())
+}
+
+/// The pegs of board (`column`, `row`), counted from the picture's
+/// top-left, with a bead on each that has a tile.
+fn board_page(
+    model: &Model,
+    codes: &[String],
+    (column, row): (u64, u64),
+    (across, down): (u64, u64),
+) -> Page {
+    let board = model.options.board_size;
+    let left = column * board;
+    // Board rows run down from the top, grid rows up from the bottom.
+    let top = Y_SIZE - row * board;
+    let mut page = Page::new();
+    page.fill_color(0, 0, 0);
+    page.text(
+        MARGIN,
+        A4.1 - MARGIN - 18.0,
+        18.0,
+        &format!(
+            "Board {} of {} (column {}, row {})",
+            row * across + column + 1,
+            across * down,
+            column + 1,
+            row + 1
+        ),
+    );
+    let last_x = (left + board).min(X_SIZE) - 1;
+    let last_y = top.saturating_sub(board);
+    page.text(
+        MARGIN,
+        A4.1 - MARGIN - 34.0,
+        10.0,
+        &format!(
+            "Tiles x {left}-{last_x}, y {last_y}-{}, counting x from the left and y from the bottom.",
+            top - 1
+        ),
+    );
+
+    let pitch = (A4.0 - 2.0 * MARGIN) / board as f32;
+    let radius = pitch * 0.45;
+    let (grid_left, grid_top) = (MARGIN, A4.1 - MARGIN - HEADER);
+    for peg_y in 0..board {
+        for peg_x in 0..board {
+            let center = (
+                grid_left + (peg_x as f32 + 0.5) * pitch,
+                grid_top - (peg_y as f32 + 0.5) * pitch,
+            );
+            let (x, y) = (left + peg_x, top as i64 - 1 - peg_y as i64);
+            let index = (x < X_SIZE && y >= 0)
+                .then(|| model.assignment[(y as u64 * X_SIZE + x) as usize])
+                .filter(|&index| index != assign::HOLE);
+            match index {
+                Some(index) => bead(
+                    &mut page,
+                    &model.palette.colors[index],
+                    center,
+                    radius,
+                    Some(&codes[index]),
+                ),
+                None => {
+                    page.stroke_color(190, 190, 190);
+                    page.line_width(0.5);
+                    page.circle(center.0, center.1, radius * 0.3, false, true);
+                }
+            }
+        }
+    }
+    page
+}
+
+/// A bead in `config`'s color centered on `center`, with `code` inside in
+/// black or white, whichever reads better.
+pub(super) fn bead(
+    page: &mut Page,
+    config: &ColorConfig,
+    (x, y): (f32, f32),
+    radius: f32,
+    code: Option<&str>,
+) {
+    page.fill_color(config.r, config.g, config.b);
+    page.stroke_color(0, 0, 0);
+    page.line_width(0.5);
+    page.circle(x, y, radius, true, true);
+    let Some(code) = code else {
+        return;
+    };
+    let luma = 0.299 * config.r as f32 + 0.587 * config.g as f32 + 0.114 * config.b as f32;
+    if luma > 128.0 {
+        page.fill_color(0, 0, 0);
+    } else {
+        page.fill_color(255, 255, 255);
+    }
+    let mut size = radius * 0.7;
+    let room = radius * 1.7;
+    let width = pdf::text_width(code, size);
+    if width > room {
+        size *= room / width;
+    }
+    page.text(
+        x - pdf::text_width(code, size) / 2.0,
+        y - size / 3.0,
+        size,
+        code,
+    );
+}
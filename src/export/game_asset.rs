@@ -0,0 +1,79 @@
+//! The mosaic as Rust source for games, written by `--export-game-asset`:
+//! `MOSAIC_WIDTH`, `MOSAIC_HEIGHT`, and every tile's color in raster order,
+//! bottom row first, ready for `include!`.
+//!
+//! With `--compress-rle` the colors are stored as runs instead, with a
+//! `decode_rle` function that expands them back into the flat list.
+
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+
+/// Tiles written per line of the array.
+const PER_LINE: usize = 8;
+
+/// Writes the constants, as a flat array or as runs with `compress`.
+pub fn export(model: &Model, path: &str, compress: bool) {
+    let colors: Vec<(u8, u8, u8)> = model
+        .pixels
+        .iter()
+        .map(|color| (color.r, color.g, color.b))
+        .collect();
+    let mut source = format!(
+        "// Written by Block Mosaic Creator. Tiles are in raster order: index\n\
+         // y * MOSAIC_WIDTH + x, with row 0 at the bottom.\n\n\
+         pub const MOSAIC_WIDTH: usize = {X_SIZE};\n\
+         pub const MOSAIC_HEIGHT: usize = {Y_SIZE};\n\n"
+    );
+    let lines = if compress {
+        let runs = runs(&colors);
+        source.push_str("/// Runs of `(length, color)` covering every tile in order.\n");
+        source.push_str("pub const MOSAIC_RLE: &[(u16, (u8, u8, u8))] = &[\n");
+        let items: Vec<String> = runs
+            .iter()
+            .map(|&(length, (r, g, b))| format!("({length}, ({r}, {g}, {b}))"))
+            .collect();
+        push_items(&mut source, &items);
+        source.push_str(
+            "];\n\n\
+             /// Every tile's color, expanded from `MOSAIC_RLE`.\n\
+             pub fn decode_rle() -> Vec<(u8, u8, u8)> {\n    \
+             let mut pixels = Vec::with_capacity(MOSAIC_WIDTH * MOSAIC_HEIGHT);\n    \
+             for &(length, color) in MOSAIC_RLE {\n        \
+             pixels.extend(std::iter::repeat(color).take(length as usize));\n    \
+             }\n    \
+             pixels\n\
+             }\n",
+        );
+        format!("{} runs", runs.len())
+    } else {
+        source.push_str("pub const MOSAIC_PIXELS: &[(u8, u8, u8)] = &[\n");
+        let items: Vec<String> = colors
+            .iter()
+            .map(|(r, g, b)| format!("({r}, {g}, {b})"))
+            .collect();
+        push_items(&mut source, &items);
+        source.push_str("];\n");
+        format!("{} tiles", colors.len())
+    };
+    fs::write(path, source).expect("Unable to write game asset.");
+    log::info!("Wrote {path} with {lines}.");
+}
+
+/// Consecutive equal colors as `(length, color)`.
+fn runs(colors: &[(u8, u8, u8)]) -> Vec<(u16, (u8, u8, u8))> {
+    let mut runs: Vec<(u16, (u8, u8, u8))> = Vec::new();
+    for &color in colors {
+        match runs.last_mut() {
+            Some((length, last)) if *last == color && *length < u16::MAX => *length += 1,
+            _ => runs.push((1, color)),
+        }
+    }
+    runs
+}
+
+fn push_items(source: &mut String, items: &[String]) {
+    for line in items.chunks(PER_LINE) {
+        writeln!(source, "    {},", line.join(", ")).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+//! A build schedule as an iCalendar file, written by `--export-ics`: an
+//! all-day event a day from `--start-date`, each named "Mosaic Day N: X
+//! tiles" and listing in its description the tiles to place that day.
+//!
+//! Tiles are built row by row from the top-left, as the build batches of
+//! `--tile-corner-indicator` are, `--tiles-per-day` at a time, and listed
+//! as runs of one color along a row. Keyed-out holes are left out.
+
+use super::preset::civil_date;
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A calendar day, as days since 1970-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date(i64);
+
+impl FromStr for Date {
+    type Err = String;
+
+    /// A date written `YYYY-MM-DD`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid date '{s}'; use YYYY-MM-DD");
+        let parts: Vec<&str> = s.split('-').collect();
+        let [year, month, day] = parts[..] else {
+            return Err(invalid());
+        };
+        let (year, month, day): (i64, u32, u32) = (
+            year.parse().map_err(|_| invalid())?,
+            month.parse().map_err(|_| invalid())?,
+            day.parse().map_err(|_| invalid())?,
+        );
+        let date = Date(days_from_civil(year, month, day));
+        // Days past the end of their month roll over into the next.
+        if !(1..=12).contains(&month) || civil_date(date.0) != (year, month, day) {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl Date {
+    fn today() -> Date {
+        Date((now() / 86_400) as i64)
+    }
+
+    /// The date as iCalendar writes it, `YYYYMMDD`.
+    fn compact(self) -> String {
+        let (year, month, day) = civil_date(self.0);
+        format!("{year:04}{month:02}{day:02}")
+    }
+}
+
+/// Converts a Gregorian date to days since 1970-01-01, after Howard
+/// Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let day_of_year = (153 * mp + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// RFC 5545 folds content lines longer than this many bytes.
+const LINE_LIMIT: usize = 75;
+
+/// Writes one event per day of building, starting today without
+/// `--start-date`.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let start = options.start_date.unwrap_or_else(Date::today);
+    // Every tile to place as (row from the top, column, color), in build
+    // order.
+    let tiles: Vec<(u64, u64, usize)> = (0..Y_SIZE)
+        .flat_map(|row| (0..X_SIZE).map(move |column| (row, column)))
+        .filter_map(|(row, column)| {
+            let assigned = model.assignment[((Y_SIZE - 1 - row) * X_SIZE + column) as usize];
+            (assigned != assign::HOLE).then_some((row, column, assigned))
+        })
+        .collect();
+
+    let seconds = now();
+    let (year, month, day) = civil_date((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    let stamp = format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    );
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Block Mosaic Creator//Build schedule//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    let days = tiles.chunks(options.tiles_per_day as usize).enumerate();
+    let day_count = days.len();
+    for (index, day) in days {
+        let date = Date(start.0 + index as i64);
+        let number = index + 1;
+        let mut description = format!(
+            "Place these {} tiles, rows counted from the top:\n",
+            day.len()
+        );
+        for run in runs(day) {
+            let (row, first, last, color) = run;
+            let name = &model.palette.colors[color].name;
+            if first == last {
+                writeln!(description, "Row {}, column {}: {name}", row + 1, first + 1).unwrap();
+            } else {
+                writeln!(
+                    description,
+                    "Row {}, columns {}-{}: {name}",
+                    row + 1,
+                    first + 1,
+                    last + 1
+                )
+                .unwrap();
+            }
+        }
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!(
+                "UID:mosaic-day-{number}-{}@block-mosaic-creator",
+                start.compact()
+            ),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART;VALUE=DATE:{}", date.compact()),
+            format!("DTEND;VALUE=DATE:{}", Date(date.0 + 1).compact()),
+            format!("SUMMARY:Mosaic Day {number}: {} tiles", day.len()),
+            format!("DESCRIPTION:{}", escape(description.trim_end())),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in &lines {
+        fold(&mut calendar, line);
+    }
+    fs::write(path, calendar).expect("Unable to write calendar.");
+    log::info!("Wrote {path} with {day_count} days of building.");
+}
+
+/// A day's tiles as runs of one color along a row: `(row, first column,
+/// last column, color)`.
+fn runs(tiles: &[(u64, u64, usize)]) -> Vec<(u64, u64, u64, usize)> {
+    let mut runs: Vec<(u64, u64, u64, usize)> = Vec::new();
+    for &(row, column, color) in tiles {
+        match runs.last_mut() {
+            Some((last_row, _, last, last_color))
+                if *last_row == row && *last + 1 == column && *last_color == color =>
+            {
+                *last = column
+            }
+            _ => runs.push((row, column, column, color)),
+        }
+    }
+    runs
+}
+
+/// Escapes a text value, newlines included.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Appends `line` with a CRLF, continuing it on lines that start with a
+/// space wherever it passes `LINE_LIMIT` bytes, without splitting a
+/// character.
+fn fold(out: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LIMIT {
+            out.push_str("\r\n ");
+            // The leading space counts towards the limit.
+            length = 1;
+        }
+        out.push(c);
+        length += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
@@ -0,0 +1,183 @@
+//! A KiCad footprint for building the mosaic as an LED matrix, written by
+//! `--export-kicad`.
+//!
+//! Every tile is one round pad on `F.Cu`, `--kicad-pitch` millimetres from
+//! its neighbours, with its color's name beside it on `User.1`. Pad `n`,
+//! counted from 1 in raster order from the bottom-left, is tile `n`;
+//! keyed-out holes get no pad and are left out of the numbering. The
+//! footprint's origin is the middle of the grid.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fs;
+use std::path::Path;
+
+/// The footprint format version written, that of KiCad 6.
+const VERSION: &str = "20211014";
+/// Pad diameter as a fraction of the pitch.
+const PAD: f64 = 0.5;
+/// Label height as a fraction of the pitch.
+const LABEL_SIZE: f64 = 0.18;
+
+/// An S-expression: KiCad files are one list of nested lists.
+enum Node {
+    /// A bare keyword or number.
+    Atom(String),
+    /// A quoted string.
+    Text(String),
+    List(Vec<Node>),
+}
+
+fn atom(value: impl ToString) -> Node {
+    Node::Atom(value.to_string())
+}
+
+fn text(value: &str) -> Node {
+    Node::Text(value.to_string())
+}
+
+/// `(head children...)`.
+fn list(head: &str, children: impl IntoIterator<Item = Node>) -> Node {
+    Node::List(std::iter::once(atom(head)).chain(children).collect())
+}
+
+/// A length in millimetres, without trailing zeros.
+fn mm(value: f64) -> Node {
+    atom((value * 1e4).round() / 1e4)
+}
+
+impl Node {
+    /// Writes the node on one line.
+    fn write(&self, out: &mut String) {
+        match self {
+            Node::Atom(atom) => out.push_str(atom),
+            Node::Text(text) => {
+                out.push('"');
+                for c in text.chars() {
+                    match c {
+                        '"' | '\\' => {
+                            out.push('\\');
+                            out.push(c);
+                        }
+                        '\n' => out.push_str("\\n"),
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            Node::List(children) => {
+                out.push('(');
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    child.write(out);
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// `(fp_text kind value (at x y) (layer layer) (effects ...))`.
+fn fp_text(kind: &str, value: &str, (x, y): (f64, f64), layer: &str, size: f64) -> Node {
+    list(
+        "fp_text",
+        [
+            atom(kind),
+            text(value),
+            list("at", [mm(x), mm(y)]),
+            list("layer", [text(layer)]),
+            list(
+                "effects",
+                [list(
+                    "font",
+                    [
+                        list("size", [mm(size), mm(size)]),
+                        list("thickness", [mm(size * 0.15)]),
+                    ],
+                )],
+            ),
+        ],
+    )
+}
+
+/// Writes the footprint to `path`, placing pads as tiles are in `--output`.
+pub fn export(model: &Model, path: &str) {
+    let pitch = model.options.kicad_pitch;
+    let (width, height) = match model.options.hex_grid {
+        Some(orientation) => {
+            let width = X_SIZE as f64 * pitch;
+            (width, width / orientation.aspect() as f64)
+        }
+        None => (X_SIZE as f64 * pitch, Y_SIZE as f64 * pitch),
+    };
+    let name = Path::new(&model.options.picture_path)
+        .file_stem()
+        .map_or("mosaic".into(), |stem| stem.to_string_lossy())
+        .into_owned();
+
+    // Footprints list their pads after their text.
+    let mut children = vec![
+        text(&name),
+        list("version", [atom(VERSION)]),
+        list("generator", [atom(env!("CARGO_PKG_NAME"))]),
+        list("layer", [text("F.Cu")]),
+        list("attr", [atom("smd")]),
+        fp_text(
+            "reference",
+            "REF**",
+            (0.0, -height / 2.0 - pitch),
+            "F.SilkS",
+            1.0,
+        ),
+        fp_text("value", &name, (0.0, height / 2.0 + pitch), "F.Fab", 1.0),
+    ];
+    let mut pads = Vec::new();
+    for (color, &index) in model.pixels.iter().zip(&model.assignment) {
+        if index == assign::HOLE {
+            continue;
+        }
+        // KiCad's y grows downwards, grid rows count up from the bottom.
+        let (x, y) = match model.options.hex_grid {
+            Some(orientation) => {
+                let (fx, fy) = orientation.center(color.x, color.y);
+                (fx as f64 * width, (1.0 - fy as f64) * height)
+            }
+            None => (
+                (color.x as f64 + 0.5) * pitch,
+                ((Y_SIZE - 1 - color.y) as f64 + 0.5) * pitch,
+            ),
+        };
+        let (x, y) = (x - width / 2.0, y - height / 2.0);
+        let label = &model.palette.colors[index].name;
+        children.push(fp_text(
+            "user",
+            label,
+            (x, y + pitch * (PAD / 2.0 + LABEL_SIZE)),
+            "User.1",
+            pitch * LABEL_SIZE,
+        ));
+        pads.push(list(
+            "pad",
+            [
+                text(&(pads.len() + 1).to_string()),
+                atom("smd"),
+                atom("circle"),
+                list("at", [mm(x), mm(y)]),
+                list("size", [mm(pitch * PAD), mm(pitch * PAD)]),
+                list("layers", [text("F.Cu"), text("F.Paste"), text("F.Mask")]),
+            ],
+        ));
+    }
+    let count = pads.len();
+    children.extend(pads);
+
+    let mut out = String::from("(footprint");
+    for child in &children {
+        out.push_str("\n  ");
+        child.write(&mut out);
+    }
+    out.push_str("\n)\n");
+    fs::write(path, out).expect("Unable to write KiCad footprint.");
+    log::info!("Wrote {path} with {count} pads.");
+}
@@ -0,0 +1,115 @@
+//! The mosaic laid over the Earth for Google Earth, written by
+//! `--export-kml`: a polygon per tile, `--tile-size-m` on a side, centered
+//! on `--center-lat` and `--center-lon`, and filled with its color by a
+//! `PolyStyle` shared by the tiles of each palette color.
+//!
+//! `--rotate-degrees` turns the layout clockwise about its center, as seen
+//! from above, so the top of the mosaic faces that compass bearing rather
+//! than north. Corners are placed on a flat approximation of the ground
+//! around the center, which is close enough for mosaics a few kilometres
+//! across away from the poles.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+
+/// The mean radius KML's WGS84 coordinates are reckoned against.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Writes a placemark per tile, leaving keyed-out holes out.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let (lat, lon) = (
+        options.center_lat.expect("Checked when parsing."),
+        options.center_lon.expect("Checked when parsing."),
+    );
+    let size = options.tile_size_m;
+    let (sin, cos) = options.rotate_degrees.to_radians().sin_cos();
+    // Metres east and north of the center to longitude and latitude.
+    let place = |x: f64, y: f64| {
+        let (east, north) = (
+            (x - X_SIZE as f64 / 2.0) * size,
+            (y - Y_SIZE as f64 / 2.0) * size,
+        );
+        let (east, north) = (east * cos + north * sin, north * cos - east * sin);
+        (
+            lon + (east / (EARTH_RADIUS_M * lat.to_radians().cos())).to_degrees(),
+            lat + (north / EARTH_RADIUS_M).to_degrees(),
+        )
+    };
+
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n",
+    );
+    writeln!(
+        kml,
+        "<name>Mosaic of {}</name>",
+        escape(&options.picture_path)
+    )
+    .unwrap();
+    writeln!(
+        kml,
+        "<description>{X_SIZE} x {Y_SIZE} tiles of {size} m, palette {}</description>",
+        escape(options.palette_label())
+    )
+    .unwrap();
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    for (index, config) in model.palette.colors.iter().enumerate() {
+        if used[index] == 0 {
+            continue;
+        }
+        // KML colors are alpha, blue, green, red.
+        writeln!(
+            kml,
+            "<Style id=\"color-{index}\"><LineStyle><width>0</width></LineStyle>\
+             <PolyStyle><color>ff{:02x}{:02x}{:02x}</color><outline>0</outline></PolyStyle></Style>",
+            config.b, config.g, config.r
+        )
+        .unwrap();
+    }
+
+    let mut tiles = 0;
+    for (color, &index) in model.pixels.iter().zip(&model.assignment) {
+        if index == assign::HOLE {
+            continue;
+        }
+        let (x, y) = (color.x as f64, color.y as f64);
+        // Counterclockwise, closed on the first corner, as KML wants.
+        let ring: Vec<String> = [
+            (x, y),
+            (x + 1.0, y),
+            (x + 1.0, y + 1.0),
+            (x, y + 1.0),
+            (x, y),
+        ]
+        .into_iter()
+        .map(|(x, y)| {
+            let (lon, lat) = place(x, y);
+            format!("{lon:.7},{lat:.7},0")
+        })
+        .collect();
+        writeln!(
+            kml,
+            "<Placemark><name>{} ({}, {})</name><styleUrl>#color-{index}</styleUrl>\
+             <Polygon><altitudeMode>clampToGround</altitudeMode><outerBoundaryIs><LinearRing>\
+             <coordinates>{}</coordinates></LinearRing></outerBoundaryIs></Polygon></Placemark>",
+            escape(&model.palette.colors[index].name),
+            color.x,
+            color.y,
+            ring.join(" ")
+        )
+        .unwrap();
+        tiles += 1;
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    fs::write(path, kml).expect("Unable to write KML.");
+    log::info!("Wrote {path} with {tiles} tiles.");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,99 @@
+//! A black-and-white A4 grid with every tile's legend number in its place,
+//! followed by legend pages matching each number to a color, for builders
+//! who work from numbers rather than colors.
+
+use super::color_sheets::{ruled_grid, CELL, GRID_CORNER, MARGIN};
+use crate::assign;
+use crate::legend;
+use crate::pdf::{self, Document, Page, A4};
+use crate::{Model, X_SIZE};
+
+/// Type for the numbers in the grid, small enough for three digits a cell.
+const NUMBER_SIZE: f32 = CELL * 0.45;
+const ROW_HEIGHT: f32 = 18.0;
+const SWATCH: f32 = 13.0;
+/// Legend rows on a page below its title.
+const ROWS_PER_PAGE: usize = ((A4.1 - 2.0 * MARGIN - 40.0) / ROW_HEIGHT) as usize;
+
+/// Writes the grid page, printable on any printer, then the legend of the
+/// colors it uses in number order, the only pages in color.
+pub fn export(model: &Model, path: &str) {
+    let numbers = legend::numbers(model);
+    let mut document = Document::new(A4);
+    document.push(grid_page(model, &numbers));
+
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut entries: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    entries.sort_by_key(|&index| numbers[index]);
+    let pages = entries.len().div_ceil(ROWS_PER_PAGE);
+    for (sheet, rows) in entries.chunks(ROWS_PER_PAGE).enumerate() {
+        let mut page = Page::new();
+        let title = if pages > 1 {
+            format!("Legend ({} of {pages})", sheet + 1)
+        } else {
+            "Legend".to_string()
+        };
+        page.fill_color(0, 0, 0);
+        page.text(MARGIN, A4.1 - MARGIN - 18.0, 18.0, &title);
+        for (row, &index) in rows.iter().enumerate() {
+            let config = &model.palette.colors[index];
+            let baseline = A4.1 - MARGIN - 40.0 - (row + 1) as f32 * ROW_HEIGHT;
+            page.fill_color(0, 0, 0);
+            let number = numbers[index].to_string();
+            page.text(
+                MARGIN + 24.0 - pdf::text_width(&number, 11.0),
+                baseline,
+                11.0,
+                &number,
+            );
+            page.fill_color(config.r, config.g, config.b);
+            page.stroke_color(0, 0, 0);
+            page.line_width(0.8);
+            page.rect(MARGIN + 34.0, baseline - 3.0, SWATCH, SWATCH, true, true);
+            page.fill_color(0, 0, 0);
+            let mut name = config.name.clone();
+            let room = A4.0 - 2.0 * MARGIN - 140.0;
+            while pdf::text_width(&name, 11.0) > room && name.pop().is_some() {}
+            page.text(MARGIN + 56.0, baseline, 11.0, &name);
+            let count = format!("{} tiles", used[index]);
+            page.text(
+                A4.0 - MARGIN - pdf::text_width(&count, 11.0),
+                baseline,
+                11.0,
+                &count,
+            );
+        }
+        document.push(page);
+    }
+    document.save(path);
+    log::info!("Wrote a label sheet of {} colors to {path}.", entries.len());
+}
+
+/// Every filled tile's number centered in its cell, in black on white.
+fn grid_page(model: &Model, numbers: &[usize]) -> Page {
+    let mut page = Page::new();
+    page.fill_color(0, 0, 0);
+    page.text(MARGIN, A4.1 - MARGIN - 18.0, 18.0, "Label sheet");
+    page.text(
+        MARGIN,
+        A4.1 - MARGIN - 36.0,
+        10.0,
+        "Each number is a color on the legend pages. x counts from the left, y from the bottom.",
+    );
+    ruled_grid(&mut page);
+    page.fill_color(0, 0, 0);
+    for (tile, &assigned) in model.assignment.iter().enumerate() {
+        if assigned == assign::HOLE {
+            continue;
+        }
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        let label = numbers[assigned].to_string();
+        page.text(
+            GRID_CORNER + (x as f32 + 0.5) * CELL - pdf::text_width(&label, NUMBER_SIZE) / 2.0,
+            GRID_CORNER + (y as f32 + 0.5) * CELL - NUMBER_SIZE / 3.0,
+            NUMBER_SIZE,
+            &label,
+        );
+    }
+    page
+}
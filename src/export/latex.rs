@@ -0,0 +1,86 @@
+//! The mosaic as a TikZ picture for papers, written by `--export-latex`: a
+//! `tikzpicture` as wide as the text, to `\input` from a document that
+//! loads `tikz`.
+//!
+//! Each row's runs of one color are filled as one rectangle rather than a
+//! tile at a time, which keeps the file small enough for TeX to typeset
+//! quickly. With `--latex-caption` a `figure` wrapping the picture with
+//! that caption is written beside it, with `-figure` added to its name.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Writes the picture, and its figure when a caption is given.
+pub fn export(model: &Model, path: &str) {
+    let mut tex = format!(
+        "% Written by Block Mosaic Creator: {X_SIZE} x {Y_SIZE} tiles. Needs \\usepackage{{tikz}}.\n\
+         \\begin{{tikzpicture}}[x={unit:.6}\\linewidth, y={unit:.6}\\linewidth]\n",
+        unit = 1.0 / X_SIZE as f64
+    );
+    let mut rectangles = 0;
+    for y in 0..Y_SIZE {
+        let row = &model.assignment[(y * X_SIZE) as usize..((y + 1) * X_SIZE) as usize];
+        let mut x = 0;
+        while x < row.len() {
+            let index = row[x];
+            let length = row[x..].iter().take_while(|&&other| other == index).count();
+            if index != assign::HOLE {
+                let config = &model.palette.colors[index];
+                writeln!(
+                    tex,
+                    "\\fill[fill={{rgb,255:red,{};green,{};blue,{}}}] ({x},{y}) rectangle ({},{});",
+                    config.r,
+                    config.g,
+                    config.b,
+                    x + length,
+                    y + 1
+                )
+                .unwrap();
+                rectangles += 1;
+            }
+            x += length;
+        }
+    }
+    tex.push_str("\\end{tikzpicture}\n");
+    fs::write(path, tex).expect("Unable to write LaTeX picture.");
+    log::info!("Wrote {path} with {rectangles} rectangles.");
+
+    if let Some(caption) = &model.options.latex_caption {
+        let picture = Path::new(path);
+        let stem = picture
+            .file_stem()
+            .map_or("mosaic".into(), |stem| stem.to_string_lossy());
+        let figure_path = picture.with_file_name(format!("{stem}-figure.tex"));
+        let figure = format!(
+            "\\begin{{figure}}\n\
+             \\centering\n\
+             \\input{{{stem}}}\n\
+             \\caption{{{}}}\n\
+             \\label{{fig:{stem}}}\n\
+             \\end{{figure}}\n",
+            escape(caption)
+        );
+        fs::write(&figure_path, figure).expect("Unable to write LaTeX figure.");
+        log::info!("Wrote {}.", figure_path.display());
+    }
+}
+
+/// Escapes the characters LaTeX gives a meaning in running text.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
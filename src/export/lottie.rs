@@ -0,0 +1,158 @@
+//! The build of the mosaic as a Lottie animation, written by
+//! `--export-lottie` for web and mobile apps: each tile is a rectangle
+//! shape layer whose opacity is keyframed from 0 to 100, one after another
+//! in the order the solve placed them, over `--duration-ms`.
+//!
+//! Tiles the solve did not place, such as `--text`, are shown throughout,
+//! over a background layer in the render's background color.
+
+use super::build_sequence;
+use crate::{render, Color, Model, X_SIZE, Y_SIZE};
+use serde_json::{json, Value};
+use std::fs;
+
+const FRAME_RATE: f64 = 60.0;
+/// The Lottie schema version written, which players from 2020 on read.
+const VERSION: &str = "5.7.0";
+
+/// Writes the animation, one layer per tile, the last placed on top.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let scale = options.output_scale as f64;
+    let (width, height) = (X_SIZE as f64 * scale, Y_SIZE as f64 * scale);
+    let frames = (options.duration_ms as f64 / 1000.0 * FRAME_RATE).max(1.0);
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let (start, order) = build_sequence(model);
+
+    // Layers listed first are drawn on top.
+    let mut layers = Vec::with_capacity(start.len() + order.len() + 1);
+    let step = frames / order.len().max(1) as f64;
+    for (rank, &tile) in order.iter().enumerate().rev() {
+        let fade = json!({
+            "a": 1,
+            "k": [
+                keyframe(rank as f64 * step, 0.0),
+                keyframe((rank + 1) as f64 * step, 100.0),
+            ],
+        });
+        layers.push(tile_layer(&pixels[tile], scale, fade, frames));
+    }
+    for &tile in &start {
+        layers.push(tile_layer(
+            &pixels[tile],
+            scale,
+            static_value(100.0),
+            frames,
+        ));
+    }
+    let [r, g, b] = render::BACKGROUND.0;
+    layers.push(layer(
+        "background",
+        [width / 2.0, height / 2.0],
+        [width, height],
+        [r, g, b],
+        static_value(100.0),
+        frames,
+    ));
+    for (index, layer) in layers.iter_mut().enumerate() {
+        layer["ind"] = json!(index + 1);
+    }
+
+    let animation = json!({
+        "v": VERSION,
+        "nm": "Mosaic",
+        "fr": FRAME_RATE,
+        "ip": 0,
+        "op": frames,
+        "w": width,
+        "h": height,
+        "ddd": 0,
+        "assets": [],
+        "layers": layers,
+    });
+    let json = serde_json::to_string(&animation).expect("Lottie animation should serialize.");
+    fs::write(path, json).expect("Unable to write Lottie animation.");
+    log::info!(
+        "Wrote {path} with {} tiles over {} ms.",
+        order.len(),
+        options.duration_ms
+    );
+}
+
+/// `value` from frame `time` on, eased in and out.
+fn keyframe(time: f64, value: f64) -> Value {
+    json!({
+        "t": time,
+        "s": [value],
+        "i": { "x": [0.833], "y": [0.833] },
+        "o": { "x": [0.167], "y": [0.167] },
+    })
+}
+
+fn static_value(value: impl Into<Value>) -> Value {
+    json!({ "a": 0, "k": value.into() })
+}
+
+/// A tile's square, its gap left out as in `render_to_rgba`.
+fn tile_layer(color: &Color, scale: f64, opacity: Value, frames: f64) -> Value {
+    let inner = (scale - 1.0).max(1.0);
+    // Grid rows count up from the bottom, Lottie's y down from the top.
+    let left = color.x as f64 * scale;
+    let top = (Y_SIZE - 1 - color.y) as f64 * scale;
+    layer(
+        &format!("tile {},{}", color.x, color.y),
+        [left + inner / 2.0, top + inner / 2.0],
+        [inner, inner],
+        [color.r, color.g, color.b],
+        opacity,
+        frames,
+    )
+}
+
+/// A shape layer of one filled rectangle centred on `center`.
+fn layer(
+    name: &str,
+    center: [f64; 2],
+    size: [f64; 2],
+    rgb: [u8; 3],
+    opacity: Value,
+    frames: f64,
+) -> Value {
+    let [r, g, b] = rgb.map(|channel| channel as f64 / 255.0);
+    json!({
+        "ddd": 0,
+        "ty": 4,
+        "nm": name,
+        "sr": 1,
+        "ks": {
+            "o": opacity,
+            "r": static_value(0),
+            "p": static_value(vec![center[0], center[1], 0.0]),
+            "a": static_value(vec![0.0, 0.0, 0.0]),
+            "s": static_value(vec![100.0, 100.0, 100.0]),
+        },
+        "ao": 0,
+        "shapes": [
+            {
+                "ty": "rc",
+                "nm": "square",
+                "d": 1,
+                "s": static_value(size.to_vec()),
+                "p": static_value(vec![0.0, 0.0]),
+                "r": static_value(0),
+            },
+            {
+                "ty": "fl",
+                "nm": "fill",
+                "c": static_value(vec![r, g, b, 1.0]),
+                "o": static_value(100),
+                "r": 1,
+                "bm": 0,
+            },
+        ],
+        "ip": 0,
+        "op": frames,
+        "st": 0,
+        "bm": 0,
+    })
+}
@@ -0,0 +1,350 @@
+//! The mosaic as a printable 3-D model, written by `--export-stl` as one
+//! merged mesh and by `--export-obj` with one object and material per
+//! palette color.
+//!
+//! Each tile is a box as tall as its color's `height_mm`. Walls are only
+//! built where the surface steps, so neighbouring tiles of one height share
+//! a flat top, and every wall edge is split wherever a face beside it ends
+//! so the mesh closes without gaps. Tiles are laid like `--export-openscad`:
+//! bottom-left corner at the origin, `x` across, `y` up, `z` out of the
+//! picture, so the print reads like the PNG from above.
+
+use crate::legend;
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Stud radius and height as fractions of the pitch, as on the bricks the
+/// default pitch comes from.
+const STUD_RADIUS: f64 = 0.3;
+const STUD_HEIGHT: f64 = 0.2125;
+const STUD_SEGMENTS: usize = 24;
+
+/// Triangles over shared, deduplicated vertices, in millimetres.
+#[derive(Debug, Default)]
+struct Mesh {
+    vertices: Vec<[f64; 3]>,
+    index: HashMap<[u64; 3], usize>,
+    triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    fn vertex(&mut self, point: [f64; 3]) -> usize {
+        let key = point.map(f64::to_bits);
+        *self.index.entry(key).or_insert_with(|| {
+            self.vertices.push(point);
+            self.vertices.len() - 1
+        })
+    }
+
+    /// Adds a triangle wound counter-clockwise when seen from `outward`.
+    fn triangle(&mut self, points: [[f64; 3]; 3], outward: [f64; 3]) {
+        let [a, b, c] = points;
+        let facing = dot(cross(sub(b, a), sub(c, a)), outward);
+        let corners = if facing < 0.0 { [a, c, b] } else { [a, b, c] };
+        let triangle = corners.map(|point| self.vertex(point));
+        self.triangles.push(triangle);
+    }
+
+    fn quad(&mut self, [a, b, c, d]: [[f64; 3]; 4], outward: [f64; 3]) {
+        self.triangle([a, b, c], outward);
+        self.triangle([a, c, d], outward);
+    }
+}
+
+/// Closed boxes for the tiles with a height, side by side at `pitch`
+/// millimetres. `heights` is in raster order.
+fn heightfield(heights: &[Option<f64>], pitch: f64) -> Mesh {
+    let height = |x: i64, y: i64| {
+        let inside = (0..X_SIZE as i64).contains(&x) && (0..Y_SIZE as i64).contains(&y);
+        inside
+            .then(|| heights[(y as u64 * X_SIZE + x as u64) as usize])
+            .flatten()
+    };
+    // Every height at which a face meets the vertical line through a grid
+    // corner, so walls along it can share their vertices.
+    let column = |x: i64, y: i64| {
+        let mut levels = vec![0.0];
+        for (dx, dy) in [(-1, -1), (0, -1), (-1, 0), (0, 0)] {
+            levels.extend(height(x + dx, y + dy));
+        }
+        levels
+    };
+
+    let mut mesh = Mesh::default();
+    for y in 0..Y_SIZE as i64 {
+        for x in 0..X_SIZE as i64 {
+            let top = match height(x, y) {
+                Some(top) => top,
+                None => continue,
+            };
+            let (x0, y0) = (x as f64 * pitch, y as f64 * pitch);
+            let (x1, y1) = (x0 + pitch, y0 + pitch);
+            mesh.quad(
+                [[x0, y0, top], [x1, y0, top], [x1, y1, top], [x0, y1, top]],
+                [0.0, 0.0, 1.0],
+            );
+            mesh.quad(
+                [[x0, y0, 0.0], [x1, y0, 0.0], [x1, y1, 0.0], [x0, y1, 0.0]],
+                [0.0, 0.0, -1.0],
+            );
+            // Each side as its two grid corners and outward direction.
+            let sides = [
+                ((x + 1, y), (x + 1, y), (x + 1, y + 1), [1.0, 0.0, 0.0]),
+                ((x - 1, y), (x, y), (x, y + 1), [-1.0, 0.0, 0.0]),
+                ((x, y + 1), (x, y + 1), (x + 1, y + 1), [0.0, 1.0, 0.0]),
+                ((x, y - 1), (x, y), (x + 1, y), [0.0, -1.0, 0.0]),
+            ];
+            for ((nx, ny), start, end, outward) in sides {
+                // The taller side of a step builds the wall between them.
+                let bottom = height(nx, ny).unwrap_or(0.0);
+                if bottom >= top {
+                    continue;
+                }
+                let edge = |(cx, cy): (i64, i64)| {
+                    let mut levels: Vec<f64> = column(cx, cy)
+                        .into_iter()
+                        .filter(|&level| level > bottom && level < top)
+                        .collect();
+                    levels.sort_by(f64::total_cmp);
+                    levels.dedup();
+                    levels.insert(0, bottom);
+                    levels.push(top);
+                    let (px, py) = (cx as f64 * pitch, cy as f64 * pitch);
+                    levels.into_iter().map(|z| [px, py, z]).collect::<Vec<_>>()
+                };
+                wall(&mut mesh, &edge(start), &edge(end), outward);
+            }
+        }
+    }
+    mesh
+}
+
+/// Triangulates the strip between two vertical edges given bottom to top,
+/// zipping up whichever edge has the lower next vertex.
+fn wall(mesh: &mut Mesh, left: &[[f64; 3]], right: &[[f64; 3]], outward: [f64; 3]) {
+    let (mut l, mut r) = (0, 0);
+    while l + 1 < left.len() || r + 1 < right.len() {
+        let step_left =
+            r + 1 == right.len() || (l + 1 < left.len() && left[l + 1][2] <= right[r + 1][2]);
+        if step_left {
+            mesh.triangle([left[l], right[r], left[l + 1]], outward);
+            l += 1;
+        } else {
+            mesh.triangle([left[l], right[r], right[r + 1]], outward);
+            r += 1;
+        }
+    }
+}
+
+/// A closed cylinder standing on the top of tile (`x`, `y`).
+fn stud(mesh: &mut Mesh, x: u64, y: u64, top: f64, pitch: f64) {
+    let centre = [(x as f64 + 0.5) * pitch, (y as f64 + 0.5) * pitch];
+    let (radius, height) = (STUD_RADIUS * pitch, STUD_HEIGHT * pitch);
+    let rim = |i: usize, z: f64| {
+        let angle = i as f64 * std::f64::consts::TAU / STUD_SEGMENTS as f64;
+        [
+            centre[0] + radius * angle.cos(),
+            centre[1] + radius * angle.sin(),
+            z,
+        ]
+    };
+    for i in 0..STUD_SEGMENTS {
+        let j = (i + 1) % STUD_SEGMENTS;
+        let middle = (i as f64 + 0.5) * std::f64::consts::TAU / STUD_SEGMENTS as f64;
+        let outward = [middle.cos(), middle.sin(), 0.0];
+        mesh.quad(
+            [
+                rim(i, top),
+                rim(j, top),
+                rim(j, top + height),
+                rim(i, top + height),
+            ],
+            outward,
+        );
+        mesh.triangle(
+            [
+                [centre[0], centre[1], top + height],
+                rim(i, top + height),
+                rim(j, top + height),
+            ],
+            [0.0, 0.0, 1.0],
+        );
+        mesh.triangle(
+            [[centre[0], centre[1], top], rim(i, top), rim(j, top)],
+            [0.0, 0.0, -1.0],
+        );
+    }
+}
+
+/// The tiles whose color `include` accepts, with studs if asked for.
+fn build(model: &Model, include: impl Fn(usize) -> bool) -> Mesh {
+    let options = &model.options;
+    let heights: Vec<Option<f64>> = model
+        .assignment
+        .iter()
+        .map(|&index| {
+            (index != crate::assign::HOLE && include(index)).then(|| {
+                model.palette.colors[index]
+                    .height_mm
+                    .unwrap_or(options.tile_height)
+            })
+        })
+        .collect();
+    let mut mesh = heightfield(&heights, options.stud_pitch);
+    if options.studs {
+        for (tile, height) in heights.iter().enumerate() {
+            if let Some(height) = height {
+                let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+                stud(&mut mesh, x, y, *height, options.stud_pitch);
+            }
+        }
+    }
+    mesh
+}
+
+/// Writes every tile as one binary STL mesh.
+pub fn export_stl(model: &Model, path: &str) {
+    let mesh = build(model, |_| true);
+    let mut stl = Vec::with_capacity(84 + 50 * mesh.triangles.len());
+    let mut header = format!("Mosaic of {}", model.options.picture_path).into_bytes();
+    header.resize(80, b' ');
+    stl.extend(header);
+    stl.extend((mesh.triangles.len() as u32).to_le_bytes());
+    for triangle in &mesh.triangles {
+        let [a, b, c] = triangle.map(|index| mesh.vertices[index]);
+        let normal = cross(sub(b, a), sub(c, a));
+        let length = dot(normal, normal).sqrt().max(f64::MIN_POSITIVE);
+        for value in normal
+            .map(|n| n / length)
+            .iter()
+            .chain(&a)
+            .chain(&b)
+            .chain(&c)
+        {
+            stl.extend((*value as f32).to_le_bytes());
+        }
+        stl.extend(0u16.to_le_bytes());
+    }
+    fs::write(path, stl).expect("Unable to write STL model.");
+    log::info!("Wrote {path} with {} triangles.", mesh.triangles.len());
+}
+
+/// Writes one OBJ object per used color, each with a material of its own
+/// in the `.mtl` file written next to `path`.
+pub fn export_obj(model: &Model, path: &str) {
+    let numbers = legend::numbers(model);
+    let used = crate::assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| numbers[index]);
+
+    let mtl_path = Path::new(path).with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .expect("An OBJ path names a file.")
+        .to_string_lossy();
+    let (mut obj, mut mtl) = (String::new(), String::new());
+    writeln!(obj, "# Mosaic of {}", model.options.picture_path).unwrap();
+    writeln!(obj, "mtllib {mtl_name}").unwrap();
+    let mut offset = 1;
+    for &index in &order {
+        let config = &model.palette.colors[index];
+        let material = material_name(numbers[index], &config.name);
+        writeln!(mtl, "# {}", config.name).unwrap();
+        writeln!(mtl, "newmtl {material}").unwrap();
+        let [r, g, b] = [config.r, config.g, config.b].map(|c| c as f64 / 255.0);
+        writeln!(mtl, "Kd {r:.4} {g:.4} {b:.4}\n").unwrap();
+
+        let mesh = build(model, |color| color == index);
+        writeln!(obj, "o {material}\nusemtl {material}").unwrap();
+        for [x, y, z] in &mesh.vertices {
+            writeln!(obj, "v {x} {y} {z}").unwrap();
+        }
+        for [a, b, c] in &mesh.triangles {
+            writeln!(obj, "f {} {} {}", a + offset, b + offset, c + offset).unwrap();
+        }
+        offset += mesh.vertices.len();
+    }
+    fs::write(path, obj).expect("Unable to write OBJ model.");
+    fs::write(&mtl_path, mtl).expect("Unable to write OBJ materials.");
+    log::info!(
+        "Wrote {path} with {} colors and {}.",
+        order.len(),
+        mtl_path.display()
+    );
+}
+
+/// A material name without the spaces OBJ readers split on, led by the
+/// color's legend number so it stays unique.
+fn material_name(number: usize, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{number}_{name}")
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepped_tiles_close_without_gaps() {
+        // A few tiles of mixed heights with holes between them.
+        let mut heights = vec![None; (X_SIZE * Y_SIZE) as usize];
+        for (tile, height) in [
+            (0, 3.2),
+            (1, 5.0),
+            (2, 3.2),
+            (48, 5.0),
+            (49, 1.0),
+            (97, 3.2),
+        ] {
+            heights[tile] = Some(height);
+        }
+        let mesh = heightfield(&heights, 8.0);
+        // Closed and consistently wound: every edge is used as often in one
+        // direction as in the other. Tiles 48 and 97 touch only along a
+        // corner, so some edges there are used twice each way.
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+        for &[a, b, c] in &mesh.triangles {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *edges.entry((from, to)).or_default() += 1;
+            }
+        }
+        for (&(from, to), &count) in &edges {
+            assert_eq!(edges.get(&(to, from)), Some(&count));
+        }
+        // No triangle is degenerate.
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.map(|index| mesh.vertices[index]);
+            let normal = cross(sub(b, a), sub(c, a));
+            assert!(dot(normal, normal) > 0.0);
+        }
+        // The three tiles 3.2 mm tall each have a flat two-triangle top.
+        let tops = mesh
+            .triangles
+            .iter()
+            .filter(|triangle| triangle.iter().all(|&index| mesh.vertices[index][2] == 3.2))
+            .count();
+        assert_eq!(tops, 6);
+    }
+}
@@ -0,0 +1,86 @@
+//! Minecraft map item data (`map_<n>.dat`) showing the mosaic.
+
+use crate::assign;
+use crate::metric::ColorMetric;
+use crate::minecraft::{MinecraftVersion, Nbt};
+use crate::palette::{ColorConfig, ColorConfigs};
+use crate::{Model, X_SIZE, Y_SIZE};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+/// Maps are always 128 by 128 pixels.
+const MAP_SIZE: usize = 128;
+
+/// Writes a gzip-compressed, locked map whose pixels are the nearest map
+/// color to each tile, with the grid stretched over the whole map.
+pub fn export(model: &Model, path: &str, version: MinecraftVersion) {
+    let map_colors = version.map_colors();
+    // Matching against the map colors as a palette reuses the solver's
+    // distance metric.
+    let mut as_palette = ColorConfigs {
+        colors: map_colors
+            .iter()
+            .map(|&(id, [r, g, b])| ColorConfig::new(id.to_string(), r, g, b, 1))
+            .collect(),
+    };
+    as_palette.prepare();
+    let tile_ids: Vec<u8> = model
+        .pixels
+        .iter()
+        .zip(&model.assignment)
+        .map(|(color, &index)| {
+            // Map color 0 is transparent, which suits keyed-out holes.
+            if index == assign::HOLE {
+                return 0;
+            }
+            map_colors[assign::calculate_closest_color(
+                &as_palette,
+                color,
+                &HashMap::new(),
+                ColorMetric::Rgb,
+            )]
+            .0
+        })
+        .collect();
+
+    let mut colors = vec![0u8; MAP_SIZE * MAP_SIZE];
+    for (row, line) in colors.chunks_mut(MAP_SIZE).enumerate() {
+        let y = Y_SIZE - 1 - (row * Y_SIZE as usize / MAP_SIZE) as u64;
+        for (column, pixel) in line.iter_mut().enumerate() {
+            let x = (column * X_SIZE as usize / MAP_SIZE) as u64;
+            *pixel = tile_ids[(y * X_SIZE + x) as usize];
+        }
+    }
+
+    let mut nbt = Nbt::new();
+    nbt.begin_compound("");
+    nbt.begin_compound("data");
+    nbt.byte("scale", 0);
+    if version.named_dimensions() {
+        nbt.string("dimension", "minecraft:overworld");
+    } else {
+        nbt.byte("dimension", 0);
+    }
+    nbt.byte("trackingPosition", 0);
+    nbt.byte("unlimitedTracking", 0);
+    nbt.byte("locked", 1);
+    nbt.int("xCenter", 0);
+    nbt.int("zCenter", 0);
+    nbt.empty_list("banners");
+    nbt.empty_list("frames");
+    nbt.byte_array("colors", &colors);
+    nbt.end_compound();
+    nbt.int("DataVersion", version.data_version());
+    nbt.end_compound();
+
+    let file = File::create(path).expect("Unable to write Minecraft map.");
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    encoder
+        .write_all(&nbt.into_bytes())
+        .and_then(|_| encoder.finish().map(|_| ()))
+        .expect("Unable to write Minecraft map.");
+    log::info!("Wrote {path}.");
+}
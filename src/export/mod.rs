@@ -0,0 +1,553 @@
+//! Files written from a finished model.
+
+mod android;
+mod aseprite;
+mod atlas;
+mod before_after;
+mod blender;
+mod bom;
+mod braille;
+mod bricks;
+mod color_book;
+mod color_sheets;
+mod color_steps;
+mod cross_stitch;
+mod css;
+mod debug_dump;
+mod discord;
+mod embroidery;
+mod fritzing;
+mod fuse_beads;
+mod game_asset;
+mod ics;
+mod kicad;
+mod kml;
+mod label_sheet;
+mod latex;
+mod lottie;
+mod mesh;
+mod minecraft_map;
+mod openraster;
+mod openscad;
+mod packing_list;
+mod parts;
+mod png_band;
+mod png_column_labels;
+mod png_composited;
+mod png_indexed;
+mod png_numbered_grid;
+mod png_sections;
+mod png_split_rgb;
+mod png_thumbnail;
+mod png_watermarked_grid;
+mod png_zoom;
+mod preset;
+mod qr;
+mod report;
+mod segments;
+mod sprite_animation;
+mod stardew;
+mod stickersheet;
+mod svg;
+mod svg_animation;
+mod textile;
+mod xcf;
+mod xlsx;
+
+use crate::assign;
+use crate::bundle;
+use crate::font;
+use crate::label::{self, LabelFont};
+use crate::legend;
+use crate::palette;
+use crate::render::{self, Presentation};
+use crate::session::SavedAssignment;
+use crate::{Color, Model};
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+use std::fs;
+use std::path::Path;
+
+pub use before_after::Layout as BeforeAfterLayout;
+pub use ics::Date as IcsDate;
+pub use packing_list::Format as PackingListFormat;
+pub use png_sections::Axis as SectionAxis;
+pub use preset::run as run_preset;
+pub use svg::Filters as SvgFilters;
+pub use textile::{ChartStyle as TextileChartStyle, RowDirection as TextileRowDirection};
+
+/// Writes every export requested on the command line.
+pub fn run(model: &Model) {
+    let style = ImageStyle::from_options(model);
+    let output = model
+        .options
+        .output
+        .as_ref()
+        .map(|path| write_output(model, path, &style));
+    if let Some(path) = &model.options.report {
+        write_report(model, path, output, &style);
+    }
+    if let Some(path) = &model.options.export_webp {
+        write_webp(model, path, &style);
+    }
+    if let Some(path) = &model.options.export_json {
+        write_json(model, path, model.options.palette_sort_by_usage);
+    }
+    if let Some(path) = &model.options.export_pdf_color_sheets {
+        color_sheets::export(model, path);
+    }
+    if let Some(path) = &model.options.export_label_sheet {
+        label_sheet::export(model, path);
+    }
+    if let Some(path) = &model.options.export_fuse_beads {
+        fuse_beads::export(model, path);
+    }
+    if let Some(path) = &model.options.export_stickersheet {
+        stickersheet::export(model, path);
+    }
+    if let Some(path) = &model.options.export_cross_stitch {
+        cross_stitch::export(model, path);
+    }
+    if let Some(path) = &model.options.export_color_book {
+        color_book::export(model, path);
+    }
+    if let Some(path) = &model.options.export_pdf_bom {
+        bom::export(model, path);
+    }
+    if let Some(path) = &model.options.export_textile_chart {
+        textile::export(model, path);
+    }
+    if let Some(path) = &model.options.export_packing_list {
+        packing_list::export(model, path);
+    }
+    if let Some(dir) = &model.options.export_png_sections {
+        png_sections::export(model, dir);
+    }
+    if let Some(path) = &model.options.export_sprite_animation {
+        sprite_animation::export(model, path);
+    }
+    if let Some(path) = &model.options.export_lottie {
+        lottie::export(model, path);
+    }
+    if let Some(path) = &model.options.export_braille {
+        braille::export(model, path);
+    }
+    if let Some(path) = &model.options.export_png_thumbnail {
+        png_thumbnail::export(model, path);
+    }
+    if let Some(path) = &model.options.export_svg {
+        svg::export(model, path);
+    }
+    if let Some(path) = &model.options.export_blender {
+        blender::export(model, path);
+    }
+    if let Some(path) = &model.options.export_kml {
+        kml::export(model, path);
+    }
+    if let Some(path) = &model.options.export_png_composited {
+        png_composited::export(model, path);
+    }
+    if let Some(path) = &model.options.export_png_numbered_grid {
+        png_numbered_grid::export(model, path);
+    }
+    if let Some(path) = &model.options.export_png_column_labels {
+        png_column_labels::export(model, path);
+    }
+    if let Some(path) = &model.options.export_xcf {
+        xcf::export(model, path);
+    }
+    if let Some(path) = &model.options.export_ics {
+        ics::export(model, path);
+    }
+    if let Some(path) = &model.options.export_discord_embed {
+        discord::export(model, path);
+    }
+    if let Some(path) = &model.options.export_latex {
+        latex::export(model, path);
+    }
+    if let Some(dir) = &model.options.export_png_split_rgb {
+        png_split_rgb::export(model, dir);
+    }
+    if let Some(path) = &model.options.export_png_watermarked_grid {
+        png_watermarked_grid::export(model, path);
+    }
+    if let Some(path) = &model.options.export_svg_animation {
+        svg_animation::export(model, path);
+    }
+    if let Some(dir) = &model.options.export_color_steps {
+        color_steps::export(model, dir);
+    }
+    if let Some(path) = &model.options.export_before_after {
+        before_after::export(model, path);
+    }
+    if let Some(path) = &model.options.save_bundle {
+        bundle::save(model, path);
+    }
+    if let Some(path) = &model.options.export_png_indexed {
+        png_indexed::export(model, path);
+    }
+    if let Some(path) = &model.options.export_png_band {
+        png_band::export(model, path);
+    }
+    if let Some((size, path)) = &model.options.export_png_zoom {
+        png_zoom::export(model, *size, path);
+    }
+    if let Some(path) = &model.options.export_png_atlas {
+        atlas::export(model, path, model.options.atlas_layout);
+    }
+    if let Some(path) = &model.options.export_stardew {
+        stardew::export(model, path);
+    }
+    if let Some(path) = &model.options.export_gimp_palette {
+        write_gimp_palette(model, path);
+    }
+    if let Some(path) = &model.options.export_css_variables {
+        css::export(model, path);
+    }
+    if let Some(dir) = &model.options.export_android_theme {
+        android::export(model, dir);
+    }
+    if let Some(path) = &model.options.export_game_asset {
+        game_asset::export(model, path, model.options.compress_rle);
+    }
+    if let Some(path) = &model.options.export_qrcode_palette {
+        qr::export_palette(&model.declared_palette(), path);
+    }
+    if let Some(path) = &model.options.export_xls {
+        xlsx::export(model, path);
+    }
+    if let Some(path) = &model.options.export_bricks {
+        bricks::export(model, path);
+    }
+    if let Some(path) = &model.options.export_openscad {
+        openscad::export(model, path);
+    }
+    if let Some(path) = &model.options.export_stl {
+        mesh::export_stl(model, path);
+    }
+    if let Some(path) = &model.options.export_obj {
+        mesh::export_obj(model, path);
+    }
+    if let Some(path) = &model.options.export_fritzing {
+        fritzing::export(model, path);
+    }
+    if let Some(path) = &model.options.export_kicad {
+        kicad::export(model, path);
+    }
+    if let Some(path) = &model.options.export_embroidery {
+        embroidery::export(model, path);
+    }
+    if let Some(path) = &model.options.export_openraster {
+        openraster::export(model, path);
+    }
+    if let Some(path) = &model.options.output_aseprite {
+        aseprite::export(model, path);
+    }
+    if let Some(path) = &model.options.export_minecraft_map {
+        minecraft_map::export(model, path, model.options.minecraft_version);
+    }
+    if let (Some(path), Some(segments)) = (&model.options.export_segments, &model.segments) {
+        segments::export(model, segments, path);
+    }
+    if let Some(dir) = &model.options.debug_dump {
+        debug_dump::export(model, dir);
+    }
+    if let Some(name) = &model.options.export_preset {
+        if let Err(err) = run_preset(model, name) {
+            panic!("{err}")
+        }
+    }
+}
+
+/// How the mosaic image is drawn.
+struct ImageStyle {
+    scale: u32,
+    tile_label: bool,
+    transparent_bg: bool,
+    /// Height of the `--export-png-progress-bar`, when there is one.
+    progress_bar: Option<u32>,
+}
+
+impl ImageStyle {
+    fn from_options(model: &Model) -> ImageStyle {
+        ImageStyle {
+            scale: model.options.output_scale,
+            tile_label: model.options.tile_label,
+            transparent_bg: model.options.transparent_bg,
+            progress_bar: model
+                .options
+                .progress_bar
+                .then_some(model.options.bar_height_px),
+        }
+    }
+}
+
+/// Writes the report. It embeds the mosaic, so one is written next to it
+/// unless `image` was already written.
+fn write_report(model: &Model, path: &str, image: Option<String>, style: &ImageStyle) {
+    let image = image.unwrap_or_else(|| {
+        let image = Path::new(path).with_extension("png");
+        write_output(model, &image.to_string_lossy(), style)
+    });
+    report::export(model, path, &image);
+}
+
+fn write_json(model: &Model, path: &str, sort_by_usage: bool) {
+    let mut saved = SavedAssignment::from_model(model);
+    if sort_by_usage {
+        saved.sort_by_usage();
+    }
+    if model.options.export_json_schema_validated {
+        // The palette is the part of the file `--color-config-schema`
+        // describes.
+        let value = serde_json::to_value(&saved).expect("Assignment should serialize.");
+        let errors = palette::validate(&value["palette"]);
+        if !errors.is_empty() {
+            panic!(
+                "{path} was not written: its palette does not match --color-config-schema:\n{}",
+                errors.join("\n")
+            )
+        }
+        log::info!("The palette in {path} matches --color-config-schema.");
+    }
+    saved.save(path);
+    log::info!("Wrote {path}.");
+}
+
+/// The frame, margin, and caption requested for the exported mosaic.
+fn presentation(model: &Model) -> Presentation {
+    let options = &model.options;
+    Presentation {
+        frame: model.frame.map(|frame| {
+            let config = &model.palette.colors[frame.color];
+            (frame, Rgb([config.r, config.g, config.b]))
+        }),
+        margin: options.margin,
+        margin_color: options.margin_color,
+        caption: options.caption.clone(),
+    }
+}
+
+/// Writes the mosaic, or a captioned sheet of every solve when comparing,
+/// and returns the path actually written.
+fn write_output(model: &Model, path: &str, style: &ImageStyle) -> String {
+    let img = output_image(model, style);
+    let path = match img {
+        DynamicImage::ImageRgba8(_) => transparent_path(path),
+        _ => path.to_string(),
+    };
+    img.save(&path).expect("Unable to write output image.");
+    log::info!("Wrote {path}.");
+    path
+}
+
+/// Writes the `--output` image as WebP. The `image` crate only encodes
+/// WebP losslessly, which suits the mosaic's flat colors anyway, so a
+/// `--webp-quality` below 100 is ignored with a note.
+fn write_webp(model: &Model, path: &str, style: &ImageStyle) {
+    if !image::ImageFormat::WebP.writing_enabled() {
+        log::warn!("Skipped {path}: this build of the image crate has no WebP support.");
+        return;
+    }
+    let quality = model.options.webp_quality;
+    if quality < 100 {
+        log::info!("WebP quality {quality} ignored: the built-in encoder only writes lossless WebP, so {path} is lossless.");
+    }
+    let img = output_image(model, style);
+    let file = fs::File::create(path).expect("Unable to write WebP image.");
+    img.write_with_encoder(WebPEncoder::new_lossless(std::io::BufWriter::new(file)))
+        .expect("Unable to write WebP image.");
+    log::info!("Wrote {path}.");
+}
+
+/// The image `--output` writes: RGBA with `--transparent-bg`, else RGB on
+/// the background color.
+fn output_image(model: &Model, style: &ImageStyle) -> DynamicImage {
+    let scale = style.scale;
+    let jitter = model.options.brightness_jitter;
+    if !model.comparisons.is_empty() {
+        let renders: Vec<_> = model
+            .solutions()
+            .iter()
+            .map(|solution| {
+                (
+                    model.describe(solution),
+                    render::flatten(
+                        &render_grid(
+                            model,
+                            &render::jitter(solution.pixels, jitter),
+                            solution.assignment,
+                            scale,
+                        ),
+                        render::BACKGROUND,
+                    ),
+                )
+            })
+            .collect();
+        return DynamicImage::ImageRgb8(render::contact_sheet(&renders));
+    }
+    let pixels = render::jitter(&model.pixels, jitter);
+    let mut grid = render_grid(model, &pixels, &model.assignment, scale);
+    if style.tile_label {
+        // Number tiles the way the legend numbers their colors.
+        let numbers = legend::numbers(model);
+        let labels: Vec<String> = model
+            .assignment
+            .iter()
+            .filter(|&&index| index != assign::HOLE)
+            .map(|&index| numbers[index].to_string())
+            .collect();
+        let pixels = filled(&model.pixels, &model.assignment);
+        let font = LabelFont::load(model.options.tile_label_font.as_deref());
+        match model.options.hex_grid {
+            Some(orientation) => {
+                label::label_hex_tiles(&mut grid, &pixels, &labels, scale, &font, orientation)
+            }
+            None => label::label_tiles(&mut grid, &pixels, &labels, scale, &font),
+        }
+    }
+    label::mark_tiles(
+        &mut grid,
+        &model.pixels,
+        &model.forced_tiles(),
+        scale,
+        model.options.hex_grid,
+    );
+    if let Some(height) = style.progress_bar {
+        draw_progress_bar(model, &mut grid, height);
+    }
+    let img = render::present(&grid, scale, &presentation(model));
+    if style.transparent_bg {
+        DynamicImage::ImageRgba8(img)
+    } else {
+        DynamicImage::ImageRgb8(render::flatten(&img, render::BACKGROUND))
+    }
+}
+
+/// How much of the mosaic shows through the progress bar.
+const BAR_OPACITY: f32 = 0.6;
+
+/// Darkens the bottom `height` pixels of `grid` and writes the tile, grid,
+/// and color counts across them in white, as large as fits.
+fn draw_progress_bar(model: &Model, grid: &mut RgbaImage, height: u32) {
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let text = format!(
+        "{} tiles | {}x{} grid | {} colors used",
+        model.assignment.len() - model.holes.len(),
+        crate::X_SIZE,
+        crate::Y_SIZE,
+        used.iter().filter(|&&count| count > 0).count()
+    );
+    let height = height.min(grid.height());
+    let top = grid.height() - height;
+    for y in top..grid.height() {
+        for x in 0..grid.width() {
+            let Rgba([r, g, b, a]) = *grid.get_pixel(x, y);
+            let shade = |c: u8| (c as f32 * (1.0 - BAR_OPACITY)).round() as u8;
+            let alpha = a as f32 / 255.0;
+            let alpha = BAR_OPACITY + alpha * (1.0 - BAR_OPACITY);
+            grid.put_pixel(
+                x,
+                y,
+                Rgba([shade(r), shade(g), shade(b), (alpha * 255.0).round() as u8]),
+            );
+        }
+    }
+    let fits_height = (height - 2) / font::GLYPH_HEIGHT;
+    let fits_width = grid.width().saturating_sub(4) / font::text_width(&text, 1).max(1);
+    let text_scale = fits_height.min(fits_width).max(1);
+    let text_top = top + (height - font::GLYPH_HEIGHT * text_scale) / 2;
+    font::draw_text(
+        grid,
+        (grid
+            .width()
+            .saturating_sub(font::text_width(&text, text_scale)))
+            / 2,
+        text_top,
+        &text,
+        Rgba([255, 255, 255, 255]),
+        text_scale,
+    );
+}
+
+/// GIMP shows at most this many characters of a color's name.
+const GPL_NAME_LIMIT: usize = 30;
+
+/// Writes the colors the mosaic uses, frame included, as a GIMP palette.
+fn write_gimp_palette(model: &Model, path: &str) {
+    let colors: Vec<(String, [u8; 3])> = parts::parts(model)
+        .into_iter()
+        .filter(|part| part.used > 0)
+        .map(|part| {
+            if part.name.chars().count() <= GPL_NAME_LIMIT {
+                return (part.name, part.rgb);
+            }
+            let short: String = part.name.chars().take(GPL_NAME_LIMIT).collect();
+            log::info!("Shortened '{}' to '{short}' in {path}.", part.name);
+            (short, part.rgb)
+        })
+        .collect();
+    let name = Path::new(&model.options.picture_path)
+        .file_stem()
+        .map_or("Mosaic".into(), |stem| stem.to_string_lossy());
+    fs::write(path, palette::write_gpl(&name, &colors)).expect("Unable to write GIMP palette.");
+    log::info!("Wrote {} colors to {path}.", colors.len());
+}
+
+/// The tiles of one solve as the grid layout in use draws them: squares,
+/// adaptive cells, or hexagons. Keyed-out holes are left transparent.
+fn render_grid(model: &Model, pixels: &[Color], assignment: &[usize], scale: u32) -> RgbaImage {
+    if let Some(orientation) = model.options.hex_grid {
+        return render::render_hex_to_rgba(&filled(pixels, assignment), scale, orientation);
+    }
+    match model.leaves(assignment) {
+        Some(cells) => render::render_cells_to_rgba(pixels, &cells, scale),
+        None => render::render_to_rgba(&filled(pixels, assignment), scale),
+    }
+}
+
+/// The tiles shown from the start of a build animation, those the solve
+/// did not place such as `--text`, and then the rest in the order it placed
+/// them. Keyed-out holes are in neither.
+fn build_sequence(model: &Model) -> (Vec<usize>, Vec<usize>) {
+    let order: Vec<usize> = model
+        .build_order
+        .iter()
+        .copied()
+        .filter(|&tile| model.assignment[tile] != assign::HOLE)
+        .collect();
+    let mut placed = vec![false; model.assignment.len()];
+    for &tile in &order {
+        placed[tile] = true;
+    }
+    let start = (0..model.assignment.len())
+        .filter(|&tile| !placed[tile] && model.assignment[tile] != assign::HOLE)
+        .collect();
+    (start, order)
+}
+
+/// The tiles of `pixels` that are not keyed-out holes.
+fn filled(pixels: &[Color], assignment: &[usize]) -> Vec<Color> {
+    pixels
+        .iter()
+        .zip(assignment)
+        .filter(|&(_, &index)| index != assign::HOLE)
+        .map(|(color, _)| color.clone())
+        .collect()
+}
+
+/// Names transparent exports `*_transparent.png` so they are not mistaken
+/// for the usual opaque render.
+fn transparent_path(path: &str) -> String {
+    if path.ends_with("_transparent.png") {
+        return path.to_string();
+    }
+    match path.strip_suffix(".png") {
+        Some(stem) => {
+            let renamed = format!("{stem}_transparent.png");
+            log::info!("--transparent-bg writes RGBA; saving {path} as {renamed}.");
+            renamed
+        }
+        None => panic!("--transparent-bg needs a .png output, got {path}"),
+    }
+}
@@ -0,0 +1,98 @@
+//! The mosaic as an OpenRaster (`.ora`) image for GIMP, Krita, and
+//! MyPaint, written by `--export-openraster`: a layer per palette color
+//! holding just its tiles at `--output-scale`, with the gaps between tiles
+//! and everything under them left transparent.
+//!
+//! An OpenRaster file is a zip of the layers' PNGs, a `stack.xml` listing
+//! them top first, the flattened `mergedimage.png`, and a thumbnail of at
+//! most 256 pixels a side.
+
+use super::render_grid;
+use crate::render;
+use crate::{assign, quadtree, zip, Color, Model};
+use image::{imageops, ImageFormat, RgbaImage};
+use std::fs;
+use std::io::Cursor;
+
+const MIMETYPE: &str = "image/openraster";
+const THUMBNAIL_SIDE: u32 = 256;
+
+/// Writes a layer for every color in use, the first color on top.
+pub fn export(model: &Model, path: &str) {
+    let scale = model.options.output_scale;
+    let merged = render_grid(model, &model.pixels, &model.assignment, scale);
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let leaves = model.leaves(&model.assignment);
+
+    let mut parts = Vec::new();
+    let mut stack = String::new();
+    for (index, config) in model.palette.colors.iter().enumerate() {
+        if used[index] == 0 {
+            continue;
+        }
+        let tiles = || -> Vec<Color> {
+            model
+                .pixels
+                .iter()
+                .zip(&model.assignment)
+                .filter(|&(_, &assigned)| assigned == index)
+                .map(|(color, _)| color.clone())
+                .collect()
+        };
+        // The same choice of layout as `render_grid`.
+        let layer = match (model.options.hex_grid, &leaves) {
+            (Some(orientation), _) => render::render_hex_to_rgba(&tiles(), scale, orientation),
+            (None, Some(cells)) => {
+                let cells: Vec<quadtree::Cell> = cells
+                    .iter()
+                    .filter(|cell| model.assignment[cell.first_tile()] == index)
+                    .copied()
+                    .collect();
+                render::render_cells_to_rgba(&model.pixels, &cells, scale)
+            }
+            (None, None) => render::render_to_rgba(&tiles(), scale),
+        };
+        let src = format!("data/layer{index}.png");
+        stack.push_str(&format!(
+            "    <layer name=\"{}\" src=\"{src}\" x=\"0\" y=\"0\" opacity=\"1.0\" visibility=\"visible\"/>\n",
+            escape(&config.name)
+        ));
+        parts.push((src, png(&layer)));
+    }
+    let layers = parts.len();
+
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <image version=\"0.0.3\" w=\"{}\" h=\"{}\">\n  <stack>\n{stack}  </stack>\n</image>\n",
+        merged.width(),
+        merged.height()
+    );
+    let (width, height) = (merged.width(), merged.height());
+    let shrink = (THUMBNAIL_SIDE as f32 / width.max(height) as f32).min(1.0);
+    let thumbnail = imageops::thumbnail(
+        &merged,
+        ((width as f32 * shrink).round() as u32).max(1),
+        ((height as f32 * shrink).round() as u32).max(1),
+    );
+    parts.insert(0, ("stack.xml".to_string(), xml.into_bytes()));
+    parts.push(("mergedimage.png".to_string(), png(&merged)));
+    parts.push(("Thumbnails/thumbnail.png".to_string(), png(&thumbnail)));
+    fs::write(path, zip::write_with_mimetype(MIMETYPE, &parts))
+        .expect("Unable to write OpenRaster image.");
+    log::info!("Wrote {path} with {layers} layers.");
+}
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, ImageFormat::Png)
+        .expect("Writing to memory cannot fail.");
+    bytes.into_inner()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,42 @@
+//! An OpenSCAD script building the mosaic in 3-D, written by
+//! `--export-openscad`.
+//!
+//! The script calls one `tile` module per tile, so the tile's shape can be
+//! changed in OpenSCAD without exporting again.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+
+/// Writes the script to `path`. Tiles are laid from the bottom-left corner
+/// at the origin, `x` across and `y` up, with `r`, `g`, and `b` from 0 to
+/// 255 and `h` the height of the tile's color.
+pub fn export(model: &Model, path: &str) {
+    let pitch = model.options.stud_pitch;
+    let mut scad = String::new();
+    writeln!(scad, "// Mosaic of {}", model.options.picture_path).unwrap();
+    writeln!(scad, "// Grid: {X_SIZE} x {Y_SIZE} tiles, {pitch} mm apart").unwrap();
+    writeln!(scad, "// Palette: {}\n", model.options.palette_label()).unwrap();
+    writeln!(scad, "pitch = {pitch};\n").unwrap();
+    scad.push_str("module tile(x, y, r, g, b, h) {\n");
+    scad.push_str("    color([r, g, b] / 255)\n");
+    scad.push_str("        translate([x * pitch, y * pitch, 0])\n");
+    scad.push_str("            cube([pitch, pitch, h]);\n");
+    scad.push_str("}\n\n");
+    for (color, &index) in model.pixels.iter().zip(&model.assignment) {
+        if index == assign::HOLE {
+            continue;
+        }
+        let height = model.palette.colors[index]
+            .height_mm
+            .unwrap_or(model.options.tile_height);
+        writeln!(
+            scad,
+            "tile({}, {}, {}, {}, {}, {height});",
+            color.x, color.y, color.r, color.g, color.b
+        )
+        .unwrap();
+    }
+    fs::write(path, scad).expect("Unable to write OpenSCAD script.");
+    log::info!("Wrote {path}.");
+}
@@ -0,0 +1,120 @@
+//! A packing list for physical kits, written by `--export-packing-list`:
+//! each color's tiles, then how many of them go in each row of the grid so
+//! packers can bag them by row, and the totals at the end.
+//!
+//! Rows are numbered from the top, the way the mosaic is assembled. With
+//! `--format json` the same list is written as one JSON object.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::fs;
+use std::str::FromStr;
+
+/// How `--export-packing-list` is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Sections of lines separated by dashes.
+    #[default]
+    Text,
+    /// `colors`, each with its `rows`, and `total`.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!(
+                "Unknown packing list format '{s}'; use text or json"
+            )),
+        }
+    }
+}
+
+const RULE: &str = "----------------------------------------";
+
+/// Writes the colors the grid uses in palette order, skipping keyed-out
+/// tiles.
+pub fn export(model: &Model, path: &str) {
+    let colors = model.palette.colors.len();
+    let used = assign::usage(&model.assignment, colors);
+    // Tiles of each color in each row, top row first.
+    let mut rows = vec![vec![0u64; Y_SIZE as usize]; colors];
+    for (tile, &assigned) in model.assignment.iter().enumerate() {
+        if assigned != assign::HOLE {
+            let y = tile as u64 / X_SIZE;
+            rows[assigned][(Y_SIZE - 1 - y) as usize] += 1;
+        }
+    }
+    let listed: Vec<usize> = (0..colors).filter(|&index| used[index] > 0).collect();
+    let total: u64 = listed.iter().map(|&index| used[index]).sum();
+
+    let contents = match model.options.packing_list_format {
+        Format::Json => {
+            let colors: Vec<_> = listed
+                .iter()
+                .map(|&index| {
+                    let rows: Vec<_> = rows[index]
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &tiles)| tiles > 0)
+                        .map(|(row, tiles)| json!({ "row": row + 1, "tiles": tiles }))
+                        .collect();
+                    json!({
+                        "name": model.palette.colors[index].name,
+                        "count": used[index],
+                        "rows": rows,
+                    })
+                })
+                .collect();
+            let list = json!({ "colors": colors, "total": total });
+            serde_json::to_string_pretty(&list).expect("Packing list should serialize.") + "\n"
+        }
+        Format::Text => {
+            let mut out = String::new();
+            writeln!(out, "Packing list for {}", model.options.picture_path).unwrap();
+            writeln!(out, "Rows are numbered from the top of the mosaic.").unwrap();
+            for &index in &listed {
+                writeln!(out, "{RULE}").unwrap();
+                writeln!(
+                    out,
+                    "{}: {}",
+                    model.palette.colors[index].name,
+                    tiles_text(used[index])
+                )
+                .unwrap();
+                for (row, &tiles) in rows[index].iter().enumerate() {
+                    if tiles > 0 {
+                        writeln!(out, "  Row {}: {}", row + 1, tiles_text(tiles)).unwrap();
+                    }
+                }
+            }
+            writeln!(out, "{RULE}").unwrap();
+            writeln!(out, "Totals").unwrap();
+            for &index in &listed {
+                writeln!(
+                    out,
+                    "  {}: {}",
+                    model.palette.colors[index].name, used[index]
+                )
+                .unwrap();
+            }
+            writeln!(out, "Grand total: {total} tiles").unwrap();
+            out
+        }
+    };
+    fs::write(path, contents).expect("Unable to write packing list.");
+    log::info!("Wrote a packing list of {} colors to {path}.", listed.len());
+}
+
+fn tiles_text(tiles: u64) -> String {
+    if tiles == 1 {
+        "1 tile".to_string()
+    } else {
+        format!("{tiles} tiles")
+    }
+}
@@ -0,0 +1,46 @@
+//! The per-color bill of materials shared by the text exports.
+
+use crate::assign;
+use crate::Model;
+
+/// One palette color's share of the build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub name: String,
+    pub rgb: [u8; 3],
+    /// Pieces placed, frame included.
+    pub used: u64,
+    /// Pieces left over from the declared count.
+    pub remaining: u64,
+    /// Price of the pieces used, when the palette has one for this color.
+    pub cost: Option<f64>,
+}
+
+impl Part {
+    /// The color as `#rrggbb`.
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.rgb;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Every palette color in palette order.
+pub fn parts(model: &Model) -> Vec<Part> {
+    let mut used = assign::usage(&model.assignment, model.palette.colors.len());
+    if let Some(frame) = model.frame {
+        used[frame.color] += frame.tile_count();
+    }
+    model
+        .palette
+        .colors
+        .iter()
+        .zip(used)
+        .map(|(config, used)| Part {
+            name: config.name.clone(),
+            rgb: [config.r, config.g, config.b],
+            used,
+            remaining: config.count,
+            cost: config.price.map(|price| price * used as f64),
+        })
+        .collect()
+}
@@ -0,0 +1,65 @@
+//! The palette as a strip of colors, written by `--export-png-band`, for a
+//! reference card or for checking a palette at a glance.
+
+use crate::font;
+use crate::legend::{self, LegendSort};
+use crate::Model;
+use image::{imageops, Rgb, RgbImage};
+
+/// Swatch size with `--band-with-names`.
+const SWATCH_WIDTH: u32 = 20;
+const SWATCH_HEIGHT: u32 = 80;
+/// Blank pixels around each name.
+const PADDING: u32 = 2;
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Writes every palette color in hue order, grays first, one pixel each
+/// left to right, or with `--band-with-names` as swatches with their names
+/// below, running upwards to fit the swatch's width.
+pub fn export(model: &Model, path: &str) {
+    let colors = &model.palette.colors;
+    let order = legend::sorted(colors, &vec![0; colors.len()], LegendSort::Hue);
+    let rgb = |index: usize| Rgb([colors[index].r, colors[index].g, colors[index].b]);
+    let img = if model.options.band_with_names {
+        let label_height = order
+            .iter()
+            .map(|&index| font::text_width(&colors[index].name, 1))
+            .max()
+            .unwrap_or(0)
+            + 2 * PADDING;
+        let mut img = RgbImage::from_pixel(
+            SWATCH_WIDTH * order.len() as u32,
+            SWATCH_HEIGHT + label_height,
+            WHITE,
+        );
+        for (slot, &index) in order.iter().enumerate() {
+            let left = slot as u32 * SWATCH_WIDTH;
+            for y in 0..SWATCH_HEIGHT {
+                for x in left..left + SWATCH_WIDTH {
+                    img.put_pixel(x, y, rgb(index));
+                }
+            }
+            // Written across, then turned to read from the bottom up.
+            let mut label = RgbImage::from_pixel(label_height, SWATCH_WIDTH, WHITE);
+            font::draw_text(
+                &mut label,
+                PADDING,
+                (SWATCH_WIDTH - font::GLYPH_HEIGHT) / 2,
+                &colors[index].name,
+                Rgb([0, 0, 0]),
+                1,
+            );
+            let label = imageops::rotate270(&label);
+            imageops::replace(&mut img, &label, left as i64, SWATCH_HEIGHT as i64);
+        }
+        img
+    } else {
+        let mut img = RgbImage::new(order.len() as u32, 1);
+        for (slot, &index) in order.iter().enumerate() {
+            img.put_pixel(slot as u32, 0, rgb(index));
+        }
+        img
+    };
+    img.save(path).expect("Unable to write palette band.");
+    log::info!("Wrote {path} with {} colors.", order.len());
+}
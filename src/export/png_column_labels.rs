@@ -0,0 +1,24 @@
+//! The mosaic as a spreadsheet-like reference sheet, written by
+//! `--export-png-column-labels`: the headers of `--export-png-numbered-grid`
+//! numbering columns from 0 at the left and rows from 0 at the top, on
+//! alternating white and light gray.
+//!
+//! Rows are the image's, counted down the page as the numbered grid counts
+//! them, not the grid's y, which counts up from the bottom: row 0 here is
+//! the top row, at the grid's highest y.
+
+use super::png_numbered_grid::headed;
+use crate::Model;
+use image::Rgb;
+
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const LIGHT_GRAY: Rgb<u8> = Rgb([230, 230, 230]);
+
+/// Writes the mosaic with its headers, sized as `--export-png-numbered-grid`
+/// sizes its own.
+pub fn export(model: &Model, path: &str) {
+    headed(model, 0, [WHITE, LIGHT_GRAY])
+        .save(path)
+        .expect("Unable to write column labels image.");
+    log::info!("Wrote {path}.");
+}
@@ -0,0 +1,48 @@
+//! The mosaic laid over the picture it was made from, written by
+//! `--export-png-composited`: each pixel is `--blend-alpha` of the mosaic
+//! at `--output-scale` and the rest of the downscaled source under it, so
+//! the original shows through the tiles and the gaps between them.
+
+use super::render_grid;
+use crate::render;
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use image::{Rgb, RgbImage};
+
+/// Writes the blend. Keyed-out holes stay the render's background rather
+/// than showing the key color.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let scale = options.output_scale;
+    let alpha = options.blend_alpha;
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let mosaic = render::flatten(
+        &render_grid(model, &pixels, &model.assignment, scale),
+        render::BACKGROUND,
+    );
+    let original = original(model, scale);
+    let composited = RgbImage::from_fn(mosaic.width(), mosaic.height(), |x, y| {
+        let (Rgb(over), Rgb(under)) = (mosaic.get_pixel(x, y), original.get_pixel(x, y));
+        Rgb([0, 1, 2].map(|channel| {
+            (over[channel] as f32 * alpha + under[channel] as f32 * (1.0 - alpha)).round() as u8
+        }))
+    });
+    composited
+        .save(path)
+        .expect("Unable to write composited image.");
+    log::info!("Wrote {path} at {:.0}% mosaic.", alpha * 100.0);
+}
+
+/// The source colors, `scale` pixels a tile with no gaps between them.
+fn original(model: &Model, scale: u32) -> RgbImage {
+    let (width, height) = (X_SIZE as u32 * scale, Y_SIZE as u32 * scale);
+    RgbImage::from_fn(width, height, |x, y| {
+        // Image rows count down from the top, grid rows up from the bottom.
+        let (column, row) = ((x / scale) as u64, Y_SIZE - 1 - (y / scale) as u64);
+        let tile = (row * X_SIZE + column) as usize;
+        if model.assignment[tile] == assign::HOLE {
+            return render::BACKGROUND;
+        }
+        let color = &model.reference_pixels[tile];
+        Rgb([color.r, color.g, color.b])
+    })
+}
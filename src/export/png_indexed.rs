@@ -0,0 +1,161 @@
+//! The mosaic as a palette PNG, written by `--export-png-indexed`.
+//!
+//! Entry `i` of the PNG palette is palette color `i`, so every pixel holds
+//! the index of the color its tile was given. One more entry after them is
+//! the background showing through the gaps, when there are any. The file is
+//! a fraction of the size of the RGB `--output`.
+
+use crate::{assign, render, Model, X_SIZE, Y_SIZE};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// The most entries a PNG palette holds.
+const MAX_ENTRIES: usize = 256;
+
+/// Writes the tiles at `--output-scale` with their frame, laid out as in
+/// `--output`.
+pub fn export(model: &Model, path: &str) {
+    let colors = &model.palette.colors;
+    let scale = model.options.output_scale;
+    let gap = colors.len();
+    let (grid, grid_width) = grid_indices(model, scale, gap);
+    let (indices, width) = framed(model, &grid, grid_width, scale, gap);
+    let height = (indices.len() / width as usize) as u32;
+
+    let entries = if indices.contains(&gap) { gap + 1 } else { gap };
+    if entries > MAX_ENTRIES {
+        panic!(
+            "--export-png-indexed fits {MAX_ENTRIES} PNG palette entries but needs {entries}: \
+             the palette has {} colors",
+            colors.len()
+        )
+    }
+    let mut palette: Vec<u8> = colors
+        .iter()
+        .flat_map(|config| [config.r, config.g, config.b])
+        .collect();
+    if entries > gap {
+        palette.extend(render::BACKGROUND.0);
+    }
+    let data: Vec<u8> = indices.iter().map(|&index| index as u8).collect();
+
+    let file = File::create(path).expect("Unable to write indexed PNG.");
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette);
+    // Differences between neighbouring indices mean nothing, so filtering
+    // only gets in the way of the compressor.
+    encoder.set_filter(png::FilterType::NoFilter);
+    encoder.set_compression(png::Compression::Best);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .expect("Unable to write indexed PNG.");
+    log::info!("Wrote {path} with {entries} palette entries.");
+}
+
+/// The grid's palette indices in image rows from the top, and its width.
+/// Gaps and keyed-out holes are `gap`.
+fn grid_indices(model: &Model, scale: u32, gap: usize) -> (Vec<usize>, u32) {
+    let width = X_SIZE as u32 * scale;
+    let assigned = |tile: usize| match model.assignment[tile] {
+        assign::HOLE => gap,
+        index => index,
+    };
+    if let Some(orientation) = model.options.hex_grid {
+        // As `render_hex_to_rgba` does, each pixel takes the tile its
+        // centre falls in.
+        let height = render::hex_height(width, orientation);
+        let mut indices = Vec::with_capacity((width * height) as usize);
+        for py in 0..height {
+            for px in 0..width {
+                let fx = (px as f32 + 0.5) / width as f32;
+                let fy = 1.0 - (py as f32 + 0.5) / height as f32;
+                indices.push(
+                    orientation
+                        .locate(fx, fy)
+                        .map_or(gap, |(x, y)| assigned((y * X_SIZE + x) as usize)),
+                );
+            }
+        }
+        return (indices, width);
+    }
+
+    let height = Y_SIZE as u32 * scale;
+    let mut indices = vec![gap; (width * height) as usize];
+    let mut fill = |left: u32, top: u32, side: u32, index: usize| {
+        for y in top..top + side {
+            let row = (y * width) as usize;
+            indices[row + left as usize..row + (left + side) as usize].fill(index);
+        }
+    };
+    match model.leaves(&model.assignment) {
+        // As in `render_cells_to_rgba`, the gap runs only around each cell.
+        Some(cells) => {
+            for cell in cells {
+                let side = (cell.size as u32 * scale).saturating_sub(1).max(1);
+                let top = (Y_SIZE - cell.y - cell.size) as u32 * scale;
+                fill(
+                    cell.x as u32 * scale,
+                    top,
+                    side,
+                    assigned(cell.first_tile()),
+                );
+            }
+        }
+        None => {
+            let inner = scale.saturating_sub(1).max(1);
+            for tile in 0..model.assignment.len() {
+                let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+                fill(
+                    x as u32 * scale,
+                    (Y_SIZE - 1 - y) as u32 * scale,
+                    inner,
+                    assigned(tile),
+                );
+            }
+        }
+    }
+    (indices, width)
+}
+
+/// `grid` inside the `--frame`, if there is one, with its width.
+fn framed(
+    model: &Model,
+    grid: &[usize],
+    grid_width: u32,
+    scale: u32,
+    gap: usize,
+) -> (Vec<usize>, u32) {
+    let frame = match model.frame {
+        Some(frame) => frame,
+        None => return (grid.to_vec(), grid_width),
+    };
+    let grid_height = grid.len() as u32 / grid_width;
+    let border = frame.thickness * scale;
+    let (width, height) = (grid_width + 2 * border, grid_height + 2 * border);
+    let mut indices = vec![gap; (width * height) as usize];
+    // Frame tiles step out from the grid's corner like those of
+    // `render::present`, gaps included.
+    let inner = scale.saturating_sub(1).max(1);
+    for top in (0..height).step_by(scale as usize) {
+        for left in (0..width).step_by(scale as usize) {
+            let inside = (border..border + grid_width).contains(&left)
+                && (border..border + grid_height).contains(&top);
+            if inside {
+                continue;
+            }
+            for y in top..(top + inner).min(height) {
+                for x in left..(left + inner).min(width) {
+                    indices[(y * width + x) as usize] = frame.color;
+                }
+            }
+        }
+    }
+    for (row, line) in grid.chunks(grid_width as usize).enumerate() {
+        let start = (row as u32 + border) * width + border;
+        indices[start as usize..start as usize + line.len()].copy_from_slice(line);
+    }
+    (indices, width)
+}
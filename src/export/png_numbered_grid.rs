@@ -0,0 +1,129 @@
+//! The mosaic as a construction reference, written by
+//! `--export-png-numbered-grid`: the tiles at `--output-scale` with a
+//! header of row numbers down the left and column numbers along the top,
+//! shaded in alternating grays so each number lines up with its row or
+//! column at a glance.
+//!
+//! Rows are counted from 1 at the top, as `--export-png-sections` counts
+//! them, and columns from 1 at the left. Each number is drawn as large as
+//! the header cells allow, all of each header's at one size; column
+//! numbers too wide for a tile are stacked one digit above the next.
+//!
+//! `--export-png-column-labels` draws the same headers counted as a
+//! spreadsheet's are here.
+
+use super::render_grid;
+use crate::font;
+use crate::render;
+use crate::{Model, X_SIZE, Y_SIZE};
+use image::{imageops, Rgb, RgbImage};
+
+/// Blank pixels kept around each number inside its cell.
+const PADDING: u32 = 1;
+const LIGHT: Rgb<u8> = Rgb([224, 224, 224]);
+const DARK: Rgb<u8> = Rgb([200, 200, 200]);
+const INK: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// Writes the mosaic with its headers.
+pub fn export(model: &Model, path: &str) {
+    headed(model, 1, [LIGHT, DARK])
+        .save(path)
+        .expect("Unable to write numbered grid.");
+    log::info!("Wrote {path}.");
+}
+
+/// The mosaic with numbered headers, counting from `first` and shaded
+/// alternately in `shades`. `--number-cell-size` sets how wide the row
+/// header and how tall the column header are, but neither is made too
+/// small for its numbers at the font's smallest size.
+pub(super) fn headed(model: &Model, first: u32, [light, dark]: [Rgb<u8>; 2]) -> RgbImage {
+    let options = &model.options;
+    let scale = options.output_scale;
+    let cell = options.number_cell_size.unwrap_or(scale);
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let grid = render::flatten(
+        &render_grid(model, &pixels, &model.assignment, scale),
+        render::BACKGROUND,
+    );
+
+    let widest = (X_SIZE.max(Y_SIZE) as u32 - 1 + first).to_string();
+    let stacked = size(&widest, false, 1).0 + 2 * PADDING > scale;
+    let left = cell.max(size(&widest, false, 1).0 + 2 * PADDING);
+    let top = cell.max(size(&widest, stacked, 1).1 + 2 * PADDING);
+    let mut img = RgbImage::from_pixel(left + grid.width(), top + grid.height(), light);
+    imageops::replace(&mut img, &grid, left as i64, top as i64);
+    let row_scale = fit(&(Y_SIZE as u32 - 1 + first).to_string(), false, left, scale);
+    for row in 0..Y_SIZE as u32 {
+        let y = top + row * scale;
+        let color = if row % 2 == 1 { dark } else { light };
+        fill(&mut img, (0, y, left, scale), color);
+        let text = (row + first).to_string();
+        label(&mut img, (0, y, left, scale), &text, false, row_scale);
+    }
+    let column_scale = fit(
+        &(X_SIZE as u32 - 1 + first).to_string(),
+        stacked,
+        scale,
+        top,
+    );
+    for column in 0..X_SIZE as u32 {
+        let x = left + column * scale;
+        let color = if column % 2 == 1 { dark } else { light };
+        fill(&mut img, (x, 0, scale, top), color);
+        let text = (column + first).to_string();
+        label(&mut img, (x, 0, scale, top), &text, stacked, column_scale);
+    }
+    img
+}
+
+/// Fills a header cell.
+fn fill(img: &mut RgbImage, (x, y, width, height): (u32, u32, u32, u32), color: Rgb<u8>) {
+    for py in y..y + height {
+        for px in x..x + width {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Width and height of `text` at `scale`, in a line or one character above
+/// the next.
+fn size(text: &str, stacked: bool, scale: u32) -> (u32, u32) {
+    if !stacked {
+        return (font::text_width(text, scale), font::GLYPH_HEIGHT * scale);
+    }
+    let chars = text.chars().count() as u32;
+    (
+        font::GLYPH_WIDTH * scale,
+        (chars * (font::GLYPH_HEIGHT + 1) - 1) * scale,
+    )
+}
+
+/// The largest scale at which `text` fits a `width` by `height` cell
+/// inside its padding, and at least 1.
+fn fit(text: &str, stacked: bool, width: u32, height: u32) -> u32 {
+    let (text_width, text_height) = size(text, stacked, 1);
+    (width.saturating_sub(2 * PADDING) / text_width)
+        .min(height.saturating_sub(2 * PADDING) / text_height)
+        .max(1)
+}
+
+/// Draws `text` at `scale`, centred in the cell `(x, y, width, height)`.
+fn label(
+    img: &mut RgbImage,
+    (x, y, width, height): (u32, u32, u32, u32),
+    text: &str,
+    stacked: bool,
+    scale: u32,
+) {
+    let (text_width, text_height) = size(text, stacked, scale);
+    let left = x + width.saturating_sub(text_width) / 2;
+    let top = y + height.saturating_sub(text_height) / 2;
+    if !stacked {
+        font::draw_text(img, left, top, text, INK, scale);
+        return;
+    }
+    for (index, digit) in text.chars().enumerate() {
+        let line = top + index as u32 * (font::GLYPH_HEIGHT + 1) * scale;
+        font::draw_text(img, left, line, &digit.to_string(), INK, scale);
+    }
+}
@@ -0,0 +1,76 @@
+//! One PNG per row of the grid, written by `--export-png-sections` into a
+//! folder as `row_001.png` and on, top row first, for building on narrow
+//! pegboards a strip at a time. With `--section-axis col` each column is a
+//! `col_001.png` instead, from the left.
+//!
+//! Each strip is drawn at `--output-scale` under a white band labelled with
+//! its number, so strips are not mixed up.
+
+use super::render_grid;
+use crate::font;
+use crate::render;
+use crate::{Model, X_SIZE, Y_SIZE};
+use image::{imageops, Rgb, RgbImage};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Which way `--export-png-sections` cuts the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Axis {
+    #[default]
+    Row,
+    Col,
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "row" => Ok(Axis::Row),
+            "col" => Ok(Axis::Col),
+            _ => Err(format!("Unknown section axis '{s}'; use row or col")),
+        }
+    }
+}
+
+/// Blank pixels around the label.
+const PADDING: u32 = 2;
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Writes every strip into `dir`, creating it as needed.
+pub fn export(model: &Model, dir: &str) {
+    let scale = model.options.output_scale;
+    let pixels = render::jitter(&model.pixels, model.options.brightness_jitter);
+    let grid = render::flatten(
+        &render_grid(model, &pixels, &model.assignment, scale),
+        render::BACKGROUND,
+    );
+    let (prefix, label, count) = match model.options.section_axis {
+        Axis::Row => ("row", "Row", Y_SIZE),
+        Axis::Col => ("col", "Column", X_SIZE),
+    };
+    fs::create_dir_all(dir).expect("Unable to create sections folder.");
+    for number in 1..=count {
+        // Image rows already run from the top, as the strips are numbered.
+        let at = (number - 1) as u32 * scale;
+        let strip = match model.options.section_axis {
+            Axis::Row => imageops::crop_imm(&grid, 0, at, grid.width(), scale),
+            Axis::Col => imageops::crop_imm(&grid, at, 0, scale, grid.height()),
+        }
+        .to_image();
+        let text = format!("{label} {number}");
+        let band = font::GLYPH_HEIGHT + 2 * PADDING;
+        let mut img = RgbImage::from_pixel(
+            strip.width().max(font::text_width(&text, 1) + 2 * PADDING),
+            strip.height() + band,
+            WHITE,
+        );
+        font::draw_text(&mut img, PADDING, PADDING, &text, Rgb([0, 0, 0]), 1);
+        imageops::replace(&mut img, &strip, 0, band as i64);
+        let path = Path::new(dir).join(format!("{prefix}_{number:03}.png"));
+        img.save(&path).expect("Unable to write section.");
+    }
+    log::info!("Wrote {count} {prefix} sections to {dir}.");
+}
@@ -0,0 +1,61 @@
+//! The mosaic split into its color channels, written by
+//! `--export-png-split-rgb`: `mosaic_r.png`, `mosaic_g.png`, and
+//! `mosaic_b.png` show how much red, green, and blue each tile carries as
+//! 8-bit grayscale, and `mosaic_composite.png` puts the three back
+//! together as one RGB image to compare them against.
+//!
+//! All four are rendered as `--output` is, at `--output-scale` with the
+//! gaps between tiles, so they line up with it pixel for pixel.
+
+use super::render_grid;
+use crate::render;
+use crate::{assign, Model};
+use image::{GrayImage, Luma, RgbImage};
+use std::fs;
+use std::path::Path;
+
+/// Writes the three channels and the composite into `dir`, and logs each
+/// channel's mean over the tiles.
+pub fn export(model: &Model, dir: &str) {
+    fs::create_dir_all(dir).expect("Unable to create split RGB folder.");
+    let dir = Path::new(dir);
+    let options = &model.options;
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let composite = render::flatten(
+        &render_grid(model, &pixels, &model.assignment, options.output_scale),
+        render::BACKGROUND,
+    );
+    let tiles: Vec<_> = pixels
+        .iter()
+        .zip(&model.assignment)
+        .filter(|(_, &index)| index != assign::HOLE)
+        .map(|(color, _)| [color.r, color.g, color.b])
+        .collect();
+    let mut means = Vec::new();
+    for (channel, name) in ["r", "g", "b"].into_iter().enumerate() {
+        channel_image(&composite, channel)
+            .save(dir.join(format!("mosaic_{name}.png")))
+            .expect("Unable to write channel image.");
+        let sum: u64 = tiles.iter().map(|color| color[channel] as u64).sum();
+        means.push(format!(
+            "{} {:.0}",
+            name.to_uppercase(),
+            sum as f64 / tiles.len().max(1) as f64
+        ));
+    }
+    composite
+        .save(dir.join("mosaic_composite.png"))
+        .expect("Unable to write composite image.");
+    log::info!(
+        "Wrote the R, G, and B channels and their composite into {}; mean {}.",
+        dir.display(),
+        means.join(", ")
+    );
+}
+
+/// One channel of `img` as grayscale.
+fn channel_image(img: &RgbImage, channel: usize) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        Luma([img.get_pixel(x, y)[channel]])
+    })
+}
@@ -0,0 +1,24 @@
+//! A small preview of the mosaic, written by `--export-png-thumbnail`: one
+//! pixel per tile with no gaps, resized to `--thumb-size` square with
+//! Lanczos resampling.
+
+use crate::render;
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use image::{imageops, Rgb, RgbImage};
+
+/// Writes the thumbnail; keyed-out holes take the render's background.
+pub fn export(model: &Model, path: &str) {
+    let size = model.options.thumb_size;
+    let mut grid = RgbImage::from_pixel(X_SIZE as u32, Y_SIZE as u32, render::BACKGROUND);
+    for (color, &assigned) in model.pixels.iter().zip(&model.assignment) {
+        if assigned != assign::HOLE {
+            // Grid rows count up from the bottom, image rows from the top.
+            let y = (Y_SIZE - 1 - color.y) as u32;
+            grid.put_pixel(color.x as u32, y, Rgb([color.r, color.g, color.b]));
+        }
+    }
+    imageops::resize(&grid, size, size, imageops::FilterType::Lanczos3)
+        .save(path)
+        .expect("Unable to write thumbnail.");
+    log::info!("Wrote {path} at {size}x{size}.");
+}
@@ -0,0 +1,143 @@
+//! The mosaic with a faint coordinate grid over it, written by
+//! `--export-png-watermarked-grid`: white lines every `--grid-interval`
+//! rows and columns at `OPACITY`, so the picture still reads through them,
+//! and where they cross a label of how many columns and rows lie to the
+//! left and above, black on a white pill.
+//!
+//! Columns and rows are counted from the top-left, as
+//! `--export-png-numbered-grid` numbers them, so the label `8,16` marks the
+//! corner below and right of which column 9 and row 17 begin.
+
+use super::render_grid;
+use crate::font;
+use crate::render;
+use crate::{Model, X_SIZE, Y_SIZE};
+use image::{Rgb, RgbImage};
+
+/// How much of a line's white covers the mosaic under it.
+const OPACITY: f32 = 0.15;
+const LINE: Rgb<u8> = Rgb([255, 255, 255]);
+const PILL: Rgb<u8> = Rgb([255, 255, 255]);
+const INK: Rgb<u8> = Rgb([0, 0, 0]);
+/// Blank pixels between a label and the edge of its pill, at the label's
+/// scale.
+const PADDING: u32 = 2;
+
+/// Writes the mosaic at `--output-scale` with the grid and its labels.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let (scale, interval) = (options.output_scale, options.grid_interval);
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let mut img = render::flatten(
+        &render_grid(model, &pixels, &model.assignment, scale),
+        render::BACKGROUND,
+    );
+
+    let columns: Vec<u32> = (interval..X_SIZE as u32)
+        .step_by(interval as usize)
+        .collect();
+    let rows: Vec<u32> = (interval..Y_SIZE as u32)
+        .step_by(interval as usize)
+        .collect();
+    // Lines a sixth of a tile thick, centred on the edge between tiles.
+    let thickness = (scale / 6).max(1);
+    let (width, height) = img.dimensions();
+    for &column in &columns {
+        let x = (column * scale).saturating_sub(thickness / 2);
+        blend(&mut img, (x, 0, thickness, height));
+    }
+    for &row in &rows {
+        let y = (row * scale).saturating_sub(thickness / 2);
+        // Crossings are covered once, as by one line.
+        for &(left, right) in &spans(&columns, scale, thickness, width) {
+            blend(&mut img, (left, y, right - left, thickness));
+        }
+    }
+
+    // Labels as large as fits inside a grid square, at least at 1.
+    let widest = format!(
+        "{},{}",
+        columns.last().unwrap_or(&0),
+        rows.last().unwrap_or(&0)
+    );
+    let label_scale =
+        ((interval * scale / 2) / (font::text_width(&widest, 1) + 2 * PADDING)).clamp(1, 3);
+    for &row in &rows {
+        for &column in &columns {
+            label(
+                &mut img,
+                column * scale,
+                row * scale,
+                &format!("{column},{row}"),
+                label_scale,
+            );
+        }
+    }
+    img.save(path)
+        .expect("Unable to write watermarked grid image.");
+    log::info!(
+        "Wrote {path} with grid lines every {interval} tiles and {} labels.",
+        columns.len() * rows.len()
+    );
+}
+
+/// The runs of `0..width` between the vertical lines at `columns`.
+fn spans(columns: &[u32], scale: u32, thickness: u32, width: u32) -> Vec<(u32, u32)> {
+    let mut spans = Vec::new();
+    let mut left = 0;
+    for &column in columns {
+        let x = (column * scale).saturating_sub(thickness / 2);
+        spans.push((left, x));
+        left = x + thickness;
+    }
+    spans.push((left, width));
+    spans
+}
+
+/// Mixes `LINE` into the rectangle `(x, y, width, height)` at `OPACITY`.
+fn blend(img: &mut RgbImage, (x, y, width, height): (u32, u32, u32, u32)) {
+    let (img_width, img_height) = img.dimensions();
+    for py in y..(y + height).min(img_height) {
+        for px in x..(x + width).min(img_width) {
+            let Rgb(under) = *img.get_pixel(px, py);
+            let mixed = [0, 1, 2].map(|channel| {
+                (under[channel] as f32 * (1.0 - OPACITY) + LINE[channel] as f32 * OPACITY).round()
+                    as u8
+            });
+            img.put_pixel(px, py, Rgb(mixed));
+        }
+    }
+}
+
+/// Draws `text` on a pill centred on (`cx`, `cy`), kept inside the image.
+fn label(img: &mut RgbImage, cx: u32, cy: u32, text: &str, scale: u32) {
+    let pill_height = (font::GLYPH_HEIGHT + 2 * PADDING) * scale;
+    // The round ends add half the height on either side of the text.
+    let pill_width = font::text_width(text, scale) + pill_height;
+    let (width, height) = img.dimensions();
+    let left = cx
+        .saturating_sub(pill_width / 2)
+        .min(width.saturating_sub(pill_width));
+    let top = cy
+        .saturating_sub(pill_height / 2)
+        .min(height.saturating_sub(pill_height));
+    let radius = pill_height as f32 / 2.0;
+    for py in top..(top + pill_height).min(height) {
+        for px in left..(left + pill_width).min(width) {
+            // Distance from the pixel's centre to the pill's middle line.
+            let (x, y) = ((px - left) as f32 + 0.5, (py - top) as f32 + 0.5 - radius);
+            let along = x.clamp(radius, pill_width as f32 - radius);
+            if (x - along).hypot(y) <= radius {
+                img.put_pixel(px, py, PILL);
+            }
+        }
+    }
+    font::draw_text(
+        img,
+        left + pill_height / 2,
+        top + PADDING * scale,
+        text,
+        INK,
+        scale,
+    );
+}
@@ -0,0 +1,17 @@
+//! The mosaic stretched to an exact pixel size, written by
+//! `--export-png-zoom`, for wallpapers and other fixed-size screens.
+
+use super::filled;
+use crate::render::{self, ExactSize};
+use crate::Model;
+
+/// Writes the tiles, with their gaps, filling `size` edge to edge.
+pub fn export(model: &Model, size: ExactSize, path: &str) {
+    let pixels = render::jitter(&model.pixels, model.options.brightness_jitter);
+    let img =
+        render::render_to_exact_size(&filled(&pixels, &model.assignment), size.width, size.height);
+    render::flatten(&img, render::BACKGROUND)
+        .save(path)
+        .expect("Unable to write zoomed image.");
+    log::info!("Wrote {path} at {}x{}.", size.width, size.height);
+}
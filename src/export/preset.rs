@@ -0,0 +1,141 @@
+//! Named groups of exports defined in the `--config` settings file.
+
+use super::{color_sheets, qr, write_json, write_output, write_report, ImageStyle};
+use crate::settings::{ExportJob, Settings};
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Runs every job of the preset called `name`. Each output directory is
+/// checked first, so when any of them cannot be written nothing is.
+pub fn run(model: &Model, name: &str) -> Result<(), String> {
+    let config = model
+        .options
+        .config
+        .as_deref()
+        .ok_or("Export presets need a settings file; pass --config")?;
+    let settings = Settings::load(config);
+    let jobs = settings
+        .export_presets
+        .get(name)
+        .ok_or_else(|| format!("{config} has no export preset named '{name}'"))?;
+    // Keyed-out tiles have no palette index to record, as with `--export-json`.
+    if !model.holes.is_empty() && jobs.iter().any(|job| matches!(job, ExportJob::Json { .. })) {
+        return Err(format!(
+            "Export preset '{name}' not run: --key-color leaves holes its json jobs cannot record"
+        ));
+    }
+
+    let stem = Path::new(&model.options.picture_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let outputs: Vec<String> = jobs
+        .iter()
+        .map(|job| fill_template(job.output(), &stem, &today(), &format!("{X_SIZE}x{Y_SIZE}")))
+        .collect();
+    let directories: BTreeSet<&Path> = outputs
+        .iter()
+        .map(|output| match Path::new(output).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        })
+        .collect();
+    for directory in directories {
+        check_writable(directory).map_err(|err| {
+            format!(
+                "Export preset '{name}' not run: cannot write to {}: {err}",
+                directory.display()
+            )
+        })?;
+    }
+
+    log::info!("Running export preset '{name}' ({} files).", jobs.len());
+    for (job, output) in jobs.iter().zip(&outputs) {
+        match job {
+            ExportJob::Image {
+                scale,
+                tile_label,
+                transparent_bg,
+                ..
+            } => {
+                let style = ImageStyle {
+                    scale: scale.unwrap_or(model.options.output_scale),
+                    tile_label: *tile_label,
+                    transparent_bg: *transparent_bg,
+                    progress_bar: None,
+                };
+                write_output(model, output, &style);
+            }
+            ExportJob::Json { sort_by_usage, .. } => write_json(model, output, *sort_by_usage),
+            ExportJob::Report { .. } => {
+                write_report(model, output, None, &ImageStyle::from_options(model))
+            }
+            ExportJob::ColorSheets { .. } => color_sheets::export(model, output),
+            ExportJob::QrcodePalette { .. } => {
+                qr::export_palette(&model.declared_palette(), output)
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Substitutes the `{stem}`, `{date}`, and `{grid}` placeholders.
+fn fill_template(template: &str, stem: &str, date: &str, grid: &str) -> String {
+    template
+        .replace("{stem}", stem)
+        .replace("{date}", date)
+        .replace("{grid}", grid)
+}
+
+/// Creates and removes a probe file, which catches missing directories and
+/// missing permissions alike.
+fn check_writable(directory: &Path) -> std::io::Result<()> {
+    let probe = directory.join(".block-mosaic-write-test");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_date((seconds / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Converts days since 1970-01-01 to a Gregorian date, after Howard
+/// Hinnant's `civil_from_days`.
+pub(super) fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_and_dates() {
+        assert_eq!(
+            fill_template("out/{stem}_{grid}_{date}.png", "me", "2024-02-29", "48x48"),
+            "out/me_48x48_2024-02-29.png"
+        );
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19_782), (2024, 2, 29));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+    }
+}
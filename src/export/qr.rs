@@ -0,0 +1,76 @@
+use crate::palette::{ColorConfig, ColorConfigs};
+use image::Luma;
+use qrcode::QrCode;
+use std::path::Path;
+
+/// Largest JSON payload put in a single code; beyond this the codes get too
+/// dense for phone cameras to read reliably.
+const QR_PAYLOAD_LIMIT: usize = 2048;
+
+/// Encodes the palette as minified JSON in a QR code. Palettes too large for
+/// one code are split into several smaller palettes written as
+/// `<stem>_1.png`, `<stem>_2.png`, and so on.
+pub fn export_palette(color_configs: &ColorConfigs, path: &str) {
+    let json = serde_json::to_string(color_configs).expect("Palette should serialize.");
+    if json.len() <= QR_PAYLOAD_LIMIT {
+        write_code(&json, path);
+        log::info!("Wrote palette QR code to {path}.");
+        return;
+    }
+
+    let chunks = split_palette(color_configs);
+    log::info!(
+        "Palette JSON is {} bytes, more than the {QR_PAYLOAD_LIMIT} bytes one QR code holds; splitting it into {} codes.",
+        json.len(),
+        chunks.len()
+    );
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("palette");
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("png");
+    for (number, chunk) in chunks.iter().enumerate() {
+        let chunk_path = path.with_file_name(format!("{stem}_{}.{extension}", number + 1));
+        let json = serde_json::to_string(chunk).expect("Palette should serialize.");
+        write_code(
+            &json,
+            chunk_path.to_str().expect("Path should be valid UTF-8."),
+        );
+        log::info!("Wrote palette QR code to {}.", chunk_path.display());
+    }
+}
+
+/// Packs colors, in order, into palettes that each fit in one code.
+fn split_palette(color_configs: &ColorConfigs) -> Vec<ColorConfigs> {
+    let mut chunks: Vec<ColorConfigs> = Vec::new();
+    let mut current: Vec<ColorConfig> = Vec::new();
+    for config in &color_configs.colors {
+        current.push(config.clone());
+        let candidate = ColorConfigs {
+            colors: current.clone(),
+        };
+        let len = serde_json::to_string(&candidate)
+            .expect("Palette should serialize.")
+            .len();
+        if len > QR_PAYLOAD_LIMIT && current.len() > 1 {
+            let overflow = current.pop().expect("Chunk should not be empty.");
+            chunks.push(ColorConfigs {
+                colors: std::mem::replace(&mut current, vec![overflow]),
+            });
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(ColorConfigs { colors: current });
+    }
+    chunks
+}
+
+fn write_code(payload: &str, path: &str) {
+    let code = QrCode::new(payload.as_bytes()).expect("Payload should fit in a QR code.");
+    code.render::<Luma<u8>>()
+        .min_dimensions(256, 256)
+        .build()
+        .save(path)
+        .expect("Unable to write QR code image.");
+}
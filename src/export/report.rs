@@ -0,0 +1,141 @@
+//! A Markdown write-up of a build for keeping notes.
+
+use super::parts::{self, Part};
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Writes the report to `path`, embedding the mosaic at `image_path`.
+pub fn export(model: &Model, path: &str, image_path: &str) {
+    let report = render(model, &relative_to(Path::new(path), Path::new(image_path)));
+    fs::write(path, report).expect("Unable to write report.");
+    log::info!("Wrote {path}.");
+}
+
+fn render(model: &Model, image: &str) -> String {
+    let options = &model.options;
+    let stats = assign::statistics(
+        &model.reference_pixels,
+        &model.assignment,
+        &model.palette,
+        &model.emphasis,
+    );
+    let parts = parts::parts(model);
+    let mut out = String::new();
+
+    writeln!(out, "# Mosaic of {}\n", file_name(&options.picture_path)).unwrap();
+    writeln!(out, "![Mosaic]({image})\n").unwrap();
+    writeln!(out, "- Source: `{}`", options.picture_path).unwrap();
+    writeln!(
+        out,
+        "- Grid: {X_SIZE} x {Y_SIZE} ({} tiles)",
+        X_SIZE * Y_SIZE
+    )
+    .unwrap();
+    writeln!(out, "- Palette: {}", options.palette_label()).unwrap();
+    if let Some(frame) = model.frame {
+        writeln!(
+            out,
+            "- Frame: {} tiles thick in {} ({} pieces)",
+            frame.thickness,
+            model.palette.colors[frame.color].name,
+            frame.tile_count()
+        )
+        .unwrap();
+    }
+    let forced = model.forced_tiles().len();
+    if forced > 0 {
+        writeln!(out, "- Forced tiles: {forced}, marked with a dot").unwrap();
+    }
+    writeln!(out, "- Average error: {:.1}", stats.average_error).unwrap();
+    writeln!(
+        out,
+        "- Substitutions: {} tiles did not get their closest color",
+        stats.fallbacks
+    )
+    .unwrap();
+    if let Some(cost) = total_cost(&parts) {
+        writeln!(out, "- Cost: {cost:.2}").unwrap();
+    }
+
+    writeln!(out, "\n## Colors\n").unwrap();
+    let priced = parts.iter().any(|part| part.cost.is_some());
+    out.push_str("| Color | Name | Used | Remaining |");
+    out.push_str(if priced { " Cost |\n" } else { "\n" });
+    out.push_str("| --- | --- | ---: | ---: |");
+    out.push_str(if priced { " ---: |\n" } else { "\n" });
+    for part in &parts {
+        write!(
+            out,
+            "| `{}` | {} | {} | {} |",
+            part.hex(),
+            escape_cell(&part.name),
+            part.used,
+            part.remaining
+        )
+        .unwrap();
+        match (priced, part.cost) {
+            (false, _) => out.push('\n'),
+            (true, Some(cost)) => writeln!(out, " {cost:.2} |").unwrap(),
+            (true, None) => out.push_str(" |\n"),
+        }
+    }
+
+    writeln!(out, "\n## Settings\n").unwrap();
+    writeln!(out, "```").unwrap();
+    writeln!(out, "BlockMosaicCreator {}", options.args.join(" ")).unwrap();
+    writeln!(out, "```\n").unwrap();
+    if options.seed.is_none() {
+        writeln!(
+            out,
+            "No seed was given; this run used `--seed {}`.",
+            model.seed
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn total_cost(parts: &[Part]) -> Option<f64> {
+    let costs: Vec<f64> = parts.iter().filter_map(|part| part.cost).collect();
+    (!costs.is_empty()).then(|| costs.iter().sum())
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Keeps palette names from breaking the table.
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// `target` as seen from the folder containing `report`, so the report and
+/// its image can be moved together.
+fn relative_to(report: &Path, target: &Path) -> String {
+    let cwd = env::current_dir().unwrap_or_default();
+    let components = |path: &Path| -> Vec<OsString> {
+        cwd.join(path)
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .map(|component| component.as_os_str().to_os_string())
+            .collect()
+    };
+    let from = components(report.parent().unwrap_or(Path::new("")));
+    let to = components(target);
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative.to_string_lossy().replace('\\', "/")
+}
@@ -0,0 +1,58 @@
+//! The `--cel` regions drawn for tuning, written by `--export-segments`.
+
+use crate::segment::Segmentation;
+use crate::{Model, X_SIZE, Y_SIZE};
+use image::{Rgb, RgbImage};
+
+/// Boundaries between regions are drawn in this color.
+const BOUNDARY: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Writes each region filled with the average source color of its tiles and
+/// outlined where it meets another region.
+pub fn export(model: &Model, segments: &Segmentation, path: &str) {
+    let scale = model.options.output_scale;
+    let mut img = RgbImage::new(X_SIZE as u32 * scale, Y_SIZE as u32 * scale);
+    for region in &segments.regions {
+        let n = region.len() as u64;
+        let mean = [0, 1, 2].map(|c| {
+            let total: u64 = region
+                .iter()
+                .map(|&tile| {
+                    let color = &model.reference_pixels[tile];
+                    [color.r, color.g, color.b][c] as u64
+                })
+                .sum();
+            ((total + n / 2) / n) as u8
+        });
+        for &tile in region {
+            let (left, top) = corner(tile, scale);
+            for y in top..top + scale {
+                for x in left..left + scale {
+                    img.put_pixel(x, y, Rgb(mean));
+                }
+            }
+        }
+    }
+    for tile in 0..segments.labels.len() {
+        let (right, up) = segments.boundaries(tile);
+        let (left, top) = corner(tile, scale);
+        if right {
+            for y in top..top + scale {
+                img.put_pixel(left + scale - 1, y, BOUNDARY);
+            }
+        }
+        if up {
+            for x in left..left + scale {
+                img.put_pixel(x, top, BOUNDARY);
+            }
+        }
+    }
+    img.save(path).expect("Unable to write segmentation image.");
+    log::info!("Wrote {path} with {} regions.", segments.regions.len());
+}
+
+/// Top-left pixel of a tile; image rows count down from the top.
+fn corner(tile: usize, scale: u32) -> (u32, u32) {
+    let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+    (x as u32 * scale, (Y_SIZE - 1 - y) as u32 * scale)
+}
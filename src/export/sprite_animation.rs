@@ -0,0 +1,63 @@
+//! The mosaic being built tile by tile, written by
+//! `--export-sprite-animation` as an animated PNG: each frame adds the next
+//! tile in the order the solve placed them, `--fps` frames a second, and
+//! the finished mosaic holds for `--pause-ms` before it loops.
+//!
+//! After the first, whole-image frame each frame is only the new tile's
+//! square drawn over the last, so frames cost the same however far the
+//! build has got.
+
+use super::build_sequence;
+use crate::{render, Model, X_SIZE, Y_SIZE};
+use png::{BlendOp, ColorType, DisposeOp};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// Writes the background with any locked tiles, then a frame per tile.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let scale = options.output_scale;
+    let inner = scale.saturating_sub(1).max(1);
+    let pixels = render::jitter(&model.pixels, options.brightness_jitter);
+    let (start, order) = build_sequence(model);
+    let start: Vec<_> = start.iter().map(|&tile| pixels[tile].clone()).collect();
+    let first = render::flatten(&render::render_to_rgba(&start, scale), render::BACKGROUND);
+
+    let (width, height) = (X_SIZE as u32 * scale, Y_SIZE as u32 * scale);
+    let file = File::create(path).expect("Unable to write sprite animation.");
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let frames = order.len() as u32 + 1;
+    let written = encoder
+        .set_animated(frames, 0)
+        .and_then(|()| encoder.write_header())
+        .and_then(|mut writer| {
+            let delay = |writer: &mut png::Writer<_>, frame: u32| {
+                if frame + 1 == frames {
+                    writer.set_frame_delay(options.pause_ms, 1000)
+                } else {
+                    writer.set_frame_delay(1, options.fps)
+                }
+            };
+            delay(&mut writer, 0)?;
+            writer.write_image_data(first.as_raw())?;
+            writer.set_dispose_op(DisposeOp::None)?;
+            writer.set_blend_op(BlendOp::Source)?;
+            writer.set_frame_dimension(inner, inner)?;
+            for (frame, &tile) in (1..).zip(&order) {
+                let color = &pixels[tile];
+                // Grid rows count up from the bottom, image rows from the top.
+                writer.set_frame_position(
+                    color.x as u32 * scale,
+                    (Y_SIZE - 1 - color.y) as u32 * scale,
+                )?;
+                delay(&mut writer, frame)?;
+                let square: Vec<u8> = [color.r, color.g, color.b].repeat((inner * inner) as usize);
+                writer.write_image_data(&square)?;
+            }
+            writer.finish()
+        });
+    written.expect("Unable to write sprite animation.");
+    log::info!("Wrote {path} with {frames} frames.");
+}
@@ -0,0 +1,76 @@
+//! A Stardew Valley style tileset of the mosaic's colors, written by
+//! `--export-stardew`: a 256 pixel wide sheet of 16x16 tiles, 16 to a row,
+//! one solid tile per color in use, with a JSON config beside it naming the
+//! tiles and laying out the mosaic in them.
+
+use crate::legend;
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use image::{Rgba, RgbaImage};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// Side of a tile in pixels, as in the game's own sheets.
+const TILE: u32 = 16;
+const COLUMNS: u32 = 16;
+
+/// Writes the sheet with the colors in legend order and, next to `path`,
+/// its JSON: the tiles' `id`, `name`, and `color`, and the mosaic as a
+/// `map` of tile ids in rows from the top, with -1 for keyed-out tiles.
+pub fn export(model: &Model, path: &str) {
+    let numbers = legend::numbers(model);
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| numbers[index]);
+
+    let rows = (order.len() as u32).div_ceil(COLUMNS).max(1);
+    let mut sheet = RgbaImage::new(COLUMNS * TILE, rows * TILE);
+    let mut ids = vec![-1i64; model.palette.colors.len()];
+    let mut tiles = Vec::new();
+    for (id, &color) in order.iter().enumerate() {
+        let config = &model.palette.colors[color];
+        let (left, top) = (id as u32 % COLUMNS * TILE, id as u32 / COLUMNS * TILE);
+        for y in top..top + TILE {
+            for x in left..left + TILE {
+                sheet.put_pixel(x, y, Rgba([config.r, config.g, config.b, 255]));
+            }
+        }
+        ids[color] = id as i64;
+        tiles.push(json!({
+            "id": id,
+            "name": config.name,
+            "color": format!("#{:02x}{:02x}{:02x}", config.r, config.g, config.b),
+        }));
+    }
+    sheet.save(path).expect("Unable to write Stardew tileset.");
+
+    let data: Vec<i64> = (0..Y_SIZE)
+        .rev()
+        .flat_map(|y| (0..X_SIZE).map(move |x| (y * X_SIZE + x) as usize))
+        .map(|tile| match model.assignment[tile] {
+            assign::HOLE => -1,
+            color => ids[color],
+        })
+        .collect();
+    let image = Path::new(path).file_name().map_or_else(
+        || path.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let config = json!({
+        "image": image,
+        "tilewidth": TILE,
+        "tileheight": TILE,
+        "columns": COLUMNS,
+        "tilecount": order.len(),
+        "tiles": tiles,
+        "map": { "width": X_SIZE, "height": Y_SIZE, "data": data },
+    });
+    let config_path = Path::new(path).with_extension("json");
+    let text = serde_json::to_string_pretty(&config).expect("Tileset config should serialize.");
+    fs::write(&config_path, text).expect("Unable to write Stardew tileset config.");
+    log::info!(
+        "Wrote {path} with {} tiles and {}.",
+        order.len(),
+        config_path.display()
+    );
+}
@@ -0,0 +1,116 @@
+//! Sticker sheets for building the mosaic with round stickers, written by
+//! `--export-stickersheet`: as many circles of each color as it has tiles,
+//! `--sticker-diameter-mm` across, on A4 pages to print on sticker paper.
+//!
+//! Colors follow each other down the pages in palette order, each under a
+//! heading and closed by a rule. Within a color the circles are packed in
+//! staggered rows, every other row shifted by half a circle to nest in the
+//! gaps of the one before.
+
+use super::color_sheets::MARGIN;
+use super::fuse_beads::bead;
+use super::parts::{self, Part};
+use crate::palette::ColorConfig;
+use crate::pdf::{self, Document, Page, A4};
+use crate::Model;
+
+/// Space left between neighbouring stickers for cutting them apart.
+const GAP_MM: f32 = 2.0;
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+/// Height of each color's heading, from the rule above to the first row.
+const HEADING: f32 = 24.0;
+
+/// Writes a section per color in use and logs how many stickers and pages
+/// it took.
+pub fn export(model: &Model, path: &str) {
+    let diameter = model.options.sticker_diameter_mm * POINTS_PER_MM;
+    let pitch = diameter + GAP_MM * POINTS_PER_MM;
+    let width = A4.0 - 2.0 * MARGIN;
+    // Rows shifted by half a circle have room for one sticker fewer when the
+    // shift pushes the last one past the margin.
+    let per_row = ((width - diameter) / pitch) as usize + 1;
+    let per_shifted_row = ((width - diameter - pitch / 2.0) / pitch) as usize + 1;
+    let row_step = pitch * 3f32.sqrt() / 2.0;
+
+    let parts: Vec<(&ColorConfig, Part)> = model
+        .palette
+        .colors
+        .iter()
+        .zip(parts::parts(model))
+        .filter(|(_, part)| part.used > 0)
+        .collect();
+    let mut document = Document::new(A4);
+    let mut page = Page::new();
+    let mut top = A4.1 - MARGIN;
+    let (mut total, mut pages) = (0, 1);
+    for (config, part) in &parts {
+        // A heading is never left at the foot of a page without a row.
+        if top - HEADING - diameter < MARGIN {
+            document.push(page);
+            page = Page::new();
+            pages += 1;
+            top = A4.1 - MARGIN;
+        }
+        heading(&mut page, part, top);
+        top -= HEADING;
+
+        let code = part.hex();
+        let mut left = part.used as usize;
+        let mut row = 0;
+        while left > 0 {
+            if top - diameter < MARGIN {
+                document.push(page);
+                page = Page::new();
+                pages += 1;
+                top = A4.1 - MARGIN;
+                heading(&mut page, part, top);
+                top -= HEADING;
+                row = 0;
+            }
+            let (shift, room) = if row % 2 == 1 {
+                (pitch / 2.0, per_shifted_row)
+            } else {
+                (0.0, per_row)
+            };
+            for column in 0..room.min(left) {
+                let center = (
+                    MARGIN + shift + diameter / 2.0 + column as f32 * pitch,
+                    top - diameter / 2.0,
+                );
+                bead(&mut page, config, center, diameter / 2.0, Some(&code));
+            }
+            left = left.saturating_sub(room);
+            row += 1;
+            top -= if left > 0 { row_step } else { diameter };
+        }
+        total += part.used;
+
+        top -= GAP_MM * POINTS_PER_MM;
+        page.stroke_color(0, 0, 0);
+        page.line_width(0.5);
+        page.line((MARGIN, top), (A4.0 - MARGIN, top));
+        top -= GAP_MM * POINTS_PER_MM;
+    }
+    document.push(page);
+    document.save(path);
+    log::info!(
+        "Wrote {total} stickers of {} colors on {pages} pages to {path}.",
+        parts.len()
+    );
+}
+
+/// The color's name and sticker count, set below `top`.
+fn heading(page: &mut Page, part: &Part, top: f32) {
+    let count = format!("{} stickers", part.used);
+    let mut name = part.name.clone();
+    let room = A4.0 - 2.0 * MARGIN - pdf::text_width(&count, 11.0) - 20.0;
+    while pdf::text_width(&name, 11.0) > room && name.pop().is_some() {}
+    page.fill_color(0, 0, 0);
+    page.text(MARGIN, top - 14.0, 11.0, &name);
+    page.text(
+        A4.0 - MARGIN - pdf::text_width(&count, 11.0),
+        top - 14.0,
+        11.0,
+        &count,
+    );
+}
@@ -0,0 +1,180 @@
+//! The mosaic as an SVG drawing, written by `--export-svg`: a square per
+//! tile at `--output-scale`, with the tiles of each palette color grouped.
+//!
+//! `--export-svg-filters` adds an SVG filter to every group for a painted
+//! rather than technical look: `noise` displaces the edges by turbulence
+//! as if drawn by hand, `blur` softens them by a pixel, and `shadow` drops
+//! a shadow from each tile. Combined, they apply in that order.
+
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Filter {
+    Noise,
+    Blur,
+    Shadow,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "noise" => Ok(Filter::Noise),
+            "blur" => Ok(Filter::Blur),
+            "shadow" => Ok(Filter::Shadow),
+            _ => Err(format!(
+                "Unknown SVG filter '{s}'; use noise, blur, or shadow"
+            )),
+        }
+    }
+}
+
+/// The filters of `--export-svg-filters`, each at most once, in the order
+/// they apply.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filters(Vec<Filter>);
+
+impl FromStr for Filters {
+    type Err = String;
+
+    /// A comma-separated list, such as `noise,shadow`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filters: Vec<Filter> = s.split(',').map(str::parse).collect::<Result<_, _>>()?;
+        filters.sort();
+        filters.dedup();
+        Ok(Filters(filters))
+    }
+}
+
+impl Filters {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+const FILTER_ID: &str = "mosaic-filter";
+
+/// Writes the drawing over the render's black background, leaving
+/// keyed-out holes empty.
+pub fn export(model: &Model, path: &str) {
+    let scale = model.options.output_scale;
+    let inner = scale.saturating_sub(1).max(1);
+    let (width, height) = (X_SIZE as u32 * scale, Y_SIZE as u32 * scale);
+    let filters = &model.options.svg_filters;
+
+    let mut svg = String::new();
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+         width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<title>Mosaic of {}</title>",
+        escape(&model.options.picture_path)
+    )
+    .unwrap();
+    if !filters.is_empty() {
+        write_filter(&mut svg, filters);
+    }
+    writeln!(
+        svg,
+        "<rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" fill=\"#000000\"/>"
+    )
+    .unwrap();
+
+    let filter = if filters.is_empty() {
+        String::new()
+    } else {
+        format!(" filter=\"url(#{FILTER_ID})\"")
+    };
+    let mut tiles_of = vec![Vec::new(); model.palette.colors.len()];
+    for (tile, &index) in model.assignment.iter().enumerate() {
+        if index != assign::HOLE {
+            tiles_of[index].push(tile);
+        }
+    }
+    let mut groups = 0;
+    for (config, tiles) in model.palette.colors.iter().zip(tiles_of) {
+        if tiles.is_empty() {
+            continue;
+        }
+        groups += 1;
+        writeln!(
+            svg,
+            "<g data-name=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"{filter}>",
+            escape(&config.name),
+            config.r,
+            config.g,
+            config.b
+        )
+        .unwrap();
+        for tile in tiles {
+            let color = &model.pixels[tile];
+            // SVG rows count down from the top, grid rows up from the bottom.
+            let (x, y) = (
+                color.x as u32 * scale,
+                (Y_SIZE - 1 - color.y) as u32 * scale,
+            );
+            writeln!(
+                svg,
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{inner}\" height=\"{inner}\"/>"
+            )
+            .unwrap();
+        }
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    fs::write(path, svg).expect("Unable to write SVG.");
+    log::info!("Wrote {path} with {groups} color groups.");
+}
+
+/// One filter chaining the primitives of `filters`, each taking the last
+/// one's result. Its region is widened so shadows and displaced edges are
+/// not clipped.
+fn write_filter(svg: &mut String, filters: &Filters) {
+    writeln!(
+        svg,
+        "<defs>\n<filter id=\"{FILTER_ID}\" x=\"-10%\" y=\"-10%\" width=\"120%\" height=\"120%\">"
+    )
+    .unwrap();
+    let mut last = "SourceGraphic";
+    for filter in &filters.0 {
+        let primitive = match filter {
+            Filter::Noise => format!(
+                "<feTurbulence type=\"fractalNoise\" baseFrequency=\"0.08\" numOctaves=\"2\" \
+                 result=\"turbulence\"/>\n\
+                 <feDisplacementMap in=\"{last}\" in2=\"turbulence\" scale=\"3\" \
+                 xChannelSelector=\"R\" yChannelSelector=\"G\" result=\"noise\"/>"
+            ),
+            Filter::Blur => {
+                format!("<feGaussianBlur in=\"{last}\" stdDeviation=\"1\" result=\"blur\"/>")
+            }
+            Filter::Shadow => format!(
+                "<feDropShadow in=\"{last}\" dx=\"1\" dy=\"1\" stdDeviation=\"1\" \
+                 flood-color=\"#000000\" flood-opacity=\"0.6\" result=\"shadow\"/>"
+            ),
+        };
+        svg.push_str(&primitive);
+        svg.push('\n');
+        last = match filter {
+            Filter::Noise => "noise",
+            Filter::Blur => "blur",
+            Filter::Shadow => "shadow",
+        };
+    }
+    svg.push_str("</filter>\n</defs>\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,96 @@
+//! The build of the mosaic as an animated SVG, written by
+//! `--export-svg-animation`: each tile fades from transparent to its
+//! palette color one after another in the order the solve placed them,
+//! the last finishing `--duration-s` seconds in, with nothing but CSS.
+//!
+//! A `<style>` block holds one `@keyframes` rule every tile shares, and
+//! each tile only sets its own `animation-delay`, so the file plays in any
+//! browser without script. Tiles the solve did not place, such as
+//! `--text`, are shown throughout.
+
+use super::build_sequence;
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::fmt::Write as _;
+use std::fs;
+
+/// The longest a single tile takes to fade in, in seconds.
+const FADE_S: f64 = 0.5;
+
+/// Writes the animation over the render's black background, leaving
+/// keyed-out holes empty.
+pub fn export(model: &Model, path: &str) {
+    let duration = model.options.duration_s;
+    let scale = model.options.output_scale;
+    let inner = scale.saturating_sub(1).max(1);
+    let (width, height) = (X_SIZE as u32 * scale, Y_SIZE as u32 * scale);
+    let (start, order) = build_sequence(model);
+    // Fades overlap, each a tenth of the whole at most, so the tiles keep
+    // coming in evenly right up to the end.
+    let fade = FADE_S.min(duration / 10.0);
+    let step = (duration - fade) / order.len().saturating_sub(1).max(1) as f64;
+
+    let mut svg = String::new();
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+         width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<title>Building the mosaic of {}</title>",
+        escape(&model.options.picture_path)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<style>\n\
+         @keyframes reveal {{ from {{ fill-opacity: 0; }} to {{ fill-opacity: 1; }} }}\n\
+         .tile {{ fill-opacity: 0; animation: reveal {fade:.3}s ease-out forwards; }}\n\
+         </style>"
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" fill=\"#000000\"/>"
+    )
+    .unwrap();
+    let rect = |svg: &mut String, tile: usize, animation: &str| {
+        let config = &model.palette.colors[model.assignment[tile]];
+        let color = &model.pixels[tile];
+        // SVG rows count down from the top, grid rows up from the bottom.
+        let (x, y) = (
+            color.x as u32 * scale,
+            (Y_SIZE - 1 - color.y) as u32 * scale,
+        );
+        writeln!(
+            svg,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{inner}\" height=\"{inner}\" \
+             fill=\"#{:02x}{:02x}{:02x}\"{animation}/>",
+            config.r, config.g, config.b
+        )
+        .unwrap();
+    };
+    for &tile in &start {
+        rect(&mut svg, tile, "");
+    }
+    for (placed, &tile) in order.iter().enumerate() {
+        let delay = placed as f64 * step;
+        let animation = format!(" class=\"tile\" style=\"animation-delay: {delay:.3}s\"");
+        rect(&mut svg, tile, &animation);
+    }
+    svg.push_str("</svg>\n");
+    fs::write(path, svg).expect("Unable to write SVG animation.");
+    log::info!(
+        "Wrote {path}, placing {} tiles over {duration}s.",
+        order.len()
+    );
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,269 @@
+//! A knitting or weaving chart, written by `--export-textile-chart`: the
+//! grid as cells filled with each stitch's color, or with `--textile-chart-
+//! style symbols` as black-and-white cells marked with the cross-stitch
+//! chart's symbols, then a key of the colors.
+//!
+//! Rows are numbered up the left side from the bottom, the way knitting is
+//! worked, unless `--textile-row-direction top-down` numbers them from the
+//! top; columns are numbered along the top from the left. A chart too big
+//! for one page's printable area continues over several.
+
+use super::color_sheets::MARGIN;
+use super::cross_stitch::{list_pages, symbol};
+use crate::assign;
+use crate::pdf::{self, Document, Page, A4};
+use crate::{Model, X_SIZE, Y_SIZE};
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+/// Which way `--export-textile-chart` numbers its rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RowDirection {
+    /// Row 1 at the bottom, where knitting starts.
+    #[default]
+    BottomUp,
+    /// Row 1 at the top.
+    TopDown,
+}
+
+impl FromStr for RowDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bottom-up" => Ok(RowDirection::BottomUp),
+            "top-down" => Ok(RowDirection::TopDown),
+            _ => Err(format!(
+                "Unknown row direction '{s}'; use bottom-up or top-down"
+            )),
+        }
+    }
+}
+
+/// How `--export-textile-chart` marks its cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartStyle {
+    /// Filled with the stitch's color and left unlabeled.
+    #[default]
+    Color,
+    /// Left white with the color's symbol, for printing in black and white.
+    Symbols,
+}
+
+impl FromStr for ChartStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "color" => Ok(ChartStyle::Color),
+            "symbols" => Ok(ChartStyle::Symbols),
+            _ => Err(format!(
+                "Unknown textile chart style '{s}'; use color or symbols"
+            )),
+        }
+    }
+}
+
+/// Cells are about 3.5mm, near a stitch of worsted knitting.
+const CELL: f32 = 10.0;
+const TITLE_SPACE: f32 = 50.0;
+/// Room left of the grid for row numbers and above it for column numbers.
+const LABEL_SPACE: f32 = 18.0;
+const LABEL_SIZE: f32 = 6.0;
+const SYMBOL_SIZE: f32 = CELL * 0.7;
+/// Every this many cells the grid line is drawn darker, as knitting
+/// charts usually are.
+const MAJOR_EVERY: u64 = 10;
+const COLUMNS_PER_PAGE: u64 = ((A4.0 - 2.0 * MARGIN - LABEL_SPACE) / CELL) as u64;
+const ROWS_PER_PAGE: u64 = ((A4.1 - 2.0 * MARGIN - TITLE_SPACE - LABEL_SPACE) / CELL) as u64;
+
+/// Writes the chart pages, top left first, then the key, most used color
+/// first.
+pub fn export(model: &Model, path: &str) {
+    let options = &model.options;
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let mut order: Vec<usize> = (0..used.len()).filter(|&index| used[index] > 0).collect();
+    order.sort_by_key(|&index| Reverse(used[index]));
+    let mut symbols = vec![String::new(); used.len()];
+    for (rank, &index) in order.iter().enumerate() {
+        symbols[index] = symbol(rank);
+    }
+
+    let across = X_SIZE.div_ceil(COLUMNS_PER_PAGE);
+    let down = Y_SIZE.div_ceil(ROWS_PER_PAGE);
+    let mut document = Document::new(A4);
+    for part_row in 0..down {
+        // Pages run from the top of the grid, whose rows count from the
+        // bottom.
+        let top = Y_SIZE - part_row * ROWS_PER_PAGE;
+        let rows = top.saturating_sub(ROWS_PER_PAGE)..top;
+        for part_column in 0..across {
+            let start = part_column * COLUMNS_PER_PAGE;
+            let columns = start..(start + COLUMNS_PER_PAGE).min(X_SIZE);
+            let part = (across > 1 || down > 1).then(|| {
+                format!(
+                    " (part {} of {})",
+                    part_row * across + part_column + 1,
+                    across * down
+                )
+            });
+            document.push(chart_page(
+                model,
+                &symbols,
+                columns,
+                rows.clone(),
+                part.as_deref().unwrap_or(""),
+            ));
+        }
+    }
+
+    list_pages(&mut document, "Key", &order, |page, index, baseline| {
+        let config = &model.palette.colors[index];
+        page.fill_color(config.r, config.g, config.b);
+        page.stroke_color(0, 0, 0);
+        page.line_width(0.8);
+        page.rect(MARGIN, baseline - 3.0, 13.0, 13.0, true, true);
+        page.fill_color(0, 0, 0);
+        if options.textile_chart_style == ChartStyle::Symbols {
+            page.text(MARGIN + 22.0, baseline, 11.0, &symbols[index]);
+        }
+        let mut name = config.name.clone();
+        let room = A4.0 - 2.0 * MARGIN - 160.0;
+        while pdf::text_width(&name, 11.0) > room && name.pop().is_some() {}
+        page.text(MARGIN + 48.0, baseline, 11.0, &name);
+        let count = format!("{} stitches", used[index]);
+        page.text(
+            A4.0 - MARGIN - pdf::text_width(&count, 11.0),
+            baseline,
+            11.0,
+            &count,
+        );
+    });
+    document.save(path);
+    log::info!("Wrote a textile chart of {} colors to {path}.", order.len());
+}
+
+/// The cells of `columns` and `rows`, with the grid's top-left corner just
+/// under the title and the labels.
+fn chart_page(
+    model: &Model,
+    symbols: &[String],
+    columns: std::ops::Range<u64>,
+    rows: std::ops::Range<u64>,
+    part: &str,
+) -> Page {
+    let options = &model.options;
+    let mut page = Page::new();
+    page.fill_color(0, 0, 0);
+    page.text(
+        MARGIN,
+        A4.1 - MARGIN - 18.0,
+        18.0,
+        &format!("Textile chart{part}"),
+    );
+    let worked = match options.textile_row_direction {
+        RowDirection::BottomUp => "Rows are worked from the bottom, row 1 first.",
+        RowDirection::TopDown => "Rows are numbered from the top.",
+    };
+    page.text(
+        MARGIN,
+        A4.1 - MARGIN - 34.0,
+        10.0,
+        &format!("One stitch per cell. {worked}"),
+    );
+
+    let left = MARGIN + LABEL_SPACE;
+    let top = A4.1 - MARGIN - TITLE_SPACE - LABEL_SPACE;
+    // Where cell (x, y) of the grid sits on the page.
+    let corner = |x: u64, y: u64| {
+        (
+            left + (x - columns.start) as f32 * CELL,
+            top - (rows.end - y) as f32 * CELL,
+        )
+    };
+    for y in rows.clone() {
+        for x in columns.clone() {
+            let assigned = model.assignment[(y * X_SIZE + x) as usize];
+            if assigned == assign::HOLE {
+                continue;
+            }
+            let (cell_left, cell_bottom) = corner(x, y);
+            match options.textile_chart_style {
+                ChartStyle::Color => {
+                    let config = &model.palette.colors[assigned];
+                    page.fill_color(config.r, config.g, config.b);
+                    page.rect(cell_left, cell_bottom, CELL, CELL, true, false);
+                }
+                ChartStyle::Symbols => {
+                    let symbol = &symbols[assigned];
+                    page.fill_color(0, 0, 0);
+                    page.text(
+                        cell_left + CELL / 2.0 - pdf::text_width(symbol, SYMBOL_SIZE) / 2.0,
+                        cell_bottom + CELL / 2.0 - SYMBOL_SIZE / 3.0,
+                        SYMBOL_SIZE,
+                        symbol,
+                    );
+                }
+            }
+        }
+    }
+
+    let (width, height) = (
+        (columns.end - columns.start) as f32 * CELL,
+        (rows.end - rows.start) as f32 * CELL,
+    );
+    for x in columns.start..=columns.end {
+        line_style(&mut page, x);
+        let at = left + (x - columns.start) as f32 * CELL;
+        page.line((at, top), (at, top - height));
+    }
+    for y in rows.start..=rows.end {
+        // Darker lines count rows the way they are numbered.
+        line_style(
+            &mut page,
+            match options.textile_row_direction {
+                RowDirection::BottomUp => y,
+                RowDirection::TopDown => Y_SIZE - y,
+            },
+        );
+        let at = top - (rows.end - y) as f32 * CELL;
+        page.line((left, at), (left + width, at));
+    }
+
+    page.fill_color(0, 0, 0);
+    for x in columns.clone() {
+        let label = (x + 1).to_string();
+        let (cell_left, _) = corner(x, rows.end);
+        page.text(
+            cell_left + CELL / 2.0 - pdf::text_width(&label, LABEL_SIZE) / 2.0,
+            top + 4.0,
+            LABEL_SIZE,
+            &label,
+        );
+    }
+    for y in rows.clone() {
+        let label = match options.textile_row_direction {
+            RowDirection::BottomUp => y + 1,
+            RowDirection::TopDown => Y_SIZE - y,
+        }
+        .to_string();
+        let (_, cell_bottom) = corner(columns.start, y);
+        page.text(
+            left - pdf::text_width(&label, LABEL_SIZE) - 3.0,
+            cell_bottom + CELL / 2.0 - LABEL_SIZE / 3.0,
+            LABEL_SIZE,
+            &label,
+        );
+    }
+    page
+}
+
+fn line_style(page: &mut Page, line: u64) {
+    if line.is_multiple_of(MAJOR_EVERY) {
+        page.stroke_color(60, 60, 60);
+        page.line_width(0.8);
+    } else {
+        page.stroke_color(170, 170, 170);
+        page.line_width(0.3);
+    }
+}
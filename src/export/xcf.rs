@@ -0,0 +1,58 @@
+//! The mosaic as an indexed GIMP image with a layer per palette color,
+//! written by `--export-xcf`.
+
+use crate::xcf::{Image, Layer};
+use crate::{assign, Model, X_SIZE, Y_SIZE};
+
+/// Writes an image one pixel per tile with every palette color in its
+/// colormap. Each used color gets a layer holding just its tiles, first
+/// color on top. A palette of more than 256 colors cannot be indexed, so
+/// nothing is written for it.
+pub fn export(model: &Model, path: &str) {
+    let colors = &model.palette.colors;
+    if colors.len() > 256 {
+        log::warn!(
+            "Skipping {path}: GIMP's indexed images hold 256 colors, not {}.",
+            colors.len()
+        );
+        return;
+    }
+    let (width, height) = (X_SIZE as u32, Y_SIZE as u32);
+    let used = assign::usage(&model.assignment, colors.len());
+    let layers = (0..colors.len())
+        .filter(|&index| used[index] > 0)
+        .map(|index| {
+            let mut pixels = vec![0; (width * height * 2) as usize];
+            for (tile, _) in model
+                .assignment
+                .iter()
+                .enumerate()
+                .filter(|&(_, &assigned)| assigned == index)
+            {
+                // Image rows count down from the top, grid rows up.
+                let (x, row) = (tile as u64 % X_SIZE, Y_SIZE - 1 - tile as u64 / X_SIZE);
+                let at = ((row * X_SIZE + x) * 2) as usize;
+                pixels[at..at + 2].copy_from_slice(&[index as u8, 255]);
+            }
+            Layer {
+                name: colors[index].name.clone(),
+                x: 0,
+                y: 0,
+                width,
+                height,
+                pixels,
+            }
+        })
+        .collect();
+    let image = Image {
+        width,
+        height,
+        colormap: colors
+            .iter()
+            .map(|config| [config.r, config.g, config.b])
+            .collect(),
+        layers,
+    };
+    image.save(path);
+    log::info!("Wrote {path}.");
+}
@@ -0,0 +1,75 @@
+//! The mosaic as an Excel workbook with one filled cell per tile.
+
+use crate::xlsx::{Cell, Sheet, Workbook};
+use crate::{assign, legend, Model, X_SIZE, Y_SIZE};
+
+/// About 24 pixels each way at Excel's default zoom.
+const TILE_WIDTH: f32 = 2.71;
+const TILE_HEIGHT: f32 = 18.0;
+
+/// Writes a workbook whose first sheet is the grid, each cell filled with
+/// its tile's color and labelled with the color's code, the first two
+/// characters of its name. The second sheet is the palette legend, numbered
+/// and ordered like the window's.
+pub fn export(model: &Model, path: &str) {
+    let rows = (0..Y_SIZE)
+        .rev()
+        .map(|y| {
+            (0..X_SIZE)
+                .map(|x| match model.assignment[(y * X_SIZE + x) as usize] {
+                    assign::HOLE => Cell::empty(),
+                    index => {
+                        let config = &model.palette.colors[index];
+                        Cell::text(code(&config.name)).filled([config.r, config.g, config.b])
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let mosaic = Sheet {
+        name: "Mosaic".to_string(),
+        rows,
+        column_widths: vec![TILE_WIDTH],
+        row_height: Some(TILE_HEIGHT),
+    };
+
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let declared = model.declared_palette();
+    let numbers = legend::numbers(model);
+    let mut order: Vec<usize> = (0..declared.colors.len()).collect();
+    order.sort_by_key(|&index| numbers[index]);
+    let header = ["No.", "Code", "Color", "Name", "RGB", "Tiles", "Declared"];
+    let mut rows = vec![header
+        .iter()
+        .map(|title| Cell::text(*title).bold())
+        .collect()];
+    for index in order {
+        let config = &declared.colors[index];
+        let rgb = [config.r, config.g, config.b];
+        rows.push(vec![
+            Cell::number(numbers[index] as f64),
+            Cell::text(code(&config.name)),
+            Cell::empty().filled(rgb),
+            Cell::text(config.name.clone()),
+            Cell::text(format!("#{:02X}{:02X}{:02X}", config.r, config.g, config.b)),
+            Cell::number(used[index] as f64),
+            Cell::number(config.count as f64),
+        ]);
+    }
+    let legend = Sheet {
+        name: "Palette".to_string(),
+        rows,
+        column_widths: vec![5.0, 6.0, 6.0, 24.0, 10.0, 8.0, 10.0],
+        row_height: None,
+    };
+
+    Workbook {
+        sheets: vec![mosaic, legend],
+    }
+    .save(path);
+    log::info!("Wrote {path}.");
+}
+
+fn code(name: &str) -> String {
+    name.chars().take(2).collect()
+}
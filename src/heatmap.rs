@@ -0,0 +1,136 @@
+//! The tile count heatmap `K` toggles: every tile drawn in a color for how
+//! many pieces its palette color has left, to show where count-limited
+//! colors gather in the mosaic.
+//!
+//! Colors run in a straight line from blue, for the most pieces any color
+//! has left, to red, for none. The tile under the mouse gets a tooltip with
+//! its real color.
+
+use crate::palette::ColorConfigs;
+use crate::{assign, Color};
+use nannou::prelude::*;
+
+/// How the main window colors its tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeatmapMode {
+    /// By their assigned or source colors.
+    #[default]
+    Off,
+    /// By their palette color's remaining count.
+    Remaining,
+}
+
+impl HeatmapMode {
+    pub fn toggle(self) -> HeatmapMode {
+        match self {
+            HeatmapMode::Off => HeatmapMode::Remaining,
+            HeatmapMode::Remaining => HeatmapMode::Off,
+        }
+    }
+}
+
+const MANY: (f32, f32, f32) = (0.0, 0.0, 255.0);
+const NONE: (f32, f32, f32) = (255.0, 0.0, 0.0);
+
+/// The heat of `remaining` pieces when the most left of any color is `max`.
+fn heat(remaining: u64, max: u64) -> (u8, u8, u8) {
+    let t = if max == 0 {
+        0.0
+    } else {
+        remaining as f32 / max as f32
+    };
+    let mix = |none: f32, many: f32| (none + (many - none) * t).round() as u8;
+    (
+        mix(NONE.0, MANY.0),
+        mix(NONE.1, MANY.1),
+        mix(NONE.2, MANY.2),
+    )
+}
+
+/// `pixels` recolored by the remaining count of each tile's palette color.
+/// Holes keep their color.
+pub fn recolor(pixels: &[Color], assignment: &[usize], palette: &ColorConfigs) -> Vec<Color> {
+    let max = palette
+        .colors
+        .iter()
+        .map(|config| config.count)
+        .max()
+        .unwrap_or(0);
+    pixels
+        .iter()
+        .zip(assignment)
+        .map(|(color, &index)| match palette.colors.get(index) {
+            Some(config) if index != assign::HOLE => {
+                let (r, g, b) = heat(config.count, max);
+                Color {
+                    r,
+                    g,
+                    b,
+                    ..color.clone()
+                }
+            }
+            _ => color.clone(),
+        })
+        .collect()
+}
+
+const TOOLTIP_WIDTH: f32 = 180.0;
+const TOOLTIP_HEIGHT: f32 = 22.0;
+
+/// Draws the tooltip of the tile assigned palette color `index` beside
+/// `mouse`, kept inside `window`: a swatch of the color, its name, and how
+/// many pieces are left.
+pub fn draw_tooltip(
+    draw: &Draw,
+    window: Rect,
+    mouse: Point2,
+    palette: &ColorConfigs,
+    index: usize,
+) {
+    let config = match palette.colors.get(index) {
+        Some(config) => config,
+        None => return,
+    };
+    let size = vec2(TOOLTIP_WIDTH, TOOLTIP_HEIGHT);
+    let mut at = mouse + vec2(14.0, -14.0) + size * vec2(0.5, -0.5);
+    at.x = at.x.min(window.right() - size.x / 2.0);
+    at.y = at.y.max(window.bottom() + size.y / 2.0);
+    let area = Rect::from_xy_wh(at, size);
+    draw.rect()
+        .xy(area.xy())
+        .wh(area.wh())
+        .color(srgba8(20, 20, 20, 230))
+        .stroke_weight(1.0)
+        .stroke(srgb8(120, 120, 120));
+    let swatch = Rect::from_x_y_w_h(area.left() + 12.0, area.y(), 14.0, 14.0);
+    draw.rect()
+        .xy(swatch.xy())
+        .wh(swatch.wh())
+        .color(srgb8(config.r, config.g, config.b))
+        .stroke_weight(1.0)
+        .stroke(WHITE);
+    let text = Rect::from_corners(
+        pt2(swatch.right() + 6.0, area.bottom()),
+        pt2(area.right() - 4.0, area.top()),
+    );
+    draw.text(&format!("{} ({} left)", config.name, config.count))
+        .xy(text.xy())
+        .wh(text.wh())
+        .left_justify()
+        .no_line_wrap()
+        .font_size(11)
+        .color(WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_runs_from_red_to_blue() {
+        assert_eq!(heat(0, 40), (255, 0, 0));
+        assert_eq!(heat(40, 40), (0, 0, 255));
+        assert_eq!(heat(10, 40), (191, 0, 64));
+        assert_eq!(heat(0, 0), (255, 0, 0));
+    }
+}
@@ -0,0 +1,174 @@
+//! Hexagonal tiles for `--hex-grid`.
+//!
+//! Tiles keep their grid coordinates and raster order, so inventory, counts,
+//! and the parts list work as for squares. Only where a tile sits changes:
+//! with pointy-top hexagons every odd row is shifted right by half a tile,
+//! and with flat-top ones every odd column is shifted up by half a tile.
+//!
+//! Geometry is worked out for pointy-top hexagons of radius 1, where
+//! neighbours in a row are `sqrt(3)` apart and rows 1.5 apart. A flat-top
+//! grid is the same with the axes swapped. Positions handed in and out are
+//! fractions of the grid's width and height, measured from the bottom left,
+//! so callers can stretch the grid over any rectangle without opening gaps.
+
+use crate::{X_SIZE, Y_SIZE};
+use std::str::FromStr;
+
+const SQRT_3: f32 = 1.732_050_8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexOrientation {
+    Pointy,
+    Flat,
+}
+
+impl FromStr for HexOrientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pointy" => Ok(HexOrientation::Pointy),
+            "flat" => Ok(HexOrientation::Flat),
+            _ => Err(format!("expected pointy or flat, got '{s}'")),
+        }
+    }
+}
+
+impl HexOrientation {
+    /// Width over height of a grid of regular hexagons.
+    pub fn aspect(self) -> f32 {
+        let (along, across) = extent(self.sizes());
+        match self {
+            HexOrientation::Pointy => along / across,
+            HexOrientation::Flat => across / along,
+        }
+    }
+
+    /// Centre of tile (`x`, `y`).
+    pub fn center(self, x: u64, y: u64) -> (f32, f32) {
+        let (i, j) = self.to_frame(x, y);
+        self.frame_to_fractions(center(i, j))
+    }
+
+    /// The six corners of tile (`x`, `y`), going round.
+    pub fn corners(self, x: u64, y: u64) -> [(f32, f32); 6] {
+        let (i, j) = self.to_frame(x, y);
+        let (a, b) = center(i, j);
+        std::array::from_fn(|k| {
+            let angle = (30.0 + 60.0 * k as f32).to_radians();
+            self.frame_to_fractions((a + angle.cos(), b + angle.sin()))
+        })
+    }
+
+    /// The tile whose hexagon contains the point, if any.
+    pub fn locate(self, fx: f32, fy: f32) -> Option<(u64, u64)> {
+        let (a, b) = self.fractions_to_frame(fx, fy);
+        let (i, j) = self.nearest_in_frame(a, b);
+        let (ca, cb) = center(i, j);
+        let (da, db) = ((a - ca).abs(), (b - cb).abs());
+        let inside = da <= SQRT_3 / 2.0 && db <= 1.0 - da / SQRT_3;
+        inside.then(|| self.to_frame(i, j))
+    }
+
+    /// The tile with the nearest centre, so points off the grid still pick
+    /// the tile at its edge.
+    pub fn nearest(self, fx: f32, fy: f32) -> (u64, u64) {
+        let (a, b) = self.fractions_to_frame(fx, fy);
+        let (i, j) = self.nearest_in_frame(a, b);
+        self.to_frame(i, j)
+    }
+
+    /// Offset rows: the nearest centre is in the row closest to the point
+    /// or one beside it, and near the column the point is in.
+    fn nearest_in_frame(self, a: f32, b: f32) -> (u64, u64) {
+        let (along, rows) = self.sizes();
+        let row = ((b - 1.0) / 1.5).round() as i64;
+        let mut best = (0, 0);
+        let mut best_dist = f32::MAX;
+        for j in (row - 1..=row + 1).filter(|&j| (0..rows as i64).contains(&j)) {
+            let shift = if j % 2 == 1 { 0.5 } else { 0.0 };
+            let column = (a / SQRT_3 - shift).floor() as i64;
+            for i in (column - 1..=column + 1).filter(|&i| (0..along as i64).contains(&i)) {
+                let (ca, cb) = center(i as u64, j as u64);
+                let dist = (a - ca).powi(2) + (b - cb).powi(2);
+                if dist < best_dist {
+                    best_dist = dist;
+                    best = (i as u64, j as u64);
+                }
+            }
+        }
+        best
+    }
+
+    /// Tiles along an offset row, and the number of rows.
+    fn sizes(self) -> (u64, u64) {
+        match self {
+            HexOrientation::Pointy => (X_SIZE, Y_SIZE),
+            HexOrientation::Flat => (Y_SIZE, X_SIZE),
+        }
+    }
+
+    /// Swaps grid and pointy-top coordinates for flat-top grids; the swap is
+    /// its own inverse.
+    fn to_frame(self, x: u64, y: u64) -> (u64, u64) {
+        match self {
+            HexOrientation::Pointy => (x, y),
+            HexOrientation::Flat => (y, x),
+        }
+    }
+
+    fn frame_to_fractions(self, (a, b): (f32, f32)) -> (f32, f32) {
+        let (along, across) = extent(self.sizes());
+        match self {
+            HexOrientation::Pointy => (a / along, b / across),
+            HexOrientation::Flat => (b / across, a / along),
+        }
+    }
+
+    fn fractions_to_frame(self, fx: f32, fy: f32) -> (f32, f32) {
+        let (along, across) = extent(self.sizes());
+        match self {
+            HexOrientation::Pointy => (fx * along, fy * across),
+            HexOrientation::Flat => (fy * along, fx * across),
+        }
+    }
+}
+
+/// Size of the grid along and across its offset rows.
+fn extent((along, rows): (u64, u64)) -> (f32, f32) {
+    (SQRT_3 * (along as f32 + 0.5), 1.5 * rows as f32 + 0.5)
+}
+
+fn center(i: u64, j: u64) -> (f32, f32) {
+    let shift = if j % 2 == 1 { 0.5 } else { 0.0 };
+    (SQRT_3 * (i as f32 + 0.5 + shift), 1.5 * j as f32 + 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centres_locate_their_own_tiles() {
+        for orientation in [HexOrientation::Pointy, HexOrientation::Flat] {
+            for (x, y) in [(0, 0), (1, 1), (7, 30), (X_SIZE - 1, Y_SIZE - 1)] {
+                let (fx, fy) = orientation.center(x, y);
+                assert_eq!(orientation.locate(fx, fy), Some((x, y)));
+                // A point just inside a corner still belongs to the tile.
+                let (cx, cy) = orientation.corners(x, y)[0];
+                let inside = (fx + (cx - fx) * 0.95, fy + (cy - fy) * 0.95);
+                assert_eq!(orientation.locate(inside.0, inside.1), Some((x, y)));
+            }
+            // The notch left of the first row's bottom-left tile is empty,
+            // but the nearest tile is still found.
+            assert_eq!(orientation.locate(0.0, 0.0), None);
+            assert_eq!(orientation.nearest(0.0, 0.0), (0, 0));
+        }
+        // Odd rows shift right in a pointy-top grid and odd columns shift
+        // up in a flat-top one.
+        let pointy = HexOrientation::Pointy;
+        assert!(pointy.center(0, 1).0 > pointy.center(0, 0).0);
+        let flat = HexOrientation::Flat;
+        assert!(flat.center(1, 0).1 > flat.center(0, 0).1);
+    }
+}
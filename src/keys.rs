@@ -0,0 +1,569 @@
+//! Keyboard shortcuts: the actions the window responds to and the keys bound
+//! to them.
+//!
+//! A `--keybindings` TOML file maps action names to a key or a list of keys,
+//! for example `save-session = "Ctrl+S"` or `count-up = ["=", "NumpadAdd"]`.
+//! Actions it leaves out keep their default keys. The number keys that pick
+//! palette colors are not actions and cannot be moved.
+
+use nannou::prelude::Key;
+use nannou::winit::event::ModifiersState;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    CycleLayer,
+    ToggleOriginal,
+    ToggleLegend,
+    ToggleLuminance,
+    ToggleHeatmap,
+    ToggleBatchCorners,
+    ToggleTileIndex,
+    CycleLegendSort,
+    CycleSymmetry,
+    ReloadPalette,
+    ReloadPaletteFull,
+    NextPicture,
+    PreviousPicture,
+    PauseSlideshow,
+    PasteImage,
+    ClearSelection,
+    Undo,
+    ReplaceColor,
+    ReplaceColorPartial,
+    SaveSession,
+    SaveBundle,
+    CountUp,
+    CountUpTen,
+    CountDown,
+    CountDownTen,
+    Solve,
+    SolveFull,
+    SavePalette,
+    RunExportPreset,
+    SearchColor,
+}
+
+/// Every action with its file name, a description, and its default keys.
+const ACTIONS: [(Action, &str, &str, &[&str]); 30] = [
+    (
+        Action::CycleLayer,
+        "cycle-layer",
+        "Cycle assigned colors, original, and blend",
+        &["V"],
+    ),
+    (
+        Action::ToggleOriginal,
+        "toggle-original",
+        "Toggle the downscaled original",
+        &["O"],
+    ),
+    (
+        Action::ToggleLegend,
+        "toggle-legend",
+        "Show or hide the palette legend",
+        &["L"],
+    ),
+    (
+        Action::ToggleLuminance,
+        "toggle-luminance",
+        "Show tiles in grays, beside the source's",
+        &["G"],
+    ),
+    (
+        Action::ToggleHeatmap,
+        "toggle-heatmap",
+        "Color tiles by their color's remaining count",
+        &["K"],
+    ),
+    (
+        Action::ToggleBatchCorners,
+        "toggle-batch-corners",
+        "Show or hide build batch triangles in tile corners",
+        &["B"],
+    ),
+    (
+        Action::ToggleTileIndex,
+        "toggle-tile-index",
+        "Show or hide each tile's raster index",
+        &["I"],
+    ),
+    (
+        Action::CycleLegendSort,
+        "cycle-legend-sort",
+        "Cycle the legend order: file, hue, lightness, usage, name",
+        &["Shift+L"],
+    ),
+    (
+        Action::CycleSymmetry,
+        "cycle-symmetry",
+        "Cycle the recolor symmetry mode",
+        &["M"],
+    ),
+    (
+        Action::ReloadPalette,
+        "reload-palette",
+        "Reload the palette file",
+        &["R"],
+    ),
+    (
+        Action::ReloadPaletteFull,
+        "reload-palette-full",
+        "Reload the palette file and re-solve from scratch",
+        &["Shift+R"],
+    ),
+    (
+        Action::NextPicture,
+        "next-picture",
+        "Next slideshow picture",
+        &["Right"],
+    ),
+    (
+        Action::PreviousPicture,
+        "previous-picture",
+        "Previous slideshow picture",
+        &["Left"],
+    ),
+    (
+        Action::PauseSlideshow,
+        "pause-slideshow",
+        "Pause or resume the slideshow",
+        &["Space"],
+    ),
+    (
+        Action::PasteImage,
+        "paste-image",
+        "Make a mosaic of the picture on the clipboard",
+        &["Ctrl+V"],
+    ),
+    (
+        Action::ClearSelection,
+        "clear-selection",
+        "Clear the tile selection",
+        &["Escape"],
+    ),
+    (Action::Undo, "undo", "Undo the last recolor", &["U"]),
+    (
+        Action::ReplaceColor,
+        "replace-color",
+        "Replace every tile of the picked color with the next color picked",
+        &["X"],
+    ),
+    (
+        Action::ReplaceColorPartial,
+        "replace-color-partial",
+        "Replace the picked color as far as the next color's pieces go",
+        &["Shift+X"],
+    ),
+    (
+        Action::SaveSession,
+        "save-session",
+        "Save to the --session file",
+        &["S"],
+    ),
+    (
+        Action::SaveBundle,
+        "save-bundle",
+        "Save the project as a .zip bundle",
+        &["Ctrl+B"],
+    ),
+    (
+        Action::CountUp,
+        "count-up",
+        "Raise the picked color's count by 1",
+        &["=", "+", "NumpadAdd"],
+    ),
+    (
+        Action::CountUpTen,
+        "count-up-ten",
+        "Raise the picked color's count by 10",
+        &["Shift+=", "Shift++", "Shift+NumpadAdd"],
+    ),
+    (
+        Action::CountDown,
+        "count-down",
+        "Lower the picked color's count by 1",
+        &["-", "NumpadSubtract"],
+    ),
+    (
+        Action::CountDownTen,
+        "count-down-ten",
+        "Lower the picked color's count by 10",
+        &["Shift+-", "Shift+NumpadSubtract"],
+    ),
+    (
+        Action::Solve,
+        "solve",
+        "Re-solve for the edited counts",
+        &["Enter", "NumpadEnter"],
+    ),
+    (
+        Action::SolveFull,
+        "solve-full",
+        "Re-solve for the edited counts from scratch",
+        &["Shift+Enter", "Shift+NumpadEnter"],
+    ),
+    (
+        Action::SavePalette,
+        "save-palette",
+        "Save the legend's counts as palette JSON",
+        &["P"],
+    ),
+    (
+        Action::RunExportPreset,
+        "run-export-preset",
+        "Run the default export preset",
+        &["E"],
+    ),
+    (
+        Action::SearchColor,
+        "search-color",
+        "Zoom to the tiles of a color named in the title bar",
+        &["/"],
+    ),
+];
+
+/// Key names accepted in the file. The first name of a key is the one
+/// printed.
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("0", Key::Key0),
+    ("1", Key::Key1),
+    ("2", Key::Key2),
+    ("3", Key::Key3),
+    ("4", Key::Key4),
+    ("5", Key::Key5),
+    ("6", Key::Key6),
+    ("7", Key::Key7),
+    ("8", Key::Key8),
+    ("9", Key::Key9),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("Escape", Key::Escape),
+    ("Esc", Key::Escape),
+    ("Space", Key::Space),
+    ("Enter", Key::Return),
+    ("Return", Key::Return),
+    ("Tab", Key::Tab),
+    ("Backspace", Key::Back),
+    ("Delete", Key::Delete),
+    ("Insert", Key::Insert),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("=", Key::Equals),
+    ("-", Key::Minus),
+    ("+", Key::Plus),
+    (",", Key::Comma),
+    (".", Key::Period),
+    ("/", Key::Slash),
+    ("\\", Key::Backslash),
+    (";", Key::Semicolon),
+    ("'", Key::Apostrophe),
+    ("[", Key::LBracket),
+    ("]", Key::RBracket),
+    ("`", Key::Grave),
+    ("NumpadAdd", Key::NumpadAdd),
+    ("NumpadSubtract", Key::NumpadSubtract),
+    ("NumpadMultiply", Key::NumpadMultiply),
+    ("NumpadDivide", Key::NumpadDivide),
+    ("NumpadEnter", Key::NumpadEnter),
+];
+
+/// A key together with the modifiers that must be held, and no others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    key: Key,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    /// Parses `Ctrl+Shift+S` and the like; `+` on its own names the plus key.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = match s.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if s == "+" => ("", "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let key = KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|&(_, key)| key)
+            .ok_or_else(|| format!("unknown key '{key}' in '{s}'"))?;
+        let mut binding = Binding {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "alt" => binding.alt = true,
+                "shift" => binding.shift = true,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{s}'")),
+            }
+        }
+        Ok(binding)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+        ] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        let name = KEY_NAMES
+            .iter()
+            .find(|&&(_, key)| key == self.key)
+            .map(|&(name, _)| name)
+            .unwrap_or("?");
+        f.write_str(name)
+    }
+}
+
+/// Which action each binding runs.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Binding, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::from_toml("").expect("No text is valid TOML.").0
+    }
+}
+
+impl Keymap {
+    /// Reads a keybindings file, warning about anything in it that is
+    /// ignored. Without a file the defaults are used.
+    pub fn load(path: Option<&str>) -> Keymap {
+        let path = match path {
+            Some(path) => path,
+            None => return Keymap::default(),
+        };
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Unable to read keybindings file {path}: {err}"));
+        let (keymap, warnings) = Keymap::from_toml(&text)
+            .unwrap_or_else(|err| panic!("Keybindings file {path} is not valid: {err}"));
+        for warning in warnings {
+            log::warn!("{path}: {warning}");
+        }
+        keymap
+    }
+
+    /// Builds the keymap from the file text, returning it with a warning
+    /// for each unknown action, bad key, or conflict.
+    fn from_toml(text: &str) -> Result<(Keymap, Vec<String>), toml::de::Error> {
+        let table: toml::Table = text.parse()?;
+        let mut warnings = Vec::new();
+        let mut bindings: Vec<(Binding, Action)> = Vec::new();
+        let mut rebound = Vec::new();
+
+        for (name, value) in &table {
+            let action = match ACTIONS.iter().find(|(_, n, _, _)| n == name) {
+                Some(&(action, ..)) => action,
+                None => {
+                    warnings.push(format!("unknown action '{name}'"));
+                    continue;
+                }
+            };
+            let keys: Vec<&str> = match value {
+                toml::Value::String(key) => vec![key],
+                toml::Value::Array(keys) => keys.iter().filter_map(|key| key.as_str()).collect(),
+                _ => {
+                    warnings.push(format!("{name} should be a key or a list of keys"));
+                    continue;
+                }
+            };
+            let mut parsed = Vec::new();
+            for key in &keys {
+                match key.parse::<Binding>() {
+                    Ok(binding) => parsed.push(binding),
+                    Err(err) => warnings.push(format!("{name}: {err}")),
+                }
+            }
+            // An empty list unbinds the action; a list of typos should not.
+            if parsed.is_empty() && !keys.is_empty() {
+                warnings.push(format!("{name} keeps its default keys"));
+                continue;
+            }
+            rebound.push(action);
+            for binding in parsed {
+                match bindings.iter().find(|(bound, _)| *bound == binding) {
+                    Some(&(_, other)) => warnings.push(format!(
+                        "{binding} is bound to both {} and {name}; keeping {}",
+                        action_name(other),
+                        action_name(other)
+                    )),
+                    None => bindings.push((binding, action)),
+                }
+            }
+        }
+
+        for &(action, name, _, defaults) in &ACTIONS {
+            if rebound.contains(&action) {
+                continue;
+            }
+            for key in defaults {
+                let binding: Binding = key.parse().expect("Default keys should parse.");
+                match bindings.iter().find(|(bound, _)| *bound == binding) {
+                    Some(&(_, other)) => warnings.push(format!(
+                        "{binding} now runs {} instead of its default, {name}",
+                        action_name(other)
+                    )),
+                    None => bindings.push((binding, action)),
+                }
+            }
+        }
+        Ok((Keymap { bindings }, warnings))
+    }
+
+    /// The action bound to `key` with exactly these modifiers held.
+    pub fn action(&self, key: Key, mods: ModifiersState) -> Option<Action> {
+        let pressed = Binding {
+            key,
+            ctrl: mods.ctrl(),
+            alt: mods.alt(),
+            shift: mods.shift(),
+        };
+        self.bindings
+            .iter()
+            .find(|(binding, _)| *binding == pressed)
+            .map(|&(_, action)| action)
+    }
+
+    /// Lists every action with its current keys, for `--print-keys`.
+    pub fn print(&self) {
+        for &(action, name, description, _) in &ACTIONS {
+            let keys: Vec<String> = self
+                .bindings
+                .iter()
+                .filter(|(_, bound)| *bound == action)
+                .map(|(binding, _)| binding.to_string())
+                .collect();
+            let keys = if keys.is_empty() {
+                "(unbound)".to_string()
+            } else {
+                keys.join(", ")
+            };
+            println!("{name:<22} {keys:<34} {description}");
+        }
+        println!(
+            "{:<22} {:<34} Pick or recolor with a palette color (fixed)",
+            "", "1-9"
+        );
+    }
+}
+
+fn action_name(action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|(a, ..)| *a == action)
+        .map(|&(_, name, ..)| name)
+        .expect("Every action should be listed.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_parse_and_print() {
+        let binding: Binding = "ctrl+shift+s".parse().unwrap();
+        assert_eq!(binding.to_string(), "Ctrl+Shift+S");
+        assert_eq!("Shift++".parse::<Binding>().unwrap().to_string(), "Shift++");
+        assert_eq!("+".parse::<Binding>().unwrap().key, Key::Plus);
+        assert!("Hyper+S".parse::<Binding>().is_err());
+        assert!("Nope".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn file_overrides_and_conflicts() {
+        let text = r#"
+            save-session = "Ctrl+S"
+            undo = ["Z", "V"]
+            redo = "Y"
+            toggle-legend = "Z"
+        "#;
+        let (keymap, warnings) = Keymap::from_toml(text).unwrap();
+        assert_eq!(
+            keymap.action(Key::S, ModifiersState::CTRL),
+            Some(Action::SaveSession)
+        );
+        assert_eq!(keymap.action(Key::S, ModifiersState::empty()), None);
+        assert_eq!(
+            keymap.action(Key::R, ModifiersState::SHIFT),
+            Some(Action::ReloadPaletteFull)
+        );
+        // Z taken twice in the file, V taken from cycle-layer's default.
+        assert_eq!(
+            keymap.action(Key::Z, ModifiersState::empty()),
+            Some(Action::ToggleLegend)
+        );
+        assert_eq!(
+            keymap.action(Key::V, ModifiersState::empty()),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            warnings,
+            [
+                "unknown action 'redo'",
+                "Z is bound to both toggle-legend and undo; keeping toggle-legend",
+                "V now runs undo instead of its default, cycle-layer",
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Text drawn on top of individual tiles in exported images.
+
+use crate::font;
+use crate::hex::HexOrientation;
+use crate::{Color, Y_SIZE};
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use std::fs;
+
+/// How tile labels are drawn.
+pub enum LabelFont {
+    /// The built-in 5x7 bitmap font at a whole-pixel scale.
+    Bitmap,
+    /// An anti-aliased TrueType or OpenType font.
+    Outline(FontVec),
+}
+
+impl LabelFont {
+    /// Loads the font at `path`, falling back to the bitmap font with a
+    /// warning when it cannot be read.
+    pub fn load(path: Option<&str>) -> LabelFont {
+        let path = match path {
+            Some(path) => path,
+            None => return LabelFont::Bitmap,
+        };
+        let font = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| FontVec::try_from_vec(bytes).map_err(|err| err.to_string()));
+        match font {
+            Ok(font) => LabelFont::Outline(font),
+            Err(err) => {
+                log::warn!("Could not load label font {path} ({err}); using the built-in font.");
+                LabelFont::Bitmap
+            }
+        }
+    }
+}
+
+/// Writes `labels[i]` centered on the tile of `pixels[i]`, in black or white
+/// depending on the tile color. `tile_px` must match the render of `img`.
+pub fn label_tiles(
+    img: &mut RgbaImage,
+    pixels: &[Color],
+    labels: &[String],
+    tile_px: u32,
+    font: &LabelFont,
+) {
+    // Leave room for the gap and a pixel of padding on each side.
+    let room = tile_px.saturating_sub(3);
+    label_boxes(img, pixels, labels, room, font, |color| {
+        (
+            color.x as u32 * tile_px + 1,
+            (Y_SIZE - 1 - color.y) as u32 * tile_px + 1,
+        )
+    });
+}
+
+/// Like `label_tiles` for a `render::render_hex_to_rgba` image, with each
+/// label in the square that fits inside its hexagon.
+pub fn label_hex_tiles(
+    img: &mut RgbaImage,
+    pixels: &[Color],
+    labels: &[String],
+    tile_px: u32,
+    font: &LabelFont,
+    orientation: HexOrientation,
+) {
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    // A hexagon is a little narrower than a square tile of the same grid.
+    let room = (tile_px * 2 / 3).saturating_sub(2);
+    label_boxes(img, pixels, labels, room, font, |color| {
+        let (fx, fy) = orientation.center(color.x, color.y);
+        let left = fx * width - room as f32 / 2.0;
+        let top = (1.0 - fy) * height - room as f32 / 2.0;
+        (left.max(0.0) as u32, top.max(0.0) as u32)
+    });
+}
+
+/// Draws each label in the `room`-pixel square whose top-left corner
+/// `top_left` gives for its tile.
+fn label_boxes(
+    img: &mut RgbaImage,
+    pixels: &[Color],
+    labels: &[String],
+    room: u32,
+    font: &LabelFont,
+    top_left: impl Fn(&Color) -> (u32, u32),
+) {
+    for (color, label) in pixels.iter().zip(labels) {
+        let (left, top) = top_left(color);
+        let ink = ink_for(color);
+        match font {
+            LabelFont::Bitmap => draw_bitmap(img, left, top, room, label, ink),
+            LabelFont::Outline(font) => draw_outline(img, left, top, room, label, ink, font),
+        }
+    }
+}
+
+/// Puts a small dot in the top corner of each of `tiles`, in the same ink as
+/// labels, so they can be told apart from the solved ones.
+pub fn mark_tiles(
+    img: &mut RgbaImage,
+    pixels: &[Color],
+    tiles: &[usize],
+    tile_px: u32,
+    hex_grid: Option<HexOrientation>,
+) {
+    let size = (tile_px / 5).max(1);
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    for &tile in tiles {
+        let color = &pixels[tile];
+        let (left, top) = match hex_grid {
+            // Just under the hexagon's top, clear of a centred label.
+            Some(orientation) => {
+                let (fx, fy) = orientation.center(color.x, color.y);
+                let left = fx * width - size as f32 / 2.0;
+                let top = (1.0 - fy) * height - (tile_px / 3) as f32;
+                (left.max(0.0) as u32, top.max(0.0) as u32)
+            }
+            None => (
+                (color.x as u32 + 1) * tile_px - 2 - size,
+                (Y_SIZE - 1 - color.y) as u32 * tile_px + 1,
+            ),
+        };
+        let [r, g, b] = ink_for(color);
+        for y in top..(top + size).min(img.height()) {
+            for x in left..(left + size).min(img.width()) {
+                img.put_pixel(x, y, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+}
+
+/// Black on light tiles and white on dark ones.
+fn ink_for(color: &Color) -> [u8; 3] {
+    let luma = 0.3 * color.r as f32 + 0.59 * color.g as f32 + 0.11 * color.b as f32;
+    if luma > 128.0 {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    }
+}
+
+fn draw_bitmap(img: &mut RgbaImage, left: u32, top: u32, room: u32, text: &str, ink: [u8; 3]) {
+    let scale = (1..)
+        .take_while(|&scale| {
+            font::text_width(text, scale) <= room && font::GLYPH_HEIGHT * scale <= room
+        })
+        .last();
+    if let Some(scale) = scale {
+        let x = left + (room - font::text_width(text, scale)) / 2;
+        let y = top + (room - font::GLYPH_HEIGHT * scale) / 2;
+        let [r, g, b] = ink;
+        font::draw_text(img, x, y, text, Rgba([r, g, b, 255]), scale);
+    }
+}
+
+fn draw_outline(
+    img: &mut RgbaImage,
+    left: u32,
+    top: u32,
+    room: u32,
+    text: &str,
+    ink: [u8; 3],
+    font: &FontVec,
+) {
+    if room == 0 {
+        return;
+    }
+    // Size the text to the cell's height, then shrink it if it is too wide.
+    let mut scale = PxScale::from(room as f32);
+    let width = text_width(font, scale, text);
+    if width > room as f32 {
+        scale = PxScale::from(room as f32 * room as f32 / width);
+    }
+    let scaled = font.as_scaled(scale);
+    let width = text_width(font, scale, text);
+    let height = scaled.ascent() - scaled.descent();
+    let mut x = left as f32 + (room as f32 - width) / 2.0;
+    let baseline = top as f32 + (room as f32 - height) / 2.0 + scaled.ascent();
+
+    let (img_w, img_h) = img.dimensions();
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        let glyph = id.with_scale_and_position(scale, point(x, baseline));
+        x += scaled.h_advance(id);
+        let outlined = match font.outline_glyph(glyph) {
+            Some(outlined) => outlined,
+            None => continue,
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px < 0 || py < 0 || px >= img_w as i64 || py >= img_h as i64 {
+                return;
+            }
+            let pixel = img.get_pixel_mut(px as u32, py as u32);
+            let alpha = coverage.clamp(0.0, 1.0);
+            for (channel, &target) in pixel.0.iter_mut().zip(&ink) {
+                *channel = (*channel as f32 * (1.0 - alpha) + target as f32 * alpha).round() as u8;
+            }
+        });
+    }
+}
+
+fn text_width(font: &FontVec, scale: PxScale, text: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    text.chars()
+        .map(|c| scaled.h_advance(scaled.glyph_id(c)))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitmap_labels_scale_to_fit_and_skip_tiny_tiles() {
+        let tile = Color {
+            r: 250,
+            g: 250,
+            b: 250,
+            x: 0,
+            y: Y_SIZE - 1,
+        };
+        let labels = ["7".to_string()];
+        let mut img = RgbaImage::from_pixel(20, 20, Rgba([250, 250, 250, 255]));
+        label_tiles(
+            &mut img,
+            std::slice::from_ref(&tile),
+            &labels,
+            20,
+            &LabelFont::Bitmap,
+        );
+        // 17px of room fits the glyph at scale 2, dark on the light tile.
+        let dark = img.pixels().filter(|p| p.0[0] == 0).count();
+        assert!(dark > 0);
+
+        let mut tiny = RgbaImage::from_pixel(6, 6, Rgba([250, 250, 250, 255]));
+        label_tiles(&mut tiny, &[tile], &labels, 6, &LabelFont::Bitmap);
+        assert!(tiny.pixels().all(|p| p.0[0] == 250));
+    }
+}
@@ -0,0 +1,404 @@
+//! The palette legend beside the mosaic, and editing declared counts from it.
+//!
+//! Edits collect in `Model::count_edits` until `Enter` re-solves against
+//! them, so several colors can be adjusted before paying for a solve.
+//!
+//! The legend's order also numbers the colors for tile labels and printed
+//! sheets, so exports always match the legend as it was sorted when they
+//! were written.
+
+use crate::palette::{ColorConfig, ColorConfigs};
+use crate::{assign, resolve_palette, selection, Model};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How the legend orders the palette.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegendSort {
+    /// As listed in the palette file.
+    #[default]
+    File,
+    /// Around the color wheel from red, grays first.
+    Hue,
+    /// Darkest first.
+    Lightness,
+    /// Most used first.
+    Usage,
+    /// Alphabetically, ignoring case.
+    Name,
+}
+
+impl FromStr for LegendSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(LegendSort::File),
+            "hue" => Ok(LegendSort::Hue),
+            "lightness" => Ok(LegendSort::Lightness),
+            "usage" => Ok(LegendSort::Usage),
+            "name" => Ok(LegendSort::Name),
+            _ => Err(format!(
+                "expected file, hue, lightness, usage, or name, got '{s}'"
+            )),
+        }
+    }
+}
+
+impl LegendSort {
+    /// The next order for the cycle key.
+    pub fn next(self) -> LegendSort {
+        match self {
+            LegendSort::File => LegendSort::Hue,
+            LegendSort::Hue => LegendSort::Lightness,
+            LegendSort::Lightness => LegendSort::Usage,
+            LegendSort::Usage => LegendSort::Name,
+            LegendSort::Name => LegendSort::File,
+        }
+    }
+}
+
+/// Indices of `colors` in `sort` order, with file order breaking ties.
+/// `used` is how many tiles each color covers.
+pub fn sorted(colors: &[ColorConfig], used: &[u64], sort: LegendSort) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..colors.len()).collect();
+    match sort {
+        LegendSort::File => {}
+        LegendSort::Hue => order.sort_by(|&a, &b| {
+            hue_key(&colors[a])
+                .partial_cmp(&hue_key(&colors[b]))
+                .unwrap()
+        }),
+        LegendSort::Lightness => {
+            order.sort_by(|&a, &b| luma(&colors[a]).total_cmp(&luma(&colors[b])))
+        }
+        LegendSort::Usage => order.sort_by_key(|&index| Reverse(used[index])),
+        LegendSort::Name => order.sort_by_key(|&index| colors[index].name.to_lowercase()),
+    }
+    order
+}
+
+fn luma(config: &ColorConfig) -> f32 {
+    0.3 * config.r as f32 + 0.59 * config.g as f32 + 0.11 * config.b as f32
+}
+
+/// Grays before colors, then hue in degrees, then lightness.
+fn hue_key(config: &ColorConfig) -> (bool, f32, f32) {
+    let [r, g, b] = [config.r, config.g, config.b].map(|c| c as f32);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max == min {
+        return (false, 0.0, luma(config));
+    }
+    let delta = max - min;
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (true, hue * 60.0, luma(config))
+}
+
+/// Tiles covered by each palette color, frame included.
+fn used(model: &Model) -> Vec<u64> {
+    let mut used = assign::usage(&model.assignment, model.palette.colors.len());
+    if let Some(frame) = model.frame {
+        used[frame.color] += frame.tile_count();
+    }
+    used
+}
+
+/// The palette file's colors in the legend's current order.
+fn order(model: &Model) -> Vec<usize> {
+    let used = used(model);
+    sorted(
+        &model.palette.colors[..model.main_colors],
+        &used,
+        model.legend_sort,
+    )
+}
+
+/// Each palette color's number, from 1, as tile labels and sheets show it:
+/// the palette file's colors in legend order, then `--corner-tiles` pieces
+/// in palette order.
+pub fn numbers(model: &Model) -> Vec<usize> {
+    let mut numbers: Vec<usize> = (1..=model.palette.colors.len()).collect();
+    for (position, index) in order(model).into_iter().enumerate() {
+        numbers[index] = position + 1;
+    }
+    numbers
+}
+
+/// Width of the legend strip on the right of the window.
+const WIDTH: f32 = 240.0;
+const HEADER_HEIGHT: f32 = 20.0;
+const MAX_ROW_HEIGHT: f32 = 18.0;
+
+/// The part of the window left for the mosaic panes.
+pub fn mosaic_area(window: Rect, model: &Model) -> Rect {
+    if model.legend {
+        Rect::from_corners(
+            window.bottom_left(),
+            pt2(window.right() - WIDTH, window.top()),
+        )
+    } else {
+        window
+    }
+}
+
+fn area(window: Rect) -> Rect {
+    Rect::from_corners(
+        pt2(window.right() - WIDTH, window.bottom()),
+        window.top_right(),
+    )
+}
+
+fn row_height(window: Rect, rows: usize) -> f32 {
+    ((window.h() - HEADER_HEIGHT) / rows.max(1) as f32).min(MAX_ROW_HEIGHT)
+}
+
+/// The palette with the counts the legend shows: pending edits if there
+/// are any, otherwise the declared counts. `--corner-tiles` pieces are left
+/// out since they come from their own file.
+fn declared(model: &Model) -> ColorConfigs {
+    if let Some(edits) = &model.count_edits {
+        return edits.clone();
+    }
+    let mut declared = model.declared_palette();
+    declared.colors.truncate(model.main_colors);
+    declared
+}
+
+pub fn draw(draw: &Draw, window: Rect, model: &Model) {
+    if !model.legend {
+        return;
+    }
+    let area = area(window);
+    draw.rect()
+        .xy(area.xy())
+        .wh(area.wh())
+        .color(srgb8(24, 24, 24));
+    let header = Rect::from_x_y_w_h(
+        area.x(),
+        area.top() - HEADER_HEIGHT / 2.0,
+        area.w() - 8.0,
+        HEADER_HEIGHT,
+    );
+    let pending = if model.count_edits.is_some() {
+        "  (Enter to solve)"
+    } else {
+        ""
+    };
+    let sort = format!("{:?}", model.legend_sort).to_lowercase();
+    let title = match model.replacing {
+        Some(replacing) => format!(
+            "Replace {} with...",
+            model.palette.colors[replacing.from].name
+        ),
+        None => format!("Used / declared, by {sort}{pending}"),
+    };
+    draw.text(&title)
+        .xy(header.xy())
+        .wh(header.wh())
+        .left_justify()
+        .font_size(11)
+        .color(WHITE);
+
+    let declared = declared(model);
+    let used = used(model);
+    let height = row_height(window, declared.colors.len());
+    for (row, &index) in order(model).iter().enumerate() {
+        let config = &declared.colors[index];
+        let top = area.top() - HEADER_HEIGHT - row as f32 * height;
+        let y = top - height / 2.0;
+        let swatch = height - 2.0;
+        draw.rect()
+            .x_y(area.left() + 4.0 + swatch / 2.0, y)
+            .w_h(swatch, swatch)
+            .color(srgb8(config.r, config.g, config.b));
+        if model.editing_color == Some(index) {
+            draw.rect()
+                .x_y(area.x(), y)
+                .w_h(area.w() - 2.0, height)
+                .no_fill()
+                .stroke(WHITE)
+                .stroke_weight(1.0);
+        }
+        if height >= 8.0 {
+            let text_left = area.left() + 8.0 + swatch;
+            let text =
+                Rect::from_corners(pt2(text_left, top - height), pt2(area.right() - 4.0, top));
+            let mut label = format!(
+                "{}. {}  {}/{}",
+                row + 1,
+                config.name,
+                used[index],
+                config.count
+            );
+            let over = used[index] > config.count;
+            if over {
+                label.push_str("  over");
+            }
+            draw.text(&label)
+                .xy(text.xy())
+                .wh(text.wh())
+                .left_justify()
+                .no_line_wrap()
+                .font_size((height - 6.0).clamp(6.0, 11.0) as u32)
+                .color(if over {
+                    srgb8(255, 140, 120)
+                } else {
+                    srgb8(230, 230, 230)
+                });
+        }
+    }
+}
+
+/// Selects the legend entry under `point`. Returns whether the point was on
+/// the legend at all.
+pub fn click(app: &App, window: Rect, model: &mut Model, point: Point2) -> bool {
+    if !model.legend || !area(window).contains(point) {
+        return false;
+    }
+    let rows = model.main_colors;
+    let height = row_height(window, rows);
+    let row = ((window.top() - HEADER_HEIGHT - point.y) / height).floor();
+    if row >= 0.0 && (row as usize) < rows {
+        select(app, model, order(model)[row as usize]);
+    }
+    true
+}
+
+/// Selects a color with a number key, cycling like tile recoloring does.
+pub fn select_digit(app: &App, model: &mut Model, digit: usize) {
+    let current = model.editing_color.unwrap_or(usize::MAX);
+    if let Some(index) = selection::digit_color(digit, current, model.main_colors) {
+        select(app, model, index);
+    }
+}
+
+/// Picks a color to edit, or the color to replace with when a replace is
+/// waiting for one.
+fn select(app: &App, model: &mut Model, index: usize) {
+    if let Some(replacing) = model.replacing.take() {
+        selection::finish_replace(app, model, replacing, index);
+        return;
+    }
+    model.legend = true;
+    model.editing_color = Some(index);
+    let declared = declared(model);
+    let config = &declared.colors[index];
+    log::info!("Editing {}: {} declared.", config.name, config.count);
+}
+
+/// Changes the selected color's declared count by `delta`, stopping at zero.
+pub fn adjust(app: &App, model: &mut Model, delta: i64) {
+    let index = match model.editing_color {
+        Some(index) => index,
+        None => {
+            log::info!("Pick a color in the legend or with a number key first.");
+            return;
+        }
+    };
+    let mut edits = declared(model);
+    let config = &mut edits.colors[index];
+    config.count = config.count.saturating_add_signed(delta);
+    let message = format!(
+        "{}: {} declared (Enter to solve).",
+        config.name, config.count
+    );
+    app.main_window().set_title(&message);
+    model.count_edits = Some(edits);
+}
+
+/// Solves against the edited counts. Cutting a color below what it already
+/// covers frees its worst-matching tiles, as reloading a palette file does.
+/// Edits leaving too few pieces for the tiles are kept, unsolved, to be
+/// raised again.
+pub fn apply(model: &mut Model, full: bool) {
+    if model.progress.is_some() {
+        log::info!("Still solving; try again once the mosaic is complete.");
+        return;
+    }
+    let edits = match model.count_edits.take() {
+        Some(edits) => edits,
+        None => {
+            log::info!("No count edits to solve for.");
+            return;
+        }
+    };
+    if let Err(short) = resolve_palette(model, edits.clone(), full) {
+        log::warn!("{short}; raise some counts and press Enter again.");
+        model.count_edits = Some(edits);
+    }
+}
+
+/// Writes the legend's counts, edited or not, as palette JSON next to the
+/// palette file.
+pub fn save(model: &Model) {
+    let path = match &model.options.color_data {
+        Some(path) => {
+            let path = Path::new(path);
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{stem}_edited.json"))
+        }
+        None => Path::new("palette_edited.json").to_path_buf(),
+    };
+    let path = path.to_string_lossy();
+    declared(model).save(&path);
+    log::info!("Wrote {path}.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Options;
+
+    #[test]
+    fn over_cut_edits_are_kept_unsolved() {
+        let args = ["media/me.png", "sample/colors.json", "--headless"];
+        let options = Options::parse(args.map(String::from).to_vec());
+        let img = image::open("media/me.png").unwrap();
+        let mut model = crate::build_model_from(options, &img, false);
+        let before = (model.assignment.clone(), model.palette.clone());
+        let mut edits = declared(&model);
+        // One piece of each color is far too few for the grid.
+        for config in &mut edits.colors {
+            config.count = 1;
+        }
+        model.count_edits = Some(edits);
+        apply(&mut model, false);
+        assert_eq!(model.assignment, before.0);
+        let counts = |palette: &ColorConfigs| {
+            palette
+                .colors
+                .iter()
+                .map(|config| config.count)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(counts(&model.palette), counts(&before.1));
+        assert!(model.count_edits.is_some());
+    }
+
+    #[test]
+    fn sorts_keep_file_order_between_ties() {
+        let color = |name: &str, r, g, b| ColorConfig::new(name.to_string(), r, g, b, 10);
+        let colors = [
+            color("blue", 0, 0, 255),
+            color("White", 255, 255, 255),
+            color("Red", 255, 0, 0),
+            color("green", 0, 160, 0),
+        ];
+        let used = [4, 0, 4, 7];
+        assert_eq!(sorted(&colors, &used, LegendSort::File), [0, 1, 2, 3]);
+        assert_eq!(sorted(&colors, &used, LegendSort::Hue), [1, 2, 3, 0]);
+        assert_eq!(sorted(&colors, &used, LegendSort::Lightness), [0, 2, 3, 1]);
+        assert_eq!(sorted(&colors, &used, LegendSort::Usage), [3, 0, 2, 1]);
+        assert_eq!(sorted(&colors, &used, LegendSort::Name), [0, 3, 2, 1]);
+    }
+}
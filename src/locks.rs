@@ -0,0 +1,325 @@
+//! Tiles held to a chosen color whatever the picture shows: the lettering of
+//! `--text` and the tiles of `--force-mask`.
+//!
+//! Their pieces are taken out of the palette before solving, the solvers
+//! skip them, and anything that recolors tiles afterwards, such as region
+//! merging or edge pieces, has them put back.
+
+use crate::assign;
+use crate::cli::Options;
+use crate::palette::ColorConfigs;
+use crate::status::{self, Failure};
+use crate::text;
+use crate::{Color, X_SIZE, Y_SIZE};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+
+/// The color a locked tile is held to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// The palette color with this name.
+    Named(String),
+    /// Whichever palette color is nearest, however many are left.
+    Nearest([u8; 3]),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Lock {
+    target: Target,
+    /// Set for `--force-mask` tiles, which exports point out.
+    forced: bool,
+}
+
+/// One entry of a JSON force mask.
+#[derive(Debug, Deserialize)]
+struct ForcedTile {
+    x: u64,
+    y: u64,
+    color: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Locks {
+    /// The lock on every tile in raster order, if any.
+    locks: Vec<Option<Lock>>,
+}
+
+impl Locks {
+    /// The tiles `--text` and `--force-mask` lock, if there are any. Forced
+    /// tiles win where the two overlap.
+    pub fn from_options(options: &Options) -> Option<Locks> {
+        let mut locks = Locks {
+            locks: vec![None; (X_SIZE * Y_SIZE) as usize],
+        };
+        for (tile, name) in text::Banner::from_options(options).map_or(Vec::new(), |b| b.tiles()) {
+            locks.locks[tile] = Some(Lock {
+                target: Target::Named(name),
+                forced: false,
+            });
+        }
+        if let Some(path) = &options.force_mask {
+            for (tile, target) in load_mask(path) {
+                locks.locks[tile] = Some(Lock {
+                    target,
+                    forced: true,
+                });
+            }
+        }
+        locks.locks.iter().any(Option::is_some).then_some(locks)
+    }
+
+    pub fn locks(&self, tile: usize) -> bool {
+        self.locks[tile].is_some()
+    }
+
+    /// Whether `tile` was forced by `--force-mask`.
+    pub fn is_forced(&self, tile: usize) -> bool {
+        self.locks[tile].as_ref().is_some_and(|lock| lock.forced)
+    }
+
+    /// The tiles forced by `--force-mask`, in raster order.
+    pub fn forced_tiles(&self) -> Vec<usize> {
+        (0..self.locks.len())
+            .filter(|&tile| self.is_forced(tile))
+            .collect()
+    }
+
+    /// The tiles the solvers fill, in raster order.
+    pub fn free_tiles(&self) -> Vec<usize> {
+        (0..self.locks.len())
+            .filter(|&tile| !self.locks(tile))
+            .collect()
+    }
+
+    /// Takes the locked tiles' pieces out of `palette`. When a color is
+    /// short, every forced tile asking for it is reported before failing.
+    pub fn reserve(&self, palette: &mut ColorConfigs) {
+        let targets = self.targets(palette);
+        let mut needed = vec![0; palette.colors.len()];
+        for &target in targets.iter().flatten() {
+            needed[target] += 1;
+        }
+        let short: Vec<usize> = (0..needed.len())
+            .filter(|&index| needed[index] > palette.colors[index].count)
+            .collect();
+        if !short.is_empty() {
+            for (tile, target) in targets.iter().enumerate() {
+                match target {
+                    Some(target) if short.contains(target) && self.is_forced(tile) => log::error!(
+                        "Tile ({}, {}) is forced to {}, which has only {} pieces for {} locked tiles.",
+                        tile as u64 % X_SIZE,
+                        tile as u64 / X_SIZE,
+                        palette.colors[*target].name,
+                        palette.colors[*target].count,
+                        needed[*target]
+                    ),
+                    _ => {}
+                }
+            }
+            let names: Vec<String> = short
+                .iter()
+                .map(|&index| {
+                    let config = &palette.colors[index];
+                    format!(
+                        "{} ({} needed, {} available)",
+                        config.name, needed[index], config.count
+                    )
+                })
+                .collect();
+            let shortfall: Vec<_> = short
+                .iter()
+                .map(|&index| {
+                    let config = &palette.colors[index];
+                    json!({ "color": config.name, "needed": needed[index], "available": config.count })
+                })
+                .collect();
+            status::classify(Failure::Inventory, json!({ "shortfall": shortfall }));
+            panic!(
+                "Not enough pieces for the locked tiles: {}",
+                names.join(", ")
+            )
+        }
+        for (config, needed) in palette.colors.iter_mut().zip(needed) {
+            config.count -= needed;
+        }
+    }
+
+    /// Colors the locked tiles of a fresh solve, whose pieces `reserve` has
+    /// already taken.
+    pub fn place(&self, assignment: &mut [usize], palette: &ColorConfigs) {
+        for (tile, target) in self.targets(palette).into_iter().enumerate() {
+            if let Some(target) = target {
+                assignment[tile] = target;
+            }
+        }
+    }
+
+    /// Puts back any locked tile a later pass recolored, swapping the pieces
+    /// in `palette`. Returns how many tiles were restored.
+    pub fn restore(&self, assignment: &mut [usize], palette: &mut ColorConfigs) -> usize {
+        let mut restored = 0;
+        for (tile, target) in self.targets(palette).into_iter().enumerate() {
+            let target = match target {
+                Some(target) if assignment[tile] != target => target,
+                _ => continue,
+            };
+            palette.colors[assignment[tile]].count += 1;
+            palette.colors[target].decrement();
+            assignment[tile] = target;
+            restored += 1;
+        }
+        restored
+    }
+
+    /// The palette index each tile is locked to, in raster order.
+    fn targets(&self, palette: &ColorConfigs) -> Vec<Option<usize>> {
+        self.locks
+            .iter()
+            .enumerate()
+            .map(|(tile, lock)| {
+                lock.as_ref()
+                    .map(|lock| target_index(palette, &lock.target, tile))
+            })
+            .collect()
+    }
+}
+
+fn target_index(palette: &ColorConfigs, target: &Target, tile: usize) -> usize {
+    match target {
+        Target::Named(name) => palette
+            .colors
+            .iter()
+            .position(|config| &config.name == name)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Palette has no color named '{name}' for tile ({}, {})",
+                    tile as u64 % X_SIZE,
+                    tile as u64 / X_SIZE
+                )
+            }),
+        Target::Nearest([r, g, b]) => {
+            let color = Color {
+                r: *r,
+                g: *g,
+                b: *b,
+                x: tile as u64 % X_SIZE,
+                y: tile as u64 / X_SIZE,
+            };
+            assign::candidates(palette, &color, &HashMap::new(), 1)[0].0
+        }
+    }
+}
+
+/// Reads a force mask: a PNG the size of the grid whose opaque pixels force
+/// their tile to the nearest palette color, or a JSON list of
+/// `{"x": 3, "y": 0, "color": "Red"}` entries in grid coordinates counted
+/// from the bottom left.
+fn load_mask(path: &str) -> Vec<(usize, Target)> {
+    if path.ends_with(".json") {
+        let text = fs::read_to_string(path).expect("Could not open force mask.");
+        let entries: Vec<ForcedTile> =
+            serde_json::from_str(&text).expect("Force mask should list {x, y, color} entries.");
+        return entries
+            .into_iter()
+            .map(|entry| {
+                if entry.x >= X_SIZE || entry.y >= Y_SIZE {
+                    panic!(
+                        "Forced tile ({}, {}) in {path} is outside the {X_SIZE}x{Y_SIZE} grid",
+                        entry.x, entry.y
+                    )
+                }
+                let tile = (entry.y * X_SIZE + entry.x) as usize;
+                (tile, Target::Named(entry.color))
+            })
+            .collect();
+    }
+    let mask = image::open(path)
+        .unwrap_or_else(|err| panic!("Could not read force mask {path}: {err}"))
+        .to_rgba8();
+    if (mask.width() as u64, mask.height() as u64) != (X_SIZE, Y_SIZE) {
+        panic!(
+            "Force mask {path} is {}x{} but must match the {X_SIZE}x{Y_SIZE} grid",
+            mask.width(),
+            mask.height()
+        )
+    }
+    mask.enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel[3] > 0)
+        .map(|(px, py, pixel)| {
+            // Image rows count down from the top, grid rows up from the bottom.
+            let tile = ((Y_SIZE - 1 - py as u64) * X_SIZE + px as u64) as usize;
+            (tile, Target::Nearest([pixel[0], pixel[1], pixel[2]]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    fn locks(targets: &[(usize, Target, bool)]) -> Locks {
+        let mut locks = Locks {
+            locks: vec![None; (X_SIZE * Y_SIZE) as usize],
+        };
+        for (tile, target, forced) in targets {
+            locks.locks[*tile] = Some(Lock {
+                target: target.clone(),
+                forced: *forced,
+            });
+        }
+        locks
+    }
+
+    fn palette(colors: &[(&str, u8, u64)]) -> ColorConfigs {
+        let mut palette = ColorConfigs {
+            colors: colors
+                .iter()
+                .map(|&(name, gray, count)| {
+                    ColorConfig::new(name.to_string(), gray, gray, gray, count)
+                })
+                .collect(),
+        };
+        palette.prepare();
+        palette
+    }
+
+    #[test]
+    fn locked_tiles_are_reserved_placed_and_kept() {
+        let locks = locks(&[
+            (0, Target::Named("White".to_string()), false),
+            (5, Target::Nearest([20, 20, 20]), true),
+        ]);
+        let mut palette = palette(&[("Gray", 128, 10_000), ("White", 255, 1), ("Black", 0, 1)]);
+        assert_eq!(locks.free_tiles().len() as u64, X_SIZE * Y_SIZE - 2);
+        assert_eq!(locks.forced_tiles(), vec![5]);
+        locks.reserve(&mut palette);
+        assert_eq!((palette.colors[1].count, palette.colors[2].count), (0, 0));
+
+        let mut assignment = vec![0; (X_SIZE * Y_SIZE) as usize];
+        locks.place(&mut assignment, &palette);
+        assert_eq!((assignment[0], assignment[5]), (1, 2));
+        // A later pass moving a locked tile has it moved back, pieces and all.
+        palette.colors[2].count += 1;
+        palette.colors[0].count -= 1;
+        assignment[5] = 0;
+        assert_eq!(locks.restore(&mut assignment, &mut palette), 1);
+        assert_eq!(assignment[5], 2);
+        assert_eq!(
+            (palette.colors[0].count, palette.colors[2].count),
+            (10_000, 0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Black (2 needed, 1 available)")]
+    fn short_colors_fail_before_solving() {
+        let locks = locks(&[
+            (1, Target::Named("Black".to_string()), true),
+            (2, Target::Nearest([10, 10, 10]), true),
+        ]);
+        locks.reserve(&mut palette(&[("White", 255, 100), ("Black", 0, 1)]));
+    }
+}
@@ -0,0 +1,101 @@
+//! Progress and diagnostics on stderr through the `log` macros, filtered by
+//! `--log-level`.
+//!
+//! Only this program's own messages are shown; the graphics stack logs
+//! plenty of its own at the lower levels.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::str::FromStr;
+
+/// How much is printed on stderr; each level includes the ones after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogLevel {
+    /// Also every tile's assignment.
+    Debug,
+    /// Stages starting and finishing, and what was written.
+    #[default]
+    Info,
+    /// Problems the run works around.
+    Warn,
+    /// Only failures.
+    Error,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Error => LevelFilter::Error,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(format!(
+                "Unknown log level '{s}'; use debug, info, warn, or error"
+            )),
+        }
+    }
+}
+
+struct Stderr;
+
+static LOGGER: Stderr = Stderr;
+
+impl Log for Stderr {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", line(record.level(), &record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Info messages read as before there were levels; the others say theirs.
+fn line(level: Level, message: &str) -> String {
+    match level {
+        Level::Info => message.to_string(),
+        level => format!("{}: {message}", level.as_str().to_lowercase()),
+    }
+}
+
+/// Installs the logger at the default level, before the arguments are read
+/// so that their errors are printed too.
+pub fn install() {
+    log::set_logger(&LOGGER).expect("Logger should only be installed once.");
+    set_level(LogLevel::default());
+}
+
+pub fn set_level(level: LogLevel) {
+    log::set_max_level(level.filter());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_parse_and_label_lines() {
+        assert_eq!("warn".parse::<LogLevel>(), Ok(LogLevel::Warn));
+        assert!("trace".parse::<LogLevel>().is_err());
+        assert_eq!(line(Level::Info, "Wrote out.png."), "Wrote out.png.");
+        assert_eq!(line(Level::Warn, "Skipping a.txt"), "warn: Skipping a.txt");
+        assert!(LogLevel::Debug.filter() > LogLevel::Error.filter());
+    }
+}
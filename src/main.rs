@@ -1,43 +1,271 @@
-use image::imageops::FilterType;
-use image::{GenericImageView, ImageReader};
-use nannou::prelude::real::Real;
+mod aseprite;
+mod assign;
+mod autosave;
+mod bricks;
+mod bundle;
+mod cli;
+mod clipboard;
+mod cluster_view;
+mod diff;
+mod dither;
+mod edges;
+mod embroidery;
+mod export;
+mod font;
+mod heatmap;
+mod hex;
+mod keys;
+mod label;
+mod legend;
+mod locks;
+mod logging;
+mod metric;
+mod minecraft;
+mod palette;
+mod palette_editor;
+mod pdf;
+mod quadtree;
+mod recent;
+mod render;
+mod segment;
+mod selection;
+mod session;
+mod settings;
+mod slideshow;
+mod source;
+mod status;
+mod table;
+mod text;
+mod tile_mesh;
+mod timelapse;
+mod xcf;
+mod xlsx;
+mod zip;
+mod zoom;
+
+use assign::{ShuffleStrategy, Texture};
+use cli::Options;
+use dither::DitherMode;
+use hex::HexOrientation;
+use image::DynamicImage;
+use keys::Action;
+use metric::ColorMetric;
 use nannou::prelude::*;
-use nannou::rand::prelude::SliceRandom;
-use nannou::rand::thread_rng;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+use nannou::window;
+use palette::{ColorConfig, ColorConfigs};
 use serde::Deserialize;
-use serde_json;
-use std::cmp::Ordering;
-use std::env;
-use std::fs::File;
-use std::io::Read;
+use serde_json::{json, Value};
+use status::Failure;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use table::{ReportFormat, Table};
 
 const X_SIZE: u64 = 48;
 const Y_SIZE: u64 = 48;
 
 struct Model {
+    /// Assigned tile colors in raster order.
     pixels: Vec<Color>,
+    /// Downscaled source colors in raster order.
+    reference_pixels: Vec<Color>,
+    /// Palette index of every tile in raster order.
+    assignment: Vec<usize>,
+    /// The palette with counts reduced to what is still unused.
+    palette: ColorConfigs,
+    /// Distance multipliers keyed by palette color name; below 1.0 favors
+    /// the color.
+    emphasis: HashMap<String, f32>,
+    /// Border built from the palette around the grid in exports.
+    frame: Option<render::Frame>,
+    /// Sampling among near colors, when `--texture-strength` is given.
+    texture: Option<Texture>,
+    /// The planned `--adaptive-cells` blocks, each solved as one color.
+    cells: Option<Vec<quadtree::Cell>>,
+    /// The `--cel` regions, each solved as one color.
+    segments: Option<segment::Segmentation>,
+    /// Tiles locked to their colors by `--text` and `--force-mask`.
+    locks: Option<locks::Locks>,
+    /// Tiles `--key-color` left empty, in raster order.
+    holes: Vec<usize>,
+    /// The tiles the first solve placed, in the order it placed them.
+    build_order: Vec<usize>,
+    /// Which colors the window shows for each tile.
+    layer: Layer,
+    /// Draw every tile as the gray of its luminance, with the source's
+    /// luminance in a pane of its own.
+    luminance: bool,
+    /// Whether tiles show their colors or the heat of their colors'
+    /// remaining counts.
+    heatmap_mode: heatmap::HeatmapMode,
+    /// Mark each tile's build batch in its top-right corner.
+    batch_corners: bool,
+    /// Write each tile's raster index on it.
+    tile_index: bool,
+    /// Each pane's tiles as last drawn.
+    meshes: tile_mesh::MeshCache,
+    /// Tiles inside a Shift-drag, as grid `(left, bottom, right, top)`.
+    selection_rect: Option<selection::GridRect>,
+    /// Where the drag in progress started.
+    selection_anchor: Option<(u64, u64)>,
+    /// Tiles the number keys recolor, set when a drag ends.
+    selected_tiles: Vec<usize>,
+    /// Mirror images recolored along with the selection.
+    symmetry: selection::Symmetry,
+    /// A bulk color replace waiting for its target color.
+    replacing: Option<selection::Replacing>,
+    /// Recolors that `U` can take back, most recent last.
+    undo: Vec<selection::Snapshot>,
+    /// Rotating crash copies of the `--session`, when one is given.
+    autosave: Option<autosave::Autosave>,
+    /// The action each keyboard shortcut runs.
+    keys: keys::Keymap,
+    /// The tile under the mouse, as grid `(x, y)`, with `--show-grid-coords`.
+    hover_tile: Option<(u64, u64)>,
+    /// Until when the title keeps the details of the last click rather than
+    /// following the mouse.
+    click_title_until: Option<Instant>,
+    /// Magnification of the panes' grids, 1.0 showing all of it.
+    zoom: f32,
+    /// The point of the grid shown at the middle of each pane, as fractions
+    /// of its width and height.
+    pan: Vec2,
+    /// How far `--color-cycle-animation` has turned the shown colors' hues,
+    /// in turns of the color wheel.
+    hue_offset: f32,
+    /// Set while the view eases to a new `zoom` and `pan`.
+    zoom_animation: Option<zoom::Animation>,
+    /// The color name being typed after `/`.
+    color_search: Option<zoom::Search>,
+    /// Whether the palette legend is shown beside the mosaic.
+    legend: bool,
+    /// The legend's order, which also numbers colors in exports.
+    legend_sort: legend::LegendSort,
+    /// The legend entry whose count `+` and `-` change.
+    editing_color: Option<usize>,
+    /// Declared counts edited in the window but not solved for yet.
+    count_edits: Option<ColorConfigs>,
+    /// How many palette entries come from the palette file; any after them
+    /// are `--corner-tiles` pieces.
+    main_colors: usize,
+    /// Present when cycling through a folder with `--slideshow`.
+    slideshow: Option<slideshow::Slideshow>,
+    /// The `--interactive-palette-editor` window, once it is open.
+    palette_editor: Option<palette_editor::PaletteEditor>,
+    /// The `--palette-cluster-visualization` window, once it is open.
+    cluster_view: Option<cluster_view::ClusterView>,
+    /// The mosaic window once others are open; `App::main_window` follows
+    /// focus, so it can be one of them instead.
+    main_window: Option<window::Id>,
+    /// Set while the primary solve is still running in the background.
+    progress: Option<Progress>,
+    /// The `--export-timelapse-json` file the solve is recorded into.
+    timelapse: Option<timelapse::Recorder>,
+    /// Set while a `--replay-timelapse` recording is playing back.
+    replay: Option<timelapse::Replay>,
+    /// Solves of the same source against the `--compare` palettes.
+    comparisons: Vec<Comparison>,
+    options: Options,
+    /// The seed `rng` started from.
+    seed: u64,
+    rng: StdRng,
 }
 
-#[derive(Debug, Deserialize)]
-struct ColorConfigs {
-    colors: Vec<ColorConfig>,
+/// What the window draws for each tile; `V` cycles through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    /// The palette color the tile was assigned.
+    Assigned,
+    /// The downscaled source color before quantization.
+    Original,
+    /// Both mixed 50/50.
+    Blend,
 }
 
-#[derive(Debug, Deserialize)]
-struct ColorConfig {
-    name: String,
-    r: u8,
-    g: u8,
-    b: u8,
-    count: u64,
-}
+impl Layer {
+    fn next(self) -> Layer {
+        match self {
+            Layer::Assigned => Layer::Original,
+            Layer::Original => Layer::Blend,
+            Layer::Blend => Layer::Assigned,
+        }
+    }
+
+    /// Flips between the source colors and the assignment.
+    fn toggle_original(self) -> Layer {
+        match self {
+            Layer::Original => Layer::Assigned,
+            _ => Layer::Original,
+        }
+    }
 
-impl ColorConfig {
-    fn decrement(&mut self) {
-        self.count -= 1;
+    /// The color to draw for a tile given its assigned and source colors.
+    fn color(self, assigned: &Color, original: &Color) -> (u8, u8, u8) {
+        let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        match self {
+            Layer::Assigned => (assigned.r, assigned.g, assigned.b),
+            Layer::Original => (original.r, original.g, original.b),
+            Layer::Blend => (
+                mix(assigned.r, original.r),
+                mix(assigned.g, original.g),
+                mix(assigned.b, original.b),
+            ),
+        }
     }
 }
 
+/// `pixels` as neutral grays of the same luminance, for checking values
+/// apart from hue.
+fn grayscale(pixels: &[Color]) -> Vec<Color> {
+    pixels
+        .iter()
+        .map(|color| {
+            let luma = 0.299 * color.r as f32 + 0.587 * color.g as f32 + 0.114 * color.b as f32;
+            let gray = luma.round() as u8;
+            Color {
+                r: gray,
+                g: gray,
+                b: gray,
+                ..color.clone()
+            }
+        })
+        .collect()
+}
+
+/// Rows of tiles finish solving on a background thread and are revealed a
+/// few per frame.
+struct Progress {
+    pending_rows: Arc<Mutex<VecDeque<Vec<Color>>>>,
+    /// The finished assignment and remaining inventory.
+    result: Arc<Mutex<Option<Solved>>>,
+}
+
+/// An assignment together with the inventory left over after it.
+type Solved = (Vec<usize>, ColorConfigs);
+
+/// Completed rows moved from `pending_rows` into the view on each update.
+const ROWS_PER_FRAME: usize = 2;
+
+/// An independent solve of the source against another palette.
+struct Comparison {
+    label: String,
+    palette: ColorConfigs,
+    assignment: Vec<usize>,
+    pixels: Vec<Color>,
+}
+
+/// A borrowed view of one solve, whether the primary or a comparison.
+struct Solution<'a> {
+    label: &'a str,
+    palette: &'a ColorConfigs,
+    assignment: &'a [usize],
+    pixels: &'a [Color],
+}
+
 #[derive(Debug, Deserialize, Clone)]
 struct Color {
     r: u8,
@@ -47,169 +275,2155 @@ struct Color {
     y: u64,
 }
 
+/// The model built before the window opens, handed to nannou by `model`.
+static STARTUP_MODEL: Mutex<Option<Model>> = Mutex::new(None);
+
+impl Model {
+    /// The primary solve followed by every comparison.
+    fn solutions(&self) -> Vec<Solution<'_>> {
+        let primary = Solution {
+            label: self.options.palette_label(),
+            palette: &self.palette,
+            assignment: &self.assignment,
+            pixels: &self.pixels,
+        };
+        std::iter::once(primary)
+            .chain(self.comparisons.iter().map(|comparison| Solution {
+                label: &comparison.label,
+                palette: &comparison.palette,
+                assignment: &comparison.assignment,
+                pixels: &comparison.pixels,
+            }))
+            .collect()
+    }
+
+    /// Multi-line caption with a solve's quality and cost.
+    fn describe(&self, solution: &Solution) -> String {
+        let stats = assign::statistics(
+            &self.reference_pixels,
+            solution.assignment,
+            solution.palette,
+            &self.emphasis,
+        );
+        let mut label = format!(
+            "{}\nAvg error {:.1}\nFallbacks {}",
+            solution.label, stats.average_error, stats.fallbacks
+        );
+        if let Some(cost) = assign::total_cost(solution.assignment, solution.palette) {
+            label.push_str(&format!("\nCost {cost:.2}"));
+        }
+        label
+    }
+
+    /// How the window lays out tiles.
+    fn tile_shape(&self) -> tile_mesh::Shape {
+        match self.options.hex_grid {
+            Some(orientation) => tile_mesh::Shape::Hex(orientation),
+            None => tile_mesh::Shape::Square {
+                columns: X_SIZE,
+                rows: Y_SIZE,
+                fold: self.options.tile_corner_fold.map(|corner| tile_mesh::Fold {
+                    corner,
+                    size: self.options.fold_size,
+                }),
+            },
+        }
+    }
+
+    /// The adaptive cells as `assignment` colors them, if there are any.
+    fn leaves(&self, assignment: &[usize]) -> Option<Vec<quadtree::Cell>> {
+        self.cells
+            .as_deref()
+            .map(|cells| quadtree::leaves(cells, assignment))
+    }
+
+    /// The palette as declared, i.e. with the counts already used added back.
+    fn declared_palette(&self) -> ColorConfigs {
+        let mut declared = self.palette.clone();
+        for &index in self
+            .assignment
+            .iter()
+            .filter(|&&index| index != assign::HOLE)
+        {
+            declared.colors[index].count += 1;
+        }
+        if let Some(frame) = self.frame {
+            declared.colors[frame.color].count += frame.tile_count();
+        }
+        declared
+    }
+
+    /// The tiles `--force-mask` forced, which exports point out.
+    fn forced_tiles(&self) -> Vec<usize> {
+        self.locks
+            .as_ref()
+            .map_or(Vec::new(), locks::Locks::forced_tiles)
+    }
+}
+
 fn main() {
-    nannou::app(model).simple_window(view).update(update).run();
+    logging::install();
+    status::install();
+    let options = status::during(Failure::Arguments, Options::from_env);
+    logging::set_level(options.log_level);
+    metric::configure(
+        options
+            .region_palette_override
+            .clone()
+            .or(options.color_metric),
+    );
+    if options.color_config_schema {
+        let schema =
+            serde_json::to_string_pretty(&palette::schema()).expect("Schema should serialize.");
+        println!("{schema}");
+        return;
+    }
+    if options.forget_recent {
+        recent::forget();
+        return;
+    }
+    if options.recent {
+        recent::print();
+        return;
+    }
+    if let Some((before, after)) = &options.diff {
+        diff::run(
+            before,
+            after,
+            options.diff_image.as_deref(),
+            options.output_scale,
+            options.color_report_format,
+        );
+        return;
+    }
+    if let Some((before, after, path)) = &options.export_png_diff {
+        diff::export_png(before, after, path, options.output_scale);
+        return;
+    }
+    let keymap = status::during(Failure::Arguments, || {
+        keys::Keymap::load(options.keybindings.as_deref())
+    });
+    if options.print_keys {
+        keymap.print();
+        return;
+    }
+    // Ask about auto-saves before the window opens and takes the console.
+    let resume = autosave::startup_session(&options);
+    if options.headless {
+        let mut model = build_model(options, false);
+        recent::record(&model.options);
+        if let Some(saved) = resume {
+            saved.install(&mut model);
+        }
+        status::during(Failure::Export, || export::run(&model));
+        print_reports(&model);
+        if model.options.summary_json {
+            println!("{}", status::summary(&model));
+        }
+        return;
+    }
+    let mut startup = match (options.slideshow.clone(), resume) {
+        _ if options.replay_timelapse.is_some() => {
+            let path = options.replay_timelapse.clone().unwrap_or_default();
+            timelapse::replay_model(options, &path)
+        }
+        (Some(dir), _) => slideshow::start(options, &dir),
+        (None, Some(saved)) => {
+            let mut model = build_model(options, false);
+            saved.install(&mut model);
+            model
+        }
+        (None, None) => build_model(options, true),
+    };
+    if startup.slideshow.is_none() {
+        recent::record(&startup.options);
+    }
+    if let Some(path) = &startup.options.export_timelapse_json {
+        if startup.progress.is_some() {
+            startup.timelapse = Some(timelapse::Recorder::create(path));
+        } else {
+            log::warn!("The solve is not animated when resuming a session; {path} is not written.");
+        }
+    }
+    startup.keys = keymap;
+    *STARTUP_MODEL.lock().unwrap() = Some(startup);
+
+    nannou::app(model)
+        .event(event)
+        .simple_window(view)
+        .update(update)
+        .run();
+}
+
+fn event(app: &App, model: &mut Model, event: Event) {
+    let simple = match event {
+        Event::WindowEvent {
+            id,
+            simple: Some(simple),
+        } if !palette_editor::owns(model, id) && !cluster_view::owns(model, id) => simple,
+        _ => return,
+    };
+    match simple {
+        MousePressed(MouseButton::Left) => left_click(app, model),
+        MouseMoved(point) => selection::drag(app, model, point),
+        MouseReleased(MouseButton::Left) => selection::release(model),
+        KeyPressed(key) if zoom::key(app, model, key) => {}
+        ReceivedCharacter(typed) => zoom::character(app, model, typed),
+        KeyPressed(key) => match model.keys.action(key, app.keys.mods) {
+            Some(action) => run_action(app, model, action),
+            None => {
+                if let Some(digit) = digit(key) {
+                    if model.selected_tiles.is_empty() || model.replacing.is_some() {
+                        legend::select_digit(app, model, digit);
+                    } else {
+                        selection::recolor_to_digit(app, model, digit);
+                    }
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+fn run_action(app: &App, model: &mut Model, action: Action) {
+    match action {
+        Action::CycleLayer => model.layer = model.layer.next(),
+        Action::ToggleOriginal => model.layer = model.layer.toggle_original(),
+        Action::ToggleLegend => model.legend = !model.legend,
+        Action::ToggleLuminance => model.luminance = !model.luminance,
+        Action::ToggleHeatmap => model.heatmap_mode = model.heatmap_mode.toggle(),
+        Action::ToggleBatchCorners => {
+            if model.options.hex_grid.is_some() {
+                log::info!("Build batch corners need square tiles.");
+            } else {
+                model.batch_corners = !model.batch_corners;
+            }
+        }
+        Action::ToggleTileIndex => model.tile_index = !model.tile_index,
+        Action::CycleLegendSort => {
+            model.legend_sort = model.legend_sort.next();
+            log::info!("Legend sort: {:?}.", model.legend_sort);
+        }
+        Action::CycleSymmetry => {
+            model.symmetry = model.symmetry.next();
+            log::info!("Symmetry: {:?}.", model.symmetry);
+        }
+        Action::ReloadPalette => reload_palette(model, false),
+        Action::ReloadPaletteFull => reload_palette(model, true),
+        Action::NextPicture => slideshow::step(app, model, true),
+        Action::PreviousPicture => slideshow::step(app, model, false),
+        Action::PauseSlideshow => {
+            if let Some(slideshow) = &mut model.slideshow {
+                slideshow.paused = !slideshow.paused;
+                // Resuming gives the current picture a full interval.
+                slideshow.restart_timer();
+            }
+        }
+        Action::PasteImage => paste_picture(app, model),
+        Action::ClearSelection => selection::clear(model),
+        Action::Undo => selection::undo(model),
+        Action::ReplaceColor => selection::start_replace(app, model, false),
+        Action::ReplaceColorPartial => selection::start_replace(app, model, true),
+        Action::SaveSession => save_session(model),
+        Action::SaveBundle => save_bundle(model),
+        Action::CountUp => legend::adjust(app, model, 1),
+        Action::CountUpTen => legend::adjust(app, model, 10),
+        Action::CountDown => legend::adjust(app, model, -1),
+        Action::CountDownTen => legend::adjust(app, model, -10),
+        Action::Solve => legend::apply(model, false),
+        Action::SolveFull => legend::apply(model, true),
+        Action::SavePalette => legend::save(model),
+        Action::RunExportPreset => run_default_preset(model),
+        Action::SearchColor => zoom::start_search(app, model),
+    }
+}
+
+/// Replaces the mosaic with one of the picture on the clipboard, solved
+/// against the palette file as on startup.
+fn paste_picture(app: &App, model: &mut Model) {
+    if model.slideshow.is_some() {
+        log::info!("Pasting is not available during a slideshow.");
+        return;
+    }
+    let pasted = clipboard::read().and_then(|pasted| match pasted {
+        clipboard::Pasted::Image(img) => Ok(("clipboard".to_string(), img)),
+        clipboard::Pasted::File(path) => {
+            source::read_source_image(&path, model.options.assume_srgb)
+                .map(|img| (path.clone(), img))
+                .map_err(|err| format!("Unable to read pasted file {path}: {err}"))
+        }
+    });
+    let (path, img) = match pasted {
+        Ok(pasted) => pasted,
+        Err(err) => {
+            log::warn!("{err}");
+            app.main_window().set_title(&err);
+            model.click_title_until = Some(Instant::now() + CLICK_TITLE_HOLD);
+            return;
+        }
+    };
+    log::info!("Pasted {path}.");
+    let mut options = model.options.clone();
+    options.picture_path = path;
+    let mut pasted = build_model_from(options, &img, true);
+    pasted.keys = std::mem::take(&mut model.keys);
+    palette_editor::carry_over(model, &mut pasted);
+    cluster_view::carry_over(model, &mut pasted);
+    pasted.main_window = model.main_window;
+    *model = pasted;
+    app.set_loop_mode(LoopMode::refresh_sync());
+}
+
+/// Writes the mosaic to the `--session` file.
+fn save_session(model: &Model) {
+    let path = match &model.options.session {
+        Some(path) => path,
+        None => {
+            log::info!("No --session file to save to.");
+            return;
+        }
+    };
+    let saved = session::SavedAssignment::from_model(model);
+    match autosave::write_atomic(&saved, std::path::Path::new(path)) {
+        Ok(()) => log::info!("Saved {path}."),
+        Err(err) => log::error!("Could not save {path}: {err}"),
+    }
+}
+
+/// Writes the project to the `--save-bundle` path, the bundle it was opened
+/// from, or a bundle named after the picture.
+fn save_bundle(model: &Model) {
+    let options = &model.options;
+    let path = options
+        .save_bundle
+        .clone()
+        .or_else(|| options.bundle.clone())
+        .unwrap_or_else(|| {
+            let picture = std::path::Path::new(&options.picture_path);
+            picture.with_extension("zip").to_string_lossy().into_owned()
+        });
+    bundle::save(model, &path);
+}
+
+/// Runs the settings file's default export preset on the mosaic on screen.
+fn run_default_preset(model: &Model) {
+    if model.progress.is_some() {
+        log::info!("Still solving; try again once the mosaic is complete.");
+        return;
+    }
+    let name = match &model.options.config {
+        Some(path) => settings::Settings::load(path).default_export_preset,
+        None => None,
+    };
+    let result = match name {
+        Some(name) => export::run_preset(model, &name),
+        None => Err("No default_export_preset in the --config settings file.".to_string()),
+    };
+    if let Err(err) = result {
+        log::error!("{err}");
+    }
+}
+
+/// Picks a legend entry, or starts or clears a tile selection.
+fn left_click(app: &App, model: &mut Model) {
+    if !legend::click(app, main_rect(app, model), model, app.mouse.position()) {
+        selection::press(app, model);
+    }
+}
+
+/// The number 1-9 on a top-row or keypad key.
+fn digit(key: Key) -> Option<usize> {
+    const KEYS: [[Key; 2]; 9] = [
+        [Key::Key1, Key::Numpad1],
+        [Key::Key2, Key::Numpad2],
+        [Key::Key3, Key::Numpad3],
+        [Key::Key4, Key::Numpad4],
+        [Key::Key5, Key::Numpad5],
+        [Key::Key6, Key::Numpad6],
+        [Key::Key7, Key::Numpad7],
+        [Key::Key8, Key::Numpad8],
+        [Key::Key9, Key::Numpad9],
+    ];
+    KEYS.iter()
+        .position(|keys| keys.contains(&key))
+        .map(|index| index + 1)
+}
+
+/// Re-reads the palette file. Count-only edits are applied incrementally
+/// unless `full` is set or the colors themselves changed.
+fn reload_palette(model: &mut Model, full: bool) {
+    if model.progress.is_some() {
+        log::info!("Still solving; try again once the mosaic is complete.");
+        return;
+    }
+    let path = match &model.options.color_data {
+        Some(path) => path.clone(),
+        None => {
+            log::info!("No palette file to reload.");
+            return;
+        }
+    };
+    // The file may be caught half-saved, which should not close the window.
+    let loaded = match load_palette(&path, &model.options) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            log::error!("Palette not reloaded: {err}");
+            return;
+        }
+    };
+    let updated = finish_palette(
+        loaded,
+        &model.reference_pixels,
+        &model.emphasis,
+        &model.options,
+    );
+    if let Err(short) = resolve_palette(model, updated, full) {
+        log::warn!("{short}; palette not reloaded.");
+    }
+}
+
+/// Re-solves against `updated`, a palette with declared counts. Count-only
+/// changes are applied incrementally unless `full` is set. Leaves the model
+/// as it was when `updated` has too few pieces for the tiles.
+fn resolve_palette(
+    model: &mut Model,
+    mut updated: ColorConfigs,
+    full: bool,
+) -> Result<(), Shortfall> {
+    let frame = prepare_resolve(model, &mut updated)?;
+    // Edge pieces sit at the end of the palette and locked tiles keep
+    // their colors, so both are placed again after a fresh solve rather
+    // than carried over.
+    let changed = if full
+        || model.options.corner_tiles.is_some()
+        || model.cells.is_some()
+        || model.segments.is_some()
+        || model.locks.is_some()
+    {
+        None
+    } else {
+        assign::resolve_incremental(
+            &model.reference_pixels,
+            &mut model.assignment,
+            &mut model.palette,
+            updated.clone(),
+            &model.emphasis,
+            true,
+        )
+    };
+    match changed {
+        Some(changed) => log::info!("Re-solved incrementally; {changed} tiles changed."),
+        None => {
+            let before = model.assignment.clone();
+            let assignment = FreshSolve::new(model).run(&mut updated);
+            place_fresh(model, assignment, updated);
+            let changed = before
+                .iter()
+                .zip(&model.assignment)
+                .filter(|(a, b)| a != b)
+                .count();
+            log::info!("Re-solved from scratch; {changed} tiles changed.");
+        }
+    }
+    finish_resolve(model, frame);
+    Ok(())
+}
+
+/// Takes the frame and locked tiles' pieces out of `updated` before a
+/// re-solve and drops state the new palette makes stale. Fails, leaving the
+/// model alone, when what is left cannot fill the tiles to solve.
+fn prepare_resolve(
+    model: &mut Model,
+    updated: &mut ColorConfigs,
+) -> Result<Option<render::Frame>, Shortfall> {
+    let frame = reserve_frame(updated, &model.options);
+    if let Some(locks) = &model.locks {
+        locks.reserve(updated);
+    }
+    if let Some(short) = shortfall(
+        updated,
+        solved_tiles(model.locks.as_ref(), &model.holes).len(),
+    ) {
+        return Err(short);
+    }
+    model.main_colors = updated.colors.len();
+    model.count_edits = None;
+    // Snapshots from before a re-solve would bring back the old palette.
+    model.undo.clear();
+    Ok(frame)
+}
+
+/// Installs a fresh solve and the inventory it left, with the locked tiles.
+fn place_fresh(model: &mut Model, assignment: Vec<usize>, palette: ColorConfigs) {
+    model.assignment = assignment;
+    model.palette = palette;
+    assign::punch_holes(&mut model.assignment, &model.holes);
+    if let Some(locks) = &model.locks {
+        locks.place(&mut model.assignment, &model.palette);
+    }
+}
+
+/// Refines a re-solve and brings the rest of the model up to date with it.
+fn finish_resolve(model: &mut Model, frame: Option<render::Frame>) {
+    refine(
+        &model.options,
+        &model.reference_pixels,
+        &mut model.assignment,
+        &mut model.palette,
+        &model.emphasis,
+        model.locks.as_ref(),
+    );
+    model.frame = frame;
+    model.pixels =
+        assign::assigned_pixels(&model.reference_pixels, &model.assignment, &model.palette);
+    palette_editor::sync(model);
+    autosave::save_now(model);
+}
+
+/// What a from-scratch re-solve needs, owned so that it can run on another
+/// thread.
+struct FreshSolve {
+    reference: Vec<Color>,
+    cells: Option<Vec<quadtree::Cell>>,
+    segments: Option<segment::Segmentation>,
+    tiles: Vec<usize>,
+    emphasis: HashMap<String, f32>,
+    texture: Option<Texture>,
+    /// Edge strength of every tile, with `--weighted-shuffle`.
+    weights: Option<Vec<f32>>,
+    /// Keep neighbors apart in raster order, with `--no-overlap`.
+    no_overlap: bool,
+    shuffle_strategy: ShuffleStrategy,
+    rng: StdRng,
+}
+
+impl FreshSolve {
+    fn new(model: &mut Model) -> FreshSolve {
+        FreshSolve {
+            reference: model.reference_pixels.clone(),
+            cells: model.cells.clone(),
+            segments: model.segments.clone(),
+            tiles: solved_tiles(model.locks.as_ref(), &model.holes),
+            emphasis: model.emphasis.clone(),
+            texture: model.texture,
+            weights: model
+                .options
+                .weighted_shuffle
+                .then(|| assign::edge_weights(&model.reference_pixels)),
+            no_overlap: model.options.no_overlap,
+            shuffle_strategy: model.options.shuffle_strategy,
+            rng: StdRng::seed_from_u64(model.rng.gen()),
+        }
+    }
+
+    /// Solves in whichever mode the mosaic was built with, taking pieces
+    /// from `palette`.
+    fn run(mut self, palette: &mut ColorConfigs) -> Vec<usize> {
+        match (&self.cells, &self.segments) {
+            (_, Some(segments)) => segment::solve(
+                &self.reference,
+                segments,
+                &self.tiles,
+                palette,
+                &self.emphasis,
+            ),
+            (Some(cells), None) => {
+                let all: Vec<usize> = (0..self.reference.len()).collect();
+                let order = assign::shuffled(&all, self.weights.as_deref(), &mut self.rng);
+                quadtree::solve(&self.reference, cells, &order, palette, &self.emphasis)
+            }
+            (None, None) if self.shuffle_strategy == ShuffleStrategy::WeightedCount => {
+                let order = assign::shuffled(&self.tiles, self.weights.as_deref(), &mut self.rng);
+                let order =
+                    assign::most_constrained_first(order, &self.reference, palette, &self.emphasis);
+                assign::assign_colors(
+                    &self.reference,
+                    &order,
+                    palette,
+                    &self.emphasis,
+                    self.texture,
+                    false,
+                )
+            }
+            (None, None) if self.no_overlap => assign::assign_colors(
+                &self.reference,
+                &self.tiles,
+                palette,
+                &self.emphasis,
+                self.texture,
+                true,
+            ),
+            (None, None) => assign::solve(
+                &self.reference,
+                &self.tiles,
+                palette,
+                &self.emphasis,
+                self.texture,
+                self.weights.as_deref(),
+                &mut self.rng,
+            ),
+        }
+    }
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
     draw.background().color(BLACK);
-    draw_square(&app, &draw, &model);
+    let solutions = model.solutions();
+    let panes = mosaic_panes(app, model);
+    // Graying is linear, so blends of grays match grays of blends.
+    let reference = if model.luminance {
+        grayscale(&model.reference_pixels)
+    } else {
+        model.reference_pixels.clone()
+    };
+    for (index, (solution, pane)) in solutions.iter().zip(&panes).enumerate() {
+        let mut pixels = render::jitter(solution.pixels, model.options.brightness_jitter);
+        if model.options.color_cycle_animation {
+            pixels = render::shift_hue(&pixels, model.hue_offset);
+        }
+        if model.heatmap_mode == heatmap::HeatmapMode::Remaining {
+            pixels = heatmap::recolor(&pixels, solution.assignment, solution.palette);
+        } else if model.luminance {
+            pixels = grayscale(&pixels);
+        }
+        let mut reference = reference.clone();
+        checker_holes(&mut pixels, &mut reference, &model.holes);
+        let tiles = match model.leaves(solution.assignment) {
+            // Cells only hold for assigned colors; the source varies inside them.
+            Some(cells) if model.layer == Layer::Assigned && model.options.hex_grid.is_none() => {
+                shown_cells(&pixels, &cells)
+            }
+            _ => shown_tiles(&pixels, &reference, model.layer),
+        };
+        // Zoomed in, the grid reaches past its pane.
+        let clipped = draw.scissor(pane.clip);
+        model
+            .meshes
+            .draw(&clipped, index, pane.grid, &tiles, model.tile_shape());
+        if model.batch_corners {
+            draw_batch_corners(&clipped, pane.grid, model.options.batch_size);
+        }
+        if model.tile_index {
+            draw_tile_indices(
+                &clipped,
+                pane.grid,
+                &pixels,
+                &reference,
+                model.layer,
+                model.options.hex_grid,
+            );
+        }
+        if index == 0 {
+            selection::draw_axes(&clipped, pane.grid, model.symmetry);
+            selection::draw(&clipped, pane.grid, model);
+        }
+        if let Some(label_area) = pane.label {
+            let label = if index == 0 && model.progress.is_some() {
+                format!("{}  Solving...", solution.label)
+            } else {
+                model.describe(solution).replace('\n', "  ")
+            };
+            draw.text(&label)
+                .xy(label_area.xy())
+                .wh(label_area.wh())
+                .font_size(12)
+                .color(WHITE);
+        }
+    }
+    // The extra pane past the solves shows the source for comparison.
+    if let Some(pane) = panes.get(solutions.len()) {
+        let tiles = shown_tiles(&reference, &reference, Layer::Original);
+        model.meshes.draw(
+            &draw.scissor(pane.clip),
+            solutions.len(),
+            pane.grid,
+            &tiles,
+            model.tile_shape(),
+        );
+        if let Some(label_area) = pane.label {
+            draw.text("Source luminance")
+                .xy(label_area.xy())
+                .wh(label_area.wh())
+                .font_size(12)
+                .color(WHITE);
+        }
+    }
+    if model.heatmap_mode == heatmap::HeatmapMode::Remaining {
+        let mouse = app.mouse.position();
+        let hovered = solutions.iter().zip(&panes).find_map(|(solution, pane)| {
+            pane.tile_at(mouse, model.options.hex_grid)
+                .map(|tile| (solution.palette, solution.assignment[tile]))
+        });
+        if let Some((palette, index)) = hovered {
+            heatmap::draw_tooltip(&draw, main_rect(app, model), mouse, palette, index);
+        }
+    }
+    legend::draw(&draw, main_rect(app, model), model);
     draw.to_frame(app, &frame)
         .expect("Unable to draw to frame.");
 }
 
 fn update(_app: &App, _model: &mut Model, _update: Update) {
+    if _model.options.color_cycle_animation {
+        let turned = _model.options.cycle_speed * _update.since_last.as_secs_f32();
+        _model.hue_offset = (_model.hue_offset + turned).rem_euclid(1.0);
+    }
+    if _model.progress.is_some() {
+        reveal_rows(_app, _model);
+        return;
+    }
+    if timelapse::replaying(_model) {
+        timelapse::tick(_model);
+        if !timelapse::replaying(_model) {
+            _app.set_loop_mode(idle_loop_mode(_model));
+        }
+        return;
+    }
+    zoom::tick(_app, _model);
+    slideshow::tick(_app, _model);
+    autosave::tick(_model);
+    palette_editor::tick(_model);
+    if palette_editor::has_focus(_app, _model) || cluster_view::has_focus(_app, _model) {
+        return;
+    }
     let pressed_mouse = _app.mouse.buttons.left().is_down();
     if !pressed_mouse {
+        show_hovered_tile(_app, _model);
         return;
     }
 
-    let pixels = _app.main_window().inner_size_points();
+    let solutions = _model.solutions();
+    let panes = mosaic_panes(_app, _model);
+    let index = match panes
+        .iter()
+        .find_map(|pane| pane.tile_at(_app.mouse.position(), _model.options.hex_grid))
+    {
+        Some(index) => index,
+        None => return,
+    };
+    let color = _model.pixels[index].clone();
+    let original = &_model.reference_pixels[index];
 
-    let x_width = pixels.0.abs();
-    let y_height = pixels.1.abs();
+    let rgb_str = if solutions.len() == 1 {
+        let selected = if _model.assignment[index] == assign::HOLE {
+            "empty".to_string()
+        } else {
+            format!("rgb({}, {}, {})", color.r, color.g, color.b)
+        };
+        format!(
+            "Selected Color: {selected}, Original: rgb({}, {}, {}), Position: xy({x}, {y})",
+            original.r,
+            original.g,
+            original.b,
+            x = color.x,
+            y = color.y
+        )
+    } else {
+        let assigned: Vec<String> = solutions
+            .iter()
+            .map(|solution| {
+                if solution.assignment[index] == assign::HOLE {
+                    return format!("{}: empty", solution.label);
+                }
+                let config = &solution.palette.colors[solution.assignment[index]];
+                format!(
+                    "{}: {} rgb({}, {}, {})",
+                    solution.label, config.name, config.r, config.g, config.b
+                )
+            })
+            .collect();
+        format!(
+            "Position: xy({x}, {y}) | original rgb({}, {}, {}) | {}",
+            original.r,
+            original.g,
+            original.b,
+            assigned.join(" | "),
+            x = color.x,
+            y = color.y
+        )
+    };
+    _app.main_window().set_title(rgb_str.as_str());
+    _model.click_title_until = Some(Instant::now() + CLICK_TITLE_HOLD);
+}
 
-    // Ensures we align completely with the grid.
-    let x_offset = x_width / 2.0;
-    let y_offset = y_height / 2.0;
-    let x = _app.mouse.x + x_offset;
-    let y = _app.mouse.y + y_offset;
+/// How long a click's details stay in the title with `--show-grid-coords`.
+const CLICK_TITLE_HOLD: Duration = Duration::from_secs(2);
 
-    // Scale x from screen width to X_SIZE and truncate result to int.
-    let x_scaled = ((x as f64 / x_width as f64) * X_SIZE as f64) as u64;
-    let y_scaled = ((y as f64 / y_height as f64) * Y_SIZE as f64) as u64;
+/// With `--show-grid-coords`, keeps the title on the position and assigned
+/// color of the tile under the mouse, once any click's details have been
+/// up for `CLICK_TITLE_HOLD`.
+fn show_hovered_tile(app: &App, model: &mut Model) {
+    if !model.options.show_grid_coords || model.color_search.is_some() {
+        return;
+    }
+    if model
+        .click_title_until
+        .is_some_and(|until| Instant::now() < until)
+    {
+        return;
+    }
+    let click_expired = model.click_title_until.take().is_some();
+    let hovered = mosaic_panes(app, model)
+        .iter()
+        .find_map(|pane| pane.tile_at(app.mouse.position(), model.options.hex_grid))
+        .map(|index| (index as u64 % X_SIZE, index as u64 / X_SIZE));
+    if hovered == model.hover_tile && !click_expired {
+        return;
+    }
+    model.hover_tile = hovered;
+    if let Some((x, y)) = hovered {
+        let name = match model.assignment[(y * X_SIZE + x) as usize] {
+            assign::HOLE => "empty",
+            index => &model.palette.colors[index].name,
+        };
+        app.main_window()
+            .set_title(&format!("Row: {y}, Col: {x} | Color: {name}"));
+    }
+}
 
-    let index = y_scaled * X_SIZE + x_scaled;
-    let color = _model.pixels[index as usize].clone();
+/// Moves finished rows into view and, once the background solve is done,
+/// installs its result and runs the requested exports.
+fn reveal_rows(app: &App, model: &mut Model) {
+    let progress = model
+        .progress
+        .as_ref()
+        .expect("Should only reveal while solving.");
+    let mut pending = progress.pending_rows.lock().unwrap();
+    let ready = ROWS_PER_FRAME.min(pending.len());
+    let rows: Vec<Vec<Color>> = pending.drain(..ready).collect();
+    if let (Some(recorder), false) = (&mut model.timelapse, rows.is_empty()) {
+        recorder.record(&rows);
+    }
+    model.pixels.extend(rows.into_iter().flatten());
+    if !pending.is_empty() {
+        return;
+    }
+    drop(pending);
+    let finished = progress.result.lock().unwrap().take();
+    if let Some((assignment, palette)) = finished {
+        model.assignment = assignment;
+        model.palette = palette;
+        refine(
+            &model.options,
+            &model.reference_pixels,
+            &mut model.assignment,
+            &mut model.palette,
+            &model.emphasis,
+            model.locks.as_ref(),
+        );
+        let revealed = std::mem::take(&mut model.pixels);
+        model.pixels =
+            assign::assigned_pixels(&model.reference_pixels, &model.assignment, &model.palette);
+        if let Some(recorder) = model.timelapse.take() {
+            recorder.finish(&revealed, &model.pixels);
+        }
+        model.progress = None;
+        log::info!("Solved.");
+        assign::log_tiles(
+            &model.reference_pixels,
+            &model.assignment,
+            &model.palette,
+            &model.emphasis,
+        );
+        app.set_loop_mode(idle_loop_mode(model));
+        zoom_to_requested_color(app, model);
+        match &mut model.slideshow {
+            Some(slideshow) => {
+                slideshow.restart_timer();
+                app.main_window().set_title(&slideshow.current_name());
+            }
+            None => export::run(model),
+        }
+        print_reports(model);
+    }
+}
 
-    let rgb_str = format!("Selected Color: rgb({r}, {g}, {b}), Position: xy({x}, {y})",
-                          r = color.r, g = color.g, b = color.b, x = color.x, y = color.y);
-    _app.main_window().set_title(rgb_str.as_str());
+/// Where one solve is drawn: the grid and, when comparing, a caption strip.
+struct Pane {
+    /// The whole grid, which reaches past `clip` when zoomed in.
+    grid: Rect,
+    /// The part of the window the grid is seen through.
+    clip: Rect,
+    label: Option<Rect>,
 }
 
-fn model(_app: &App) -> Model {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        panic!("Need to provide file paths for picture and color config")
+impl Pane {
+    /// Raster index of the tile under `point`, if it shows in the pane.
+    fn tile_at(&self, point: Point2, hex_grid: Option<HexOrientation>) -> Option<usize> {
+        if !self.clip.contains(point) {
+            return None;
+        }
+        tile_at(self.grid, point, hex_grid)
     }
-    let picture_path = args.get(1).expect("Please specify path to picture.");
-    let color_data = args.get(2).expect("Please specify path to color data.");
-    if !color_data.ends_with(".json") {
-        panic!("Need to provide filepath for color.json file")
+}
+
+/// The mosaic window's rect, whichever window has focus.
+fn main_rect(app: &App, model: &Model) -> Rect {
+    model
+        .main_window
+        .and_then(|id| app.window(id))
+        .map_or_else(|| app.window_rect(), |window| window.rect())
+}
+
+/// The panes of every solve, laid out in the part of the window the legend
+/// leaves free, and last the source's luminance while it is shown. With
+/// `--tile-size-from-window` each grid is shrunk to square tiles, sized
+/// from the current window every frame.
+fn mosaic_panes(app: &App, model: &Model) -> Vec<Pane> {
+    let area = legend::mosaic_area(main_rect(app, model), model);
+    let count = 1 + model.comparisons.len() + model.luminance as usize;
+    let mut panes = pane_areas(area, count);
+    for pane in &mut panes {
+        if model.options.tile_size_from_window {
+            pane.grid = regular_tiles(pane.grid, model.options.hex_grid);
+        }
+        pane.clip = pane.grid;
+        pane.grid = zoom::apply(pane.grid, model);
     }
+    panes
+}
 
-    let img = ImageReader::open(picture_path)
-        .expect("Image failed to parse.")
-        .decode()
-        .expect("Failed to decode image.");
-    let img_resized = img.resize_exact(X_SIZE as u32, Y_SIZE as u32, FilterType::Nearest);
+/// The largest grid of undistorted tiles, squares or regular hexagons, that
+/// fits in `area`, centred in it.
+fn regular_tiles(area: Rect, hex_grid: Option<HexOrientation>) -> Rect {
+    let aspect = hex_grid.map_or(X_SIZE as f32 / Y_SIZE as f32, HexOrientation::aspect);
+    let width = area.w().min(area.h() * aspect);
+    Rect::from_xy_wh(area.xy(), pt2(width, width / aspect))
+}
 
-    let mut file = File::open(color_data).expect("Could not open color data file.");
-    let mut buff = String::new();
-    file.read_to_string(&mut buff)
-        .expect("Unable to read color data file.");
+/// Splits the window into side-by-side panes, one per solve.
+fn pane_areas(window: Rect, count: usize) -> Vec<Pane> {
+    if count <= 1 {
+        return vec![Pane {
+            grid: window,
+            clip: window,
+            label: None,
+        }];
+    }
+    const GUTTER: f32 = 8.0;
+    const LABEL_HEIGHT: f32 = 24.0;
+    let width = (window.w() - GUTTER * (count - 1) as f32) / count as f32;
+    (0..count)
+        .map(|i| {
+            let left = window.left() + i as f32 * (width + GUTTER);
+            let label = Rect::from_x_y_w_h(
+                left + width / 2.0,
+                window.top() - LABEL_HEIGHT / 2.0,
+                width,
+                LABEL_HEIGHT,
+            );
+            let grid = Rect::from_corners(
+                pt2(left, window.bottom()),
+                pt2(left + width, window.top() - LABEL_HEIGHT),
+            );
+            Pane {
+                grid,
+                clip: grid,
+                label: Some(label),
+            }
+        })
+        .collect()
+}
 
-    let mut color_configs: ColorConfigs =
-        serde_json::from_str(buff.as_str()).expect("JSON not parseable.");
+/// Raster index of the tile under `point`, if it falls inside `area`.
+fn tile_at(area: Rect, point: Point2, hex_grid: Option<HexOrientation>) -> Option<usize> {
+    if !area.contains(point) {
+        return None;
+    }
+    if let Some(orientation) = hex_grid {
+        let fx = (point.x - area.left()) / area.w();
+        let fy = (point.y - area.bottom()) / area.h();
+        return orientation
+            .locate(fx, fy)
+            .map(|(x, y)| (y * X_SIZE + x) as usize);
+    }
+    // Scale from the area's size to the grid and truncate to a tile.
+    let x = ((point.x - area.left()) / area.w() * X_SIZE as f32) as u64;
+    let y = ((point.y - area.bottom()) / area.h() * Y_SIZE as f32) as u64;
+    Some((y.min(Y_SIZE - 1) * X_SIZE + x.min(X_SIZE - 1)) as usize)
+}
 
+fn model(app: &App) -> Model {
+    let mut model = STARTUP_MODEL
+        .lock()
+        .unwrap()
+        .take()
+        .expect("Model should be built before the app starts.");
+    if let Some(title) = &model.options.window_title {
+        app.main_window().set_title(title);
+    }
+    if model.options.interactive_palette_editor || model.options.palette_cluster_visualization {
+        model.main_window = Some(app.window_id());
+    }
+    if model.options.interactive_palette_editor {
+        model.palette_editor = Some(palette_editor::open(app, &model));
+    }
+    if model.options.palette_cluster_visualization {
+        model.cluster_view = Some(cluster_view::open(app));
+    }
+    if model.progress.is_some() || timelapse::replaying(&model) {
+        app.set_loop_mode(LoopMode::refresh_sync());
+    } else {
+        app.set_loop_mode(idle_loop_mode(&model));
+        zoom_to_requested_color(app, &mut model);
+        print_reports(&model);
+    }
+    model
+}
+
+/// Starts the `--interactive-zoom-to-color` zoom on a solved mosaic.
+fn zoom_to_requested_color(app: &App, model: &mut Model) {
+    if let Some(name) = model.options.interactive_zoom_to_color.clone() {
+        zoom::zoom_to_color(app, model, &name);
+    }
+}
+
+/// Nothing moves on its own once solved, so only redraw in response to input
+/// and window events rather than at the display's refresh rate. A slideshow,
+/// auto-saves, the `--show-grid-coords` title, and the palette editor's
+/// background solves still need to wake up now and then to check on them,
+/// and `--color-cycle-animation` never stops moving.
+fn idle_loop_mode(model: &Model) -> LoopMode {
+    if model.options.color_cycle_animation {
+        LoopMode::refresh_sync()
+    } else if model.slideshow.is_some()
+        || model.options.show_grid_coords
+        || model.options.interactive_palette_editor
+    {
+        LoopMode::rate_fps(4.0)
+    } else if model.autosave.is_some() {
+        // Often enough to notice when an auto-save is due.
+        LoopMode::rate_fps(1.0)
+    } else {
+        LoopMode::wait()
+    }
+}
+
+/// Loads the source and palettes and solves them. With `progressive` the
+/// primary solve runs on a background thread and the model starts empty.
+fn build_model(options: Options, progressive: bool) -> Model {
+    log::info!("Loading {}.", options.picture_path);
+    let img = status::during(Failure::Image, || {
+        source::load_source_image(&options.picture_path, options.assume_srgb)
+    });
+    build_model_from(options, &img, progressive)
+}
+
+/// Pixels per tile across when sampling the picture for `--hex-grid`.
+const HEX_SAMPLES: u32 = 4;
+
+/// The source color of every tile in raster order: a pixel of the picture
+/// scaled to the grid, or with `--hex-grid` the pixel under each hexagon's
+/// centre once the picture is scaled to the hexagons' outline.
+fn source_tiles(img: &DynamicImage, options: &Options) -> Vec<Color> {
+    let upscaled = options.super_resolution.map(|factor| {
+        let upscaled = source::upscale(img, factor, options.upscale_filter);
+        log::info!(
+            "Upscaled the picture from {} x {} to {} x {}.",
+            img.width(),
+            img.height(),
+            upscaled.width(),
+            upscaled.height()
+        );
+        upscaled
+    });
+    let img = upscaled.as_ref().unwrap_or(img);
+    let (mode, anchor) = (options.scale_mode, options.resize_anchor);
+    let resized = match options.hex_grid {
+        Some(orientation) => {
+            let width = X_SIZE as u32 * HEX_SAMPLES;
+            let height = render::hex_height(width, orientation);
+            source::resize_to(img, mode, anchor, width, height)
+        }
+        None => source::resize_to_grid(img, mode, anchor),
+    };
+    let center = |x: u64, y: u64| match options.hex_grid {
+        Some(orientation) => orientation.center(x, y),
+        None => (
+            (x as f32 + 0.5) / X_SIZE as f32,
+            (y as f32 + 0.5) / Y_SIZE as f32,
+        ),
+    };
+    let (width, height) = resized.dimensions();
     let mut colors: Vec<Color> = Vec::new();
     for y in 0..Y_SIZE {
         for x in 0..X_SIZE {
-            let pixel = img_resized.get_pixel(x as u32, (Y_SIZE - y - 1) as u32);
+            // Image rows count down from the top, grid rows up from the bottom.
+            let (fx, fy) = center(x, y);
+            let px = ((fx * width as f32) as u32).min(width - 1);
+            let py = (((1.0 - fy) * height as f32) as u32).min(height - 1);
+            let pixel = resized.get_pixel(px, py);
             colors.push(Color {
-                r: pixel.0[0] as u8,
-                g: pixel.0[1] as u8,
-                b: pixel.0[2] as u8,
+                r: pixel.0[0],
+                g: pixel.0[1],
+                b: pixel.0[2],
                 x,
                 y,
             })
         }
     }
-    colors.shuffle(&mut thread_rng());
+    colors
+}
+
+/// `build_model` for a picture that is already decoded.
+fn build_model_from(options: Options, img: &DynamicImage, progressive: bool) -> Model {
+    // Pick a seed even when none was given so reports can say how to
+    // reproduce the run.
+    let seed = options.seed.unwrap_or_else(nannou::rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let texture = options.texture_strength.map(|strength| Texture {
+        k: options.texture_k,
+        strength,
+        seed,
+    });
+    let emphasis: HashMap<String, f32> = options.emphasis.iter().cloned().collect();
+    let colors = source_tiles(img, &options);
+    let mut color_configs = status::during(Failure::Palette, || {
+        let color_configs = match (
+            &options.replay_timelapse,
+            options.random_palette,
+            options.palette_from_image,
+            options.palette_gradient,
+            &options.palette_from_css,
+            &options.palette_from_png,
+            &options.color_data,
+        ) {
+            (Some(path), _, _, _, _, _, _) => timelapse::palette(path),
+            (None, Some(n), _, _, _, _, _) => ColorConfigs::random(n, &mut rng),
+            (None, None, Some(n), _, _, _, _) => {
+                let sampled = ColorConfigs::from_image(&img.to_rgb8(), n);
+                log::info!(
+                    "Sampled {} colors from {n} areas of the picture.",
+                    sampled.colors.len()
+                );
+                sampled
+            }
+            (None, None, None, Some(n), _, _, _) => {
+                let end = |value: &Option<String>| {
+                    gradient_end(
+                        value.as_deref().expect("--from and --to are checked"),
+                        &options,
+                    )
+                };
+                ColorConfigs::gradient(
+                    end(&options.gradient_from),
+                    end(&options.gradient_to),
+                    n,
+                    options.gradient_space,
+                )
+            }
+            (None, None, None, None, Some(path), _, _) => {
+                let css = ColorConfigs::from_css(path);
+                log::info!("Read {} colors from {path}.", css.colors.len());
+                css
+            }
+            (None, None, None, None, None, Some(path), _) => {
+                let swatches = ColorConfigs::from_png(path, options.max_palette_colors);
+                log::info!("Read {} colors from {path}.", swatches.colors.len());
+                swatches
+            }
+            (None, None, None, None, None, None, Some(path)) => {
+                load_palette(path, &options).unwrap_or_else(|err| panic!("{err}"))
+            }
+            (None, None, None, None, None, None, None) => {
+                panic!("Need to provide file paths for picture and color config")
+            }
+        };
+        finish_palette(color_configs, &colors, &emphasis, &options)
+    });
 
-    let mut colors: Vec<Color> = colors
+    if options.palette_diversity_score {
+        print_coverage(&color_configs, options.color_report_format);
+    }
+    if options.color_distance_table {
+        print_distances(
+            &color_configs,
+            options.color_metric,
+            options.distance_threshold,
+        );
+    }
+    if let Some(path) = &options.palette_html_preview {
+        let html = palette::write_html(options.palette_label(), &color_configs);
+        status::during(Failure::Export, || fs::write(path, html))
+            .expect("Unable to write palette preview.");
+        log::info!("Wrote {path}.");
+    }
+    let frame = status::during(Failure::Palette, || {
+        reserve_frame(&mut color_configs, &options)
+    });
+    let locks = status::during(Failure::Arguments, || locks::Locks::from_options(&options));
+    if let Some(locks) = &locks {
+        locks.reserve(&mut color_configs);
+    }
+    let main_colors = color_configs.colors.len();
+
+    for name in emphasis.keys() {
+        if !color_configs
+            .colors
+            .iter()
+            .any(|config| &config.name == name)
+        {
+            log::warn!("Emphasized color '{name}' is not in the palette.");
+        }
+    }
+
+    let holes = status::during(Failure::Image, || {
+        keyed_tiles(&colors, &options, locks.as_ref())
+    });
+    // Dithered once the palette is known, as the thresholds are scaled to
+    // it, and before anything is measured against the tiles.
+    let colors = match options.dither_mode {
+        Some(DitherMode::Ordered) => dither::ordered(&colors, &color_configs, options.bayer_size),
+        None => colors,
+    };
+
+    // Every palette sees the tiles in the same order so that differences
+    // come from the palettes alone.
+    let tiles = solved_tiles(locks.as_ref(), &holes);
+    check_inventory(&color_configs, tiles.len());
+    log::info!(
+        "Solving {} tiles with {} colors.",
+        tiles.len(),
+        color_configs.colors.len()
+    );
+    let started = Instant::now();
+    let weights = options
+        .weighted_shuffle
+        .then(|| assign::edge_weights(&colors));
+    // Keeping neighbors apart looks back at the tiles to the left and below.
+    let order = if options.no_overlap {
+        tiles.clone()
+    } else {
+        let order = assign::shuffled(&tiles, weights.as_deref(), &mut rng);
+        match options.shuffle_strategy {
+            ShuffleStrategy::Random => order,
+            ShuffleStrategy::WeightedCount => {
+                assign::most_constrained_first(order, &colors, &color_configs, &emphasis)
+            }
+        }
+    };
+    let cells = options
+        .adaptive_cells
+        .map(|threshold| quadtree::subdivide(&colors, options.adaptive_max_cell, threshold));
+    let segments = options
+        .cel
+        .then(|| segment::segment(&colors, options.cel_regions, options.cel_min_size));
+    let solve = |palette: &mut ColorConfigs| {
+        let mut assignment = match (&cells, &segments) {
+            (_, Some(segments)) => segment::solve(&colors, segments, &tiles, palette, &emphasis),
+            (Some(cells), None) => quadtree::solve(&colors, cells, &order, palette, &emphasis),
+            (None, None) => assign::assign_colors(
+                &colors,
+                &order,
+                palette,
+                &emphasis,
+                texture,
+                options.no_overlap,
+            ),
+        };
+        assign::punch_holes(&mut assignment, &holes);
+        assignment
+    };
+    let (assignment, progress) = if let Some(runs) = options.restarts {
+        // Restarts finish together, so there are no rows to show early.
+        let seeds: Vec<u64> = (0..runs).map(|_| rng.gen()).collect();
+        let mut assignment = best_of_restarts(
+            &seeds,
+            &colors,
+            &tiles,
+            &mut color_configs,
+            &emphasis,
+            texture,
+            weights.as_deref(),
+        );
+        assign::punch_holes(&mut assignment, &holes);
+        if let Some(locks) = &locks {
+            locks.place(&mut assignment, &color_configs);
+        }
+        refine(
+            &options,
+            &colors,
+            &mut assignment,
+            &mut color_configs,
+            &emphasis,
+            locks.as_ref(),
+        );
+        (assignment, None)
+    } else if progressive
+        && cells.is_none()
+        && segments.is_none()
+        && locks.is_none()
+        && holes.is_empty()
+    {
+        // Rows are shown as they finish, which locked tiles and holes would
+        // hold up, so those mosaics solve up front.
+        (
+            Vec::new(),
+            Some(solve_in_background(
+                &colors,
+                &order,
+                &color_configs,
+                &emphasis,
+                texture,
+                options.no_overlap,
+            )),
+        )
+    } else {
+        let mut assignment = solve(&mut color_configs);
+        if let Some(locks) = &locks {
+            locks.place(&mut assignment, &color_configs);
+        }
+        refine(
+            &options,
+            &colors,
+            &mut assignment,
+            &mut color_configs,
+            &emphasis,
+            locks.as_ref(),
+        );
+        (assignment, None)
+    };
+    if progress.is_none() {
+        log::info!("Solved in {:.2?}.", started.elapsed());
+        assign::log_tiles(&colors, &assignment, &color_configs, &emphasis);
+    }
+    if let Some(cells) = &cells {
+        log::info!(
+            "Adaptive cells: {} cells cover the {} tiles.",
+            quadtree::leaves(cells, &assignment).len(),
+            X_SIZE * Y_SIZE
+        );
+    }
+    if let Some(segments) = &segments {
+        log::info!(
+            "Cel: {} regions cover the {} tiles.",
+            segments.regions.len(),
+            X_SIZE * Y_SIZE
+        );
+    }
+    let comparisons = options
+        .compare
         .iter()
-        .map(|original_color| -> Color {
-            let nearest_color = calculate_closest_color(&color_configs, original_color);
-            let selected_config = color_configs
-                .colors
-                .get_mut(nearest_color)
-                .expect("Color configs should have value within index range");
-            selected_config.decrement();
-            Color {
-                r: selected_config.r,
-                g: selected_config.g,
-                b: selected_config.b,
-                x: original_color.x,
-                y: original_color.y,
+        .map(|path| {
+            let mut palette = load_palette(path, &options).unwrap_or_else(|err| panic!("{err}"));
+            if options.auto_count {
+                auto_count(&mut palette, &colors, &emphasis, &options);
+            }
+            if let Some(locks) = &locks {
+                locks.reserve(&mut palette);
+            }
+            let mut assignment = solve(&mut palette);
+            if let Some(locks) = &locks {
+                locks.place(&mut assignment, &palette);
+            }
+            merge_regions(&options, &colors, &mut assignment, &mut palette, &emphasis);
+            if let Some(locks) = &locks {
+                locks.restore(&mut assignment, &mut palette);
+            }
+            Comparison {
+                label: path.clone(),
+                pixels: assign::assigned_pixels(&colors, &assignment, &palette),
+                palette,
+                assignment,
             }
         })
         .collect();
-    colors.sort_by(|a, b| match a.y.cmp(&b.y) {
-        Ordering::Equal => a.x.cmp(&b.x),
-        other => other,
+    Model {
+        pixels: assign::assigned_pixels(&colors, &assignment, &color_configs),
+        reference_pixels: colors,
+        assignment,
+        palette: color_configs,
+        emphasis,
+        frame,
+        texture,
+        cells,
+        segments,
+        locks,
+        holes,
+        build_order: order,
+        layer: if options.show_original_colors {
+            Layer::Original
+        } else {
+            Layer::Assigned
+        },
+        luminance: false,
+        heatmap_mode: if options.tile_count_heatmap {
+            heatmap::HeatmapMode::Remaining
+        } else {
+            heatmap::HeatmapMode::default()
+        },
+        batch_corners: options.tile_corner_indicator,
+        tile_index: options.tile_debug_index,
+        meshes: tile_mesh::MeshCache::default(),
+        selection_rect: None,
+        selection_anchor: None,
+        selected_tiles: Vec::new(),
+        symmetry: options.symmetry,
+        undo: Vec::new(),
+        autosave: autosave::Autosave::new(&options),
+        keys: keys::Keymap::default(),
+        legend: false,
+        legend_sort: options.legend_sort,
+        editing_color: None,
+        replacing: None,
+        hover_tile: None,
+        click_title_until: None,
+        zoom: zoom::whole().0,
+        pan: zoom::whole().1,
+        hue_offset: 0.0,
+        zoom_animation: None,
+        color_search: None,
+        count_edits: None,
+        main_colors,
+        slideshow: None,
+        palette_editor: None,
+        cluster_view: None,
+        main_window: None,
+        progress,
+        timelapse: None,
+        replay: None,
+        comparisons,
+        options,
+        seed,
+        rng,
+    }
+}
+
+/// A `--from` or `--to` color: `rrggbb`, or else the name of a color in
+/// the palette file.
+fn gradient_end(value: &str, options: &Options) -> [u8; 3] {
+    if let Ok(hex) = value.parse::<render::HexColor>() {
+        return hex.0 .0;
+    }
+    let path = options.color_data.as_deref().unwrap_or_else(|| {
+        panic!("'{value}' is not rrggbb and there is no palette file to look it up in")
     });
+    let palette = if palette::is_imported(path) {
+        ColorConfigs::import(path, Some(0), &HashMap::new())
+    } else {
+        ColorConfigs::load(path, options.parse_strict)
+    };
+    palette
+        .colors
+        .iter()
+        .find(|config| config.name == value)
+        .map(|config| [config.r, config.g, config.b])
+        .unwrap_or_else(|| panic!("No color named '{value}' in {path}"))
+}
+
+/// Reads a JSON palette, or imports a GIMP or Photoshop one with counts from
+/// `--count-overlay` and `--count-per-color`, then applies `--gamma-palette`
+/// and `--normalize-counts`. Fails if the file cannot be read or parsed.
+fn load_palette(path: &str, options: &Options) -> Result<ColorConfigs, String> {
+    let palette = if palette::is_imported(path) {
+        let overlay = options
+            .count_overlay
+            .as_deref()
+            .map(palette::load_counts)
+            .unwrap_or_default();
+        ColorConfigs::try_import(path, options.count_per_color, &overlay)?
+    } else {
+        ColorConfigs::try_load(path, options.parse_strict)?
+    };
+    if let Some(measurements) = &options.palette_validate_physical {
+        validate_physical(&palette, measurements, options.measurement_tolerance);
+    }
+    let palette = match options.gamma_palette {
+        Some(gamma) => palette.gamma_corrected(gamma),
+        None => palette,
+    };
+    if !options.normalize_counts {
+        return Ok(palette);
+    }
+    let total = X_SIZE * Y_SIZE;
+    let normalized = palette.normalize(total);
+    let declared: u64 = palette.colors.iter().map(|config| config.count).sum();
+    log::info!("Normalized counts in {path} from {declared} to {total} tiles.");
+    if options.verbose {
+        let width = palette
+            .colors
+            .iter()
+            .map(|config| config.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Color".len());
+        log::info!(
+            "  {:<width$}  {:>8}  {:>10}",
+            "Color",
+            "Original",
+            "Normalized"
+        );
+        for (original, config) in palette.colors.iter().zip(&normalized.colors) {
+            log::info!(
+                "  {:<width$}  {:>8}  {:>10}",
+                config.name,
+                original.count,
+                config.count
+            );
+        }
+    }
+    Ok(normalized)
+}
+
+/// Warns about the colors of `palette` whose values measured in the
+/// `measurements` CSV are more than `tolerance` off.
+fn validate_physical(palette: &ColorConfigs, measurements: &str, tolerance: f32) {
+    let measured = palette::load_measurements(measurements);
+    let (mismatches, unknown) = palette.physical_mismatches(&measured, tolerance);
+    let hex = |[r, g, b]: [u8; 3]| format!("#{r:02x}{g:02x}{b:02x}");
+    for mismatch in &mismatches {
+        let [dr, dg, db] = [0, 1, 2]
+            .map(|channel| mismatch.measured[channel] as i16 - mismatch.nominal[channel] as i16);
+        log::warn!(
+            "{} measures {} against {} in the palette, {:.1} apart (r {dr:+}, g {dg:+}, b {db:+}).",
+            mismatch.name,
+            hex(mismatch.measured),
+            hex(mismatch.nominal),
+            mismatch.distance
+        );
+    }
+    if !unknown.is_empty() {
+        log::warn!(
+            "{} measured colors are not in the palette: {}",
+            unknown.len(),
+            unknown.join(", ")
+        );
+    }
+    log::info!(
+        "Checked {} measured colors against the palette: {} more than {tolerance} off.",
+        measured.len() - unknown.len(),
+        mismatches.len()
+    );
+}
+
+/// Reduces the palette, applies `--auto-count`, and saves the result for
+/// `--palette-out`.
+fn finish_palette(
+    palette: ColorConfigs,
+    colors: &[Color],
+    emphasis: &HashMap<String, f32>,
+    options: &Options,
+) -> ColorConfigs {
+    let mut palette = reduce_palette(palette, options);
+    if options.auto_count {
+        auto_count(&mut palette, colors, emphasis, options);
+    }
+    if let Some(path) = &options.palette_out {
+        status::during(Failure::Export, || palette.save(path));
+        log::info!("Wrote {path}.");
+    }
+    palette
+}
+
+/// Applies `--quantize-palette` and `--color-reduce`, reporting the result.
+fn reduce_palette(palette: ColorConfigs, options: &Options) -> ColorConfigs {
+    let palette = match options.quantize_palette {
+        Some(levels) => {
+            let quantized = palette.quantize(levels);
+            log::info!(
+                "Quantized palette to {levels} levels per channel: {} colors became {}.",
+                palette.colors.len(),
+                quantized.colors.len()
+            );
+            quantized
+        }
+        None => palette,
+    };
+    let palette = match options.color_reduce {
+        Some(n) => {
+            let reduced = palette.reduce(n);
+            log::info!(
+                "Reduced palette from {} to {} colors:",
+                palette.colors.len(),
+                reduced.colors.len()
+            );
+            for config in &reduced.colors {
+                log::info!(
+                    "  {} rgb({}, {}, {}) x{}",
+                    config.name,
+                    config.r,
+                    config.g,
+                    config.b,
+                    config.count
+                );
+            }
+            reduced
+        }
+        None => palette,
+    };
+    palette
+}
 
-    Model { pixels: colors }
+/// Replaces the counts of `palette` for `--auto-count` with how many tiles
+/// of the grid are nearest each color when stock is ignored, which add up
+/// to the grid's tiles.
+fn auto_count(
+    palette: &mut ColorConfigs,
+    colors: &[Color],
+    emphasis: &HashMap<String, f32>,
+    options: &Options,
+) {
+    let counts = assign::nearest_counts(colors, palette, emphasis);
+    let width = palette
+        .colors
+        .iter()
+        .map(|config| config.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Color".len());
+    if options.verbose {
+        log::info!("  {:<width$}  {:>8}  {:>5}", "Color", "Declared", "Auto");
+    }
+    for (config, &count) in palette.colors.iter_mut().zip(&counts) {
+        if options.verbose {
+            log::info!("  {:<width$}  {:>8}  {count:>5}", config.name, config.count);
+        }
+        config.count = count;
+    }
+    log::info!(
+        "Counted {} colors nearest the picture's {} tiles.",
+        counts.iter().filter(|&&count| count > 0).count(),
+        colors.len()
+    );
+}
+
+fn print_coverage(palette: &ColorConfigs, format: ReportFormat) {
+    let coverage = palette.coverage();
+    let percent = 100.0 * coverage.covered as f32 / coverage.total as f32;
+    if format != ReportFormat::Text {
+        let mut table = Table::new(&[
+            "covered",
+            "total",
+            "pct",
+            "gap_r",
+            "gap_g",
+            "gap_b",
+            "gap_hue",
+            "gap_saturation",
+            "gap_value",
+        ]);
+        let mut row = vec![
+            json!(coverage.covered),
+            json!(coverage.total),
+            json!(rounded(percent)),
+        ];
+        row.extend(match coverage.emptiest {
+            Some(rgb) => {
+                let (hue, saturation, value) = palette::hsv(rgb);
+                vec![
+                    json!(rgb[0]),
+                    json!(rgb[1]),
+                    json!(rgb[2]),
+                    json!(hue.round()),
+                    json!(rounded(saturation * 100.0)),
+                    json!(rounded(value * 100.0)),
+                ]
+            }
+            None => vec![Value::Null; 6],
+        });
+        table.push(row);
+        table.print(format);
+        return;
+    }
+    println!(
+        "Palette covers {}/{} cells ({percent:.1}%)",
+        coverage.covered, coverage.total
+    );
+    if let Some(rgb) = coverage.emptiest {
+        let (hue, saturation, value) = palette::hsv(rgb);
+        println!(
+            "Largest gap near rgb({}, {}, {}): hue {hue:.0}, saturation {:.0}%, value {:.0}%",
+            rgb[0],
+            rgb[1],
+            rgb[2],
+            saturation * 100.0,
+            value * 100.0
+        );
+    }
 }
 
-fn calculate_closest_color(color_configs: &ColorConfigs, original_color: &Color) -> usize {
-    let mut closest_dist: f32 = f32::MAX;
-    let mut closest_index = usize::MAX;
-    let mut count = 0;
-    let mut has_available_color = false;
-    for color_config in color_configs.colors.iter() {
-        if color_config.count == 0 {
-            count += 1;
-            continue;
+/// The distance by `metric` between every two palette colors, as a TSV
+/// matrix with the names across the top and down the side. With a
+/// `threshold`, only the pairs closer than it, one `a`, `b`, and `distance`
+/// row each, closest first.
+fn print_distances(palette: &ColorConfigs, metric: ColorMetric, threshold: Option<f32>) {
+    let measured = |config: &ColorConfig| match metric {
+        ColorMetric::Rgb => config.working,
+        ColorMetric::Lab => config.lab,
+    };
+    let distance = |a: &ColorConfig, b: &ColorConfig| {
+        let (a, b) = (measured(a), measured(b));
+        (0..3)
+            .map(|channel| (a[channel] - b[channel]).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let colors = &palette.colors;
+    if let Some(threshold) = threshold {
+        let mut pairs = Vec::new();
+        for (row, a) in colors.iter().enumerate() {
+            for b in &colors[row + 1..] {
+                let between = distance(a, b);
+                if between < threshold {
+                    pairs.push((a, b, between));
+                }
+            }
+        }
+        pairs.sort_by(|x, y| x.2.total_cmp(&y.2));
+        println!("a\tb\tdistance");
+        for (a, b, between) in pairs {
+            println!("{}\t{}\t{between:.1}", a.name, b.name);
         }
-        let mut r_dist = (color_config.r as f32 - original_color.r as f32) * 0.3;
-        let mut g_dist = (color_config.g as f32 - original_color.g as f32) * 0.59;
-        let mut b_dist = (color_config.b as f32 - original_color.b as f32) * 0.11;
-        r_dist = r_dist * r_dist;
-        g_dist = g_dist * g_dist;
-        b_dist = b_dist * b_dist;
+        return;
+    }
+    let names: Vec<&str> = colors.iter().map(|config| config.name.as_str()).collect();
+    println!("\t{}", names.join("\t"));
+    for a in colors {
+        let row: Vec<String> = colors
+            .iter()
+            .map(|b| format!("{:.1}", distance(a, b)))
+            .collect();
+        println!("{}\t{}", a.name, row.join("\t"));
+    }
+}
+
+/// The reports asked for once the mosaic is solved.
+fn print_reports(model: &Model) {
+    if model.options.stats {
+        print_stats(model);
+    }
+    if model.options.color_affinity_map {
+        print_affinity(model);
+    }
+}
+
+/// With `--stats`, prints each palette color's tiles, the pieces left, and
+/// its share of the grid.
+fn print_stats(model: &Model) {
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let tiles = (model.assignment.len() - model.holes.len()).max(1) as f32;
+    let mut table = Table::new(&["name", "r", "g", "b", "assigned", "remaining", "pct"]);
+    for (config, &assigned) in model.palette.colors.iter().zip(&used) {
+        table.push(vec![
+            json!(config.name),
+            json!(config.r),
+            json!(config.g),
+            json!(config.b),
+            json!(assigned),
+            json!(config.count),
+            json!(rounded(100.0 * assigned as f32 / tiles)),
+        ]);
+    }
+    table.print(model.options.color_report_format);
+}
 
-        let dist = r_dist + g_dist + b_dist;
-        if dist < closest_dist {
-            closest_dist = dist;
-            closest_index = count;
+/// The upper triangle of `assign::affinity` over the colors in use. Text is
+/// a matrix with colors numbered down the side and across the top; JSON and
+/// CSV give a row of `a`, `b`, and `count` per pair.
+fn print_affinity(model: &Model) {
+    let colors = &model.palette.colors;
+    let counts = assign::affinity(&model.assignment, colors.len());
+    let used: Vec<usize> = assign::usage(&model.assignment, colors.len())
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(index, _)| index)
+        .collect();
+    if model.options.color_report_format != ReportFormat::Text {
+        let mut table = Table::new(&["a", "b", "count"]);
+        for (row, &a) in used.iter().enumerate() {
+            for &b in &used[row..] {
+                table.push(vec![
+                    json!(colors[a].name),
+                    json!(colors[b].name),
+                    json!(counts[a][b]),
+                ]);
+            }
         }
-        has_available_color = true;
-        count += 1;
+        table.print(model.options.color_report_format);
+        return;
+    }
+    println!(
+        "Pairs of tiles within {} tiles of each other, by color:",
+        assign::AFFINITY_RADIUS
+    );
+    let name_width = used
+        .iter()
+        .map(|&index| colors[index].name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Color".len());
+    let largest = counts.iter().flatten().max().copied().unwrap_or(0);
+    let cell = largest.to_string().len().max(used.len().to_string().len()) + 1;
+    let mut line = format!("{:>3}  {:<name_width$}", "", "Color");
+    for number in 1..=used.len() {
+        line += &format!("{number:>cell$}");
     }
-    if !has_available_color || closest_dist == f32::MAX {
-        panic!("Invalid configuration of colors.  Not enough colors present.")
+    println!("{line}");
+    for (row, &a) in used.iter().enumerate() {
+        let mut line = format!("{:>3}  {:<name_width$}", row + 1, colors[a].name);
+        line += &" ".repeat(cell * row);
+        for &b in &used[row..] {
+            line += &format!("{:>cell$}", counts[a][b]);
+        }
+        println!("{line}");
+    }
+}
+
+/// `value` to one decimal place, for reports.
+fn rounded(value: f32) -> f64 {
+    (value as f64 * 10.0).round() / 10.0
+}
+
+/// A palette with fewer pieces than there are tiles to solve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortfall {
+    pub tiles: usize,
+    pub pieces: u64,
+}
+
+impl Shortfall {
+    /// How many more pieces the tiles need.
+    pub fn short(&self) -> u64 {
+        self.tiles as u64 - self.pieces
     }
-    closest_index
 }
 
-fn draw_square(app: &App, draw: &Draw, model: &Model) {
-    let window_size = app.main_window().inner_size_points();
+impl std::fmt::Display for Shortfall {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Not enough pieces: {} tiles to fill but only {} in the palette",
+            self.tiles, self.pieces
+        )
+    }
+}
 
-    let x_width = window_size.0.abs() / X_SIZE as f32;
-    let y_height = window_size.1.abs() / Y_SIZE as f32;
+/// What `palette` lacks to fill `tiles` tiles, if anything.
+fn shortfall(palette: &ColorConfigs, tiles: usize) -> Option<Shortfall> {
+    let pieces: u64 = palette.colors.iter().map(|config| config.count).sum();
+    (pieces < tiles as u64).then_some(Shortfall { tiles, pieces })
+}
 
-    // Ensures we align completely with the grid.
-    let x_offset: f32 = (window_size.0.abs() / 2.0) - (x_width / 2.0);
-    let y_offset: f32 = (window_size.1.abs() / 2.0) - (y_height / 2.0);
+/// Fails before solving when the palette has fewer pieces than there are
+/// tiles to solve.
+fn check_inventory(palette: &ColorConfigs, tiles: usize) {
+    if let Some(short) = shortfall(palette, tiles) {
+        status::classify(
+            Failure::Inventory,
+            json!({ "tiles": tiles, "pieces": short.pieces, "short": short.short() }),
+        );
+        panic!("{short}")
+    }
+}
 
-    let mut count = 0;
+/// Takes the pieces for `--frame` out of the palette before solving, so
+/// the grid can only use what is left.
+fn reserve_frame(palette: &mut ColorConfigs, options: &Options) -> Option<render::Frame> {
+    if options.frame == 0 {
+        return None;
+    }
+    let name = options
+        .frame_color
+        .as_deref()
+        .expect("--frame needs --frame-color");
+    let mut frame = render::Frame {
+        color: 0,
+        thickness: options.frame,
+    };
+    if options.auto_count {
+        // Auto counts cover the grid alone, so the frame's pieces come on
+        // top of them.
+        if let Some(config) = palette.colors.iter_mut().find(|config| config.name == name) {
+            config.count += frame.tile_count();
+        }
+    }
+    frame.color = palette.reserve(name, frame.tile_count());
+    Some(frame)
+}
+
+/// Solves once per seed, each with its own shuffle, and keeps the run with
+/// the lowest total error, leaving its remaining inventory in `palette`.
+fn best_of_restarts(
+    seeds: &[u64],
+    reference: &[Color],
+    tiles: &[usize],
+    palette: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    weights: Option<&[f32]>,
+) -> Vec<usize> {
+    let restarts = assign::restarts(reference, tiles, palette, emphasis, texture, weights, seeds);
+    for (run, restart) in restarts.iter().enumerate() {
+        log::info!(
+            "Restart {}: total error {:.1}",
+            run + 1,
+            restart.total_error
+        );
+    }
+    let (best, winner) = restarts
+        .into_iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_error.total_cmp(&b.1.total_error))
+        .expect("--restarts must be at least 1");
+    log::info!("Keeping restart {} of {}.", best + 1, seeds.len());
+    *palette = winner.palette;
+    winner.assignment
+}
+
+/// The tiles the solvers fill: all of them but any that are locked.
+fn solved_tiles(locks: Option<&locks::Locks>, holes: &[usize]) -> Vec<usize> {
+    let mut tiles: Vec<usize> = match locks {
+        Some(locks) => locks.free_tiles(),
+        None => (0..X_SIZE * Y_SIZE).map(|tile| tile as usize).collect(),
+    };
+    tiles.retain(|tile| holes.binary_search(tile).is_err());
+    tiles
+}
+
+/// The tiles `--key-color` leaves empty: those within `--key-tolerance` of
+/// the key, measured as RGB distance, apart from locked ones.
+fn keyed_tiles(colors: &[Color], options: &Options, locks: Option<&locks::Locks>) -> Vec<usize> {
+    let [r, g, b] = match options.key_color {
+        Some(render::HexColor(key)) => key.0,
+        None => return Vec::new(),
+    };
+    let holes: Vec<usize> = colors
+        .iter()
+        .enumerate()
+        .filter(|&(tile, color)| {
+            let distance = [(color.r, r), (color.g, g), (color.b, b)]
+                .iter()
+                .map(|&(a, b)| (a as f32 - b as f32).powi(2))
+                .sum::<f32>()
+                .sqrt();
+            distance <= options.key_tolerance && !locks.is_some_and(|locks| locks.locks(tile))
+        })
+        .map(|(tile, _)| tile)
+        .collect();
+    if holes.len() == colors.len() {
+        panic!(
+            "--key-color #{r:02x}{g:02x}{b:02x} keys out every tile; lower --key-tolerance or pick another color"
+        )
+    }
+    if !holes.is_empty() {
+        log::info!("Keyed out {} of {} tiles.", holes.len(), colors.len());
+    }
+    holes
+}
+
+/// Merges small regions and places edge pieces on a finished primary solve,
+/// then puts back any locked tile either of them moved.
+fn refine(
+    options: &Options,
+    reference: &[Color],
+    assignment: &mut [usize],
+    palette: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    locks: Option<&locks::Locks>,
+) {
+    merge_regions(options, reference, assignment, palette, emphasis);
+    place_edges(options, reference, assignment, palette, emphasis);
+    if let Some(locks) = locks {
+        locks.restore(assignment, palette);
+    }
+}
+
+/// Runs the `--merge-small-regions` passes over a finished solve, then
+/// merges regions under `--min-region-size`.
+fn merge_regions(
+    options: &Options,
+    reference: &[Color],
+    assignment: &mut [usize],
+    palette: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) {
+    let reassigned = assign::merge_small_regions(
+        reference,
+        assignment,
+        palette,
+        emphasis,
+        options.merge_small_regions,
+    );
+    if options.verbose {
+        for (pass, count) in reassigned.iter().enumerate() {
+            log::info!("Merge pass {}: {count} tiles reassigned.", pass + 1);
+        }
+    }
+    if options.min_region_size > 1 {
+        let (regions, tiles) = assign::merge_tiny_regions(
+            reference,
+            assignment,
+            palette,
+            emphasis,
+            options.min_region_size,
+        );
+        log::info!(
+            "Merged {regions} regions under {} tiles, recoloring {tiles} tiles.",
+            options.min_region_size
+        );
+    }
+}
+
+/// Swaps in `--corner-tiles` pieces along the border of a finished primary
+/// solve.
+fn place_edges(
+    options: &Options,
+    reference: &[Color],
+    assignment: &mut [usize],
+    palette: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) {
+    if let Some(path) = &options.corner_tiles {
+        let edges = edges::EdgeConfig::load(path);
+        let replaced = edges::apply(&edges, reference, assignment, palette, emphasis);
+        log::info!("Placed {replaced} edge pieces from {path}.");
+    }
+}
+
+fn solve_in_background(
+    reference: &[Color],
+    order: &[usize],
+    color_configs: &ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+    texture: Option<Texture>,
+    no_overlap: bool,
+) -> Progress {
+    let progress = Progress {
+        pending_rows: Arc::new(Mutex::new(VecDeque::new())),
+        result: Arc::new(Mutex::new(None)),
+    };
+    let pending_rows = Arc::clone(&progress.pending_rows);
+    let result = Arc::clone(&progress.result);
+    let (reference, order) = (reference.to_vec(), order.to_vec());
+    let (mut color_configs, emphasis) = (color_configs.clone(), emphasis.clone());
+    thread::spawn(move || {
+        let assignment = assign::assign_colors_by_row(
+            &reference,
+            &order,
+            &mut color_configs,
+            &emphasis,
+            texture,
+            no_overlap,
+            |row| pending_rows.lock().unwrap().push_back(row),
+        );
+        *result.lock().unwrap() = Some((assignment, color_configs));
+    });
+    progress
+}
+
+/// Whichever tiles are present in the colors of `layer`. `original` holds
+/// the source color of every tile.
+fn shown_tiles(pixels: &[Color], original: &[Color], layer: Layer) -> Vec<tile_mesh::Shown> {
+    pixels
+        .iter()
+        .map(|color| tile_mesh::Shown {
+            x: color.x,
+            y: color.y,
+            size: 1,
+            rgb: layer.color(color, &original[(color.y * X_SIZE + color.x) as usize]),
+        })
+        .collect()
+}
+
+/// Every adaptive cell as one square in the color of its tiles. `pixels`
+/// holds every tile in raster order.
+fn shown_cells(pixels: &[Color], cells: &[quadtree::Cell]) -> Vec<tile_mesh::Shown> {
+    cells
+        .iter()
+        .map(|cell| {
+            let color = &pixels[cell.first_tile()];
+            tile_mesh::Shown {
+                x: cell.x,
+                y: cell.y,
+                size: cell.size,
+                rgb: (color.r, color.g, color.b),
+            }
+        })
+        .collect()
+}
+
+/// Paints the keyed-out holes in both layers as a checkerboard of grays,
+/// the way image editors show transparency.
+fn checker_holes(pixels: &mut [Color], reference: &mut [Color], holes: &[usize]) {
+    for &tile in holes {
+        let pixel = &mut pixels[tile];
+        let gray = if (pixel.x + pixel.y).is_multiple_of(2) {
+            200
+        } else {
+            150
+        };
+        (pixel.r, pixel.g, pixel.b) = (gray, gray, gray);
+        reference[tile] = pixel.clone();
+    }
+}
+
+/// Colors of successive build batches, repeating once they run out.
+const BATCH_COLORS: [(u8, u8, u8); 8] = [
+    (230, 40, 40),
+    (40, 90, 230),
+    (40, 180, 60),
+    (240, 210, 30),
+    (220, 50, 200),
+    (40, 210, 220),
+    (245, 140, 30),
+    (130, 60, 200),
+];
+
+/// Draws a triangle in the top-right corner of every square tile in the
+/// color of its build batch. Tiles are built row by row from the top-left,
+/// `batch_size` to a batch.
+fn draw_batch_corners(draw: &Draw, area: Rect, batch_size: u64) {
+    let x_width = area.w() / X_SIZE as f32;
+    let y_height = area.h() / Y_SIZE as f32;
+    let side = 0.35 * x_width.min(y_height);
     for y in 0..Y_SIZE {
         for x in 0..X_SIZE {
-            let x_f: f32 = (x as f32 * x_width) - x_offset;
-            let y_f: f32 = (y as f32 * y_height) - y_offset;
-            let color = model
-                .pixels
-                .get(count)
-                .expect("Model should have appropriate pixel count.");
-            draw.rect()
-                .xy(Point2::new(x_f, y_f))
-                .color(srgb8(color.r, color.g, color.b))
-                .width(x_width as f32 - 1.0)
-                .height(y_height as f32 - 1.0);
-            count += 1;
+            let built = (Y_SIZE - 1 - y) * X_SIZE + x;
+            let batch = (built / batch_size) as usize % BATCH_COLORS.len();
+            let (r, g, b) = BATCH_COLORS[batch];
+            // Inside the one-pixel gap between tiles.
+            let right = area.left() + (x + 1) as f32 * x_width - 1.0;
+            let top = area.bottom() + (y + 1) as f32 * y_height - 1.0;
+            draw.tri()
+                .points(
+                    pt2(right, top),
+                    pt2(right - side, top),
+                    pt2(right, top - side),
+                )
+                .color(srgb8(r, g, b));
         }
     }
 }
+
+/// Writes every tile's raster index over it in tiny type, white on dark
+/// tiles and black on light ones as they are shown.
+fn draw_tile_indices(
+    draw: &Draw,
+    area: Rect,
+    pixels: &[Color],
+    original: &[Color],
+    layer: Layer,
+    hex_grid: Option<HexOrientation>,
+) {
+    for color in pixels {
+        let tile = (color.y * X_SIZE + color.x) as usize;
+        let (r, g, b) = layer.color(color, &original[tile]);
+        let (fx, fy) = match hex_grid {
+            Some(orientation) => orientation.center(color.x, color.y),
+            None => (
+                (color.x as f32 + 0.5) / X_SIZE as f32,
+                (color.y as f32 + 0.5) / Y_SIZE as f32,
+            ),
+        };
+        // Rec. 709 luma.
+        let luma = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0;
+        let ink = if luma > 0.5 { BLACK } else { WHITE };
+        draw.text(&tile.to_string())
+            .x_y(area.left() + fx * area.w(), area.bottom() + fy * area.h())
+            .w(area.w() / X_SIZE as f32)
+            .font_size(6)
+            .color(ink);
+    }
+}
@@ -0,0 +1,235 @@
+//! How color distances are measured, and where in the grid each way is
+//! used, from `--region-palette-override`.
+//!
+//! The regions are set once at startup and read by the matcher for every
+//! tile it places, so they are kept here rather than passed down through
+//! every solver.
+
+use crate::{Color, X_SIZE, Y_SIZE};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMetric {
+    /// sRGB with each channel weighted by how bright it looks.
+    #[default]
+    Rgb,
+    /// CIELAB, whose distances follow perceived differences more closely,
+    /// in skin tones and skies especially.
+    Lab,
+}
+
+impl FromStr for ColorMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgb" => Ok(ColorMetric::Rgb),
+            "lab" => Ok(ColorMetric::Lab),
+            _ => Err(format!("unknown color metric '{s}'; use rgb or lab")),
+        }
+    }
+}
+
+/// CIELAB of an sRGB color, under D65.
+pub fn lab(r: u8, g: u8, b: u8) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    // Relative to the D65 white point.
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.95047;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// The sRGB color of a CIELAB one, clamped into the sRGB cube.
+pub fn srgb_from_lab([l, a, b]: [f32; 3]) -> [u8; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let (fx, fz) = (fy + a / 500.0, fy - b / 200.0);
+    let f_inverse = |f: f32| {
+        if f > 6.0 / 29.0 {
+            f * f * f
+        } else {
+            (116.0 * f - 16.0) * 27.0 / 24389.0
+        }
+    };
+    let (x, y, z) = (
+        f_inverse(fx) * 0.95047,
+        f_inverse(fy),
+        f_inverse(fz) * 1.08883,
+    );
+    let encode = |c: f32| {
+        let c = if c <= 0.003_130_8 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+    [
+        encode(3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z),
+        encode(-0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z),
+        encode(0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// A band along one side of the grid, `1 / parts` of it deep, or the whole
+/// grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Band { side: Side, parts: u64 },
+    Rest,
+}
+
+impl Region {
+    fn contains(self, x: u64, y: u64) -> bool {
+        match self {
+            Region::Rest => true,
+            // Grid rows count up from the bottom, so the top band is the
+            // last rows.
+            Region::Band { side, parts } => match side {
+                Side::Top => y >= Y_SIZE - Y_SIZE / parts,
+                Side::Bottom => y < Y_SIZE / parts,
+                Side::Left => x < X_SIZE / parts,
+                Side::Right => x >= X_SIZE - X_SIZE / parts,
+            },
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "rest" {
+            return Ok(Region::Rest);
+        }
+        let unknown = || {
+            format!(
+                "unknown region '{s}'; use rest or top, bottom, left, or right with -half, \
+                 -third, or -quarter"
+            )
+        };
+        let (side, part) = s.split_once('-').ok_or_else(unknown)?;
+        let side = match side {
+            "top" => Side::Top,
+            "bottom" => Side::Bottom,
+            "left" => Side::Left,
+            "right" => Side::Right,
+            _ => return Err(unknown()),
+        };
+        let parts = match part {
+            "half" => 2,
+            "third" => 3,
+            "quarter" => 4,
+            _ => return Err(unknown()),
+        };
+        Ok(Region::Band { side, parts })
+    }
+}
+
+/// Regions paired with the metric used in them. A tile takes the metric of
+/// the first region it falls in, and `Rgb` outside them all.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegionMetrics(Vec<(Region, ColorMetric)>);
+
+impl RegionMetrics {
+    /// These regions, with `metric` for the tiles outside them all.
+    pub fn or(mut self, metric: ColorMetric) -> RegionMetrics {
+        self.0.push((Region::Rest, metric));
+        self
+    }
+
+    pub fn at(&self, x: u64, y: u64) -> ColorMetric {
+        self.0
+            .iter()
+            .find(|(region, _)| region.contains(x, y))
+            .map_or(ColorMetric::Rgb, |&(_, metric)| metric)
+    }
+}
+
+impl FromStr for RegionMetrics {
+    type Err = String;
+
+    /// A comma-separated list of `region:metric`, such as
+    /// `top-quarter:lab,rest:rgb`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|entry| {
+                let (region, metric) = entry
+                    .trim()
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected region:metric, got '{entry}'"))?;
+                Ok((region.trim().parse()?, metric.trim().parse()?))
+            })
+            .collect::<Result<_, String>>()
+            .map(RegionMetrics)
+    }
+}
+
+static REGIONS: OnceLock<RegionMetrics> = OnceLock::new();
+
+/// Sets the regions for the rest of the run.
+pub fn configure(regions: RegionMetrics) {
+    REGIONS.set(regions).ok();
+}
+
+/// The metric used for the tile `color` stands for.
+pub fn for_tile(color: &Color) -> ColorMetric {
+    REGIONS
+        .get()
+        .map_or(ColorMetric::Rgb, |regions| regions.at(color.x, color.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_matching_region_sets_the_metric() {
+        let regions: RegionMetrics = "top-quarter:lab, left-half:rgb, rest:lab".parse().unwrap();
+        assert_eq!(regions.at(0, Y_SIZE - 1), ColorMetric::Lab);
+        assert_eq!(regions.at(0, 0), ColorMetric::Rgb);
+        assert_eq!(regions.at(X_SIZE - 1, 0), ColorMetric::Lab);
+        let top: RegionMetrics = "top-quarter:lab".parse().unwrap();
+        assert_eq!(top.at(0, Y_SIZE - Y_SIZE / 4), ColorMetric::Lab);
+        assert_eq!(top.at(0, Y_SIZE - Y_SIZE / 4 - 1), ColorMetric::Rgb);
+        assert!("middle:lab".parse::<RegionMetrics>().is_err());
+        assert!("rest:hsv".parse::<RegionMetrics>().is_err());
+        assert_eq!(top.clone().or(ColorMetric::Lab).at(0, 0), ColorMetric::Lab);
+        assert_eq!(top.or(ColorMetric::Lab).at(0, Y_SIZE - 1), ColorMetric::Lab);
+    }
+
+    #[test]
+    fn lab_of_white_and_black_and_back() {
+        let [l, a, b] = lab(255, 255, 255);
+        assert!((l - 100.0).abs() < 0.01 && a.abs() < 0.01 && b.abs() < 0.01);
+        assert_eq!(lab(0, 0, 0), [0.0, 0.0, 0.0]);
+        for rgb in [[12, 200, 99], [255, 0, 0], [0, 0, 255], [128, 128, 128]] {
+            assert_eq!(srgb_from_lab(lab(rgb[0], rgb[1], rgb[2])), rgb);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Minecraft map colors and the bits of the NBT format needed to write map
+//! item data.
+
+use std::str::FromStr;
+
+/// RGB of every base map color in Java Edition, indexed by base id. Id 0 is
+/// transparent and never used for tiles.
+pub const BASE_COLORS: [[u8; 3]; 62] = [
+    [0, 0, 0],
+    [127, 178, 56],
+    [247, 233, 163],
+    [199, 199, 199],
+    [255, 0, 0],
+    [160, 160, 255],
+    [167, 167, 167],
+    [0, 124, 0],
+    [255, 255, 255],
+    [164, 168, 184],
+    [151, 109, 77],
+    [112, 112, 112],
+    [64, 64, 255],
+    [143, 119, 72],
+    [255, 252, 245],
+    [216, 127, 51],
+    [178, 76, 216],
+    [102, 153, 216],
+    [229, 229, 51],
+    [127, 204, 25],
+    [242, 127, 165],
+    [76, 76, 76],
+    [153, 153, 153],
+    [76, 127, 153],
+    [127, 63, 178],
+    [51, 76, 178],
+    [102, 76, 51],
+    [102, 127, 51],
+    [153, 51, 51],
+    [25, 25, 25],
+    [250, 238, 77],
+    [92, 219, 213],
+    [74, 128, 255],
+    [0, 217, 58],
+    [129, 86, 49],
+    [112, 2, 0],
+    [209, 177, 161],
+    [159, 82, 36],
+    [149, 87, 108],
+    [112, 108, 138],
+    [186, 133, 36],
+    [103, 117, 53],
+    [160, 77, 78],
+    [57, 41, 35],
+    [135, 107, 98],
+    [87, 92, 92],
+    [122, 73, 88],
+    [76, 62, 92],
+    [76, 50, 35],
+    [76, 82, 42],
+    [142, 60, 46],
+    [37, 22, 16],
+    [189, 48, 49],
+    [148, 63, 97],
+    [92, 25, 29],
+    [22, 126, 134],
+    [58, 142, 140],
+    [86, 44, 62],
+    [20, 180, 133],
+    [100, 100, 100],
+    [216, 175, 147],
+    [127, 167, 150],
+];
+
+/// Brightness of the four shades of each base color, out of 255. A map
+/// color id is `base * 4 + shade`.
+pub const SHADES: [u16; 4] = [180, 220, 255, 135];
+
+/// A Java Edition release, which decides the map colors that exist and the
+/// data version written to the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinecraftVersion {
+    minor: u32,
+}
+
+impl Default for MinecraftVersion {
+    fn default() -> MinecraftVersion {
+        MinecraftVersion { minor: 20 }
+    }
+}
+
+impl FromStr for MinecraftVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MinecraftVersion, String> {
+        let mut parts = s.split('.');
+        let major = parts.next().and_then(|part| part.parse::<u32>().ok());
+        let minor = parts.next().and_then(|part| part.parse::<u32>().ok());
+        match (major, minor) {
+            (Some(1), Some(minor)) if minor >= 12 => Ok(MinecraftVersion { minor }),
+            (Some(1), Some(_)) => Err(format!(
+                "Minecraft {s} uses an older map color table; 1.12 or later is supported"
+            )),
+            _ => Err(format!("expected a version like 1.20, got '{s}'")),
+        }
+    }
+}
+
+impl MinecraftVersion {
+    /// Base ids `1..base_count()` are available in this version.
+    pub fn base_count(self) -> usize {
+        match self.minor {
+            12..=15 => 52,
+            16 => 59,
+            _ => 62,
+        }
+    }
+
+    /// Every map color id usable in this version with its RGB value.
+    pub fn map_colors(self) -> Vec<(u8, [u8; 3])> {
+        (1..self.base_count())
+            .flat_map(|base| {
+                SHADES.iter().enumerate().map(move |(shade, &level)| {
+                    let rgb = BASE_COLORS[base].map(|c| (c as u16 * level / 255) as u8);
+                    ((base * 4 + shade) as u8, rgb)
+                })
+            })
+            .collect()
+    }
+
+    /// The `DataVersion` of the last patch of this release. Newer releases
+    /// than listed get the newest known value, which the game upgrades.
+    pub fn data_version(self) -> i32 {
+        match self.minor {
+            12 => 1343,
+            13 => 1631,
+            14 => 1976,
+            15 => 2230,
+            16 => 2586,
+            17 => 2730,
+            18 => 2975,
+            19 => 3337,
+            20 => 3839,
+            _ => 3955,
+        }
+    }
+
+    /// Before 1.16 the map's dimension was stored as a byte, not a name.
+    pub fn named_dimensions(self) -> bool {
+        self.minor >= 16
+    }
+}
+
+/// Builds uncompressed NBT. Every tag is written with its name, as inside a
+/// compound.
+#[derive(Debug, Default)]
+pub struct Nbt {
+    bytes: Vec<u8>,
+}
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_INT: u8 = 3;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+
+impl Nbt {
+    pub fn new() -> Nbt {
+        Nbt::default()
+    }
+
+    fn header(&mut self, tag: u8, name: &str) {
+        self.bytes.push(tag);
+        self.string_payload(name);
+    }
+
+    fn string_payload(&mut self, value: &str) {
+        self.bytes.extend((value.len() as u16).to_be_bytes());
+        self.bytes.extend(value.as_bytes());
+    }
+
+    pub fn begin_compound(&mut self, name: &str) {
+        self.header(TAG_COMPOUND, name);
+    }
+
+    pub fn end_compound(&mut self) {
+        self.bytes.push(TAG_END);
+    }
+
+    pub fn byte(&mut self, name: &str, value: i8) {
+        self.header(TAG_BYTE, name);
+        self.bytes.push(value as u8);
+    }
+
+    pub fn int(&mut self, name: &str, value: i32) {
+        self.header(TAG_INT, name);
+        self.bytes.extend(value.to_be_bytes());
+    }
+
+    pub fn string(&mut self, name: &str, value: &str) {
+        self.header(TAG_STRING, name);
+        self.string_payload(value);
+    }
+
+    pub fn byte_array(&mut self, name: &str, values: &[u8]) {
+        self.header(TAG_BYTE_ARRAY, name);
+        self.bytes.extend((values.len() as i32).to_be_bytes());
+        self.bytes.extend(values);
+    }
+
+    /// An empty list of compounds.
+    pub fn empty_list(&mut self, name: &str) {
+        self.header(TAG_LIST, name);
+        self.bytes.push(TAG_COMPOUND);
+        self.bytes.extend(0i32.to_be_bytes());
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_pick_color_tables() {
+        let latest: MinecraftVersion = "1.20.1".parse().unwrap();
+        assert_eq!(latest.map_colors().len(), 61 * 4);
+        assert_eq!(
+            "1.12"
+                .parse::<MinecraftVersion>()
+                .unwrap()
+                .map_colors()
+                .len(),
+            51 * 4
+        );
+        assert!("1.8".parse::<MinecraftVersion>().is_err());
+        assert!("bedrock".parse::<MinecraftVersion>().is_err());
+        // Grass at its brightest shade keeps the base color.
+        assert!(latest.map_colors().contains(&(6, [127, 178, 56])));
+    }
+
+    #[test]
+    fn nbt_tags_are_named_and_big_endian() {
+        let mut nbt = Nbt::new();
+        nbt.begin_compound("");
+        nbt.int("x", 258);
+        nbt.end_compound();
+        assert_eq!(nbt.into_bytes(), [10, 0, 0, 3, 0, 1, b'x', 0, 0, 1, 2, 0]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Photoshop color swatch files (`.aco`).
+//!
+//! A file holds a version 1 section of bare colors, usually followed by a
+//! version 2 section repeating them with names. Every value is big-endian.
+
+use super::Entry;
+
+/// Reads the swatches, preferring the named version 2 section. RGB, HSB, and
+/// grayscale entries are converted; anything else (CMYK, Lab, ...) is
+/// returned in the second list by name so it can be reported.
+pub fn parse(bytes: &[u8]) -> Result<(Vec<Entry>, Vec<String>), String> {
+    let mut reader = Reader { bytes, at: 0 };
+    let mut section = read_section(&mut reader, 1)?;
+    if reader.at < bytes.len() {
+        section = read_section(&mut reader, 2)?;
+    }
+
+    let mut colors = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, (space, values, name)) in section.into_iter().enumerate() {
+        let name = name.unwrap_or_else(|| format!("Swatch {}", index + 1));
+        match convert(space, values) {
+            Some(rgb) => colors.push((name, rgb)),
+            None => skipped.push(format!("{name} ({})", space_name(space))),
+        }
+    }
+    Ok((colors, skipped))
+}
+
+type Swatch = (u16, [u16; 4], Option<String>);
+
+fn read_section(reader: &mut Reader, version: u16) -> Result<Vec<Swatch>, String> {
+    let found = reader.u16()?;
+    if found != version {
+        return Err(format!(
+            "expected a version {version} section, found {found}"
+        ));
+    }
+    let count = reader.u16()?;
+    let mut swatches = Vec::new();
+    for _ in 0..count {
+        let space = reader.u16()?;
+        let values = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+        let name = if version == 2 {
+            // A zero word, then the name length in UTF-16 units including
+            // the terminating null.
+            reader.u16()?;
+            let length = reader.u16()? as usize;
+            let units = (0..length)
+                .map(|_| reader.u16())
+                .collect::<Result<Vec<_>, _>>()?;
+            let name = String::from_utf16_lossy(&units);
+            Some(name.trim_end_matches('\0').to_string())
+        } else {
+            None
+        };
+        swatches.push((space, values, name));
+    }
+    Ok(swatches)
+}
+
+fn convert(space: u16, [w, x, y, _]: [u16; 4]) -> Option<[u8; 3]> {
+    let byte = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    match space {
+        0 => Some([w, x, y].map(|channel| (channel / 257) as u8)),
+        1 => {
+            let hue = w as f32 / 65535.0 * 360.0;
+            let saturation = x as f32 / 65535.0;
+            let value = y as f32 / 65535.0;
+            let chroma = value * saturation;
+            let h = hue / 60.0;
+            let second = chroma * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+            let (r, g, b) = match h as u32 {
+                0 => (chroma, second, 0.0),
+                1 => (second, chroma, 0.0),
+                2 => (0.0, chroma, second),
+                3 => (0.0, second, chroma),
+                4 => (second, 0.0, chroma),
+                _ => (chroma, 0.0, second),
+            };
+            let m = value - chroma;
+            Some([byte(r + m), byte(g + m), byte(b + m)])
+        }
+        // Gray is stored as 0..=10000, with 10000 meaning black.
+        8 => {
+            let gray = byte(1.0 - w.min(10000) as f32 / 10000.0);
+            Some([gray; 3])
+        }
+        _ => None,
+    }
+}
+
+fn space_name(space: u16) -> &'static str {
+    match space {
+        2 => "CMYK",
+        7 => "Lab",
+        _ => "unsupported color space",
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn u16(&mut self) -> Result<u16, String> {
+        let pair = self
+            .bytes
+            .get(self.at..self.at + 2)
+            .ok_or("file ends in the middle of a swatch")?;
+        self.at += 2;
+        Ok(u16::from_be_bytes([pair[0], pair[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn prefers_named_section_and_reports_skipped_spaces() {
+        let mut bytes = words(&[1, 2, 0, 65535, 0, 0, 0, 7, 100, 0, 0, 0]);
+        bytes.extend(words(&[2, 2, 0, 65535, 0, 0, 0, 0, 4]));
+        bytes.extend(words(&"Red\0".encode_utf16().collect::<Vec<_>>()));
+        bytes.extend(words(&[7, 100, 0, 0, 0, 0, 3]));
+        bytes.extend(words(&"Lb\0".encode_utf16().collect::<Vec<_>>()));
+        let (colors, skipped) = parse(&bytes).unwrap();
+        assert_eq!(colors, [("Red".to_string(), [255, 0, 0])]);
+        assert_eq!(skipped, ["Lb (Lab)"]);
+    }
+
+    #[test]
+    fn converts_hsb_and_gray_in_version_one() {
+        let bytes = words(&[1, 2, 1, 21845, 65535, 65535, 0, 8, 2500, 0, 0, 0]);
+        let (colors, _) = parse(&bytes).unwrap();
+        assert_eq!(
+            colors,
+            [
+                ("Swatch 1".to_string(), [0, 255, 0]),
+                ("Swatch 2".to_string(), [191, 191, 191]),
+            ]
+        );
+        assert!(parse(&words(&[1, 1, 0])).is_err());
+    }
+}
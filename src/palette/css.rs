@@ -0,0 +1,184 @@
+//! Colors read out of a stylesheet for `--palette-from-css`: the `#rrggbb`,
+//! `#rgb`, `rgb()`, and `hsl()` literals in its declarations.
+//!
+//! Only declaration values are scanned, so ID selectors such as `#fade`
+//! are not taken for colors. Alpha, where given, is dropped.
+
+/// Every distinct color in `text`, in the order they first appear.
+pub fn parse(text: &str) -> Vec<[u8; 3]> {
+    let text = strip_comments(text);
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    let mut rest = text.as_str();
+    // Each piece ends at a brace or semicolon; those ending in `{` are
+    // selectors or at-rule preludes rather than declarations.
+    while let Some(end) = rest.find(['{', '}', ';']) {
+        let (piece, after) = rest.split_at(end);
+        if !after.starts_with('{') {
+            scan_value(piece, &mut colors);
+        }
+        rest = &after[1..];
+    }
+    scan_value(rest, &mut colors);
+    colors
+}
+
+fn strip_comments(text: &str) -> String {
+    let mut kept = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("/*") {
+        kept.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    kept.push_str(rest);
+    kept
+}
+
+/// Adds the colors in the value of the declaration `piece`, if it is one.
+fn scan_value(piece: &str, colors: &mut Vec<[u8; 3]>) {
+    let value = match piece.split_once(':') {
+        Some((_, value)) => value,
+        None => return,
+    };
+    let bytes = value.as_bytes();
+    let mut at = 0;
+    while at < bytes.len() {
+        let word_end = at
+            + value[at..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(value.len() - at);
+        let color = if bytes[at] == b'#' {
+            let digits_end = at
+                + 1
+                + value[at + 1..]
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(value.len() - at - 1);
+            let color = hex(&value[at + 1..digits_end]);
+            at = digits_end;
+            color
+        } else if word_end > at && value[word_end..].starts_with('(') {
+            let name = value[at..word_end].to_ascii_lowercase();
+            let close = value[word_end..]
+                .find(')')
+                .map_or(value.len(), |close| word_end + close);
+            let arguments = &value[(word_end + 1).min(close)..close];
+            match name.as_str() {
+                "rgb" | "rgba" => {
+                    at = close;
+                    rgb(arguments)
+                }
+                "hsl" | "hsla" => {
+                    at = close;
+                    hsl(arguments)
+                }
+                // Colors inside other functions, such as gradients, count.
+                _ => {
+                    at = word_end + 1;
+                    None
+                }
+            }
+        } else {
+            at = word_end.max(at + value[at..].chars().next().map_or(1, char::len_utf8));
+            None
+        };
+        if let Some(color) = color {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+    }
+}
+
+/// The color of the `rrggbb` or `rgb` digits after a `#`.
+fn hex(digits: &str) -> Option<[u8; 3]> {
+    let value = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match digits.len() {
+        6 => Some([
+            value(&digits[0..2])?,
+            value(&digits[2..4])?,
+            value(&digits[4..6])?,
+        ]),
+        3 => {
+            let mut color = [0; 3];
+            for (channel, digit) in color.iter_mut().zip(digits.chars()) {
+                *channel = value(&digit.to_string())? * 17;
+            }
+            Some(color)
+        }
+        _ => None,
+    }
+}
+
+/// The first three numbers of a function's arguments, separated by
+/// commas, spaces, or a slash, each with its trailing unit.
+fn numbers(arguments: &str) -> Option<[(f32, &str); 3]> {
+    let mut parts = arguments
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let unit_at = part
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+                .unwrap_or(part.len());
+            part[..unit_at]
+                .parse::<f32>()
+                .ok()
+                .map(|number| (number, &part[unit_at..]))
+        });
+    Some([parts.next()??, parts.next()??, parts.next()??])
+}
+
+fn rgb(arguments: &str) -> Option<[u8; 3]> {
+    let channels = numbers(arguments)?;
+    Some(channels.map(|(number, unit)| {
+        let number = if unit == "%" { number * 2.55 } else { number };
+        number.round().clamp(0.0, 255.0) as u8
+    }))
+}
+
+fn hsl(arguments: &str) -> Option<[u8; 3]> {
+    let [(hue, _), (saturation, _), (lightness, _)] = numbers(arguments)?;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let saturation = (saturation / 100.0).clamp(0.0, 1.0);
+    let lightness = (lightness / 100.0).clamp(0.0, 1.0);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let base = lightness - chroma / 2.0;
+    Some([r, g, b].map(|channel| ((channel + base) * 255.0).round().clamp(0.0, 255.0) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_each_literal_once_from_declarations() {
+        let css = "/* brand #123456 */\n\
+                   #fade, .btn:hover { color: #FF2020; border: 1px solid #0f0 }\n\
+                   @media (min-width: 600px) { a { background: rgb(0, 0, 255); } }\n\
+                   p { color: rgba(100%, 0%, 0%, 0.5); fill: hsl(120, 100%, 25%); }\n\
+                   em { color: #ff2020; outline-color: #abcd; }\n\
+                   hr { background: linear-gradient(#000, #FFF); }";
+        assert_eq!(
+            parse(css),
+            [
+                [255, 32, 32],
+                [0, 255, 0],
+                [0, 0, 255],
+                [255, 0, 0],
+                [0, 128, 0],
+                [0, 0, 0],
+                [255, 255, 255],
+            ]
+        );
+    }
+}
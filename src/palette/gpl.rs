@@ -0,0 +1,78 @@
+//! GIMP palette files (`.gpl`).
+
+use super::Entry;
+
+/// Reads the `R G B name` lines of a GIMP palette. Colors without a name are
+/// named by their hex value.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err("missing 'GIMP Palette' header".to_string());
+    }
+    let mut colors = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line.trim();
+        let is_header = ["Name:", "Columns:"]
+            .iter()
+            .any(|key| line.starts_with(key));
+        if line.is_empty() || line.starts_with('#') || is_header {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let mut channel = || -> Result<u8, String> {
+            fields
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| format!("line {}: expected 'R G B name'", number + 2))
+        };
+        let rgb = [channel()?, channel()?, channel()?];
+        let name = fields.collect::<Vec<_>>().join(" ");
+        let name = if name.is_empty() {
+            format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+        } else {
+            name
+        };
+        colors.push((name, rgb));
+    }
+    Ok(colors)
+}
+
+/// Writes `colors` as a GIMP palette called `name`.
+pub fn write(name: &str, colors: &[Entry]) -> String {
+    let mut text = format!("GIMP Palette\nName: {name}\n#\n");
+    for (name, [r, g, b]) in colors {
+        text.push_str(&format!("{r:3} {g:3} {b:3}\t{name}\n"));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_colors_and_skips_headers() {
+        let text = "GIMP Palette\nName: Bricks\nColumns: 4\n# comment\n\
+                    255   0   0\tBright Red\n  0 128 255\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            [
+                ("Bright Red".to_string(), [255, 0, 0]),
+                ("#0080ff".to_string(), [0, 128, 255]),
+            ]
+        );
+        assert!(parse("not a palette").is_err());
+        assert!(parse("GIMP Palette\n12 oops\n").is_err());
+    }
+
+    #[test]
+    fn written_palettes_read_back() {
+        let colors = vec![
+            ("Bright Red".to_string(), [255, 0, 0]),
+            ("Black".to_string(), [0, 0, 0]),
+        ];
+        let text = write("Mosaic", &colors);
+        assert!(text.starts_with("GIMP Palette\nName: Mosaic\n"));
+        assert_eq!(parse(&text).unwrap(), colors);
+    }
+}
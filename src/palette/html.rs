@@ -0,0 +1,112 @@
+//! A standalone HTML page of swatches for `--palette-html-preview`.
+
+use super::ColorConfigs;
+use std::fmt::Write as _;
+
+/// Writes every color of `palette` as a labeled swatch with its hex code,
+/// count, and group if it has one, under a box that hides swatches whose
+/// name does not match. Styles and the filter script are inline so the page
+/// can be shared as a single file.
+pub fn write(title: &str, palette: &ColorConfigs) -> String {
+    let title = escape(title);
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )
+    .unwrap();
+    writeln!(html, "<title>{title}</title>").unwrap();
+    html.push_str(
+        "<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         #filter { font-size: 1em; padding: 0.4em; width: 20em; }\n\
+         .swatches { display: flex; flex-wrap: wrap; gap: 1em; margin-top: 1em; }\n\
+         .swatch { width: 10em; border: 1px solid #ccc; border-radius: 4px; }\n\
+         .swatch[hidden] { display: none; }\n\
+         .chip { height: 5em; border-radius: 4px 4px 0 0; }\n\
+         .label { padding: 0.4em; font-size: 0.9em; }\n\
+         .name { font-weight: bold; overflow-wrap: anywhere; }\n\
+         .group { color: #666; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    writeln!(html, "<h1>{title}</h1>").unwrap();
+    writeln!(
+        html,
+        "<p>{} colors, {} pieces.</p>",
+        palette.colors.len(),
+        palette
+            .colors
+            .iter()
+            .map(|config| config.count)
+            .sum::<u64>()
+    )
+    .unwrap();
+    html.push_str("<input id=\"filter\" type=\"search\" placeholder=\"Filter by name\">\n");
+    html.push_str("<div class=\"swatches\">\n");
+    for config in &palette.colors {
+        let hex = format!("#{:02x}{:02x}{:02x}", config.r, config.g, config.b);
+        let name = escape(&config.name);
+        let group = config.group.as_ref().map_or(String::new(), |group| {
+            format!("<div class=\"group\">Group: {}</div>", escape(group))
+        });
+        writeln!(
+            html,
+            "<div class=\"swatch\" data-name=\"{}\">\
+             <div class=\"chip\" style=\"background: {hex}\"></div>\
+             <div class=\"label\"><div class=\"name\">{name}</div>\
+             <div>{hex}</div><div>Count: {}</div>{group}</div></div>",
+            escape(&config.name.to_lowercase()),
+            config.count
+        )
+        .unwrap();
+    }
+    html.push_str("</div>\n");
+    html.push_str(
+        "<script>\n\
+         document.getElementById('filter').addEventListener('input', function () {\n\
+         \x20 var query = this.value.toLowerCase();\n\
+         \x20 document.querySelectorAll('.swatch').forEach(function (swatch) {\n\
+         \x20   swatch.hidden = !swatch.dataset.name.includes(query);\n\
+         \x20 });\n\
+         });\n\
+         </script>\n</body>\n</html>\n",
+    );
+    html
+}
+
+/// Escapes text for use in HTML content and quoted attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    #[test]
+    fn every_color_gets_an_escaped_swatch() {
+        let color = |name: &str, r, count| ColorConfig::new(name.to_string(), r, 0, 0, count);
+        let palette = ColorConfigs {
+            colors: vec![
+                ColorConfig {
+                    group: Some("Reds & Pinks".to_string()),
+                    ..color("Red", 255, 3)
+                },
+                color("<b>\"Dark\" & Red</b>", 64, 1),
+            ],
+        };
+        let html = write("colors.json", &palette);
+        assert_eq!(html.matches("class=\"swatch\"").count(), 2);
+        assert!(html.contains("#ff0000") && html.contains("Count: 3"));
+        assert!(html.contains("&lt;b&gt;&quot;Dark&quot; &amp; Red&lt;/b&gt;"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("2 colors, 4 pieces."));
+        // Only the color with a group shows one.
+        assert_eq!(html.matches("class=\"group\"").count(), 1);
+        assert!(html.contains("Group: Reds &amp; Pinks"));
+    }
+}
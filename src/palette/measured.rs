@@ -0,0 +1,49 @@
+//! Measured physical colors for `--palette-validate-physical`: a CSV of
+//! `name,r,g,b` lines, as read off real beads or bricks with a colorimeter
+//! or a calibrated photo.
+
+use super::Entry;
+
+/// Reads the `name,r,g,b` lines of a measurements CSV. A first line of
+/// column names, blank lines, and lines starting with `#` are skipped.
+/// Names may hold commas, since the channels are taken from the right.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut colors = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.rsplitn(4, ',').map(str::trim);
+        let mut channel = || fields.next().and_then(|value| value.parse::<u8>().ok());
+        let (b, g, r) = (channel(), channel(), channel());
+        let name = fields.next().map(|name| name.trim_matches('"'));
+        match (name, r, g, b) {
+            (Some(name), Some(r), Some(g), Some(b)) if !name.is_empty() => {
+                colors.push((name.to_string(), [r, g, b]))
+            }
+            _ if number == 0 => {}
+            _ => return Err(format!("line {}: expected 'name,r,g,b'", number + 1)),
+        }
+    }
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_rows_after_a_header() {
+        let text =
+            "name,r,g,b\nBright Red, 201,26,9\n\n# lighter than sold\n\"Tan, light\",228,205,158\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            [
+                ("Bright Red".to_string(), [201, 26, 9]),
+                ("Tan, light".to_string(), [228, 205, 158]),
+            ]
+        );
+        assert!(parse("Red,1,2,3\nBlue,1,2\n").is_err());
+    }
+}
@@ -0,0 +1,862 @@
+mod aco;
+mod css;
+mod gpl;
+mod html;
+mod measured;
+mod schema;
+
+use crate::assign::working_color;
+use crate::metric::{self, ColorMetric};
+use crate::status::{self, Failure};
+use crate::{X_SIZE, Y_SIZE};
+use image::{RgbImage, RgbaImage};
+use nannou::rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+
+pub use gpl::write as write_gpl;
+pub use html::write as write_html;
+pub use schema::{schema, unknown_fields, validate};
+
+/// How close in sRGB two `--palette-from-image` samples may be before the
+/// later one is dropped.
+const SAMPLE_MERGE_DISTANCE: f32 = 10.0;
+
+/// A color read from a palette format without counts.
+type Entry = (String, [u8; 3]);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ColorConfigs {
+    pub colors: Vec<ColorConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ColorConfig {
+    pub name: String,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// Pieces available; palettes may leave it out for `--auto-count`.
+    #[serde(default)]
+    pub count: u64,
+    /// Price of a single piece, used for cost estimates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    /// How tall 3-D exports make this color's tiles, in millimetres.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height_mm: Option<f64>,
+    /// The Perler bead code `--export-fuse-beads` prints for this color,
+    /// such as `P05`, when it is not found by name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perler_code: Option<String>,
+    /// The DMC thread `--export-cross-stitch` lists for this color, such as
+    /// `DMC-321`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dmc_code: Option<String>,
+    /// The BrickLink color ID `--export-color-book` prints for this color.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bricklink_code: Option<String>,
+    /// The family `--export-color-book` files this color under, such as
+    /// `Reds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Where to buy this color, listed and encoded as a QR code by
+    /// `--export-pdf-bom`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purchase_url: Option<String>,
+    /// This color converted to the matcher's working space.
+    #[serde(skip)]
+    pub working: [f32; 3],
+    /// This color in CIELAB, for tiles matched by `ColorMetric::Lab`.
+    #[serde(skip)]
+    pub lab: [f32; 3],
+}
+
+impl ColorConfig {
+    /// A color with `count` pieces and none of the optional details.
+    pub fn new(name: String, r: u8, g: u8, b: u8, count: u64) -> ColorConfig {
+        ColorConfig {
+            name,
+            r,
+            g,
+            b,
+            count,
+            price: None,
+            height_mm: None,
+            perler_code: None,
+            dmc_code: None,
+            bricklink_code: None,
+            group: None,
+            purchase_url: None,
+            working: [0.0; 3],
+            lab: [0.0; 3],
+        }
+    }
+
+    pub fn decrement(&mut self) {
+        self.count -= 1;
+    }
+}
+
+impl ColorConfigs {
+    /// Reads a JSON palette. With `strict`, fields the palette schema does
+    /// not describe are errors rather than passed over.
+    pub fn load(path: &str, strict: bool) -> ColorConfigs {
+        ColorConfigs::try_load(path, strict).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// `load`, returning what went wrong rather than panicking, for reloads
+    /// that may catch the file half-saved.
+    pub fn try_load(path: &str, strict: bool) -> Result<ColorConfigs, String> {
+        let mut buff = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut buff))
+            .map_err(|err| format!("Could not read color data file {path}: {err}"))?;
+        let unparseable = |err: serde_json::Error| format!("JSON in {path} not parseable: {err}");
+        if strict {
+            let value: serde_json::Value = serde_json::from_str(&buff).map_err(unparseable)?;
+            let unknown = unknown_fields(&value);
+            if !unknown.is_empty() {
+                return Err(format!(
+                    "Unknown fields in {path}, which would have no effect: {}",
+                    unknown.join(", ")
+                ));
+            }
+        }
+        let mut color_configs: ColorConfigs = serde_json::from_str(&buff).map_err(unparseable)?;
+        color_configs.prepare();
+        Ok(color_configs)
+    }
+
+    /// Reads a GIMP `.gpl` or Photoshop `.aco` palette. Neither format has
+    /// counts, so each color takes its count from `overlay` by name, falling
+    /// back to `per_color`.
+    pub fn import(
+        path: &str,
+        per_color: Option<u64>,
+        overlay: &HashMap<String, u64>,
+    ) -> ColorConfigs {
+        ColorConfigs::try_import(path, per_color, overlay).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// `import`, returning what went wrong rather than panicking.
+    pub fn try_import(
+        path: &str,
+        per_color: Option<u64>,
+        overlay: &HashMap<String, u64>,
+    ) -> Result<ColorConfigs, String> {
+        let unreadable = |err: String| format!("Could not read {path}: {err}");
+        let entries = if path.ends_with(".aco") {
+            let bytes = fs::read(path).map_err(|err| unreadable(err.to_string()))?;
+            let (entries, skipped) = aco::parse(&bytes).map_err(unreadable)?;
+            if !skipped.is_empty() {
+                log::warn!(
+                    "Skipped {} swatches in {path} with no RGB conversion: {}",
+                    skipped.len(),
+                    skipped.join(", ")
+                );
+            }
+            entries
+        } else {
+            let text = fs::read_to_string(path).map_err(|err| unreadable(err.to_string()))?;
+            gpl::parse(&text).map_err(unreadable)?
+        };
+
+        let mut uncounted = Vec::new();
+        let colors = entries
+            .into_iter()
+            .map(|(name, [r, g, b])| {
+                let count = match overlay.get(&name).copied().or(per_color) {
+                    Some(count) => count,
+                    None => {
+                        uncounted.push(name.clone());
+                        0
+                    }
+                };
+                ColorConfig::new(name, r, g, b, count)
+            })
+            .collect();
+        if !uncounted.is_empty() {
+            log::warn!(
+                "No count for {} colors in {path}, so they are unused: {}",
+                uncounted.len(),
+                uncounted.join(", ")
+            );
+        }
+        let mut color_configs = ColorConfigs { colors };
+        color_configs.prepare();
+        Ok(color_configs)
+    }
+
+    /// Converts every color into the matcher's working space. Call again
+    /// whenever a color's RGB value changes.
+    pub fn prepare(&mut self) {
+        for config in self.colors.iter_mut() {
+            config.working = working_color(config.r, config.g, config.b);
+            config.lab = metric::lab(config.r, config.g, config.b);
+        }
+    }
+
+    pub fn save(&self, path: &str) {
+        let json = serde_json::to_string_pretty(self).expect("Palette should serialize.");
+        fs::write(path, json).expect("Unable to write palette file.");
+    }
+
+    /// Keeps at most `n` colors chosen by farthest-point sampling: the color
+    /// with the largest count first, then repeatedly whichever remaining
+    /// color is farthest in RGB from every color kept so far. Each dropped
+    /// color's count goes to the nearest kept color.
+    pub fn reduce(&self, n: usize) -> ColorConfigs {
+        if n == 0 {
+            panic!("--color-reduce needs at least one color")
+        }
+        if self.colors.len() <= n {
+            return self.clone();
+        }
+        let rgb_distance = |a: &ColorConfig, b: &ColorConfig| {
+            let (dr, dg, db) = (
+                a.r as f32 - b.r as f32,
+                a.g as f32 - b.g as f32,
+                a.b as f32 - b.b as f32,
+            );
+            dr * dr + dg * dg + db * db
+        };
+        let first = (0..self.colors.len())
+            .max_by_key(|&index| (self.colors[index].count, Reverse(index)))
+            .expect("Palette should not be empty.");
+        let mut kept = vec![first];
+        // Distance from each color to the nearest kept one.
+        let mut nearest: Vec<f32> = self
+            .colors
+            .iter()
+            .map(|config| rgb_distance(config, &self.colors[first]))
+            .collect();
+        while kept.len() < n {
+            let (farthest, _) = nearest
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !kept.contains(index))
+                .max_by(|a, b| a.1.total_cmp(b.1).then(b.0.cmp(&a.0)))
+                .expect("Should have colors left to keep.");
+            kept.push(farthest);
+            for (config, dist) in self.colors.iter().zip(nearest.iter_mut()) {
+                *dist = dist.min(rgb_distance(config, &self.colors[farthest]));
+            }
+        }
+        kept.sort();
+
+        let mut reduced = ColorConfigs {
+            colors: kept
+                .iter()
+                .map(|&index| self.colors[index].clone())
+                .collect(),
+        };
+        for (index, config) in self.colors.iter().enumerate() {
+            if kept.contains(&index) {
+                continue;
+            }
+            let target = reduced
+                .colors
+                .iter_mut()
+                .min_by(|a, b| rgb_distance(config, a).total_cmp(&rgb_distance(config, b)))
+                .expect("Reduced palette should not be empty.");
+            target.count += config.count;
+        }
+        reduced
+    }
+
+    /// Rounds every channel to the nearest of `levels` evenly spaced values,
+    /// multiples of `256 / levels` with 256 taken as 255. Colors that land
+    /// on the same value are merged into the first of them, counts summed.
+    pub fn quantize(&self, levels: u32) -> ColorConfigs {
+        if !(1..=256).contains(&levels) {
+            panic!("--quantize-palette needs 1 to 256 levels")
+        }
+        let step = 256.0 / levels as f32;
+        let round = |c: u8| ((c as f32 / step).round() * step).min(255.0) as u8;
+        let mut quantized = ColorConfigs { colors: Vec::new() };
+        for config in &self.colors {
+            let (r, g, b) = (round(config.r), round(config.g), round(config.b));
+            match quantized
+                .colors
+                .iter_mut()
+                .find(|kept| (kept.r, kept.g, kept.b) == (r, g, b))
+            {
+                Some(kept) => kept.count += config.count,
+                None => quantized.colors.push(ColorConfig {
+                    r,
+                    g,
+                    b,
+                    ..config.clone()
+                }),
+            }
+        }
+        quantized.prepare();
+        quantized
+    }
+
+    /// Every channel raised to `1 / gamma` as a fraction of 255, for
+    /// pieces whose colors respond with a different gamma than sRGB.
+    pub fn gamma_corrected(&self, gamma: f32) -> ColorConfigs {
+        if !(gamma > 0.0 && gamma.is_finite()) {
+            panic!("--gamma-palette must be a positive number")
+        }
+        let correct = |c: u8| ((c as f32 / 255.0).powf(1.0 / gamma) * 255.0).round() as u8;
+        let mut corrected = self.clone();
+        for config in &mut corrected.colors {
+            config.r = correct(config.r);
+            config.g = correct(config.g);
+            config.b = correct(config.b);
+        }
+        corrected.prepare();
+        corrected
+    }
+
+    /// Scales every count so they sum to `total`, rounding down and then
+    /// giving the pieces left over to the colors with the largest remainders
+    /// (the largest remainder method). Ties go to the earlier color.
+    pub fn normalize(&self, total: u64) -> ColorConfigs {
+        let declared: u64 = self.colors.iter().map(|config| config.count).sum();
+        if declared == 0 {
+            panic!("--normalize-counts needs a palette with some pieces in it")
+        }
+        let scaled: Vec<(u64, u128)> = self
+            .colors
+            .iter()
+            .map(|config| {
+                let exact = config.count as u128 * total as u128;
+                ((exact / declared as u128) as u64, exact % declared as u128)
+            })
+            .collect();
+        let mut normalized = self.clone();
+        for (config, &(count, _)) in normalized.colors.iter_mut().zip(&scaled) {
+            config.count = count;
+        }
+        let left_over = total - scaled.iter().map(|&(count, _)| count).sum::<u64>();
+        let mut by_remainder: Vec<usize> = (0..scaled.len()).collect();
+        by_remainder.sort_by_key(|&index| Reverse(scaled[index].1));
+        for &index in &by_remainder[..left_over as usize] {
+            normalized.colors[index].count += 1;
+        }
+        normalized
+    }
+
+    /// Sets aside `count` pieces of the color called `name` for use outside
+    /// the grid, returning its index.
+    pub fn reserve(&mut self, name: &str, count: u64) -> usize {
+        let index = self
+            .colors
+            .iter()
+            .position(|config| config.name == name)
+            .unwrap_or_else(|| panic!("Palette has no color named '{name}'"));
+        let config = &mut self.colors[index];
+        if config.count < count {
+            let shortfall = json!({ "color": name, "needed": count, "available": config.count });
+            status::classify(Failure::Inventory, json!({ "shortfall": [shortfall] }));
+            panic!(
+                "Not enough {name}: {count} needed outside the grid but only {} available",
+                config.count
+            )
+        }
+        config.count -= count;
+        index
+    }
+
+    /// Generates `n` distinct random colors named `rnd_0..rnd_{n-1}` whose
+    /// counts split the grid as evenly as possible.
+    pub fn random(n: u64, rng: &mut impl Rng) -> ColorConfigs {
+        if n == 0 {
+            panic!("--random-palette needs at least one color")
+        }
+        let mut seen: HashSet<[u8; 3]> = HashSet::new();
+        let mut colors = Vec::new();
+        while (colors.len() as u64) < n {
+            let rgb: [u8; 3] = [rng.gen(), rng.gen(), rng.gen()];
+            if seen.insert(rgb) {
+                colors.push(rgb);
+            }
+        }
+        ColorConfigs::evenly("rnd", &colors)
+    }
+
+    /// Colors averaged over `n` equal areas of `image`, read in rows from
+    /// the top, with those within `SAMPLE_MERGE_DISTANCE` of an earlier one
+    /// dropped and the grid's tiles shared evenly among the rest.
+    pub fn from_image(image: &RgbImage, n: u64) -> ColorConfigs {
+        if n == 0 {
+            panic!("--palette-from-image needs at least one color")
+        }
+        let (width, height) = image.dimensions();
+        // Rows as near square to the picture's shape as `n` allows; each
+        // holds `n / rows` areas or one more, and is as tall as its share
+        // of `n`, so that every area is the same size.
+        let rows = ((n as f64 * height as f64 / width as f64).sqrt().round() as u64).clamp(1, n);
+        let mut samples: Vec<[u8; 3]> = Vec::new();
+        let mut done = 0;
+        for row in 0..rows {
+            let columns = n / rows + u64::from(row < n % rows);
+            let edge = |areas: u64, across: u32| (areas * across as u64 / n) as u32;
+            let (top, bottom) = (edge(done, height), edge(done + columns, height));
+            done += columns;
+            for column in 0..columns {
+                let left = (column * width as u64 / columns) as u32;
+                let right = ((column + 1) * width as u64 / columns) as u32;
+                // Areas narrower than a pixel take the one they start in.
+                let (right, bottom) = (right.max(left + 1), bottom.max(top + 1));
+                let mut sum = [0u64; 3];
+                for y in top..bottom.min(height) {
+                    for x in left..right.min(width) {
+                        for (total, &channel) in sum.iter_mut().zip(&image.get_pixel(x, y).0) {
+                            *total += channel as u64;
+                        }
+                    }
+                }
+                let pixels = ((right.min(width) - left) * (bottom.min(height) - top)) as u64;
+                let mean = sum.map(|total| ((total + pixels / 2) / pixels) as u8);
+                let near = |kept: &[u8; 3]| {
+                    let distance: f32 = (0..3)
+                        .map(|c| (kept[c] as f32 - mean[c] as f32).powi(2))
+                        .sum();
+                    distance.sqrt() <= SAMPLE_MERGE_DISTANCE
+                };
+                if !samples.iter().any(near) {
+                    samples.push(mean);
+                }
+            }
+        }
+        ColorConfigs::evenly("sample", &samples)
+    }
+
+    /// `n` colors evenly spaced from `from` to `to`, both included, in the
+    /// space `space` measures in, with the grid's tiles shared evenly.
+    pub fn gradient(from: [u8; 3], to: [u8; 3], n: u64, space: ColorMetric) -> ColorConfigs {
+        if n == 0 {
+            panic!("--palette-gradient needs at least one color")
+        }
+        let colors: Vec<[u8; 3]> = (0..n)
+            .map(|step| {
+                let t = if n == 1 {
+                    0.0
+                } else {
+                    step as f32 / (n - 1) as f32
+                };
+                let mix = |a: [f32; 3], b: [f32; 3]| {
+                    [0, 1, 2].map(|channel| a[channel] + (b[channel] - a[channel]) * t)
+                };
+                match space {
+                    ColorMetric::Rgb => mix(from.map(f32::from), to.map(f32::from))
+                        .map(|channel| channel.round() as u8),
+                    ColorMetric::Lab => metric::srgb_from_lab(mix(
+                        metric::lab(from[0], from[1], from[2]),
+                        metric::lab(to[0], to[1], to[2]),
+                    )),
+                }
+            })
+            .collect();
+        ColorConfigs::evenly("gradient", &colors)
+    }
+
+    /// Every distinct color literal in the stylesheet at `path`, named
+    /// `css_{hex}` and sharing the grid's tiles evenly.
+    pub fn from_css(path: &str) -> ColorConfigs {
+        let text = fs::read_to_string(path).expect("Could not open stylesheet.");
+        let colors = css::parse(&text);
+        if colors.is_empty() {
+            panic!("{path} has no color literals for --palette-from-css")
+        }
+        let named: Vec<Entry> = colors
+            .into_iter()
+            .map(|[r, g, b]| (format!("css_{r:02x}{g:02x}{b:02x}"), [r, g, b]))
+            .collect();
+        ColorConfigs::evenly_named(&named)
+    }
+
+    /// Every distinct opaque pixel of the swatch image at `path`, such as a
+    /// Lospec palette PNG, named by its hex value and sharing the grid's
+    /// tiles evenly. Images with more than `max` colors are refused.
+    pub fn from_png(path: &str, max: usize) -> ColorConfigs {
+        let image = image::open(path)
+            .unwrap_or_else(|err| panic!("Could not open {path}: {err}"))
+            .to_rgba8();
+        let colors = swatches(&image);
+        if colors.is_empty() {
+            panic!("{path} has no opaque pixels for --palette-from-png")
+        }
+        if colors.len() > max {
+            panic!(
+                "{path} has {} colors, more than --max-palette-colors {max}; is it a picture \
+                 rather than a palette?",
+                colors.len()
+            )
+        }
+        let named: Vec<Entry> = colors
+            .into_iter()
+            .map(|[r, g, b]| (format!("#{r:02x}{g:02x}{b:02x}"), [r, g, b]))
+            .collect();
+        ColorConfigs::evenly_named(&named)
+    }
+
+    /// `colors` named `{prefix}_0` onwards, sharing the grid's tiles evenly.
+    fn evenly(prefix: &str, colors: &[[u8; 3]]) -> ColorConfigs {
+        let named: Vec<Entry> = colors
+            .iter()
+            .enumerate()
+            .map(|(index, &rgb)| (format!("{prefix}_{index}"), rgb))
+            .collect();
+        ColorConfigs::evenly_named(&named)
+    }
+
+    fn evenly_named(colors: &[Entry]) -> ColorConfigs {
+        let total = X_SIZE * Y_SIZE;
+        let n = colors.len() as u64;
+        let colors = colors
+            .iter()
+            .enumerate()
+            .map(|(index, &(ref name, [r, g, b]))| {
+                ColorConfig::new(
+                    name.clone(),
+                    r,
+                    g,
+                    b,
+                    total / n + u64::from((index as u64) < total % n),
+                )
+            })
+            .collect();
+        let mut color_configs = ColorConfigs { colors };
+        color_configs.prepare();
+        color_configs
+    }
+
+    /// Colors whose `measured` values lie more than `tolerance` from the
+    /// palette's in sRGB, in palette order, with the names measured but not
+    /// in the palette.
+    pub fn physical_mismatches(
+        &self,
+        measured: &[(String, [u8; 3])],
+        tolerance: f32,
+    ) -> (Vec<Mismatch>, Vec<String>) {
+        let mismatches = self
+            .colors
+            .iter()
+            .filter_map(|config| {
+                let (_, actual) = measured.iter().find(|(name, _)| *name == config.name)?;
+                let nominal = [config.r, config.g, config.b];
+                let distance = nominal
+                    .iter()
+                    .zip(actual)
+                    .map(|(&a, &b)| (a as f32 - b as f32).powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+                (distance > tolerance).then(|| Mismatch {
+                    name: config.name.clone(),
+                    nominal,
+                    measured: *actual,
+                    distance,
+                })
+            })
+            .collect();
+        let unknown = measured
+            .iter()
+            .filter(|(name, _)| !self.colors.iter().any(|config| config.name == *name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        (mismatches, unknown)
+    }
+
+    /// How much of the sRGB cube lies near some palette color.
+    pub fn coverage(&self) -> Coverage {
+        let mut covered = 0;
+        let mut emptiest: Option<([u8; 3], f32)> = None;
+        for i in 0..COVERAGE_CELLS {
+            for j in 0..COVERAGE_CELLS {
+                for k in 0..COVERAGE_CELLS {
+                    let center = [i, j, k].map(|c| (c * CELL_SIZE + CELL_SIZE / 2) as u8);
+                    let nearest = self
+                        .colors
+                        .iter()
+                        .map(|config| {
+                            let r = config.r as f32 - center[0] as f32;
+                            let g = config.g as f32 - center[1] as f32;
+                            let b = config.b as f32 - center[2] as f32;
+                            (r * r + g * g + b * b).sqrt()
+                        })
+                        .fold(f32::MAX, f32::min);
+                    if nearest <= COVERAGE_RADIUS {
+                        covered += 1;
+                    } else if emptiest.is_none_or(|(_, dist)| nearest > dist) {
+                        emptiest = Some((center, nearest));
+                    }
+                }
+            }
+        }
+        Coverage {
+            covered,
+            total: COVERAGE_CELLS.pow(3),
+            emptiest: emptiest.map(|(center, _)| center),
+        }
+    }
+}
+
+/// Whether `path` is a palette format read by `ColorConfigs::import`.
+pub fn is_imported(path: &str) -> bool {
+    path.ends_with(".gpl") || path.ends_with(".aco")
+}
+
+/// The distinct colors of `image`'s opaque pixels, read in rows from the
+/// top. Fully transparent pixels, as around some swatch layouts, are left
+/// out.
+fn swatches(image: &RgbaImage) -> Vec<[u8; 3]> {
+    let mut seen: HashSet<[u8; 3]> = HashSet::new();
+    image
+        .pixels()
+        .filter(|pixel| pixel.0[3] > 0)
+        .map(|pixel| [pixel.0[0], pixel.0[1], pixel.0[2]])
+        .filter(|&rgb| seen.insert(rgb))
+        .collect()
+}
+
+/// Reads a `{"name": count}` JSON map of counts for an imported palette.
+pub fn load_counts(path: &str) -> HashMap<String, u64> {
+    let text = fs::read_to_string(path).expect("Could not open count overlay file.");
+    serde_json::from_str(&text).expect("Count overlay should map color names to counts.")
+}
+
+/// Reads a `name,r,g,b` CSV of measured physical colors.
+pub fn load_measurements(path: &str) -> Vec<Entry> {
+    let text = fs::read_to_string(path).expect("Could not open color measurements file.");
+    measured::parse(&text).unwrap_or_else(|err| panic!("Could not read {path}: {err}"))
+}
+
+/// A palette color whose measured value is off, from
+/// `ColorConfigs::physical_mismatches`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub name: String,
+    pub nominal: [u8; 3],
+    pub measured: [u8; 3],
+    /// How far apart the two are in sRGB.
+    pub distance: f32,
+}
+
+/// Cells per channel when sampling the cube for `coverage`.
+const COVERAGE_CELLS: u32 = 16;
+const CELL_SIZE: u32 = 256 / COVERAGE_CELLS;
+/// A cell counts as covered when a palette color is this close to its center.
+const COVERAGE_RADIUS: f32 = 30.0;
+
+/// Result of `ColorConfigs::coverage`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    pub covered: u32,
+    pub total: u32,
+    /// Center of the uncovered cell farthest from every palette color.
+    pub emptiest: Option<[u8; 3]>,
+}
+
+/// Hue in degrees, saturation and value in 0..=1.
+pub fn hsv(rgb: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(r: u8, g: u8, b: u8) -> ColorConfigs {
+        ColorConfigs {
+            colors: vec![ColorConfig::new("Only".to_string(), r, g, b, 1)],
+        }
+    }
+
+    #[test]
+    fn coverage_counts_cells_near_palette() {
+        // A color on a cell center reaches the whole 3x3x3 block around it:
+        // even the corner cells are only 16 * sqrt(3) ~ 27.7 away.
+        let coverage = single(136, 136, 136).coverage();
+        assert_eq!(coverage.covered, 27);
+        assert_eq!(coverage.total, 4096);
+        assert_eq!(coverage.emptiest, Some([8, 8, 8]));
+    }
+
+    #[test]
+    fn reduce_keeps_spread_colors_and_moves_counts() {
+        let color = |name: &str, r, g, b, count| ColorConfig::new(name.to_string(), r, g, b, count);
+        let palette = ColorConfigs {
+            colors: vec![
+                color("Black", 0, 0, 0, 10),
+                color("Charcoal", 20, 20, 20, 5),
+                color("White", 255, 255, 255, 50),
+                color("Snow", 250, 250, 250, 3),
+                color("Red", 200, 0, 0, 7),
+            ],
+        };
+        let reduced = palette.reduce(3);
+        let kept: Vec<(&str, u64)> = reduced
+            .colors
+            .iter()
+            .map(|config| (config.name.as_str(), config.count))
+            .collect();
+        assert_eq!(kept, [("Black", 15), ("White", 53), ("Red", 7)]);
+        assert_eq!(palette.reduce(10).colors.len(), 5);
+    }
+
+    #[test]
+    fn physical_mismatches_flag_colors_past_tolerance() {
+        let palette = single(200, 0, 0);
+        let measured = |rgb| vec![("Only".to_string(), rgb), ("Gone".to_string(), rgb)];
+        let (mismatches, unknown) = palette.physical_mismatches(&measured([190, 0, 0]), 20.0);
+        assert!(mismatches.is_empty());
+        assert_eq!(unknown, ["Gone"]);
+        let (mismatches, _) = palette.physical_mismatches(&measured([188, 16, 12]), 20.0);
+        assert_eq!(
+            mismatches,
+            [Mismatch {
+                name: "Only".to_string(),
+                nominal: [200, 0, 0],
+                measured: [188, 16, 12],
+                distance: 23.323807,
+            }]
+        );
+    }
+
+    #[test]
+    fn quantize_rounds_channels_and_merges_duplicates() {
+        let color = |name: &str, r, g, b, count| ColorConfig::new(name.to_string(), r, g, b, count);
+        let palette = ColorConfigs {
+            colors: vec![
+                color("Navy", 10, 20, 100, 4),
+                color("Ink", 5, 25, 90, 2),
+                color("Snow", 250, 241, 239, 1),
+            ],
+        };
+        let quantized = palette.quantize(8);
+        let colors: Vec<(&str, u8, u8, u8, u64)> = quantized
+            .colors
+            .iter()
+            .map(|config| {
+                (
+                    config.name.as_str(),
+                    config.r,
+                    config.g,
+                    config.b,
+                    config.count,
+                )
+            })
+            .collect();
+        assert_eq!(colors, [("Navy", 0, 32, 96, 6), ("Snow", 255, 255, 224, 1)]);
+        assert_ne!(quantized.colors[0].working, [0.0; 3]);
+    }
+
+    #[test]
+    fn normalize_scales_counts_to_the_total() {
+        let palette = ColorConfigs {
+            colors: [("A", 1), ("B", 1), ("C", 1), ("D", 0)]
+                .iter()
+                .map(|&(name, count)| ColorConfig::new(name.to_string(), 0, 0, 0, count))
+                .collect(),
+        };
+        let counts = |palette: &ColorConfigs| -> Vec<u64> {
+            palette.colors.iter().map(|config| config.count).collect()
+        };
+        // 10 / 3 leaves one piece over, which goes to the first tied color.
+        assert_eq!(counts(&palette.normalize(10)), [4, 3, 3, 0]);
+        assert_eq!(counts(&palette.normalize(2304)), [768, 768, 768, 0]);
+        let uneven = ColorConfigs {
+            colors: palette
+                .colors
+                .iter()
+                .zip([5, 3, 1, 1])
+                .map(|(config, count)| ColorConfig {
+                    count,
+                    ..config.clone()
+                })
+                .collect(),
+        };
+        // Exact shares 3.5, 2.1, 0.7, 0.7: the two .7s beat the .5.
+        assert_eq!(counts(&uneven.normalize(7)), [3, 2, 1, 1]);
+    }
+
+    #[test]
+    fn hsv_of_primaries() {
+        assert_eq!(hsv([255, 0, 0]), (0.0, 1.0, 1.0));
+        assert_eq!(hsv([0, 0, 255]), (240.0, 1.0, 1.0));
+        assert_eq!(hsv([0, 0, 0]), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn image_samples_average_equal_areas() {
+        // Four quadrants, the bottom two within merging distance.
+        let image = RgbImage::from_fn(4, 4, |x, y| match (x < 2, y < 2) {
+            (true, true) => image::Rgb([200, 0, 0]),
+            (false, true) => image::Rgb([0, 0, 200]),
+            (_, false) => image::Rgb([20 + x as u8, 100, 20]),
+        });
+        let palette = ColorConfigs::from_image(&image, 4);
+        let colors: Vec<[u8; 3]> = palette
+            .colors
+            .iter()
+            .map(|config| [config.r, config.g, config.b])
+            .collect();
+        assert_eq!(colors, [[200, 0, 0], [0, 0, 200], [21, 100, 20]]);
+        assert_eq!(palette.colors[2].name, "sample_2");
+        let total: u64 = palette.colors.iter().map(|config| config.count).sum();
+        assert_eq!(total, X_SIZE * Y_SIZE);
+    }
+
+    #[test]
+    fn gradients_include_both_ends() {
+        let rgb = |palette: &ColorConfigs| -> Vec<[u8; 3]> {
+            palette
+                .colors
+                .iter()
+                .map(|config| [config.r, config.g, config.b])
+                .collect()
+        };
+        let red_to_blue = ColorConfigs::gradient([255, 0, 0], [0, 0, 255], 3, ColorMetric::Rgb);
+        assert_eq!(rgb(&red_to_blue), [[255, 0, 0], [128, 0, 128], [0, 0, 255]]);
+        assert_eq!(red_to_blue.colors[1].name, "gradient_1");
+        let lab = ColorConfigs::gradient([0, 0, 0], [255, 255, 255], 3, ColorMetric::Lab);
+        let colors = rgb(&lab);
+        assert_eq!((colors[0], colors[2]), ([0, 0, 0], [255, 255, 255]));
+        // Lab's midpoint is L* 50, the gray that looks halfway, which is
+        // darker than sRGB's.
+        assert_eq!(colors[1], [119, 119, 119]);
+    }
+
+    #[test]
+    fn gamma_keeps_the_ends_and_lifts_the_middle() {
+        let corrected = single(0, 128, 255).gamma_corrected(1.8);
+        let only = &corrected.colors[0];
+        assert_eq!((only.r, only.b), (0, 255));
+        assert_eq!(only.g, 174);
+        assert_eq!(single(0, 128, 255).gamma_corrected(1.0).colors[0].g, 128);
+    }
+
+    #[test]
+    fn swatches_keep_the_first_of_each_opaque_color() {
+        let image = RgbaImage::from_fn(3, 2, |x, y| match (x, y) {
+            (0, 0) | (2, 1) => image::Rgba([255, 0, 0, 255]),
+            (1, 0) => image::Rgba([0, 0, 255, 0]),
+            _ => image::Rgba([0, 255, 0, 255]),
+        });
+        assert_eq!(swatches(&image), [[255, 0, 0], [0, 255, 0]]);
+    }
+}
@@ -0,0 +1,250 @@
+//! The JSON Schema of palette files, printed by `--color-config-schema`.
+//!
+//! A test serializes a fully populated `ColorConfig` and checks its fields
+//! against the schema, so adding a field without describing it fails.
+//!
+//! `validate` checks a value against it for `--export-json-schema-validated`,
+//! and `unknown_fields` finds the keys it does not describe for
+//! `--parse-strict`, which the palette's own deserializing passes over.
+//! It understands only the keywords the schema uses: `type`, `required`,
+//! `properties`, `items`, `minimum`, `maximum`, and `$ref`s into its
+//! `definitions`; the rest, such as `format`, are descriptive and skipped.
+
+use serde_json::{json, Value};
+
+/// A draft-07 schema for `ColorConfigs`.
+pub fn schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "ColorConfigs",
+        "description": "A palette: the colors available and how many pieces of each there are.",
+        "type": "object",
+        "required": ["colors"],
+        "properties": {
+            "colors": {
+                "type": "array",
+                "items": { "$ref": "#/definitions/ColorConfig" }
+            }
+        },
+        "definitions": {
+            "ColorConfig": {
+                "type": "object",
+                "required": ["name", "r", "g", "b"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "r": { "type": "integer", "format": "uint8", "minimum": 0, "maximum": 255 },
+                    "g": { "type": "integer", "format": "uint8", "minimum": 0, "maximum": 255 },
+                    "b": { "type": "integer", "format": "uint8", "minimum": 0, "maximum": 255 },
+                    "count": {
+                        "description": "Pieces of this color available; 0 when left out, as palettes for --auto-count may.",
+                        "type": "integer",
+                        "format": "uint64",
+                        "minimum": 0
+                    },
+                    "price": {
+                        "description": "Price of a single piece, used for cost estimates.",
+                        "type": ["number", "null"],
+                        "format": "double"
+                    },
+                    "height_mm": {
+                        "description": "How tall 3-D exports make this color's tiles, in millimetres.",
+                        "type": ["number", "null"],
+                        "format": "double"
+                    },
+                    "perler_code": {
+                        "description": "Perler bead code printed by --export-fuse-beads, such as P05, for colors not named as in its bead table.",
+                        "type": ["string", "null"]
+                    },
+                    "dmc_code": {
+                        "description": "DMC thread number listed by --export-cross-stitch, such as DMC-321.",
+                        "type": ["string", "null"]
+                    },
+                    "bricklink_code": {
+                        "description": "BrickLink color ID printed in --export-color-book, such as 5 for Red.",
+                        "type": ["string", "null"]
+                    },
+                    "group": {
+                        "description": "Family the color is filed under in --export-color-book, such as Reds.",
+                        "type": ["string", "null"]
+                    },
+                    "purchase_url": {
+                        "description": "Where to buy the color, listed with a QR code in --export-pdf-bom.",
+                        "type": ["string", "null"]
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Everything in `value` that `schema()` rejects, each as the JSON Pointer
+/// of the offending value and what is wrong with it.
+pub fn validate(value: &Value) -> Vec<String> {
+    let root = schema();
+    let mut errors = Vec::new();
+    check(&root, &root, value, "", &mut errors);
+    errors
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference
+            .strip_prefix("#/definitions/")
+            .expect("The schema only refers to its own definitions.");
+        return check(root, &root["definitions"][name], value, at, errors);
+    }
+    let at_or_root = if at.is_empty() { "/" } else { at };
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|&kind| is_type(value, kind)) {
+            errors.push(format!("{at_or_root}: expected {}", types.join(" or ")));
+            return;
+        }
+    }
+    if let Some(number) = value.as_f64() {
+        if schema["minimum"]
+            .as_f64()
+            .is_some_and(|minimum| number < minimum)
+        {
+            errors.push(format!(
+                "{at_or_root}: {number} is below {}",
+                schema["minimum"]
+            ));
+        }
+        if schema["maximum"]
+            .as_f64()
+            .is_some_and(|maximum| number > maximum)
+        {
+            errors.push(format!(
+                "{at_or_root}: {number} is above {}",
+                schema["maximum"]
+            ));
+        }
+    }
+    if let Some(object) = value.as_object() {
+        for required in schema["required"].as_array().into_iter().flatten() {
+            let key = required.as_str().unwrap_or_default();
+            if !object.contains_key(key) {
+                errors.push(format!("{at_or_root}: missing \"{key}\""));
+            }
+        }
+        if let Some(properties) = schema["properties"].as_object() {
+            for (key, property) in properties {
+                if let Some(member) = object.get(key) {
+                    check(root, property, member, &format!("{at}/{key}"), errors);
+                }
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            check(root, items, item, &format!("{at}/{index}"), errors);
+        }
+    }
+}
+
+/// The JSON Pointer of every member of `value` that `schema()` has no
+/// property for, such as a misspelled optional field.
+pub fn unknown_fields(value: &Value) -> Vec<String> {
+    let root = schema();
+    let mut unknown = Vec::new();
+    find_unknown(&root, &root, value, "", &mut unknown);
+    unknown
+}
+
+fn find_unknown(root: &Value, schema: &Value, value: &Value, at: &str, unknown: &mut Vec<String>) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference
+            .strip_prefix("#/definitions/")
+            .expect("The schema only refers to its own definitions.");
+        return find_unknown(root, &root["definitions"][name], value, at, unknown);
+    }
+    if let (Some(properties), Some(object)) = (schema["properties"].as_object(), value.as_object())
+    {
+        for (key, member) in object {
+            let at = format!("{at}/{key}");
+            match properties.get(key) {
+                Some(property) => find_unknown(root, property, member, &at, unknown),
+                None => unknown.push(at),
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            find_unknown(root, items, item, &format!("{at}/{index}"), unknown);
+        }
+    }
+}
+
+fn is_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    #[test]
+    fn schema_lists_every_serialized_field() {
+        let config = ColorConfig {
+            price: Some(0.1),
+            height_mm: Some(3.2),
+            perler_code: Some("P05".to_string()),
+            dmc_code: Some("DMC-321".to_string()),
+            bricklink_code: Some("5".to_string()),
+            group: Some("Reds".to_string()),
+            purchase_url: Some("https://example.com/red".to_string()),
+            ..ColorConfig::new("Red".to_string(), 200, 0, 0, 3)
+        };
+        let serialized = serde_json::to_value(config).unwrap();
+        let mut fields: Vec<&String> = serialized.as_object().unwrap().keys().collect();
+        let schema = schema();
+        let properties = &schema["definitions"]["ColorConfig"]["properties"];
+        let mut described: Vec<&String> = properties.as_object().unwrap().keys().collect();
+        fields.sort();
+        described.sort();
+        assert_eq!(fields, described);
+    }
+
+    #[test]
+    fn validation_reports_where_and_why() {
+        let palette =
+            json!({ "colors": [{ "name": "Red", "r": 200, "g": 0, "b": 0, "count": 3 }] });
+        assert!(validate(&palette).is_empty());
+        let bad = json!({ "colors": [{ "r": 300, "g": "0", "b": 0, "price": null }] });
+        assert_eq!(
+            validate(&bad),
+            [
+                "/colors/0: missing \"name\"",
+                "/colors/0/g: expected integer",
+                "/colors/0/r: 300 is above 255",
+            ]
+        );
+        assert_eq!(validate(&json!([])), ["/: expected object"]);
+    }
+
+    #[test]
+    fn unknown_fields_are_found_at_any_depth() {
+        let palette = json!({
+            "colours": [],
+            "colors": [{ "name": "Red", "r": 200, "g": 0, "b": 0, "bordre_color": "black" }]
+        });
+        assert_eq!(
+            unknown_fields(&palette),
+            ["/colors/0/bordre_color", "/colours"]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! The `--interactive-palette-editor` window: every palette color as a
+//! swatch with buttons that change its declared count.
+//!
+//! The counts live in an `Arc<Mutex<ColorConfigs>>` the editor window edits
+//! and each solve copies. After a change the mosaic re-solves from scratch
+//! on a background thread, and the main window shows the result once it
+//! lands; changes made meanwhile are picked up by the next solve.
+
+use crate::palette::ColorConfigs;
+use crate::{assign, finish_resolve, place_fresh, prepare_resolve, render, FreshSolve};
+use crate::{Model, Shortfall, Solved};
+use nannou::prelude::*;
+use nannou::window;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 640;
+const HEADER_HEIGHT: f32 = 24.0;
+const MAX_ROW_HEIGHT: f32 = 22.0;
+/// Side of the `-` and `+` buttons, at their largest.
+const BUTTON: f32 = 18.0;
+
+pub struct PaletteEditor {
+    window: window::Id,
+    /// Declared counts of the palette file's colors as edited here.
+    palette: Arc<Mutex<ColorConfigs>>,
+    /// Whether the counts changed since the last solve started.
+    dirty: bool,
+    solving: Option<Solving>,
+    /// Why the last edit was not solved, shown until the next one.
+    shortfall: Option<Shortfall>,
+}
+
+/// A solve in flight.
+struct Solving {
+    result: Arc<Mutex<Option<Solved>>>,
+    /// The frame reserved from the palette the solve started with.
+    frame: Option<render::Frame>,
+}
+
+/// Opens the editor window beside the mosaic.
+pub fn open(app: &App, model: &Model) -> PaletteEditor {
+    let window = app
+        .new_window()
+        .title("Palette")
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .mouse_pressed(mouse_pressed)
+        .build()
+        .expect("Unable to open the palette editor window.");
+    PaletteEditor {
+        window,
+        palette: Arc::new(Mutex::new(declared(model))),
+        dirty: false,
+        solving: None,
+        shortfall: None,
+    }
+}
+
+/// Whether `id` is the editor window, whose events the mosaic ignores.
+pub fn owns(model: &Model, id: window::Id) -> bool {
+    model
+        .palette_editor
+        .as_ref()
+        .is_some_and(|editor| editor.window == id)
+}
+
+/// Whether the editor window has focus, so the mouse is over it.
+pub fn has_focus(app: &App, model: &Model) -> bool {
+    model
+        .palette_editor
+        .as_ref()
+        .is_some_and(|editor| app.window_id() == editor.window)
+}
+
+/// The palette file's colors with their declared counts.
+fn declared(model: &Model) -> ColorConfigs {
+    let mut declared = model.declared_palette();
+    declared.colors.truncate(model.main_colors);
+    declared
+}
+
+/// Takes a finished solve into the mosaic and starts the next one if the
+/// counts changed since, unless they leave too few pieces for the tiles.
+/// Call on every update.
+pub fn tick(model: &mut Model) {
+    let editor = match &mut model.palette_editor {
+        Some(editor) => editor,
+        None => return,
+    };
+    if let Some(solving) = &editor.solving {
+        let finished = solving.result.lock().unwrap().take();
+        let (assignment, palette) = match finished {
+            Some(finished) => finished,
+            None => return,
+        };
+        let frame = solving.frame;
+        editor.solving = None;
+        place_fresh(model, assignment, palette);
+        finish_resolve(model, frame);
+        log::info!("Re-solved for the edited counts.");
+        return;
+    }
+    if !editor.dirty {
+        return;
+    }
+    editor.dirty = false;
+    let mut palette = editor.palette.lock().unwrap().clone();
+    let frame = match prepare_resolve(model, &mut palette) {
+        Ok(frame) => frame,
+        Err(short) => {
+            log::warn!("{short}; not solving until they are raised.");
+            if let Some(editor) = &mut model.palette_editor {
+                editor.shortfall = Some(short);
+            }
+            return;
+        }
+    };
+    let job = FreshSolve::new(model);
+    let result = Arc::new(Mutex::new(None));
+    let sender = Arc::clone(&result);
+    thread::spawn(move || {
+        let assignment = job.run(&mut palette);
+        *sender.lock().unwrap() = Some((assignment, palette));
+    });
+    if let Some(editor) = &mut model.palette_editor {
+        editor.solving = Some(Solving { result, frame });
+        editor.shortfall = None;
+    }
+}
+
+/// Catches the editor up with counts changed elsewhere, such as a palette
+/// reload, unless it has edits of its own waiting.
+pub fn sync(model: &mut Model) {
+    let declared = declared(model);
+    if let Some(editor) = &mut model.palette_editor {
+        if !editor.dirty && editor.solving.is_none() {
+            *editor.palette.lock().unwrap() = declared;
+        }
+    }
+}
+
+/// Moves the editor window over to a model built for another picture. A
+/// solve still running for the old picture is dropped.
+pub fn carry_over(from: &mut Model, to: &mut Model) {
+    to.palette_editor = from.palette_editor.take();
+    if let Some(editor) = &mut to.palette_editor {
+        editor.dirty = false;
+        editor.solving = None;
+    }
+    sync(to);
+}
+
+fn row_height(window: Rect, rows: usize) -> f32 {
+    ((window.h() - HEADER_HEIGHT) / rows.max(1) as f32).min(MAX_ROW_HEIGHT)
+}
+
+/// The `-` and `+` buttons of the row whose vertical centre is `y`.
+fn buttons(window: Rect, y: f32, height: f32) -> [Rect; 2] {
+    let side = BUTTON.min(height - 2.0);
+    let plus = Rect::from_x_y_w_h(window.right() - 4.0 - side / 2.0, y, side, side);
+    let minus = Rect::from_x_y_w_h(plus.x() - side - 4.0, y, side, side);
+    [minus, plus]
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let editor = match &model.palette_editor {
+        Some(editor) => editor,
+        None => return,
+    };
+    let window = frame.rect();
+    let draw = app.draw();
+    draw.background().color(srgb8(24, 24, 24));
+    let status = if editor.dirty || editor.solving.is_some() {
+        "Solving...".to_string()
+    } else if let Some(short) = editor.shortfall {
+        format!("{} pieces short of the tiles; add some", short.short())
+    } else {
+        "Click - or +, with Shift for 10".to_string()
+    };
+    let header = Rect::from_x_y_w_h(
+        window.x(),
+        window.top() - HEADER_HEIGHT / 2.0,
+        window.w() - 8.0,
+        HEADER_HEIGHT,
+    );
+    draw.text(&status)
+        .xy(header.xy())
+        .wh(header.wh())
+        .left_justify()
+        .font_size(12)
+        .color(WHITE);
+
+    let palette = editor.palette.lock().unwrap();
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    let height = row_height(window, palette.colors.len());
+    for (index, config) in palette.colors.iter().enumerate() {
+        let top = window.top() - HEADER_HEIGHT - index as f32 * height;
+        let y = top - height / 2.0;
+        let swatch = height - 2.0;
+        draw.rect()
+            .x_y(window.left() + 4.0 + swatch / 2.0, y)
+            .w_h(swatch, swatch)
+            .color(srgb8(config.r, config.g, config.b));
+        let [minus, plus] = buttons(window, y, height);
+        for (button, label) in [(minus, "-"), (plus, "+")] {
+            draw.rect()
+                .xy(button.xy())
+                .wh(button.wh())
+                .color(srgb8(60, 60, 60));
+            draw.text(label)
+                .xy(button.xy())
+                .wh(button.wh())
+                .font_size(12)
+                .color(WHITE);
+        }
+        if height >= 8.0 {
+            let text = Rect::from_corners(
+                pt2(window.left() + 8.0 + swatch, top - height),
+                pt2(minus.left() - 4.0, top),
+            );
+            let used = used.get(index).copied().unwrap_or_default();
+            draw.text(&format!("{}  {used}/{}", config.name, config.count))
+                .xy(text.xy())
+                .wh(text.wh())
+                .left_justify()
+                .no_line_wrap()
+                .font_size((height - 6.0).clamp(6.0, 11.0) as u32)
+                .color(srgb8(230, 230, 230));
+        }
+    }
+    draw.to_frame(app, &frame)
+        .expect("Unable to draw the palette editor.");
+}
+
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    if button != MouseButton::Left {
+        return;
+    }
+    let editor = match &mut model.palette_editor {
+        Some(editor) => editor,
+        None => return,
+    };
+    let window = match app.window(editor.window) {
+        Some(window) => window.rect(),
+        None => return,
+    };
+    let point = app.mouse.position();
+    let step = if app.keys.mods.shift() { 10 } else { 1 };
+    let mut palette = editor.palette.lock().unwrap();
+    let height = row_height(window, palette.colors.len());
+    let row = ((window.top() - HEADER_HEIGHT - point.y) / height).floor();
+    if row < 0.0 || row as usize >= palette.colors.len() {
+        return;
+    }
+    let y = window.top() - HEADER_HEIGHT - (row + 0.5) * height;
+    let [minus, plus] = buttons(window, y, height);
+    let config = &mut palette.colors[row as usize];
+    if minus.contains(point) {
+        config.count = config.count.saturating_sub(step);
+    } else if plus.contains(point) {
+        config.count += step;
+    } else {
+        return;
+    }
+    log::info!("{}: {} declared.", config.name, config.count);
+    editor.dirty = true;
+}
@@ -0,0 +1,222 @@
+//! A minimal PDF writer: vector shapes and Helvetica text, nothing embedded.
+//!
+//! Coordinates are in points with the origin at the bottom-left of the page.
+
+use std::fmt::Write as _;
+use std::fs;
+
+/// Width and height of an A4 page in points.
+pub const A4: (f32, f32) = (595.0, 842.0);
+
+/// Bezier handle length for approximating a quarter circle.
+const KAPPA: f32 = 0.552_284_8;
+
+/// Drawing commands for one page.
+#[derive(Debug, Default)]
+pub struct Page {
+    content: String,
+}
+
+impl Page {
+    pub fn new() -> Page {
+        Page::default()
+    }
+
+    pub fn fill_color(&mut self, r: u8, g: u8, b: u8) {
+        let [r, g, b] = [r, g, b].map(|c| c as f32 / 255.0);
+        writeln!(self.content, "{r:.3} {g:.3} {b:.3} rg").unwrap();
+    }
+
+    pub fn stroke_color(&mut self, r: u8, g: u8, b: u8) {
+        let [r, g, b] = [r, g, b].map(|c| c as f32 / 255.0);
+        writeln!(self.content, "{r:.3} {g:.3} {b:.3} RG").unwrap();
+    }
+
+    pub fn line_width(&mut self, width: f32) {
+        writeln!(self.content, "{width:.2} w").unwrap();
+    }
+
+    /// A rectangle with its bottom-left corner at (`x`, `y`).
+    pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, fill: bool, stroke: bool) {
+        writeln!(
+            self.content,
+            "{x:.2} {y:.2} {w:.2} {h:.2} re {}",
+            paint(fill, stroke)
+        )
+        .unwrap();
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32)) {
+        writeln!(
+            self.content,
+            "{:.2} {:.2} m {:.2} {:.2} l S",
+            from.0, from.1, to.0, to.1
+        )
+        .unwrap();
+    }
+
+    pub fn circle(&mut self, cx: f32, cy: f32, radius: f32, fill: bool, stroke: bool) {
+        let k = radius * KAPPA;
+        let (l, r, b, t) = (cx - radius, cx + radius, cy - radius, cy + radius);
+        let segments = [
+            [r, cy + k, cx + k, t, cx, t],
+            [cx - k, t, l, cy + k, l, cy],
+            [l, cy - k, cx - k, b, cx, b],
+            [cx + k, b, r, cy - k, r, cy],
+        ];
+        writeln!(self.content, "{r:.2} {cy:.2} m").unwrap();
+        for [x1, y1, x2, y2, x3, y3] in segments {
+            writeln!(
+                self.content,
+                "{x1:.2} {y1:.2} {x2:.2} {y2:.2} {x3:.2} {y3:.2} c"
+            )
+            .unwrap();
+        }
+        writeln!(self.content, "{}", paint(fill, stroke)).unwrap();
+    }
+
+    /// Text in the current fill color with its baseline starting at (`x`, `y`).
+    pub fn text(&mut self, x: f32, y: f32, size: f32, text: &str) {
+        writeln!(
+            self.content,
+            "BT /F1 {size:.1} Tf {x:.2} {y:.2} Td ({}) Tj ET",
+            escape(text)
+        )
+        .unwrap();
+    }
+}
+
+/// Approximate width of `text` in Helvetica at `size`, for centering and
+/// truncation. Digits and most lowercase letters are about half an em.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().count() as f32 * size * 0.55
+}
+
+fn paint(fill: bool, stroke: bool) -> &'static str {
+    match (fill, stroke) {
+        (true, true) => "B",
+        (true, false) => "f",
+        (false, true) => "S",
+        (false, false) => "n",
+    }
+}
+
+/// Escapes a PDF string literal. Helvetica here only covers ASCII, so
+/// anything else is replaced.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// A document of same-sized pages.
+#[derive(Debug)]
+pub struct Document {
+    size: (f32, f32),
+    pages: Vec<Page>,
+}
+
+impl Document {
+    pub fn new(size: (f32, f32)) -> Document {
+        Document {
+            size,
+            pages: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    /// Serializes the document. Objects are laid out as catalog, page tree,
+    /// font, then a page and its content stream for each page.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let page_id = |index: usize| 4 + index * 2;
+        let mut objects: Vec<String> = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len())
+                    .map(|index| format!("{} 0 R", page_id(index)))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                self.pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        ];
+        for (index, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                self.size.0,
+                self.size.1,
+                page_id(index) + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.content.len(),
+                page.content
+            ));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            writeln!(out, "{} 0 obj\n{object}\nendobj", index + 1).unwrap();
+        }
+        let xref = out.len();
+        writeln!(out, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1).unwrap();
+        for offset in offsets {
+            writeln!(out, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .unwrap();
+        out.into_bytes()
+    }
+
+    pub fn save(&self, path: &str) {
+        fs::write(path, self.to_bytes()).expect("Unable to write PDF.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xref_offsets_point_at_objects() {
+        let mut document = Document::new(A4);
+        let mut page = Page::new();
+        page.text(10.0, 10.0, 12.0, "Hi (there)");
+        document.push(page);
+        let bytes = document.to_bytes();
+        let pdf = String::from_utf8(bytes).unwrap();
+        let xref = pdf.find("xref\n").unwrap();
+        let entries: Vec<usize> = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take(5)
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        for (index, offset) in entries.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+        assert!(pdf.contains("(Hi \\(there\\)) Tj"));
+        assert!(pdf.ends_with(&format!("startxref\n{xref}\n%%EOF\n")));
+    }
+}
@@ -0,0 +1,244 @@
+//! Adaptive cell sizes for `--adaptive-cells`: the grid is cut into large
+//! square cells and any cell whose source colors vary too much is split
+//! into quarters, down to single tiles. Each cell then takes one color.
+//!
+//! Tiles stay the unit everywhere else. A cell's tiles simply share a
+//! palette color, so counts, the parts list, and exports all measure pieces
+//! in 1x1 tiles.
+
+use crate::assign::{self, working_color};
+use crate::metric;
+use crate::palette::ColorConfigs;
+use crate::{Color, X_SIZE, Y_SIZE};
+use std::collections::HashMap;
+
+/// A square block of tiles: its bottom-left tile and side length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub x: u64,
+    pub y: u64,
+    pub size: u64,
+}
+
+impl Cell {
+    pub fn area(&self) -> u64 {
+        self.size * self.size
+    }
+
+    /// Raster index of the bottom-left tile.
+    pub fn first_tile(&self) -> usize {
+        (self.y * X_SIZE + self.x) as usize
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = usize> + '_ {
+        (self.y..self.y + self.size)
+            .flat_map(move |y| (self.x..self.x + self.size).map(move |x| (y * X_SIZE + x) as usize))
+    }
+
+    fn quarters(&self) -> [Cell; 4] {
+        let half = self.size / 2;
+        [(0, 0), (half, 0), (0, half), (half, half)].map(|(dx, dy)| Cell {
+            x: self.x + dx,
+            y: self.y + dy,
+            size: half,
+        })
+    }
+}
+
+/// Cuts the grid into `max_size` cells and splits each while the RMS
+/// distance of its tiles from their mean, in the matcher's working space,
+/// is above `threshold`. `max_size` must be a power of two dividing both
+/// grid sides.
+pub fn subdivide(reference: &[Color], max_size: u64, threshold: f32) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    for y in (0..Y_SIZE).step_by(max_size as usize) {
+        for x in (0..X_SIZE).step_by(max_size as usize) {
+            split(
+                reference,
+                Cell {
+                    x,
+                    y,
+                    size: max_size,
+                },
+                threshold,
+                &mut cells,
+            );
+        }
+    }
+    cells
+}
+
+fn split(reference: &[Color], cell: Cell, threshold: f32, cells: &mut Vec<Cell>) {
+    if cell.size > 1 && spread(reference, &cell) > threshold {
+        for quarter in cell.quarters() {
+            split(reference, quarter, threshold, cells);
+        }
+    } else {
+        cells.push(cell);
+    }
+}
+
+/// RMS distance of the cell's tiles from their mean working color.
+fn spread(reference: &[Color], cell: &Cell) -> f32 {
+    let colors: Vec<[f32; 3]> = cell
+        .tiles()
+        .map(|tile| {
+            let color = &reference[tile];
+            working_color(color.r, color.g, color.b)
+        })
+        .collect();
+    let n = colors.len() as f32;
+    let mean = [0, 1, 2].map(|c| colors.iter().map(|color| color[c]).sum::<f32>() / n);
+    let squared: f32 = colors
+        .iter()
+        .map(|color| (0..3).map(|c| (color[c] - mean[c]).powi(2)).sum::<f32>())
+        .sum();
+    (squared / n).sqrt()
+}
+
+/// The average source color of a cell.
+fn mean_color(reference: &[Color], cell: &Cell) -> Color {
+    let n = cell.area();
+    let sum = |channel: fn(&Color) -> u8| {
+        let total: u64 = cell
+            .tiles()
+            .map(|tile| channel(&reference[tile]) as u64)
+            .sum();
+        ((total + n / 2) / n) as u8
+    };
+    Color {
+        r: sum(|color| color.r),
+        g: sum(|color| color.g),
+        b: sum(|color| color.b),
+        x: cell.x,
+        y: cell.y,
+    }
+}
+
+/// Gives each cell the color closest to its average that has a piece left
+/// for every tile in it, visiting cells in the order their bottom-left
+/// tiles appear in `order`. A cell no color can cover is split instead.
+/// The result is indexed by raster position like `assign::assign_colors`.
+pub fn solve(
+    reference: &[Color],
+    cells: &[Cell],
+    order: &[usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) -> Vec<usize> {
+    let mut starting_at: Vec<Option<Cell>> = vec![None; reference.len()];
+    for cell in cells {
+        starting_at[cell.first_tile()] = Some(*cell);
+    }
+    let mut assignment = vec![0; reference.len()];
+    for &tile in order {
+        if let Some(cell) = starting_at[tile] {
+            assign_cell(reference, cell, &mut assignment, color_configs, emphasis);
+        }
+    }
+    assignment
+}
+
+fn assign_cell(
+    reference: &[Color],
+    cell: Cell,
+    assignment: &mut [usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) {
+    let color = if cell.size == 1 {
+        let first = &reference[cell.first_tile()];
+        assign::calculate_closest_color(color_configs, first, emphasis, metric::for_tile(first))
+    } else {
+        let mean = mean_color(reference, &cell);
+        // A cell takes the metric of its corner tile.
+        let metric = metric::for_tile(&reference[cell.first_tile()]);
+        match assign::closest_in_stock(color_configs, &mean, emphasis, cell.area(), metric) {
+            Some(color) => color,
+            None => {
+                for quarter in cell.quarters() {
+                    assign_cell(reference, quarter, assignment, color_configs, emphasis);
+                }
+                return;
+            }
+        }
+    };
+    color_configs.colors[color].count -= cell.area();
+    for tile in cell.tiles() {
+        assignment[tile] = color;
+    }
+}
+
+/// The cells as `assignment` actually colors them: a planned cell whose
+/// tiles no longer share one color, after a split for lack of stock or an
+/// edit, is broken into quarters until each part does.
+pub fn leaves(cells: &[Cell], assignment: &[usize]) -> Vec<Cell> {
+    fn split_mixed(cell: Cell, assignment: &[usize], leaves: &mut Vec<Cell>) {
+        let color = assignment[cell.first_tile()];
+        if cell.size == 1 || cell.tiles().all(|tile| assignment[tile] == color) {
+            leaves.push(cell);
+        } else {
+            for quarter in cell.quarters() {
+                split_mixed(quarter, assignment, leaves);
+            }
+        }
+    }
+    let mut leaves = Vec::new();
+    for &cell in cells {
+        split_mixed(cell, assignment, &mut leaves);
+    }
+    leaves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    fn config(name: &str, gray: u8, count: u64) -> ColorConfig {
+        ColorConfig::new(name.to_string(), gray, gray, gray, count)
+    }
+
+    #[test]
+    fn detail_is_split_and_cells_share_a_color() {
+        // A flat gray grid with a single white tile at the origin.
+        let reference: Vec<Color> = (0..Y_SIZE)
+            .flat_map(|y| (0..X_SIZE).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let gray = if (x, y) == (0, 0) { 255 } else { 100 };
+                Color {
+                    r: gray,
+                    g: gray,
+                    b: gray,
+                    x,
+                    y,
+                }
+            })
+            .collect();
+        let cells = subdivide(&reference, 4, 5.0);
+        let per_side = X_SIZE / 4;
+        // The corner 4x4 splits into three 2x2s and a 2x2 split into 1x1s.
+        assert_eq!(cells.len() as u64, per_side * per_side - 1 + 3 + 4);
+        assert!(cells.contains(&Cell {
+            x: 0,
+            y: 0,
+            size: 1
+        }));
+        assert!(cells.contains(&Cell {
+            x: 4,
+            y: 4,
+            size: 4
+        }));
+
+        let mut palette = ColorConfigs {
+            colors: vec![config("White", 255, 1), config("Gray", 100, 10_000)],
+        };
+        palette.prepare();
+        let order: Vec<usize> = (0..reference.len()).collect();
+        let assignment = solve(&reference, &cells, &order, &mut palette, &HashMap::new());
+        assert_eq!(assignment[0], 0);
+        assert!(assignment[1..].iter().all(|&color| color == 1));
+        assert_eq!(palette.colors[1].count, 10_000 - (X_SIZE * Y_SIZE - 1));
+        assert_eq!(leaves(&cells, &assignment).len(), cells.len());
+    }
+}
@@ -0,0 +1,257 @@
+//! The recently opened projects, kept in the platform's configuration
+//! folder so that a launch with no arguments can reopen the last one.
+
+use crate::cli::Options;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many projects are remembered.
+const MAX_ENTRIES: usize = 10;
+
+/// A picture with the palette and `--config` settings file it was opened
+/// with, or a project bundle alone, as absolute paths.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub picture: String,
+    #[serde(default)]
+    pub palette: Option<String>,
+    #[serde(default)]
+    pub config: Option<String>,
+}
+
+impl Entry {
+    fn files(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.picture)
+            .chain(&self.palette)
+            .chain(&self.config)
+    }
+
+    /// The first of the entry's files that is no longer there.
+    fn missing(&self) -> Option<&String> {
+        self.files().find(|path| !Path::new(path).exists())
+    }
+
+    /// The arguments that open the project again.
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![self.picture.clone()];
+        args.extend(self.palette.clone());
+        if let Some(config) = &self.config {
+            args.extend(["--config".to_string(), config.clone()]);
+        }
+        args
+    }
+
+    fn describe(&self) -> String {
+        let mut text = self.picture.clone();
+        if let Some(palette) = &self.palette {
+            text.push_str(&format!(" with {palette}"));
+        }
+        if let Some(config) = &self.config {
+            text.push_str(&format!(" (settings {config})"));
+        }
+        text
+    }
+}
+
+/// Where the list is kept: `recent.json` in the program's folder under
+/// `$XDG_CONFIG_HOME` or `~/.config`, `~/Library/Application Support` on
+/// macOS, or `%APPDATA%` on Windows.
+fn list_path() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join(env!("CARGO_PKG_NAME")).join("recent.json"))
+}
+
+/// The remembered projects, most recent first. A missing or unreadable list
+/// is empty.
+pub fn load() -> Vec<Entry> {
+    let path = match list_path() {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+            log::warn!(
+                "Ignoring unreadable recent files list {}: {err}",
+                path.display()
+            );
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn save(entries: &[Entry]) {
+    let path = match list_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let json = serde_json::to_string_pretty(entries).expect("Recent files should serialize.");
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&path, json));
+    if let Err(err) = written {
+        log::warn!(
+            "Could not update recent files list {}: {err}",
+            path.display()
+        );
+    }
+}
+
+/// Puts the project `options` opened at the top of the list.
+pub fn record(options: &Options) {
+    let absolute = |path: &str| {
+        fs::canonicalize(path)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.to_string())
+    };
+    // A bundle unpacks to a temporary folder, so it is the bundle that is
+    // opened again.
+    if let Some(bundle) = &options.bundle {
+        let entry = Entry {
+            picture: absolute(bundle),
+            palette: None,
+            config: None,
+        };
+        return save(&push(load(), entry));
+    }
+    let entry = Entry {
+        picture: absolute(&options.picture_path),
+        palette: options.color_data.as_deref().map(absolute),
+        config: options.config.as_deref().map(absolute),
+    };
+    save(&push(load(), entry));
+}
+
+/// `entries` with `entry` moved or added to the front, trimmed to length.
+fn push(mut entries: Vec<Entry>, entry: Entry) -> Vec<Entry> {
+    entries.retain(|other| *other != entry);
+    entries.insert(0, entry);
+    entries.truncate(MAX_ENTRIES);
+    entries
+}
+
+/// Empties the list for `--forget-recent`.
+pub fn forget() {
+    save(&[]);
+    log::info!("Forgot the recent files.");
+}
+
+/// Prints the list for `--recent`, numbered for `--open-recent`.
+pub fn print() {
+    let entries = load();
+    if entries.is_empty() {
+        println!("No recent projects.");
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        match entry.missing() {
+            Some(missing) => println!("{}. {} [missing {missing}]", index + 1, entry.describe()),
+            None => println!("{}. {}", index + 1, entry.describe()),
+        }
+    }
+}
+
+/// The arguments to run with: the most recent project whose files are all
+/// still there when `args` is empty, `--open-recent N` replaced by the Nth
+/// project, or `args` as given.
+pub fn expand(args: Vec<String>) -> Vec<String> {
+    if args.is_empty() {
+        return reopen_last(&load());
+    }
+    let position = match args.iter().position(|arg| arg == "--open-recent") {
+        Some(position) => position,
+        None => return args,
+    };
+    let value = args
+        .get(position + 1)
+        .unwrap_or_else(|| panic!("Missing value for --open-recent"));
+    let entries = load();
+    let entry = value
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| entries.get(number.checked_sub(1)?))
+        .unwrap_or_else(|| {
+            panic!(
+                "Invalid value '{value}' for --open-recent; --recent lists {} projects",
+                entries.len()
+            )
+        });
+    if let Some(missing) = entry.missing() {
+        panic!("Recent project {value} cannot be opened; {missing} has moved")
+    }
+    log::info!("Reopening {}.", entry.describe());
+    let mut expanded = args[..position].to_vec();
+    expanded.extend(entry.args());
+    expanded.extend_from_slice(&args[position + 2..]);
+    expanded
+}
+
+fn reopen_last(entries: &[Entry]) -> Vec<String> {
+    for entry in entries {
+        match entry.missing() {
+            Some(missing) => log::warn!(
+                "Skipping recent project {}; {missing} has moved.",
+                entry.picture
+            ),
+            None => {
+                log::info!("Reopening {}.", entry.describe());
+                return entry.args();
+            }
+        }
+    }
+    panic!("Need to provide file paths for picture and color config")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(picture: &str) -> Entry {
+        Entry {
+            picture: picture.to_string(),
+            palette: Some("colors.json".to_string()),
+            config: None,
+        }
+    }
+
+    #[test]
+    fn recent_entries_move_to_front() {
+        let entries = (0..MAX_ENTRIES)
+            .map(|n| entry(&format!("{n}.png")))
+            .collect();
+        let entries = push(entries, entry("5.png"));
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0], entry("5.png"));
+        assert_eq!(entries[1], entry("0.png"));
+        let entries = push(entries, entry("new.png"));
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(!entries.contains(&entry("9.png")));
+    }
+
+    #[test]
+    fn moved_files_are_skipped() {
+        let readme = concat!(env!("CARGO_MANIFEST_DIR"), "/README.md").to_string();
+        let moved = entry("/nowhere/gone.png");
+        let present = Entry {
+            picture: readme.clone(),
+            palette: None,
+            config: Some(readme.clone()),
+        };
+        assert_eq!(moved.missing(), Some(&moved.picture));
+        assert_eq!(
+            reopen_last(&[moved, present]),
+            [readme.clone(), "--config".to_string(), readme]
+        );
+    }
+}
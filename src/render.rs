@@ -0,0 +1,550 @@
+//! Rendering the mosaic to images for export.
+
+use crate::bricks::Placement;
+use crate::font;
+use crate::hex::HexOrientation;
+use crate::quadtree::Cell;
+use crate::{Color, X_SIZE, Y_SIZE};
+use image::{imageops, Rgb, RgbImage, Rgba, RgbaImage};
+use std::str::FromStr;
+
+/// Pixels per tile in exported images unless `--output-scale` says otherwise.
+pub const DEFAULT_TILE_PX: u32 = 10;
+
+pub const BACKGROUND: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// `pixels` with each tile brightened or darkened by up to `amplitude`, a
+/// fraction of its color, so flat areas look pieced by hand. The change
+/// depends only on the tile's position, so it is the same in every frame
+/// and export; at 0 the tiles are returned unchanged.
+pub fn jitter(pixels: &[Color], amplitude: f32) -> Vec<Color> {
+    if amplitude == 0.0 {
+        return pixels.to_vec();
+    }
+    pixels
+        .iter()
+        .map(|color| {
+            let factor = 1.0 + amplitude * tile_noise(color.x, color.y);
+            let shade = |channel: u8| (channel as f32 * factor).round().clamp(0.0, 255.0) as u8;
+            Color {
+                r: shade(color.r),
+                g: shade(color.g),
+                b: shade(color.b),
+                ..color.clone()
+            }
+        })
+        .collect()
+}
+
+/// `pixels` with every color's hue turned `turns` of the way round the
+/// HSL color wheel, keeping its saturation and lightness; grays stay gray.
+pub fn shift_hue(pixels: &[Color], turns: f32) -> Vec<Color> {
+    pixels
+        .iter()
+        .map(|color| {
+            let [r, g, b] = [color.r, color.g, color.b].map(|c| c as f32 / 255.0);
+            let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+            let (delta, lightness) = (max - min, (max + min) / 2.0);
+            if delta == 0.0 {
+                return color.clone();
+            }
+            // Hue in sixths of a turn.
+            let hue = if max == r {
+                ((g - b) / delta).rem_euclid(6.0)
+            } else if max == g {
+                (b - r) / delta + 2.0
+            } else {
+                (r - g) / delta + 4.0
+            };
+            let hue = (hue + turns * 6.0).rem_euclid(6.0);
+            let second = delta * (1.0 - (hue % 2.0 - 1.0).abs());
+            let (r, g, b) = match hue as u32 {
+                0 => (delta, second, 0.0),
+                1 => (second, delta, 0.0),
+                2 => (0.0, delta, second),
+                3 => (0.0, second, delta),
+                4 => (second, 0.0, delta),
+                _ => (delta, 0.0, second),
+            };
+            let base = lightness - delta / 2.0;
+            let channel = |c: f32| ((c + base) * 255.0).round().clamp(0.0, 255.0) as u8;
+            Color {
+                r: channel(r),
+                g: channel(g),
+                b: channel(b),
+                ..color.clone()
+            }
+        })
+        .collect()
+}
+
+/// A value in `-1..=1` that looks random from tile to tile.
+fn tile_noise(x: u64, y: u64) -> f32 {
+    // The SplitMix64 finalizer.
+    let mut z = (y * X_SIZE + x).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+/// Draws the tiles the way the window does: one square per tile on a black
+/// background with a one-pixel gap between neighbours.
+pub fn render_to_image(pixels: &[Color], tile_px: u32) -> RgbImage {
+    flatten(&render_to_rgba(pixels, tile_px), BACKGROUND)
+}
+
+/// Like `render_to_image`, but the gaps and any tiles not drawn are left
+/// fully transparent.
+pub fn render_to_rgba(pixels: &[Color], tile_px: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(X_SIZE as u32 * tile_px, Y_SIZE as u32 * tile_px);
+    let inner = tile_px.saturating_sub(1).max(1);
+    for color in pixels {
+        let left = color.x as u32 * tile_px;
+        // Grid rows count up from the bottom, image rows from the top.
+        let top = (Y_SIZE - 1 - color.y) as u32 * tile_px;
+        for dy in 0..inner {
+            for dx in 0..inner {
+                img.put_pixel(left + dx, top + dy, Rgba([color.r, color.g, color.b, 255]));
+            }
+        }
+    }
+    img
+}
+
+/// Like `render_to_rgba` at exactly `width` by `height` pixels. Where the
+/// tiles do not divide the image evenly, the spare pixels go one apiece to
+/// tiles spread across each row and column, so sizes differ by one at most.
+pub fn render_to_exact_size(pixels: &[Color], width: u32, height: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(width, height);
+    // Where tile `i` of `tiles` starts along an edge of `length` pixels.
+    let edge = |i: u64, tiles: u64, length: u32| (i * length as u64 / tiles) as u32;
+    for color in pixels {
+        let left = edge(color.x, X_SIZE, width);
+        let right = edge(color.x + 1, X_SIZE, width);
+        // Grid rows count up from the bottom, image rows from the top.
+        let row = Y_SIZE - 1 - color.y;
+        let top = edge(row, Y_SIZE, height);
+        let bottom = edge(row + 1, Y_SIZE, height);
+        let inner_width = (right - left).saturating_sub(1).max(1);
+        let inner_height = (bottom - top).saturating_sub(1).max(1);
+        for dy in 0..inner_height {
+            for dx in 0..inner_width {
+                img.put_pixel(left + dx, top + dy, Rgba([color.r, color.g, color.b, 255]));
+            }
+        }
+    }
+    img
+}
+
+/// An exact image size, written `WxH`, with room for a pixel per tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExactSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for ExactSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .ok_or_else(|| format!("expected a size like 1080x1920, got '{s}'"))?;
+        let side = |side: &str, tiles: u64| match side.trim().parse::<u32>() {
+            Ok(side) if side as u64 >= tiles => Ok(side),
+            Ok(_) => Err(format!("'{s}' leaves less than a pixel per tile")),
+            Err(_) => Err(format!("expected a size like 1080x1920, got '{s}'")),
+        };
+        Ok(ExactSize {
+            width: side(width, X_SIZE)?,
+            height: side(height, Y_SIZE)?,
+        })
+    }
+}
+
+/// Like `render_to_rgba` for `--adaptive-cells`: each cell is one square
+/// in the color of its tiles, with the gap only around its outside.
+/// `pixels` holds every tile in raster order.
+pub fn render_cells_to_rgba(pixels: &[Color], cells: &[Cell], tile_px: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(X_SIZE as u32 * tile_px, Y_SIZE as u32 * tile_px);
+    for cell in cells {
+        let color = &pixels[cell.first_tile()];
+        let left = cell.x as u32 * tile_px;
+        let top = (Y_SIZE - cell.y - cell.size) as u32 * tile_px;
+        let inner = (cell.size as u32 * tile_px).saturating_sub(1).max(1);
+        for dy in 0..inner {
+            for dx in 0..inner {
+                img.put_pixel(left + dx, top + dy, Rgba([color.r, color.g, color.b, 255]));
+            }
+        }
+    }
+    img
+}
+
+/// Like `render_cells_to_rgba` for the parts of `--export-bricks`: each
+/// part is one rectangle with the gap only around its outside.
+pub fn render_bricks_to_rgba(
+    pixels: &[Color],
+    placements: &[Placement],
+    tile_px: u32,
+) -> RgbaImage {
+    let mut img = RgbaImage::new(X_SIZE as u32 * tile_px, Y_SIZE as u32 * tile_px);
+    for placement in placements {
+        let color = &pixels[(placement.y * X_SIZE + placement.x) as usize];
+        let left = placement.x as u32 * tile_px;
+        let top = (Y_SIZE - placement.y - placement.brick.height) as u32 * tile_px;
+        let width = (placement.brick.width as u32 * tile_px)
+            .saturating_sub(1)
+            .max(1);
+        let height = (placement.brick.height as u32 * tile_px)
+            .saturating_sub(1)
+            .max(1);
+        for dy in 0..height {
+            for dx in 0..width {
+                img.put_pixel(left + dx, top + dy, Rgba([color.r, color.g, color.b, 255]));
+            }
+        }
+    }
+    img
+}
+
+/// Like `render_to_rgba` for `--hex-grid`: the tiles are hexagons filling
+/// an image `X_SIZE * tile_px` wide and as tall as the orientation needs.
+/// The notches along the grid's edges are left transparent.
+pub fn render_hex_to_rgba(
+    pixels: &[Color],
+    tile_px: u32,
+    orientation: HexOrientation,
+) -> RgbaImage {
+    let width = X_SIZE as u32 * tile_px;
+    let height = hex_height(width, orientation);
+    let mut tiles = vec![None; (X_SIZE * Y_SIZE) as usize];
+    for color in pixels {
+        tiles[(color.y * X_SIZE + color.x) as usize] = Some(color);
+    }
+    RgbaImage::from_fn(width, height, |px, py| {
+        let fx = (px as f32 + 0.5) / width as f32;
+        let fy = 1.0 - (py as f32 + 0.5) / height as f32;
+        match orientation
+            .locate(fx, fy)
+            .and_then(|(x, y)| tiles[(y * X_SIZE + x) as usize])
+        {
+            Some(color) => Rgba([color.r, color.g, color.b, 255]),
+            None => Rgba([0, 0, 0, 0]),
+        }
+    })
+}
+
+/// Height of a hex render `width` pixels wide.
+pub fn hex_height(width: u32, orientation: HexOrientation) -> u32 {
+    (width as f32 / orientation.aspect()).round() as u32
+}
+
+/// Composites `img` over a solid `background`.
+pub fn flatten(img: &RgbaImage, background: Rgb<u8>) -> RgbImage {
+    RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *img.get_pixel(x, y);
+        let mix = |c: u8, under: u8| {
+            ((c as u32 * a as u32 + under as u32 * (255 - a as u32)) / 255) as u8
+        };
+        Rgb([
+            mix(r, background[0]),
+            mix(g, background[1]),
+            mix(b, background[2]),
+        ])
+    })
+}
+
+/// A border of real tiles drawn around the grid in one palette color.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// Index of the frame color in the palette.
+    pub color: usize,
+    /// Width of the border in tiles.
+    pub thickness: u32,
+}
+
+impl Frame {
+    /// Pieces needed to build the frame.
+    pub fn tile_count(&self) -> u64 {
+        let t = self.thickness as u64;
+        (X_SIZE + 2 * t) * (Y_SIZE + 2 * t) - X_SIZE * Y_SIZE
+    }
+}
+
+/// An RGB color written as `rrggbb` or `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HexColor(pub Rgb<u8>);
+
+impl Default for HexColor {
+    fn default() -> Self {
+        HexColor(Rgb([255, 255, 255]))
+    }
+}
+
+impl FromStr for HexColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(format!("expected rrggbb, got '{s}'"));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string());
+        Ok(HexColor(Rgb([channel(0)?, channel(2)?, channel(4)?])))
+    }
+}
+
+/// Extras drawn around an exported mosaic.
+#[derive(Debug, Clone, Default)]
+pub struct Presentation {
+    pub frame: Option<(Frame, Rgb<u8>)>,
+    /// Blank pixels on every side, outside the frame.
+    pub margin: u32,
+    pub margin_color: HexColor,
+    /// A line of text below the grid.
+    pub caption: Option<String>,
+}
+
+/// Wraps a rendered grid in its frame, margin, and caption. Gaps between
+/// frame tiles stay transparent like those between grid tiles.
+pub fn present(grid: &RgbaImage, tile_px: u32, presentation: &Presentation) -> RgbaImage {
+    let mut framed = grid.clone();
+    if let Some((frame, Rgb([r, g, b]))) = presentation.frame {
+        let color = Rgba([r, g, b, 255]);
+        let border = frame.thickness * tile_px;
+        let (width, height) = (grid.width() + 2 * border, grid.height() + 2 * border);
+        framed = RgbaImage::new(width, height);
+        let inner = tile_px.saturating_sub(1).max(1);
+        for top in (0..height).step_by(tile_px as usize) {
+            for left in (0..width).step_by(tile_px as usize) {
+                let inside = (border..border + grid.width()).contains(&left)
+                    && (border..border + grid.height()).contains(&top);
+                if inside {
+                    continue;
+                }
+                for dy in 0..inner {
+                    for dx in 0..inner {
+                        framed.put_pixel(left + dx, top + dy, color);
+                    }
+                }
+            }
+        }
+        imageops::replace(&mut framed, grid, border as i64, border as i64);
+    }
+
+    const CAPTION_SCALE: u32 = 2;
+    let caption_height = match presentation.caption {
+        Some(_) => (font::GLYPH_HEIGHT + 4) * CAPTION_SCALE,
+        None => 0,
+    };
+    let margin = presentation.margin;
+    let HexColor(background) = presentation.margin_color;
+    let [r, g, b] = background.0;
+    let mut img = RgbaImage::from_pixel(
+        framed.width() + 2 * margin,
+        framed.height() + 2 * margin + caption_height,
+        Rgba([r, g, b, 255]),
+    );
+    imageops::replace(&mut img, &framed, margin as i64, margin as i64);
+    if let Some(caption) = &presentation.caption {
+        let mut caption = caption.clone();
+        while font::text_width(&caption, CAPTION_SCALE) > framed.width() {
+            caption.pop();
+        }
+        // Keep the text readable whatever the margin color is.
+        let light = 0.3 * r as f32 + 0.59 * g as f32 + 0.11 * b as f32 > 128.0;
+        let ink = if light {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        };
+        font::draw_text(
+            &mut img,
+            margin + (framed.width() - font::text_width(&caption, CAPTION_SCALE)) / 2,
+            margin + framed.height() + 2 * CAPTION_SCALE,
+            &caption,
+            ink,
+            CAPTION_SCALE,
+        );
+    }
+    img
+}
+
+/// Places several renders side by side, each captioned above by the lines
+/// of its label. Lines too wide for their render are truncated.
+pub fn contact_sheet(renders: &[(String, RgbImage)]) -> RgbImage {
+    const GUTTER: u32 = 16;
+    const LABEL_SCALE: u32 = 2;
+    let line_height = (font::GLYPH_HEIGHT + 3) * LABEL_SCALE;
+    let lines = renders
+        .iter()
+        .map(|(label, _)| label.lines().count() as u32)
+        .max()
+        .unwrap_or(0);
+    let label_height = lines * line_height;
+    let width = renders.iter().map(|(_, img)| img.width()).sum::<u32>()
+        + GUTTER * (renders.len() as u32 + 1);
+    let height = renders
+        .iter()
+        .map(|(_, img)| img.height())
+        .max()
+        .unwrap_or(0)
+        + label_height
+        + GUTTER * 3;
+
+    let mut sheet = RgbImage::from_pixel(width, height, Rgb([32, 32, 32]));
+    let mut left = GUTTER;
+    for (label, img) in renders {
+        for (row, line) in label.lines().enumerate() {
+            let mut line = line.to_string();
+            while font::text_width(&line, LABEL_SCALE) > img.width() {
+                line.pop();
+            }
+            font::draw_text(
+                &mut sheet,
+                left,
+                GUTTER + row as u32 * line_height,
+                &line,
+                Rgb([255, 255, 255]),
+                LABEL_SCALE,
+            );
+        }
+        imageops::overlay(
+            &mut sheet,
+            img,
+            left as i64,
+            (GUTTER * 2 + label_height) as i64,
+        );
+        left += img.width() + GUTTER;
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_is_stable_and_off_at_zero() {
+        let pixels: Vec<Color> = (0..X_SIZE)
+            .map(|x| Color {
+                r: 120,
+                g: 60,
+                b: 250,
+                x,
+                y: 3,
+            })
+            .collect();
+        let rgb = |pixels: &[Color]| -> Vec<(u8, u8, u8)> {
+            pixels
+                .iter()
+                .map(|color| (color.r, color.g, color.b))
+                .collect()
+        };
+        assert_eq!(rgb(&jitter(&pixels, 0.0)), rgb(&pixels));
+        let shaded = rgb(&jitter(&pixels, 0.1));
+        assert_eq!(shaded, rgb(&jitter(&pixels, 0.1)));
+        assert!(shaded.iter().any(|&(r, _, _)| r != 120));
+        assert!(shaded.iter().all(|&(r, _, _)| (108..=132).contains(&r)));
+    }
+
+    #[test]
+    fn hue_shift_turns_primaries_and_keeps_grays() {
+        let tile = |r, g, b| Color {
+            r,
+            g,
+            b,
+            x: 0,
+            y: 0,
+        };
+        let rgb = |color: &Color| (color.r, color.g, color.b);
+        let shifted = shift_hue(
+            &[tile(255, 0, 0), tile(128, 128, 128), tile(0, 0, 255)],
+            1.0 / 3.0,
+        );
+        assert_eq!(rgb(&shifted[0]), (0, 255, 0));
+        assert_eq!(rgb(&shifted[1]), (128, 128, 128));
+        assert_eq!(rgb(&shifted[2]), (255, 0, 0));
+        let dark = shift_hue(&[tile(100, 50, 50)], 1.0);
+        assert_eq!(rgb(&dark[0]), (100, 50, 50));
+    }
+
+    #[test]
+    fn frame_and_margin_surround_the_grid() {
+        let grid = render_to_rgba(&[], 4);
+        let frame = Frame {
+            color: 0,
+            thickness: 2,
+        };
+        let presentation = Presentation {
+            frame: Some((frame, Rgb([200, 0, 0]))),
+            margin: 5,
+            margin_color: HexColor(Rgb([0, 0, 255])),
+            caption: None,
+        };
+        let img = present(&grid, 4, &presentation);
+        assert_eq!(img.width(), grid.width() + 2 * (2 * 4 + 5));
+        assert_eq!(img.height(), grid.height() + 2 * (2 * 4 + 5));
+        assert_eq!(*img.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(*img.get_pixel(5, 5), Rgba([200, 0, 0, 255]));
+        // The gap after the first frame tile.
+        assert_eq!(*img.get_pixel(8, 5), Rgba([0, 0, 0, 0]));
+        assert_eq!(frame.tile_count(), 52 * 52 - 48 * 48);
+    }
+
+    #[test]
+    fn transparent_render_keeps_gaps_clear() {
+        let tile = Color {
+            r: 10,
+            g: 20,
+            b: 30,
+            x: 0,
+            y: Y_SIZE - 1,
+        };
+        let img = render_to_rgba(std::slice::from_ref(&tile), 4);
+        assert_eq!(*img.get_pixel(0, 0), Rgba([10, 20, 30, 255]));
+        assert_eq!(*img.get_pixel(3, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*img.get_pixel(4, 0), Rgba([0, 0, 0, 0]));
+        let flat = render_to_image(&[tile], 4);
+        assert_eq!(*flat.get_pixel(0, 0), Rgb([10, 20, 30]));
+        assert_eq!(*flat.get_pixel(3, 0), BACKGROUND);
+    }
+
+    #[test]
+    fn hex_colors_parse_with_or_without_hash() {
+        assert_eq!("#ff8000".parse(), Ok(HexColor(Rgb([255, 128, 0]))));
+        assert_eq!("0a0B0c".parse(), Ok(HexColor(Rgb([10, 11, 12]))));
+        assert!("fff".parse::<HexColor>().is_err());
+    }
+
+    #[test]
+    fn exact_size_spreads_spare_pixels() {
+        // 100 pixels over 48 columns: tiles of 2 or 3, never 4.
+        let pixels: Vec<Color> = (0..X_SIZE)
+            .map(|x| Color {
+                r: 9,
+                g: 9,
+                b: 9,
+                x,
+                y: Y_SIZE - 1,
+            })
+            .collect();
+        let img = render_to_exact_size(&pixels, 100, 130);
+        assert_eq!(img.dimensions(), (100, 130));
+        let row: Vec<bool> = (0..100).map(|x| img.get_pixel(x, 0)[3] == 255).collect();
+        let runs: Vec<usize> = row
+            .split(|&filled| !filled)
+            .filter(|run| !run.is_empty())
+            .map(|run| run.len())
+            .collect();
+        assert_eq!(runs.len(), 48);
+        assert!(runs.iter().all(|&run| run == 1 || run == 2));
+        assert_eq!(runs.iter().sum::<usize>() + 48, 100);
+        assert_eq!(
+            "48x47".parse::<ExactSize>().map(|size| size.height),
+            Err("'48x47' leaves less than a pixel per tile".to_string())
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Cel shading for `--cel`: the picture is cut into regions of similar
+//! color before solving and each region takes one palette color.
+//!
+//! Regions come from SLIC-style clustering of the tiles on color and
+//! position, split into connected pieces, with pieces smaller than the
+//! minimum size merged into the neighbour closest in color. As with
+//! `--adaptive-cells`, tiles stay the unit everywhere else.
+
+use crate::assign::{self, working_color};
+use crate::metric;
+use crate::palette::ColorConfigs;
+use crate::{Color, X_SIZE, Y_SIZE};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Weight of distance on the grid against distance in color; higher makes
+/// rounder, more even regions.
+const COMPACTNESS: f32 = 15.0;
+
+/// Rounds of moving the cluster centres before the regions are fixed.
+const ITERATIONS: usize = 10;
+
+/// The picture cut into regions.
+#[derive(Debug, Clone)]
+pub struct Segmentation {
+    /// The region of every tile in raster order.
+    pub labels: Vec<usize>,
+    /// The tiles of each region in raster order.
+    pub regions: Vec<Vec<usize>>,
+}
+
+impl Segmentation {
+    /// Whether a tile's neighbour to the right, and the one above, lies in
+    /// another region.
+    pub fn boundaries(&self, tile: usize) -> (bool, bool) {
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        let right = x + 1 < X_SIZE && self.labels[tile + 1] != self.labels[tile];
+        let up = y + 1 < Y_SIZE && self.labels[tile + X_SIZE as usize] != self.labels[tile];
+        (right, up)
+    }
+}
+
+/// Cuts the grid into roughly `regions` regions of similar color, none
+/// smaller than `min_size` tiles.
+pub fn segment(reference: &[Color], regions: usize, min_size: usize) -> Segmentation {
+    let colors: Vec<[f32; 3]> = reference
+        .iter()
+        .map(|color| working_color(color.r, color.g, color.b))
+        .collect();
+    let labels = cluster(&colors, regions);
+    let mut regions = connected(&labels);
+    merge_small(&colors, &mut regions, min_size);
+    let mut labels = vec![0; colors.len()];
+    for (region, tiles) in regions.iter().enumerate() {
+        for &tile in tiles {
+            labels[tile] = region;
+        }
+    }
+    Segmentation { labels, regions }
+}
+
+/// A cluster centre: mean working color and mean position.
+#[derive(Debug, Clone, Copy)]
+struct Centre {
+    color: [f32; 3],
+    x: f32,
+    y: f32,
+}
+
+/// SLIC on the grid: centres start evenly spaced and every round each tile
+/// joins the nearest centre within two spacings, then each centre moves to
+/// the mean of its tiles.
+fn cluster(colors: &[[f32; 3]], regions: usize) -> Vec<usize> {
+    let spacing = ((X_SIZE * Y_SIZE) as f32 / regions.max(1) as f32)
+        .sqrt()
+        .max(1.0);
+    let position = |tile: usize| ((tile as u64 % X_SIZE) as f32, (tile as u64 / X_SIZE) as f32);
+    let mut centres = Vec::new();
+    let mut y = spacing / 2.0;
+    while y < Y_SIZE as f32 {
+        let mut x = spacing / 2.0;
+        while x < X_SIZE as f32 {
+            let tile = (y as u64 * X_SIZE + x as u64) as usize;
+            centres.push(Centre {
+                color: colors[tile],
+                x,
+                y,
+            });
+            x += spacing;
+        }
+        y += spacing;
+    }
+
+    let mut labels = vec![0; colors.len()];
+    for _ in 0..ITERATIONS {
+        for (tile, color) in colors.iter().enumerate() {
+            let (x, y) = position(tile);
+            let mut best = f32::MAX;
+            for (index, centre) in centres.iter().enumerate() {
+                let (dx, dy) = (centre.x - x, centre.y - y);
+                if dx.abs() > 2.0 * spacing || dy.abs() > 2.0 * spacing {
+                    continue;
+                }
+                let spatial = (dx * dx + dy * dy) / (spacing * spacing);
+                let dist = squared(color, &centre.color) + COMPACTNESS * COMPACTNESS * spatial;
+                if dist < best {
+                    best = dist;
+                    labels[tile] = index;
+                }
+            }
+        }
+        let mut sums = vec![([0.0f32; 3], 0.0f32, 0.0f32, 0u32); centres.len()];
+        for (tile, &label) in labels.iter().enumerate() {
+            let (x, y) = position(tile);
+            let sum = &mut sums[label];
+            for (total, channel) in sum.0.iter_mut().zip(colors[tile]) {
+                *total += channel;
+            }
+            sum.1 += x;
+            sum.2 += y;
+            sum.3 += 1;
+        }
+        for (centre, (color, x, y, n)) in centres.iter_mut().zip(sums) {
+            // A centre that lost all its tiles stays put.
+            if n > 0 {
+                let n = n as f32;
+                *centre = Centre {
+                    color: color.map(|c| c / n),
+                    x: x / n,
+                    y: y / n,
+                };
+            }
+        }
+    }
+    labels
+}
+
+/// Splits clusters into pieces whose tiles touch side to side.
+pub fn connected(labels: &[usize]) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; labels.len()];
+    let mut regions = Vec::new();
+    for start in 0..labels.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut region = Vec::new();
+        let mut stack = vec![start];
+        while let Some(tile) = stack.pop() {
+            region.push(tile);
+            for neighbour in neighbours(tile) {
+                if !seen[neighbour] && labels[neighbour] == labels[start] {
+                    seen[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        region.sort_unstable();
+        regions.push(region);
+    }
+    regions
+}
+
+/// Merges the smallest region below `min_size` into the touching region
+/// closest to it in mean color, until none is left.
+fn merge_small(colors: &[[f32; 3]], regions: &mut Vec<Vec<usize>>, min_size: usize) {
+    let mean = |tiles: &[usize]| {
+        let n = tiles.len() as f32;
+        [0, 1, 2].map(|c| tiles.iter().map(|&tile| colors[tile][c]).sum::<f32>() / n)
+    };
+    while regions.len() > 1 {
+        let (small, _) = match regions
+            .iter()
+            .enumerate()
+            .filter(|(_, tiles)| tiles.len() < min_size)
+            .min_by_key(|(_, tiles)| tiles.len())
+        {
+            Some(small) => small,
+            None => break,
+        };
+        let mut labels = vec![0; colors.len()];
+        for (region, tiles) in regions.iter().enumerate() {
+            for &tile in tiles {
+                labels[tile] = region;
+            }
+        }
+        let own = mean(&regions[small]);
+        let into = regions[small]
+            .iter()
+            .flat_map(|&tile| neighbours(tile))
+            .map(|tile| labels[tile])
+            .filter(|&region| region != small)
+            .min_by(|&a, &b| {
+                squared(&own, &mean(&regions[a])).total_cmp(&squared(&own, &mean(&regions[b])))
+            })
+            .expect("A region smaller than the grid has a neighbour.");
+        let tiles = regions.swap_remove(small);
+        // `swap_remove` moved the last region into `small`'s place.
+        let into = if into == regions.len() { small } else { into };
+        regions[into].extend(tiles);
+        regions[into].sort_unstable();
+    }
+}
+
+/// The tiles sharing a side with `tile`.
+pub fn neighbours(tile: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+    [
+        (x > 0).then(|| tile - 1),
+        (x + 1 < X_SIZE).then(|| tile + 1),
+        (y > 0).then(|| tile - X_SIZE as usize),
+        (y + 1 < Y_SIZE).then(|| tile + X_SIZE as usize),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+fn squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|c| (a[c] - b[c]).powi(2)).sum()
+}
+
+/// Gives each region, largest first, the color with a piece left for each
+/// of its tiles that is closest to them in total. A region no color can
+/// cover has its tiles solved one by one. Only `tiles` are solved, so
+/// locked tiles are left out of their regions. The result is indexed by
+/// raster position like `assign::assign_colors`.
+pub fn solve(
+    reference: &[Color],
+    segmentation: &Segmentation,
+    tiles: &[usize],
+    color_configs: &mut ColorConfigs,
+    emphasis: &HashMap<String, f32>,
+) -> Vec<usize> {
+    let mut solved = vec![false; reference.len()];
+    for &tile in tiles {
+        solved[tile] = true;
+    }
+    let mut regions: Vec<Vec<usize>> = segmentation
+        .regions
+        .iter()
+        .map(|region| {
+            region
+                .iter()
+                .copied()
+                .filter(|&tile| solved[tile])
+                .collect()
+        })
+        .collect();
+    regions.sort_by_key(|region: &Vec<usize>| Reverse(region.len()));
+
+    let mut assignment = vec![0; reference.len()];
+    for region in regions.iter().filter(|region| !region.is_empty()) {
+        match assign::closest_for_tiles(color_configs, reference, region, emphasis) {
+            Some(color) => {
+                color_configs.colors[color].count -= region.len() as u64;
+                for &tile in region {
+                    assignment[tile] = color;
+                }
+            }
+            None => {
+                for &tile in region {
+                    let color = assign::calculate_closest_color(
+                        color_configs,
+                        &reference[tile],
+                        emphasis,
+                        metric::for_tile(&reference[tile]),
+                    );
+                    color_configs.colors[color].decrement();
+                    assignment[tile] = color;
+                }
+            }
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    fn config(name: &str, gray: u8, count: u64) -> ColorConfig {
+        ColorConfig::new(name.to_string(), gray, gray, gray, count)
+    }
+
+    #[test]
+    fn regions_follow_edges_and_take_one_color() {
+        // Dark on the left, light on the right, with one stray light tile.
+        let reference: Vec<Color> = (0..Y_SIZE)
+            .flat_map(|y| (0..X_SIZE).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let gray = if x < X_SIZE / 2 && (x, y) != (5, 5) {
+                    30
+                } else {
+                    220
+                };
+                Color {
+                    r: gray,
+                    g: gray,
+                    b: gray,
+                    x,
+                    y,
+                }
+            })
+            .collect();
+        let segmentation = segment(&reference, 16, 4);
+        let tiles = segmentation.regions.iter().map(Vec::len).sum::<usize>();
+        assert_eq!(tiles as u64, X_SIZE * Y_SIZE);
+        assert!(segmentation.regions.iter().all(|region| region.len() >= 4));
+        // The stray tile was folded into the dark region around it.
+        let stray = (5 * X_SIZE + 5) as usize;
+        assert_eq!(segmentation.labels[stray], segmentation.labels[stray + 1]);
+        for region in &segmentation.regions {
+            let left = region
+                .iter()
+                .filter(|&&tile| (tile as u64 % X_SIZE) < X_SIZE / 2);
+            assert!(
+                left.count() == 0 || region.iter().all(|&tile| tile as u64 % X_SIZE < X_SIZE / 2)
+            );
+        }
+
+        let mut palette = ColorConfigs {
+            colors: vec![config("Dark", 30, 10_000), config("Light", 220, 10_000)],
+        };
+        palette.prepare();
+        let all: Vec<usize> = (0..reference.len()).collect();
+        let assignment = solve(
+            &reference,
+            &segmentation,
+            &all,
+            &mut palette,
+            &HashMap::new(),
+        );
+        assert_eq!(assignment[stray], 0);
+        for region in &segmentation.regions {
+            assert!(region
+                .iter()
+                .all(|&tile| assignment[tile] == assignment[region[0]]));
+        }
+    }
+}
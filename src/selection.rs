@@ -0,0 +1,448 @@
+//! Selecting a rectangle of tiles in the window and recoloring them at once.
+//!
+//! Shift-dragging over the primary mosaic marks a rectangle; releasing the
+//! mouse selects its tiles, and a number key then moves them all to one
+//! palette color. With a symmetry mode on, the mirrored tiles are recolored
+//! in the same step, and each recolor can be undone with `U`.
+//!
+//! A whole color can also be replaced: with it picked in the legend, `X`
+//! waits for the color to replace it with, picked the same way.
+
+use crate::hex::HexOrientation;
+use crate::palette::ColorConfigs;
+use crate::{assign, mosaic_panes, Model, X_SIZE, Y_SIZE};
+use nannou::prelude::*;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// Grid rectangle as inclusive `(left, bottom, right, top)` tile positions.
+pub type GridRect = (u64, u64, u64, u64);
+
+/// Which mirror images of an edit are made along with it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    #[default]
+    Off,
+    /// Mirror left to right, across the vertical center line.
+    Horizontal,
+    /// Mirror top to bottom, across the horizontal center line.
+    Vertical,
+    /// Both, so every edit lands in all four quadrants.
+    FourWay,
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Symmetry::Off),
+            "horizontal" => Ok(Symmetry::Horizontal),
+            "vertical" => Ok(Symmetry::Vertical),
+            "four-way" => Ok(Symmetry::FourWay),
+            _ => Err(format!("unknown symmetry '{s}'")),
+        }
+    }
+}
+
+impl Symmetry {
+    /// The next mode for the `M` key.
+    pub fn next(self) -> Symmetry {
+        match self {
+            Symmetry::Off => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::FourWay,
+            Symmetry::FourWay => Symmetry::Off,
+        }
+    }
+
+    fn mirrors_x(self) -> bool {
+        matches!(self, Symmetry::Horizontal | Symmetry::FourWay)
+    }
+
+    fn mirrors_y(self) -> bool {
+        matches!(self, Symmetry::Vertical | Symmetry::FourWay)
+    }
+
+    /// `tiles` together with their mirror images, each listed once. On an
+    /// odd-sized grid the center row or column is its own mirror.
+    fn expand(self, tiles: &[usize]) -> Vec<usize> {
+        let mut expanded = BTreeSet::new();
+        for &tile in tiles {
+            let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+            let xs = [x, if self.mirrors_x() { X_SIZE - 1 - x } else { x }];
+            let ys = [y, if self.mirrors_y() { Y_SIZE - 1 - y } else { y }];
+            for &y in &ys {
+                for &x in &xs {
+                    expanded.insert((y * X_SIZE + x) as usize);
+                }
+            }
+        }
+        expanded.into_iter().collect()
+    }
+}
+
+/// The assignment and inventory before an edit, restored by `undo`.
+pub type Snapshot = (Vec<usize>, ColorConfigs);
+
+/// A replace waiting for the color to replace with.
+#[derive(Debug, Clone, Copy)]
+pub struct Replacing {
+    pub from: usize,
+    /// Move as many tiles as there are pieces rather than none when short.
+    pub partial: bool,
+}
+
+/// Starts a selection on Shift+click over the primary mosaic. Any other
+/// click outside the current selection clears it.
+pub fn press(app: &App, model: &mut Model) {
+    if model.progress.is_some() {
+        return;
+    }
+    let area = primary_area(app, model);
+    let point = app.mouse.position();
+    if app.keys.mods.shift() && area.contains(point) {
+        let (x, y) = grid_position(area, point, model.options.hex_grid);
+        model.selection_anchor = Some((x, y));
+        model.selection_rect = Some((x, y, x, y));
+        model.selected_tiles.clear();
+        return;
+    }
+    let inside = model.selection_rect.is_some_and(|rect| {
+        area.contains(point) && {
+            let (x, y) = grid_position(area, point, model.options.hex_grid);
+            contains(rect, x, y)
+        }
+    });
+    if !inside {
+        clear(model);
+    }
+}
+
+/// Stretches the rectangle being dragged to the tile under the mouse.
+pub fn drag(app: &App, model: &mut Model, point: Point2) {
+    if let Some((ax, ay)) = model.selection_anchor {
+        let (x, y) = grid_position(primary_area(app, model), point, model.options.hex_grid);
+        model.selection_rect = Some((ax.min(x), ay.min(y), ax.max(x), ay.max(y)));
+    }
+}
+
+/// Finishes a drag by selecting every tile in the rectangle.
+pub fn release(model: &mut Model) {
+    if model.selection_anchor.take().is_none() {
+        return;
+    }
+    if let Some(rect) = model.selection_rect {
+        model.selected_tiles = tiles_in(rect);
+        log::info!("Selected {} tiles.", model.selected_tiles.len());
+    }
+}
+
+pub fn clear(model: &mut Model) {
+    model.replacing = None;
+    model.selection_anchor = None;
+    model.selection_rect = None;
+    model.selected_tiles.clear();
+}
+
+/// Moves the selected tiles to the palette color picked by `digit` (1-9).
+/// Pressing the same digit again steps nine colors further, so every color
+/// can be reached: 1 picks the first, then the tenth, and so on.
+pub fn recolor_to_digit(app: &App, model: &mut Model, digit: usize) {
+    if model.selected_tiles.is_empty() || model.progress.is_some() {
+        return;
+    }
+    // Keyed-out holes have no color to step from.
+    let current = match model
+        .selected_tiles
+        .iter()
+        .map(|&tile| model.assignment[tile])
+        .find(|&index| index != assign::HOLE)
+    {
+        Some(current) => current,
+        None => return,
+    };
+    let target = match digit_color(digit, current, model.palette.colors.len()) {
+        Some(target) => target,
+        None => return,
+    };
+    let mut tiles = model.symmetry.expand(&model.selected_tiles);
+    if let Some(locks) = &model.locks {
+        tiles.retain(|&tile| !locks.locks(tile));
+    }
+    let before = (model.assignment.clone(), model.palette.clone());
+    match recolor(&mut model.assignment, &mut model.palette, &tiles, target) {
+        Ok(moved) => {
+            model.undo.push(before);
+            let config = &model.palette.colors[target];
+            let message = format!(
+                "Recolored {moved} tiles to {}; {} left.",
+                config.name, config.count
+            );
+            log::info!("{message}");
+            app.main_window().set_title(&message);
+            model.pixels =
+                assign::assigned_pixels(&model.reference_pixels, &model.assignment, &model.palette);
+        }
+        Err(err) => log::warn!("{err}"),
+    }
+}
+
+/// Starts replacing the color picked in the legend; the next color picked
+/// replaces it.
+pub fn start_replace(app: &App, model: &mut Model, partial: bool) {
+    let from = match model.editing_color {
+        Some(from) if model.progress.is_none() => from,
+        Some(_) => {
+            log::info!("Still solving; try again once the mosaic is complete.");
+            return;
+        }
+        None => {
+            log::info!("Pick the color to replace in the legend or with a number key first.");
+            return;
+        }
+    };
+    model.replacing = Some(Replacing { from, partial });
+    let message = format!(
+        "Replace {} with: pick a color (Escape cancels).",
+        model.palette.colors[from].name
+    );
+    log::info!("{message}");
+    app.main_window().set_title(&message);
+}
+
+/// Finishes a replace by moving the tiles to `to`, as one undo step.
+/// Locked tiles keep their color.
+pub fn finish_replace(app: &App, model: &mut Model, replacing: Replacing, to: usize) {
+    let Replacing { from, partial } = replacing;
+    let before = (model.assignment.clone(), model.palette.clone());
+    let locks = model.locks.as_ref();
+    let result = assign::replace_color(
+        &model.reference_pixels,
+        &mut model.assignment,
+        &mut model.palette,
+        &model.emphasis,
+        (from, to),
+        |tile| locks.is_none_or(|locks| !locks.locks(tile)),
+        partial,
+    );
+    let (from_name, to_name) = (
+        &model.palette.colors[from].name,
+        &model.palette.colors[to].name,
+    );
+    let message = match result {
+        Ok(moved) => {
+            let left = assign::usage(&model.assignment, model.palette.colors.len())[from];
+            let message = if left > 0 {
+                format!("Replaced {moved} {from_name} tiles with {to_name}; {left} remain.")
+            } else {
+                format!("Replaced {moved} {from_name} tiles with {to_name}.")
+            };
+            log::info!("{message}");
+            if moved > 0 {
+                model.undo.push(before);
+                model.pixels = assign::assigned_pixels(
+                    &model.reference_pixels,
+                    &model.assignment,
+                    &model.palette,
+                );
+            }
+            message
+        }
+        Err(short) => {
+            let message = format!(
+                "Not enough {to_name} to replace {from_name}: {short} short. Shift+X replaces \
+                 as many of the worst-fitting tiles as there are pieces."
+            );
+            log::warn!("{message}");
+            message
+        }
+    };
+    app.main_window().set_title(&message);
+}
+
+/// Reverts the most recent recolor, mirrored copies included.
+pub fn undo(model: &mut Model) {
+    match model.undo.pop() {
+        Some((assignment, palette)) => {
+            model.assignment = assignment;
+            model.palette = palette;
+            model.pixels =
+                assign::assigned_pixels(&model.reference_pixels, &model.assignment, &model.palette);
+            log::info!("Undid the last recolor.");
+        }
+        None => log::info!("Nothing to undo."),
+    }
+}
+
+/// Draws the mirror lines of the symmetry mode faintly over the mosaic.
+pub fn draw_axes(draw: &Draw, area: Rect, symmetry: Symmetry) {
+    let color = srgba(1.0, 1.0, 1.0, 0.3);
+    if symmetry.mirrors_x() {
+        draw.line()
+            .start(pt2(area.x(), area.bottom()))
+            .end(pt2(area.x(), area.top()))
+            .weight(1.0)
+            .color(color);
+    }
+    if symmetry.mirrors_y() {
+        draw.line()
+            .start(pt2(area.left(), area.y()))
+            .end(pt2(area.right(), area.y()))
+            .weight(1.0)
+            .color(color);
+    }
+}
+
+/// The palette index a number key picks: `digit - 1`, or nine past
+/// `current` when that is already the digit's color, wrapping back around.
+pub fn digit_color(digit: usize, current: usize, count: usize) -> Option<usize> {
+    let first = digit - 1;
+    if first >= count {
+        log::info!("The palette has only {count} colors.");
+        return None;
+    }
+    if current % 9 == first && current + 9 < count {
+        Some(current + 9)
+    } else {
+        Some(first)
+    }
+}
+
+/// Outlines the rectangle and lightens the selected tiles.
+pub fn draw(draw: &Draw, area: Rect, model: &Model) {
+    let (left, bottom, right, top) = match model.selection_rect {
+        Some(rect) => rect,
+        None => return,
+    };
+    let x_width = area.w() / X_SIZE as f32;
+    let y_height = area.h() / Y_SIZE as f32;
+    for &tile in &model.selected_tiles {
+        let (x, y) = (tile as u64 % X_SIZE, tile as u64 / X_SIZE);
+        draw.rect()
+            .x_y(
+                area.left() + (x as f32 + 0.5) * x_width,
+                area.bottom() + (y as f32 + 0.5) * y_height,
+            )
+            .w_h(x_width - 1.0, y_height - 1.0)
+            .color(srgba(1.0, 1.0, 1.0, 0.35));
+    }
+    let corner = |x: u64, y: u64| {
+        pt2(
+            area.left() + x as f32 * x_width,
+            area.bottom() + y as f32 * y_height,
+        )
+    };
+    let outline = Rect::from_corners(corner(left, bottom), corner(right + 1, top + 1));
+    draw.rect()
+        .xy(outline.xy())
+        .wh(outline.wh())
+        .no_fill()
+        .stroke(WHITE)
+        .stroke_weight(2.0);
+}
+
+/// Reassigns `tiles` to palette color `target`, returning pieces to the
+/// colors they leave, and reports how many moved. Nothing changes when the
+/// target does not have enough left. Keyed-out holes stay empty.
+fn recolor(
+    assignment: &mut [usize],
+    palette: &mut ColorConfigs,
+    tiles: &[usize],
+    target: usize,
+) -> Result<usize, String> {
+    let moving: Vec<usize> = tiles
+        .iter()
+        .copied()
+        .filter(|&tile| assignment[tile] != target && assignment[tile] != assign::HOLE)
+        .collect();
+    let available = palette.colors[target].count;
+    if (moving.len() as u64) > available {
+        return Err(format!(
+            "Only {available} {} left; {} needed.",
+            palette.colors[target].name,
+            moving.len()
+        ));
+    }
+    for &tile in &moving {
+        palette.colors[assignment[tile]].count += 1;
+        assignment[tile] = target;
+    }
+    palette.colors[target].count -= moving.len() as u64;
+    Ok(moving.len())
+}
+
+/// Raster indices of the tiles inside `rect`.
+fn tiles_in((left, bottom, right, top): GridRect) -> Vec<usize> {
+    (bottom..=top)
+        .flat_map(|y| (left..=right).map(move |x| (y * X_SIZE + x) as usize))
+        .collect()
+}
+
+fn contains((left, bottom, right, top): GridRect, x: u64, y: u64) -> bool {
+    (left..=right).contains(&x) && (bottom..=top).contains(&y)
+}
+
+/// The area the primary mosaic is drawn in.
+fn primary_area(app: &App, model: &Model) -> Rect {
+    mosaic_panes(app, model).swap_remove(0).grid
+}
+
+/// The tile position under `point`, clamped to the grid so a drag can
+/// leave the mosaic.
+fn grid_position(area: Rect, point: Point2, hex_grid: Option<HexOrientation>) -> (u64, u64) {
+    if let Some(orientation) = hex_grid {
+        let fx = (point.x - area.left()) / area.w();
+        let fy = (point.y - area.bottom()) / area.h();
+        return orientation.nearest(fx, fy);
+    }
+    let x = ((point.x - area.left()) / area.w() * X_SIZE as f32).max(0.0) as u64;
+    let y = ((point.y - area.bottom()) / area.h() * Y_SIZE as f32).max(0.0) as u64;
+    (x.min(X_SIZE - 1), y.min(Y_SIZE - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    fn palette(counts: &[u64]) -> ColorConfigs {
+        ColorConfigs {
+            colors: counts
+                .iter()
+                .enumerate()
+                .map(|(index, &count)| ColorConfig::new(format!("c{index}"), 0, 0, 0, count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn symmetry_mirrors_each_tile_once() {
+        let last = X_SIZE as usize - 1;
+        assert_eq!(Symmetry::Off.expand(&[1]), [1]);
+        assert_eq!(Symmetry::Horizontal.expand(&[1]), [1, last - 1]);
+        let top = (Y_SIZE as usize - 1) * X_SIZE as usize;
+        assert_eq!(
+            Symmetry::FourWay.expand(&[0, last]),
+            [0, last, top, top + last]
+        );
+    }
+
+    #[test]
+    fn recolor_moves_counts_and_refuses_short_colors() {
+        let tiles = tiles_in((1, 0, 2, 1));
+        assert_eq!(tiles, [1, 2, X_SIZE as usize + 1, X_SIZE as usize + 2]);
+
+        let mut assignment = vec![0; (X_SIZE * Y_SIZE) as usize];
+        assignment[2] = 1;
+        let mut counts = palette(&[5, 3]);
+        assert_eq!(recolor(&mut assignment, &mut counts, &tiles, 1), Ok(3));
+        assert!(tiles.iter().all(|&tile| assignment[tile] == 1));
+        assert_eq!([counts.colors[0].count, counts.colors[1].count], [8, 0]);
+
+        let before = assignment.clone();
+        let mut short = palette(&[0, 0, 2]);
+        assert!(recolor(&mut assignment, &mut short, &tiles, 2).is_err());
+        assert_eq!(assignment, before);
+    }
+}
@@ -0,0 +1,193 @@
+//! Assignments saved to disk so runs can be compared and reloaded.
+
+use crate::assign;
+use crate::legend::{self, LegendSort};
+use crate::palette::ColorConfigs;
+use crate::{Color, Model, X_SIZE, Y_SIZE};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+
+/// A finished assignment: the declared palette plus the palette index of
+/// every tile in raster order, bottom row first.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SavedAssignment {
+    pub width: u64,
+    pub height: u64,
+    /// The picture the assignment was made from.
+    pub source: String,
+    pub palette: ColorConfigs,
+    pub tiles: Vec<usize>,
+    /// Tiles forced by `--force-mask`, in raster order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forced: Vec<usize>,
+    /// The legend's order when saved.
+    #[serde(default)]
+    pub legend_sort: LegendSort,
+    /// Each palette color's legend number, as printed on labels and sheets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub numbers: Vec<usize>,
+}
+
+impl SavedAssignment {
+    pub fn from_model(model: &Model) -> SavedAssignment {
+        SavedAssignment {
+            width: X_SIZE,
+            height: Y_SIZE,
+            source: model.options.picture_path.clone(),
+            palette: model.declared_palette(),
+            tiles: model.assignment.clone(),
+            forced: model.forced_tiles(),
+            legend_sort: model.legend_sort,
+            numbers: legend::numbers(model),
+        }
+    }
+
+    /// Reorders the palette by descending tile count, keeping file order
+    /// between equally used colors, and renumbers the tiles to match.
+    pub fn sort_by_usage(&mut self) {
+        let usage = assign::usage(&self.tiles, self.palette.colors.len());
+        let mut order: Vec<usize> = (0..self.palette.colors.len()).collect();
+        order.sort_by_key(|&index| Reverse(usage[index]));
+        let mut new_index = vec![0; order.len()];
+        for (position, &old) in order.iter().enumerate() {
+            new_index[old] = position;
+        }
+        self.palette.colors = order
+            .iter()
+            .map(|&old| self.palette.colors[old].clone())
+            .collect();
+        for tile in &mut self.tiles {
+            *tile = new_index[*tile];
+        }
+        if !self.numbers.is_empty() {
+            self.numbers = order.iter().map(|&old| self.numbers[old]).collect();
+        }
+    }
+
+    pub fn load(path: &str) -> SavedAssignment {
+        let buff = fs::read_to_string(path).expect("Unable to read assignment file.");
+        let mut saved: SavedAssignment =
+            serde_json::from_str(&buff).expect("Assignment JSON not parseable.");
+        if saved.tiles.len() as u64 != saved.width * saved.height {
+            panic!(
+                "Assignment {path} has {} tiles but a {}x{} grid",
+                saved.tiles.len(),
+                saved.width,
+                saved.height
+            )
+        }
+        if let Some(&index) = saved
+            .tiles
+            .iter()
+            .find(|&&index| index >= saved.palette.colors.len())
+        {
+            panic!("Assignment {path} refers to missing palette color {index}")
+        }
+        saved.palette.prepare();
+        saved
+    }
+
+    pub fn save(&self, path: &str) {
+        let json = serde_json::to_string_pretty(self).expect("Assignment should serialize.");
+        fs::write(path, json).expect("Unable to write assignment file.");
+    }
+
+    /// Replaces the model's assignment with this one, taking the remaining
+    /// inventory from the saved declared counts.
+    pub fn install(self, model: &mut Model) {
+        if (self.width, self.height) != (X_SIZE, Y_SIZE) {
+            panic!(
+                "Session is a {}x{} grid but this build uses {X_SIZE}x{Y_SIZE}",
+                self.width, self.height
+            )
+        }
+        let mut palette = self.palette;
+        let used = assign::usage(&self.tiles, palette.colors.len());
+        for (config, used) in palette.colors.iter_mut().zip(used) {
+            config.count = config.count.saturating_sub(used);
+        }
+        if let Some(frame) = model
+            .frame
+            .filter(|frame| frame.color < palette.colors.len())
+        {
+            let config = &mut palette.colors[frame.color];
+            config.count = config.count.saturating_sub(frame.tile_count());
+        }
+        if palette.colors.len() < model.main_colors {
+            model.main_colors = palette.colors.len();
+        }
+        model.pixels = assign::assigned_pixels(&model.reference_pixels, &self.tiles, &palette);
+        model.assignment = self.tiles;
+        model.palette = palette;
+        model.legend_sort = self.legend_sort;
+    }
+
+    /// The assigned tile colors in raster order.
+    pub fn pixels(&self) -> Vec<Color> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(tile, &index)| {
+                let config = &self.palette.colors[index];
+                Color {
+                    r: config.r,
+                    g: config.g,
+                    b: config.b,
+                    x: tile as u64 % self.width,
+                    y: tile as u64 / self.width,
+                }
+            })
+            .collect()
+    }
+
+    /// Name of the color assigned to `tile`.
+    pub fn name(&self, tile: usize) -> &str {
+        &self.palette.colors[self.tiles[tile]].name
+    }
+
+    /// Whether `tile` shows a different color in `other`.
+    pub fn pixels_differ(&self, other: &SavedAssignment, tile: usize) -> bool {
+        let a = &self.palette.colors[self.tiles[tile]];
+        let b = &other.palette.colors[other.tiles[tile]];
+        (a.name.as_str(), a.r, a.g, a.b) != (b.name.as_str(), b.r, b.g, b.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorConfig;
+
+    #[test]
+    fn sort_by_usage_keeps_every_tile_color() {
+        let color = |name: &str, r| ColorConfig::new(name.to_string(), r, 0, 0, 10);
+        let mut saved = SavedAssignment {
+            width: 3,
+            height: 2,
+            source: String::new(),
+            palette: ColorConfigs {
+                colors: vec![color("Rare", 1), color("Unused", 2), color("Common", 3)],
+            },
+            tiles: vec![2, 0, 2, 2, 0, 2],
+            forced: Vec::new(),
+            legend_sort: LegendSort::File,
+            numbers: vec![1, 2, 3],
+        };
+        let before = saved.pixels();
+        saved.sort_by_usage();
+        let names: Vec<_> = saved
+            .palette
+            .colors
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, ["Common", "Rare", "Unused"]);
+        assert_eq!(saved.tiles, [0, 1, 0, 0, 1, 0]);
+        // Colors keep their legend numbers wherever they move to.
+        assert_eq!(saved.numbers, [3, 1, 2]);
+        for (a, b) in before.iter().zip(saved.pixels()) {
+            assert_eq!((a.r, a.x, a.y), (b.r, b.x, b.y));
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Project settings read from the JSON file given with `--config`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Settings {
+    /// Named lists of exports run together with `--export` or `E`.
+    #[serde(default)]
+    pub export_presets: HashMap<String, Vec<ExportJob>>,
+    /// The preset `E` runs from the window.
+    #[serde(default)]
+    pub default_export_preset: Option<String>,
+}
+
+/// One file written by an export preset. `output` is a file name template
+/// where `{stem}`, `{date}`, and `{grid}` stand for the picture's file stem,
+/// today's date, and the grid size.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ExportJob {
+    /// The mosaic as a PNG, like `--output`.
+    Image {
+        output: String,
+        /// Pixels per tile; defaults to `--output-scale`.
+        #[serde(default)]
+        scale: Option<u32>,
+        #[serde(default)]
+        tile_label: bool,
+        #[serde(default)]
+        transparent_bg: bool,
+    },
+    /// The assignment and declared palette, like `--export-json`.
+    Json {
+        output: String,
+        #[serde(default)]
+        sort_by_usage: bool,
+    },
+    /// A Markdown report, like `--report`.
+    Report { output: String },
+    /// Per-color placement sheets, like `--export-pdf-color-sheets`.
+    ColorSheets { output: String },
+    /// The palette as QR codes, like `--export-qrcode-palette`.
+    QrcodePalette { output: String },
+}
+
+impl ExportJob {
+    pub fn output(&self) -> &str {
+        match self {
+            ExportJob::Image { output, .. }
+            | ExportJob::Json { output, .. }
+            | ExportJob::Report { output }
+            | ExportJob::ColorSheets { output }
+            | ExportJob::QrcodePalette { output } => output,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &str) -> Settings {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Unable to read settings file {path}: {err}"));
+        serde_json::from_str(&text)
+            .unwrap_or_else(|err| panic!("Settings file {path} is not valid: {err}"))
+    }
+}
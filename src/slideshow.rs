@@ -0,0 +1,126 @@
+//! Cycling through a folder of pictures, one mosaic at a time.
+//!
+//! Only the picture on screen is kept; each step loads and solves the next
+//! one from scratch against a fresh copy of the palette.
+
+use crate::cli::Options;
+use crate::{build_model_from, source, Model};
+use nannou::prelude::*;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::seq::SliceRandom;
+use nannou::rand::SeedableRng;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+pub struct Slideshow {
+    files: Vec<PathBuf>,
+    current: usize,
+    pub paused: bool,
+    /// When the current picture finished solving.
+    shown_at: Instant,
+}
+
+impl Slideshow {
+    fn new(options: &Options, dir: &str) -> Slideshow {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap_or_else(|err| panic!("Unable to read slideshow folder {dir}: {err}"))
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        if files.is_empty() {
+            panic!("Slideshow folder {dir} has no files")
+        }
+        files.sort();
+        if options.shuffle {
+            let mut rng = match options.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            files.shuffle(&mut rng);
+        }
+        Slideshow {
+            files,
+            current: 0,
+            paused: false,
+            shown_at: Instant::now(),
+        }
+    }
+
+    /// Restarts the display timer, e.g. once a picture finishes solving.
+    pub fn restart_timer(&mut self) {
+        self.shown_at = Instant::now();
+    }
+
+    /// File name of the picture on screen.
+    pub fn current_name(&self) -> String {
+        let path = &self.files[self.current];
+        path.file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Builds the model for the first readable picture in the folder.
+pub fn start(options: Options, dir: &str) -> Model {
+    let slideshow = Slideshow::new(&options, dir);
+    open(options, slideshow, 0, true)
+}
+
+/// Moves to the next or previous picture.
+pub fn step(app: &App, model: &mut Model, forward: bool) {
+    let slideshow = match model.slideshow.take() {
+        Some(slideshow) => slideshow,
+        None => return,
+    };
+    let count = slideshow.files.len();
+    let start = if forward {
+        (slideshow.current + 1) % count
+    } else {
+        (slideshow.current + count - 1) % count
+    };
+    let keys = std::mem::take(&mut model.keys);
+    *model = open(model.options.clone(), slideshow, start, forward);
+    model.keys = keys;
+    app.set_loop_mode(LoopMode::refresh_sync());
+}
+
+/// Advances once the current picture has been up for the interval.
+pub fn tick(app: &App, model: &mut Model) {
+    let due = match &model.slideshow {
+        Some(slideshow) => {
+            !slideshow.paused && slideshow.shown_at.elapsed() >= model.options.interval.0
+        }
+        None => false,
+    };
+    if due {
+        step(app, model, true);
+    }
+}
+
+/// Solves the first picture that loads, trying `start` and then its
+/// neighbors in the direction of travel. Unreadable files are skipped.
+fn open(mut options: Options, mut slideshow: Slideshow, start: usize, forward: bool) -> Model {
+    let count = slideshow.files.len();
+    for attempt in 0..count {
+        let index = if forward {
+            (start + attempt) % count
+        } else {
+            (start + count - attempt) % count
+        };
+        let path = slideshow.files[index].to_string_lossy().into_owned();
+        match source::read_source_image(&path, options.assume_srgb) {
+            Ok(img) => {
+                options.picture_path = path;
+                slideshow.current = index;
+                slideshow.restart_timer();
+                let mut model = build_model_from(options, &img, true);
+                model.slideshow = Some(slideshow);
+                return model;
+            }
+            Err(err) => log::warn!("Skipping {path}: {err}"),
+        }
+    }
+    panic!("No readable pictures in the slideshow folder")
+}
@@ -0,0 +1,304 @@
+use crate::{X_SIZE, Y_SIZE};
+use image::imageops::FilterType;
+use image::{imageops, DynamicImage, ImageDecoder, ImageReader, ImageResult, Rgba, RgbaImage};
+use qcms::{DataType, Intent, Profile, Transform};
+use std::str::FromStr;
+
+/// How the picture is fitted to the grid when their aspect ratios differ.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ScaleMode {
+    /// Distort the picture to cover the grid exactly.
+    #[default]
+    Stretch,
+    /// Keep the aspect ratio and letterbox the unused tiles in black.
+    Fit,
+    /// Keep the aspect ratio and cut off whatever falls outside the grid.
+    Crop,
+}
+
+impl FromStr for ScaleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stretch" => Ok(ScaleMode::Stretch),
+            "fit" => Ok(ScaleMode::Fit),
+            "crop" => Ok(ScaleMode::Crop),
+            _ => Err(format!("unknown scale mode '{s}'")),
+        }
+    }
+}
+
+/// The point of the picture kept aligned with the grid in `Fit` and `Crop`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResizeAnchor {
+    TopLeft,
+    #[default]
+    Center,
+    BottomRight,
+}
+
+impl FromStr for ResizeAnchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(ResizeAnchor::TopLeft),
+            "center" => Ok(ResizeAnchor::Center),
+            "bottom-right" => Ok(ResizeAnchor::BottomRight),
+            _ => Err(format!("unknown resize anchor '{s}'")),
+        }
+    }
+}
+
+impl ResizeAnchor {
+    /// Offset of a span of length `inner` within one of length `outer`.
+    fn offset(self, outer: u32, inner: u32) -> u32 {
+        let slack = outer.abs_diff(inner);
+        match self {
+            ResizeAnchor::TopLeft => 0,
+            ResizeAnchor::Center => slack / 2,
+            ResizeAnchor::BottomRight => slack,
+        }
+    }
+}
+
+/// How `--super-resolution` enlarges the picture before it is fitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UpscaleFilter {
+    /// Repeat each pixel, keeping pixel art's hard edges.
+    #[default]
+    Nearest,
+    /// Blend between neighbouring pixels, for photos.
+    Bilinear,
+}
+
+impl FromStr for UpscaleFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(UpscaleFilter::Nearest),
+            "bilinear" => Ok(UpscaleFilter::Bilinear),
+            _ => Err(format!("unknown upscale filter '{s}'")),
+        }
+    }
+}
+
+/// The picture enlarged `factor` times each way. Fitting a picture smaller
+/// than the grid then samples the enlarged pixels, or the blends between
+/// them, rather than doubling up some source pixels and not others.
+pub fn upscale(img: &DynamicImage, factor: u32, filter: UpscaleFilter) -> DynamicImage {
+    let filter = match filter {
+        UpscaleFilter::Nearest => FilterType::Nearest,
+        UpscaleFilter::Bilinear => FilterType::Triangle,
+    };
+    img.resize_exact(img.width() * factor, img.height() * factor, filter)
+}
+
+/// Scales the picture down to one pixel per tile.
+pub fn resize_to_grid(img: &DynamicImage, mode: ScaleMode, anchor: ResizeAnchor) -> RgbaImage {
+    resize_to(img, mode, anchor, X_SIZE as u32, Y_SIZE as u32)
+}
+
+/// Like `resize_to_grid`, but to `grid_w` by `grid_h` pixels.
+pub fn resize_to(
+    img: &DynamicImage,
+    mode: ScaleMode,
+    anchor: ResizeAnchor,
+    grid_w: u32,
+    grid_h: u32,
+) -> RgbaImage {
+    let (img_w, img_h) = (img.width().max(1), img.height().max(1));
+    match mode {
+        ScaleMode::Stretch => img
+            .resize_exact(grid_w, grid_h, FilterType::Nearest)
+            .to_rgba8(),
+        ScaleMode::Fit => {
+            let scale = f64::min(grid_w as f64 / img_w as f64, grid_h as f64 / img_h as f64);
+            let w = ((img_w as f64 * scale).round() as u32).clamp(1, grid_w);
+            let h = ((img_h as f64 * scale).round() as u32).clamp(1, grid_h);
+            let scaled = img.resize_exact(w, h, FilterType::Nearest).to_rgba8();
+            let mut canvas = RgbaImage::from_pixel(grid_w, grid_h, Rgba([0, 0, 0, 255]));
+            imageops::overlay(
+                &mut canvas,
+                &scaled,
+                anchor.offset(grid_w, w) as i64,
+                anchor.offset(grid_h, h) as i64,
+            );
+            canvas
+        }
+        ScaleMode::Crop => {
+            let scale = f64::max(grid_w as f64 / img_w as f64, grid_h as f64 / img_h as f64);
+            let w = ((img_w as f64 * scale).round() as u32).max(grid_w);
+            let h = ((img_h as f64 * scale).round() as u32).max(grid_h);
+            let scaled = img.resize_exact(w, h, FilterType::Nearest).to_rgba8();
+            imageops::crop_imm(
+                &scaled,
+                anchor.offset(w, grid_w),
+                anchor.offset(h, grid_h),
+                grid_w,
+                grid_h,
+            )
+            .to_image()
+        }
+    }
+}
+
+/// Decodes the picture at `path` and converts it to sRGB using its embedded
+/// ICC profile, unless `assume_srgb` is set.
+pub fn load_source_image(path: &str, assume_srgb: bool) -> DynamicImage {
+    read_source_image(path, assume_srgb)
+        .unwrap_or_else(|err| panic!("Failed to decode image {path}: {err}"))
+}
+
+/// Like `load_source_image`, but reports unreadable files instead of
+/// panicking.
+pub fn read_source_image(path: &str, assume_srgb: bool) -> ImageResult<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let icc = decoder.icc_profile().unwrap_or(None);
+    let img = DynamicImage::from_decoder(decoder)?;
+    Ok(convert_to_srgb(img, icc, assume_srgb))
+}
+
+fn convert_to_srgb(img: DynamicImage, icc: Option<Vec<u8>>, assume_srgb: bool) -> DynamicImage {
+    let icc = match icc {
+        Some(icc) => icc,
+        None => {
+            log::info!("No embedded ICC profile; treating image as sRGB.");
+            return img;
+        }
+    };
+    let description = profile_description(&icc).unwrap_or_else(|| "unnamed".to_string());
+    log::info!(
+        "Detected ICC profile '{}' ({}).",
+        description,
+        classify_profile(&description)
+    );
+    if assume_srgb {
+        log::info!("--assume-srgb given; skipping color conversion.");
+        return img;
+    }
+    if icc.get(16..20) != Some(&b"RGB "[..]) {
+        log::info!("Profile is not an RGB profile; skipping color conversion.");
+        return img;
+    }
+
+    let profile = match Profile::new_from_slice(&icc, false) {
+        Some(profile) => profile,
+        None => {
+            log::warn!("Could not parse ICC profile; treating image as sRGB.");
+            return img;
+        }
+    };
+    if profile.is_sRGB() {
+        log::info!("Profile is sRGB; no conversion needed.");
+        return img;
+    }
+    let mut srgb = Profile::new_sRGB();
+    srgb.precache_output_transform();
+    let transform = match Transform::new(&profile, &srgb, DataType::RGBA8, Intent::Perceptual) {
+        Some(transform) => transform,
+        None => {
+            log::warn!("Could not build a transform to sRGB; treating image as sRGB.");
+            return img;
+        }
+    };
+
+    let mut rgba: RgbaImage = img.to_rgba8();
+    transform.apply(&mut rgba);
+    log::info!("Converted image from '{}' to sRGB.", description);
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Names the well-known color spaces so the log line reads at a glance.
+fn classify_profile(description: &str) -> &'static str {
+    let lower = description.to_lowercase();
+    if lower.contains("srgb") {
+        "sRGB"
+    } else if lower.contains("p3") {
+        "Display P3"
+    } else if lower.contains("adobe rgb") {
+        "Adobe RGB"
+    } else {
+        "other"
+    }
+}
+
+/// Reads the `desc` tag of an ICC profile, handling both the v2 `desc` and v4
+/// `mluc` encodings.
+fn profile_description(icc: &[u8]) -> Option<String> {
+    let read_u32 = |at: usize| -> Option<usize> {
+        let bytes = icc.get(at..at + 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let tag_count = read_u32(128)?;
+    for tag in 0..tag_count {
+        let entry = 132 + tag * 12;
+        if icc.get(entry..entry + 4)? != b"desc" {
+            continue;
+        }
+        let offset = read_u32(entry + 4)?;
+        let data = icc.get(offset..offset + read_u32(entry + 8)?)?;
+        return match data.get(0..4)? {
+            b"desc" => {
+                let len = u32::from_be_bytes(data.get(8..12)?.try_into().ok()?) as usize;
+                let text = data.get(12..12 + len)?;
+                Some(
+                    String::from_utf8_lossy(text)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                )
+            }
+            b"mluc" => {
+                let len = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?) as usize;
+                let start = u32::from_be_bytes(data.get(24..28)?.try_into().ok()?) as usize;
+                let units: Vec<u16> = data
+                    .get(start..start + len)?
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                Some(String::from_utf16_lossy(&units))
+            }
+            _ => None,
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wide_white_image() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(96, 48, Rgba([255, 255, 255, 255])))
+    }
+
+    #[test]
+    fn fit_letterboxes_at_anchor() {
+        let top = resize_to_grid(&wide_white_image(), ScaleMode::Fit, ResizeAnchor::TopLeft);
+        assert_eq!(top.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(top.get_pixel(0, 47).0, [0, 0, 0, 255]);
+
+        let bottom = resize_to_grid(
+            &wide_white_image(),
+            ScaleMode::Fit,
+            ResizeAnchor::BottomRight,
+        );
+        assert_eq!(bottom.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(bottom.get_pixel(0, 47).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn crop_keeps_anchored_edge() {
+        let mut img = RgbaImage::from_pixel(96, 48, Rgba([255, 255, 255, 255]));
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let left = resize_to_grid(&img, ScaleMode::Crop, ResizeAnchor::TopLeft);
+        assert_eq!(left.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        let right = resize_to_grid(&img, ScaleMode::Crop, ResizeAnchor::BottomRight);
+        assert_eq!(right.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+}
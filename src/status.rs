@@ -0,0 +1,268 @@
+//! Exit codes and the machine-readable output asked for by scripts:
+//! `--error-format json` for failures and `--summary-json` for headless
+//! runs that succeed.
+//!
+//! Failures stay panics. A panic hook logs them as errors, or reports
+//! internal ones as the usual panic with its location, and decides the exit
+//! code from the stage the run was in when it panicked, set with `during`,
+//! unless the panicking code called `classify` first to name the failure and
+//! attach details.
+
+use crate::{assign, Model};
+use serde_json::{json, Map, Value};
+use std::env;
+use std::panic;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+
+/// The failure classes scripts can tell apart by exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Arguments,
+    Image,
+    Palette,
+    Inventory,
+    Export,
+    /// Anything else; a bug rather than a bad input.
+    Internal,
+}
+
+impl Failure {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Failure::Arguments => 2,
+            Failure::Image => 3,
+            Failure::Palette => 4,
+            Failure::Inventory => 5,
+            Failure::Export => 6,
+            // What an uncaught panic exits with anyway.
+            Failure::Internal => 101,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Failure::Arguments => "bad_arguments",
+            Failure::Image => "unreadable_image",
+            Failure::Palette => "invalid_palette",
+            Failure::Inventory => "insufficient_inventory",
+            Failure::Export => "export_failed",
+            Failure::Internal => "internal",
+        }
+    }
+}
+
+/// How failures are printed on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// The usual panic message.
+    #[default]
+    Text,
+    /// One JSON object with `code`, `exit_code`, `message`, and `details`.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Unknown error format '{s}'; use text or json")),
+        }
+    }
+}
+
+static STAGE: Mutex<Failure> = Mutex::new(Failure::Internal);
+static CLASSIFIED: Mutex<Option<(Failure, Value)>> = Mutex::new(None);
+
+/// Installs the panic hook. The format is read straight from the process
+/// arguments so that mistakes in the rest of them are reported in it too.
+pub fn install() {
+    let args: Vec<String> = env::args().collect();
+    let format = args
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0] == "--error-format")
+        .and_then(|pair| pair[1].parse().ok())
+        .unwrap_or_default();
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let (failure, details) = CLASSIFIED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .unwrap_or_else(|| (stage(), Value::Null));
+        let message = info.payload_as_str().unwrap_or("Unknown error");
+        match format {
+            // Bugs keep the usual report with where they happened.
+            ErrorFormat::Text if failure == Failure::Internal => default(info),
+            ErrorFormat::Text => log::error!("{message}"),
+            ErrorFormat::Json => eprintln!("{}", error_json(failure, message, details)),
+        }
+        std::process::exit(failure.exit_code());
+    }));
+}
+
+fn stage() -> Failure {
+    *STAGE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs `f` with panics inside it reported as `failure`.
+pub fn during<T>(failure: Failure, f: impl FnOnce() -> T) -> T {
+    let outer = stage();
+    *STAGE.lock().unwrap() = failure;
+    let result = f();
+    *STAGE.lock().unwrap() = outer;
+    result
+}
+
+/// Names the failure behind the panic that follows, with `details` for its
+/// JSON report.
+pub fn classify(failure: Failure, details: Value) {
+    *CLASSIFIED.lock().unwrap() = Some((failure, details));
+}
+
+pub fn error_json(failure: Failure, message: &str, details: Value) -> Value {
+    json!({
+        "code": failure.code(),
+        "exit_code": failure.exit_code(),
+        "message": message,
+        "details": details,
+    })
+}
+
+/// What a successful headless run wrote, keyed by the flag that asked for
+/// it, and how well the mosaic matches the picture.
+pub fn summary(model: &Model) -> Value {
+    let options = &model.options;
+    let mut outputs = Map::new();
+    for (flag, path) in [
+        ("--output", &options.output),
+        ("--export-webp", &options.export_webp),
+        ("--report", &options.report),
+        ("--export-json", &options.export_json),
+        (
+            "--export-pdf-color-sheets",
+            &options.export_pdf_color_sheets,
+        ),
+        ("--export-label-sheet", &options.export_label_sheet),
+        ("--export-fuse-beads", &options.export_fuse_beads),
+        ("--export-stickersheet", &options.export_stickersheet),
+        ("--export-cross-stitch", &options.export_cross_stitch),
+        ("--export-color-book", &options.export_color_book),
+        ("--export-pdf-bom", &options.export_pdf_bom),
+        ("--export-textile-chart", &options.export_textile_chart),
+        ("--export-packing-list", &options.export_packing_list),
+        ("--export-png-sections", &options.export_png_sections),
+        (
+            "--export-sprite-animation",
+            &options.export_sprite_animation,
+        ),
+        ("--export-lottie", &options.export_lottie),
+        ("--export-braille", &options.export_braille),
+        ("--export-png-thumbnail", &options.export_png_thumbnail),
+        ("--export-svg", &options.export_svg),
+        ("--export-svg-animation", &options.export_svg_animation),
+        ("--export-color-steps", &options.export_color_steps),
+        ("--export-before-after", &options.export_before_after),
+        ("--save-bundle", &options.save_bundle),
+        ("--export-png-indexed", &options.export_png_indexed),
+        ("--export-png-atlas", &options.export_png_atlas),
+        ("--export-stardew", &options.export_stardew),
+        ("--export-png-band", &options.export_png_band),
+        ("--export-gimp-palette", &options.export_gimp_palette),
+        ("--export-css-variables", &options.export_css_variables),
+        ("--export-android-theme", &options.export_android_theme),
+        ("--export-game-asset", &options.export_game_asset),
+        ("--export-qrcode-palette", &options.export_qrcode_palette),
+        ("--export-xls", &options.export_xls),
+        ("--export-bricks", &options.export_bricks),
+        ("--export-openscad", &options.export_openscad),
+        ("--export-blender", &options.export_blender),
+        ("--export-kml", &options.export_kml),
+        ("--export-png-composited", &options.export_png_composited),
+        (
+            "--export-png-numbered-grid",
+            &options.export_png_numbered_grid,
+        ),
+        (
+            "--export-png-column-labels",
+            &options.export_png_column_labels,
+        ),
+        ("--export-xcf", &options.export_xcf),
+        ("--export-ics", &options.export_ics),
+        ("--export-discord-embed", &options.export_discord_embed),
+        ("--export-latex", &options.export_latex),
+        ("--export-png-split-rgb", &options.export_png_split_rgb),
+        (
+            "--export-png-watermarked-grid",
+            &options.export_png_watermarked_grid,
+        ),
+        ("--export-stl", &options.export_stl),
+        ("--export-obj", &options.export_obj),
+        ("--export-fritzing", &options.export_fritzing),
+        ("--export-kicad", &options.export_kicad),
+        ("--export-embroidery", &options.export_embroidery),
+        ("--output-aseprite", &options.output_aseprite),
+        ("--export-openraster", &options.export_openraster),
+        ("--export-timelapse-json", &options.export_timelapse_json),
+        ("--export-minecraft-map", &options.export_minecraft_map),
+        ("--export-segments", &options.export_segments),
+        ("--debug-dump", &options.debug_dump),
+        ("--palette-out", &options.palette_out),
+        ("--palette-html-preview", &options.palette_html_preview),
+        ("--export", &options.export_preset),
+    ] {
+        if let Some(path) = path {
+            outputs.insert(flag.to_string(), json!(path));
+        }
+    }
+    if let Some((_, path)) = &options.export_png_zoom {
+        outputs.insert("--export-png-zoom".to_string(), json!(path));
+    }
+    let stats = assign::statistics(
+        &model.reference_pixels,
+        &model.assignment,
+        &model.palette,
+        &model.emphasis,
+    );
+    let used = assign::usage(&model.assignment, model.palette.colors.len());
+    json!({
+        "outputs": outputs,
+        "seed": model.seed,
+        "tiles": model.assignment.len(),
+        "empty_tiles": model.holes.len(),
+        "colors_used": used.iter().filter(|&&count| count > 0).count(),
+        "average_error": stats.average_error,
+        "substitutions": stats.fallbacks,
+        "forced_tiles": model.forced_tiles().len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_carry_code_and_details() {
+        let details = json!({"shortfall": [{"color": "Black", "needed": 2, "available": 1}]});
+        let error = error_json(Failure::Inventory, "Not enough pieces", details.clone());
+        assert_eq!(error["code"], "insufficient_inventory");
+        assert_eq!(error["exit_code"], 5);
+        assert_eq!(error["message"], "Not enough pieces");
+        assert_eq!(error["details"], details);
+        assert_eq!("json".parse::<ErrorFormat>(), Ok(ErrorFormat::Json));
+        assert!("yaml".parse::<ErrorFormat>().is_err());
+    }
+
+    #[test]
+    fn stages_nest() {
+        let inner = during(Failure::Image, || {
+            during(Failure::Export, || assert_eq!(stage(), Failure::Export));
+            stage()
+        });
+        assert_eq!(inner, Failure::Image);
+    }
+}
@@ -0,0 +1,183 @@
+//! The diagnostic reports printed on stdout, as a text table for reading or
+//! as JSON or CSV for scripts, picked with `--color-report-format`.
+
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// Aligned columns, or the report's own sentences.
+    #[default]
+    Text,
+    /// An array of objects, one per row, keyed by column.
+    Json,
+    /// A header line of column names, then one line per row.
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(format!(
+                "Unknown report format '{s}'; use text, json, or csv"
+            )),
+        }
+    }
+}
+
+/// Rows of named columns. Cells are JSON values so that numbers stay
+/// numbers in JSON output.
+pub struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: &[&'static str]) -> Table {
+        Table {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    /// The rows as JSON objects.
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = self
+                        .columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(row.iter().cloned())
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.columns.join(",");
+        csv.push('\n');
+        for row in &self.rows {
+            let cells: Vec<String> = row.iter().map(|cell| csv_field(&plain(cell))).collect();
+            csv.push_str(&cells.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Columns padded to their widest cell, numbers to the right.
+    pub fn to_text(&self) -> String {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(plain).collect())
+            .collect();
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|column| {
+                cells
+                    .iter()
+                    .map(|row| row[column].len())
+                    .chain([self.columns[column].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let numeric: Vec<bool> = (0..self.columns.len())
+            .map(|column| {
+                !self.rows.is_empty() && self.rows.iter().all(|row| row[column].is_number())
+            })
+            .collect();
+        let line = |row: &[String]| {
+            let padded: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(column, cell)| {
+                    let width = widths[column];
+                    if numeric[column] {
+                        format!("{cell:>width$}")
+                    } else {
+                        format!("{cell:<width$}")
+                    }
+                })
+                .collect();
+            padded.join("  ").trim_end().to_string() + "\n"
+        };
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column.to_string())
+            .collect();
+        let mut text = line(&header);
+        for row in &cells {
+            text.push_str(&line(row));
+        }
+        text
+    }
+
+    /// Prints the table; text is the caller's to word when it wants
+    /// sentences instead.
+    pub fn print(&self, format: ReportFormat) {
+        match format {
+            ReportFormat::Text => print!("{}", self.to_text()),
+            ReportFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&self.to_json()).expect("Report should serialize.")
+            ),
+            ReportFormat::Csv => print!("{}", self.to_csv()),
+        }
+    }
+}
+
+/// A cell as it reads in text and CSV: strings without quotes.
+fn plain(cell: &Value) -> String {
+    match cell {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tables_render_in_every_format() {
+        let mut table = Table::new(&["name", "assigned"]);
+        table.push(vec![json!("Red, dark"), json!(12)]);
+        table.push(vec![json!("Blue"), json!(3)]);
+        assert_eq!(
+            table.to_json(),
+            json!([{"name": "Red, dark", "assigned": 12}, {"name": "Blue", "assigned": 3}])
+        );
+        assert_eq!(table.to_csv(), "name,assigned\n\"Red, dark\",12\nBlue,3\n");
+        assert_eq!(
+            table.to_text(),
+            "name       assigned\nRed, dark        12\nBlue              3\n"
+        );
+        assert_eq!("csv".parse::<ReportFormat>(), Ok(ReportFormat::Csv));
+    }
+}
@@ -0,0 +1,180 @@
+//! Words spelled out in tiles with `--text`, one tile per dot of the 5x7
+//! label font, optionally on a background box one tile wider all round.
+//! The banner's tiles are locked to their colors; see `locks`.
+
+use crate::cli::Options;
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::{X_SIZE, Y_SIZE};
+use std::str::FromStr;
+
+/// Where the banner sits on the grid. It is always centred across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextPosition {
+    Top,
+    Center,
+    #[default]
+    Bottom,
+}
+
+impl FromStr for TextPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top" => Ok(TextPosition::Top),
+            "center" => Ok(TextPosition::Center),
+            "bottom" => Ok(TextPosition::Bottom),
+            _ => Err(format!("expected top, center, or bottom, got '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ink {
+    Text,
+    Background,
+}
+
+#[derive(Debug, Clone)]
+pub struct Banner {
+    /// The ink of every tile in raster order; `None` for tiles left to the
+    /// solver.
+    inks: Vec<Option<Ink>>,
+    text_color: String,
+    background_color: Option<String>,
+}
+
+impl Banner {
+    /// The banner `--text` asks for, if any.
+    pub fn from_options(options: &Options) -> Option<Banner> {
+        let text = options.text.as_deref()?;
+        let text_color = options
+            .text_color
+            .clone()
+            .expect("--text needs --text-color");
+        Some(
+            Banner::new(
+                text,
+                options.text_position,
+                text_color,
+                options.text_background.clone(),
+            )
+            .unwrap_or_else(|err| panic!("{err}")),
+        )
+    }
+
+    /// Lays `text` out in a single line, failing when it is wider than the
+    /// grid.
+    pub fn new(
+        text: &str,
+        position: TextPosition,
+        text_color: String,
+        background_color: Option<String>,
+    ) -> Result<Banner, String> {
+        let width = font::text_width(text, 1) as u64;
+        if width == 0 {
+            return Err("--text cannot be empty".to_string());
+        }
+        if width > X_SIZE {
+            return Err(format!(
+                "--text '{text}' is {width} tiles wide but the grid has only {X_SIZE}"
+            ));
+        }
+        // The box adds a tile of background on every side, clipped at the
+        // grid's edges.
+        let box_height = GLYPH_HEIGHT as u64 + 2;
+        let box_bottom = match position {
+            TextPosition::Bottom => 0,
+            TextPosition::Center => (Y_SIZE - box_height) / 2,
+            TextPosition::Top => Y_SIZE - box_height,
+        };
+        let left = (X_SIZE - width) / 2;
+        let mut inks = vec![None; (X_SIZE * Y_SIZE) as usize];
+        if background_color.is_some() {
+            for y in box_bottom..box_bottom + box_height {
+                for x in left.saturating_sub(1)..(left + width + 1).min(X_SIZE) {
+                    inks[(y * X_SIZE + x) as usize] = Some(Ink::Background);
+                }
+            }
+        }
+        // Glyph rows run from the top down, grid rows from the bottom up.
+        let top = box_bottom + GLYPH_HEIGHT as u64;
+        for (index, c) in text.chars().enumerate() {
+            let glyph_left = left + index as u64 * (GLYPH_WIDTH as u64 + 1);
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        let (x, y) = (glyph_left + column as u64, top - row as u64);
+                        inks[(y * X_SIZE + x) as usize] = Some(Ink::Text);
+                    }
+                }
+            }
+        }
+        Ok(Banner {
+            inks,
+            text_color,
+            background_color,
+        })
+    }
+
+    /// Every tile of the banner with the palette color it is locked to.
+    pub fn tiles(&self) -> Vec<(usize, String)> {
+        self.inks
+            .iter()
+            .enumerate()
+            .filter_map(|(tile, ink)| {
+                let name = match (*ink)? {
+                    Ink::Text => &self.text_color,
+                    Ink::Background => self.background_color.as_ref()?,
+                };
+                Some((tile, name.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banner_boxes_its_letters() {
+        let banner = Banner::new(
+            "HI",
+            TextPosition::Bottom,
+            "White".to_string(),
+            Some("Black".to_string()),
+        )
+        .unwrap();
+        let tiles = banner.tiles();
+        // "HI" is 11 tiles wide, so its box is 13 by 9 from the bottom row.
+        assert_eq!(tiles.len(), 13 * 9);
+        // H has 17 dots and I has 11.
+        let white = tiles.iter().filter(|(_, name)| name == "White").count();
+        assert_eq!(white, 17 + 11);
+        // The H's left stroke runs from row 1 to row 7; row 0 is padding.
+        let left = (X_SIZE - 11) / 2;
+        let at = |x: u64, y: u64| {
+            let tile = (y * X_SIZE + x) as usize;
+            tiles
+                .iter()
+                .find(|(locked, _)| *locked == tile)
+                .map(|(_, name)| name.as_str())
+        };
+        assert_eq!(at(left, 7), Some("White"));
+        assert_eq!(at(left, 0), Some("Black"));
+        assert_eq!(at(left, 9), None);
+    }
+
+    #[test]
+    fn text_wider_than_the_grid_is_refused() {
+        let err = Banner::new(
+            "MOSAIC FOR YOU",
+            TextPosition::Top,
+            "White".to_string(),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("83 tiles wide"), "{err}");
+    }
+}
@@ -0,0 +1,353 @@
+//! The window's tiles as a single mesh of colored triangles instead of a
+//! rectangle or polygon apiece, which nannou records, tessellates, and
+//! uploads one by one.
+//!
+//! Each pane keeps the last mesh it drew and rebuilds it only when the
+//! tiles it shows or its place in the window change. The benchmark at the
+//! bottom compares both ways at several grid sizes:
+//! `cargo test --release tile_mesh -- --ignored --nocapture`.
+
+use crate::hex::HexOrientation;
+use nannou::color::Srgb;
+use nannou::prelude::*;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A tile, or an adaptive cell of `size` tiles a side, in the color it is
+/// shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shown {
+    pub x: u64,
+    pub y: u64,
+    pub size: u64,
+    pub rgb: (u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Squares on a `columns` by `rows` grid, with a corner folded under
+    /// when `fold` is set.
+    Square {
+        columns: u64,
+        rows: u64,
+        fold: Option<Fold>,
+    },
+    Hex(HexOrientation),
+}
+
+/// The corner `--tile-corner-fold` folds under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldCorner {
+    TopRight,
+    BottomLeft,
+}
+
+impl FromStr for FoldCorner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-right" => Ok(FoldCorner::TopRight),
+            "bottom-left" => Ok(FoldCorner::BottomLeft),
+            _ => Err(format!("expected top-right or bottom-left, got '{s}'")),
+        }
+    }
+}
+
+/// A corner cut from every square, `size` pixels along both of its edges,
+/// for an origami look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fold {
+    pub corner: FoldCorner,
+    pub size: f32,
+}
+
+impl Hash for Shape {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Shape::Square {
+                columns,
+                rows,
+                fold,
+            } => {
+                (0, columns, rows).hash(state);
+                fold.map(|fold| (fold.corner as u8, fold.size.to_bits()))
+                    .hash(state);
+            }
+            Shape::Hex(HexOrientation::Pointy) => 1.hash(state),
+            Shape::Hex(HexOrientation::Flat) => 2.hash(state),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TileMesh {
+    points: Vec<(Point3, Srgb<u8>)>,
+    indices: Vec<usize>,
+}
+
+impl TileMesh {
+    /// `tiles` laid out over `area`, each parted from its neighbours by the
+    /// one-pixel gap the grid has always had.
+    pub fn build(area: Rect, tiles: &[Shown], shape: Shape) -> TileMesh {
+        let corners = if let Shape::Hex(_) = shape { 6 } else { 4 };
+        let mut mesh = TileMesh {
+            points: Vec::with_capacity(tiles.len() * corners),
+            indices: Vec::with_capacity(tiles.len() * (corners - 2) * 3),
+        };
+        let at =
+            |(fx, fy): (f32, f32)| pt2(area.left() + fx * area.w(), area.bottom() + fy * area.h());
+        for tile in tiles {
+            let (r, g, b) = tile.rgb;
+            let color = srgb8(r, g, b);
+            match shape {
+                Shape::Square {
+                    columns,
+                    rows,
+                    fold,
+                } => {
+                    let width = area.w() / columns as f32;
+                    let height = area.h() / rows as f32;
+                    let size = tile.size as f32;
+                    let center = pt2(
+                        area.left() + (tile.x as f32 + size / 2.0) * width,
+                        area.bottom() + (tile.y as f32 + size / 2.0) * height,
+                    );
+                    let half = vec2(size * width - 1.0, size * height - 1.0) / 2.0;
+                    let corners = [
+                        center + vec2(-half.x, -half.y),
+                        center + vec2(half.x, -half.y),
+                        center + half,
+                        center + vec2(-half.x, half.y),
+                    ];
+                    match fold {
+                        Some(fold) => mesh.polygon(&folded(corners, fold), color),
+                        None => mesh.polygon(&corners, color),
+                    }
+                }
+                Shape::Hex(orientation) => {
+                    let center = at(orientation.center(tile.x, tile.y));
+                    // Pull the corners in a pixel so neighbours are parted
+                    // like squares.
+                    let corners = orientation.corners(tile.x, tile.y).map(|corner| {
+                        let corner = at(corner);
+                        corner - (corner - center).normalize_or_zero()
+                    });
+                    mesh.polygon(&corners, color);
+                }
+            }
+        }
+        mesh
+    }
+
+    /// A convex polygon as a fan of triangles from its first corner.
+    fn polygon(&mut self, corners: &[Point2], color: Srgb<u8>) {
+        let first = self.points.len();
+        self.points
+            .extend(corners.iter().map(|&corner| (corner.extend(0.0), color)));
+        for corner in 1..corners.len() - 1 {
+            self.indices
+                .extend([first, first + corner, first + corner + 1]);
+        }
+    }
+
+    pub fn draw(&self, draw: &Draw) {
+        draw.mesh()
+            .indexed_colored(self.points.iter().copied(), self.indices.iter().copied());
+    }
+}
+
+/// The corners of a square, counter-clockwise from its bottom-left, with
+/// `fold`'s corner replaced by the two ends of the cut. The background
+/// shows through where the corner was. A cut longer than the square's
+/// sides stops at the opposite corners.
+fn folded(corners: [Point2; 4], fold: Fold) -> [Point2; 5] {
+    let [bottom_left, bottom_right, top_right, top_left] = corners;
+    let along = fold.size.min(bottom_right.x - bottom_left.x);
+    let up = fold.size.min(top_left.y - bottom_left.y);
+    match fold.corner {
+        FoldCorner::TopRight => [
+            bottom_left,
+            bottom_right,
+            top_right - vec2(0.0, up),
+            top_right - vec2(along, 0.0),
+            top_left,
+        ],
+        FoldCorner::BottomLeft => [
+            bottom_left + vec2(along, 0.0),
+            bottom_right,
+            top_right,
+            top_left,
+            bottom_left + vec2(0.0, up),
+        ],
+    }
+}
+
+/// The last mesh drawn in each pane, with a hash of what it was built from.
+#[derive(Debug, Default)]
+pub struct MeshCache(RefCell<Vec<Option<(u64, TileMesh)>>>);
+
+impl MeshCache {
+    /// Draws `tiles` in pane `pane`, reusing its mesh from the last frame
+    /// when nothing it was built from has changed.
+    pub fn draw(&self, draw: &Draw, pane: usize, area: Rect, tiles: &[Shown], shape: Shape) {
+        let mut hasher = DefaultHasher::new();
+        for edge in [area.left(), area.right(), area.bottom(), area.top()] {
+            edge.to_bits().hash(&mut hasher);
+        }
+        shape.hash(&mut hasher);
+        tiles.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut meshes = self.0.borrow_mut();
+        if meshes.len() <= pane {
+            meshes.resize_with(pane + 1, || None);
+        }
+        match &meshes[pane] {
+            Some((built, mesh)) if *built == key => mesh.draw(draw),
+            _ => {
+                let mesh = TileMesh::build(area, tiles, shape);
+                mesh.draw(draw);
+                meshes[pane] = Some((key, mesh));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn grid(side: u64) -> Vec<Shown> {
+        (0..side * side)
+            .map(|tile| Shown {
+                x: tile % side,
+                y: tile / side,
+                size: 1,
+                rgb: ((tile % 251) as u8, (tile % 241) as u8, (tile % 239) as u8),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn squares_keep_their_gaps() {
+        let area = Rect::from_x_y_w_h(0.0, 0.0, 20.0, 10.0);
+        let shape = Shape::Square {
+            columns: 2,
+            rows: 1,
+            fold: None,
+        };
+        let tiles = [Shown {
+            x: 1,
+            y: 0,
+            size: 1,
+            rgb: (1, 2, 3),
+        }];
+        let mesh = TileMesh::build(area, &tiles, shape);
+        let corners: Vec<Point2> = mesh
+            .points
+            .iter()
+            .map(|&(point, _)| point.truncate())
+            .collect();
+        assert_eq!(
+            corners,
+            [pt2(0.5, -4.5), pt2(9.5, -4.5), pt2(9.5, 4.5), pt2(0.5, 4.5)]
+        );
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn folds_cut_their_corner() {
+        let area = Rect::from_x_y_w_h(0.0, 0.0, 11.0, 11.0);
+        let tiles = [Shown {
+            x: 0,
+            y: 0,
+            size: 1,
+            rgb: (1, 2, 3),
+        }];
+        let corners = |corner, size| {
+            let shape = Shape::Square {
+                columns: 1,
+                rows: 1,
+                fold: Some(Fold { corner, size }),
+            };
+            let mesh = TileMesh::build(area, &tiles, shape);
+            assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+            mesh.points
+                .iter()
+                .map(|&(point, _)| point.truncate())
+                .collect::<Vec<Point2>>()
+        };
+        assert_eq!(
+            corners(FoldCorner::TopRight, 3.0),
+            [
+                pt2(-5.0, -5.0),
+                pt2(5.0, -5.0),
+                pt2(5.0, 2.0),
+                pt2(2.0, 5.0),
+                pt2(-5.0, 5.0)
+            ]
+        );
+        assert_eq!(
+            corners(FoldCorner::BottomLeft, 30.0),
+            [
+                pt2(5.0, -5.0),
+                pt2(5.0, -5.0),
+                pt2(5.0, 5.0),
+                pt2(-5.0, 5.0),
+                pt2(-5.0, 5.0)
+            ]
+        );
+    }
+
+    /// Times a frame's worth of drawing, a rectangle per tile against one
+    /// mesh, up to the commands nannou's renderer takes. Tessellating and
+    /// uploading them need a GPU and are left out, though the rectangles
+    /// pay for those per tile as well.
+    #[test]
+    #[ignore]
+    fn mesh_outpaces_a_rectangle_per_tile() {
+        const FRAMES: u32 = 20;
+        let area = Rect::from_w_h(960.0, 960.0);
+        for side in [48, 96, 192] {
+            let tiles = grid(side);
+            let shape = Shape::Square {
+                columns: side,
+                rows: side,
+                fold: None,
+            };
+            let draw = Draw::new();
+            let start = Instant::now();
+            for _ in 0..FRAMES {
+                let width = area.w() / side as f32;
+                for tile in &tiles {
+                    let (r, g, b) = tile.rgb;
+                    draw.rect()
+                        .x_y(
+                            area.left() + (tile.x as f32 + 0.5) * width,
+                            area.bottom() + (tile.y as f32 + 0.5) * width,
+                        )
+                        .color(srgb8(r, g, b))
+                        .width(width - 1.0)
+                        .height(width - 1.0);
+                }
+                draw.drain_commands().for_each(drop);
+                draw.reset();
+            }
+            let rectangles = start.elapsed() / FRAMES;
+
+            let cache = MeshCache::default();
+            let start = Instant::now();
+            for _ in 0..FRAMES {
+                cache.draw(&draw, 0, area, &tiles, shape);
+                draw.drain_commands().for_each(drop);
+                draw.reset();
+            }
+            let mesh = start.elapsed() / FRAMES;
+            println!("{side}x{side}: {rectangles:?} per frame as rectangles, {mesh:?} as one mesh");
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Recording the window's solving animation with `--export-timelapse-json`
+//! and playing it back with `--replay-timelapse`.
+//!
+//! A recording is JSON Lines, one object per tile as it appears:
+//! `{"frame":N,"tile_index":K,"x":X,"y":Y,"r":R,"g":G,"b":B}`, with `frame`
+//! counting the frames that revealed rows. Tiles changed by the clean-up
+//! passes once solving finishes follow in one last frame, so the recording
+//! ends on the finished mosaic.
+//!
+//! Playing back solves nothing: the last color of each tile is made into a
+//! one-pixel-per-tile picture and a palette with exactly those pieces,
+//! which match it tile for tile, and the mosaic is then emptied and filled
+//! again from the recording.
+
+use crate::palette::{ColorConfig, ColorConfigs};
+use crate::{assign, Color, Model, Options, X_SIZE, Y_SIZE};
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+/// One tile appearing, as a line of the recording.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub frame: u64,
+    pub tile_index: u64,
+    pub x: u64,
+    pub y: u64,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Entry {
+    fn new(frame: u64, color: &Color) -> Entry {
+        Entry {
+            frame,
+            tile_index: color.y * X_SIZE + color.x,
+            x: color.x,
+            y: color.y,
+            r: color.r,
+            g: color.g,
+            b: color.b,
+        }
+    }
+}
+
+/// Writes the tiles of a solve to the recording as they are revealed.
+pub struct Recorder {
+    path: String,
+    out: BufWriter<File>,
+    frame: u64,
+    tiles: u64,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> Recorder {
+        let file = File::create(path).expect("Unable to write timelapse file.");
+        Recorder {
+            path: path.to_string(),
+            out: BufWriter::new(file),
+            frame: 0,
+            tiles: 0,
+        }
+    }
+
+    /// Records the rows one frame revealed.
+    pub fn record(&mut self, rows: &[Vec<Color>]) {
+        for color in rows.iter().flatten() {
+            self.write(&Entry::new(self.frame, color));
+        }
+        self.frame += 1;
+    }
+
+    fn write(&mut self, entry: &Entry) {
+        let line = serde_json::to_string(entry).expect("Timelapse entries should serialize.");
+        writeln!(self.out, "{line}").expect("Unable to write timelapse file.");
+        self.tiles += 1;
+    }
+
+    /// Records the tiles of `finished` that differ from how they were
+    /// revealed as one last frame, and closes the file.
+    pub fn finish(mut self, revealed: &[Color], finished: &[Color]) {
+        let shown: HashMap<(u64, u64), (u8, u8, u8)> = revealed
+            .iter()
+            .map(|color| ((color.x, color.y), (color.r, color.g, color.b)))
+            .collect();
+        let changed: Vec<&Color> = finished
+            .iter()
+            .filter(|color| shown.get(&(color.x, color.y)) != Some(&(color.r, color.g, color.b)))
+            .collect();
+        for color in &changed {
+            self.write(&Entry::new(self.frame, color));
+        }
+        if !changed.is_empty() {
+            self.frame += 1;
+        }
+        self.out.flush().expect("Unable to write timelapse file.");
+        log::info!(
+            "Wrote {} tiles in {} frames to {}.",
+            self.tiles,
+            self.frame,
+            self.path
+        );
+    }
+}
+
+/// Reads a recording, line by line.
+pub fn load(path: &str) -> Vec<Entry> {
+    let text = fs::read_to_string(path).expect("Could not open timelapse file.");
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            let entry: Entry = serde_json::from_str(line)
+                .unwrap_or_else(|err| panic!("{path} line {}: {err}", number + 1));
+            if entry.x >= X_SIZE || entry.y >= Y_SIZE {
+                panic!("{path} line {}: tile is off the grid", number + 1);
+            }
+            entry
+        })
+        .collect()
+}
+
+/// The color each tile is left with at the end of `entries`, in raster order.
+fn last_colors(entries: &[Entry], path: &str) -> Vec<[u8; 3]> {
+    let mut last = vec![None; (X_SIZE * Y_SIZE) as usize];
+    for entry in entries {
+        last[(entry.y * X_SIZE + entry.x) as usize] = Some([entry.r, entry.g, entry.b]);
+    }
+    let missing = last.iter().filter(|color| color.is_none()).count();
+    if missing > 0 {
+        panic!("{path} never shows {missing} tiles; it is not a complete recording")
+    }
+    last.into_iter().flatten().collect()
+}
+
+/// A picture of one pixel per tile in the recording's last colors.
+pub fn picture(entries: &[Entry], path: &str) -> DynamicImage {
+    let last = last_colors(entries, path);
+    DynamicImage::ImageRgb8(RgbImage::from_fn(X_SIZE as u32, Y_SIZE as u32, |x, row| {
+        // Image rows count down from the top, grid rows up from the bottom.
+        let y = Y_SIZE - 1 - row as u64;
+        Rgb(last[(y * X_SIZE + x as u64) as usize])
+    }))
+}
+
+/// The recording's last colors, named by hex value, each with as many
+/// pieces as tiles end in it.
+pub fn palette(path: &str) -> ColorConfigs {
+    let entries = load(path);
+    let mut counts: Vec<([u8; 3], u64)> = Vec::new();
+    for color in last_colors(&entries, path) {
+        match counts.iter_mut().find(|(seen, _)| *seen == color) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((color, 1)),
+        }
+    }
+    let mut palette = ColorConfigs {
+        colors: counts
+            .into_iter()
+            .map(|([r, g, b], count)| {
+                ColorConfig::new(format!("#{r:02x}{g:02x}{b:02x}"), r, g, b, count)
+            })
+            .collect(),
+    };
+    palette.prepare();
+    palette
+}
+
+/// A recording being played back in the window.
+pub struct Replay {
+    entries: Vec<Entry>,
+    /// The first entry not shown yet.
+    next: usize,
+    /// How far into the recording playback is, in its frames.
+    position: f32,
+    speed: f32,
+}
+
+/// The model `--replay-timelapse` opens with: the recording's finished
+/// mosaic, emptied to be filled in again.
+pub fn replay_model(options: Options, path: &str) -> Model {
+    log::info!("Loading {path}.");
+    let entries = load(path);
+    let speed = options.replay_speed;
+    let mut model = crate::build_model_from(options, &picture(&entries, path), false);
+    model.pixels.clear();
+    model.replay = Some(Replay {
+        entries,
+        next: 0,
+        position: 0.0,
+        speed,
+    });
+    model
+}
+
+/// Whether a recording is still playing back.
+pub fn replaying(model: &Model) -> bool {
+    model.replay.is_some()
+}
+
+/// Shows the tiles of the recording's frames that playback has reached,
+/// `--replay-speed` frames each window frame.
+pub fn tick(model: &mut Model) {
+    let replay = match &mut model.replay {
+        Some(replay) => replay,
+        None => return,
+    };
+    replay.position += replay.speed;
+    while let Some(entry) = replay.entries.get(replay.next) {
+        if entry.frame as f32 > replay.position {
+            break;
+        }
+        let color = Color {
+            r: entry.r,
+            g: entry.g,
+            b: entry.b,
+            x: entry.x,
+            y: entry.y,
+        };
+        match model
+            .pixels
+            .iter_mut()
+            .find(|shown| (shown.x, shown.y) == (color.x, color.y))
+        {
+            Some(shown) => *shown = color,
+            None => model.pixels.push(color),
+        }
+        replay.next += 1;
+    }
+    if replay.next == replay.entries.len() {
+        log::info!("Replayed {} tiles.", replay.entries.len());
+        model.replay = None;
+        // The same tiles, back in raster order for everything else.
+        model.pixels =
+            assign::assigned_pixels(&model.reference_pixels, &model.assignment, &model.palette);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_as_single_lines() {
+        let color = Color {
+            r: 10,
+            g: 20,
+            b: 30,
+            x: 3,
+            y: 2,
+        };
+        let entry = Entry::new(7, &color);
+        let line = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            line,
+            r#"{"frame":7,"tile_index":99,"x":3,"y":2,"r":10,"g":20,"b":30}"#
+        );
+        assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), entry);
+    }
+}
@@ -0,0 +1,190 @@
+//! A writer for indexed GIMP images (`.xcf`).
+//!
+//! Only what a flat pixel-art document needs is written: a version 0 file
+//! with a colormap, and layers of indexed pixels with alpha, stored
+//! uncompressed in GIMP's 64 by 64 pixel tiles. All values are big-endian.
+
+use std::fs;
+
+/// An indexed layer of `width` by `height` pixels at (`x`, `y`) on the
+/// canvas. `pixels` holds a colormap index and an alpha byte per pixel,
+/// row by row from the top.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// An indexed image. The first layer is drawn on top.
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// At most 256 colors, which the layers' pixels index.
+    pub colormap: Vec<[u8; 3]>,
+    pub layers: Vec<Layer>,
+}
+
+const MAGIC: &[u8; 14] = b"gimp xcf file\0";
+const INDEXED: u32 = 2;
+const INDEXED_ALPHA: u32 = 5;
+const PROP_END: u32 = 0;
+const PROP_COLORMAP: u32 = 1;
+const PROP_OPACITY: u32 = 6;
+const PROP_VISIBLE: u32 = 8;
+const PROP_OFFSETS: u32 = 15;
+const PROP_COMPRESSION: u32 = 17;
+const TILE: u32 = 64;
+/// Bytes per pixel of an indexed layer with alpha.
+const BPP: u32 = 2;
+
+impl Image {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        assert!(self.colormap.len() <= 256, "XCF colormaps hold 256 colors.");
+        let mut out = MAGIC.to_vec();
+        put_u32(&mut out, self.width);
+        put_u32(&mut out, self.height);
+        put_u32(&mut out, INDEXED);
+        let mut colormap = Vec::new();
+        put_u32(&mut colormap, self.colormap.len() as u32);
+        colormap.extend(self.colormap.concat());
+        put_property(&mut out, PROP_COLORMAP, &colormap);
+        put_property(&mut out, PROP_COMPRESSION, &[0]); // None.
+        put_property(&mut out, PROP_END, &[]);
+
+        // Layer pointers, then an empty list of channel pointers; the
+        // layers follow, each pointer filled in once its layer's offset
+        // is known.
+        let pointers = out.len();
+        out.resize(pointers + 4 * (self.layers.len() + 2), 0);
+        for (index, layer) in self.layers.iter().enumerate() {
+            let at = out.len() as u32;
+            set_u32(&mut out, pointers + 4 * index, at);
+            write_layer(&mut out, layer);
+        }
+        out
+    }
+
+    pub fn save(&self, path: &str) {
+        fs::write(path, self.to_bytes()).expect("Unable to write XCF file.");
+    }
+}
+
+fn write_layer(out: &mut Vec<u8>, layer: &Layer) {
+    put_u32(out, layer.width);
+    put_u32(out, layer.height);
+    put_u32(out, INDEXED_ALPHA);
+    put_string(out, &layer.name);
+    put_property(out, PROP_OPACITY, &255u32.to_be_bytes());
+    put_property(out, PROP_VISIBLE, &1u32.to_be_bytes());
+    let mut offsets = layer.x.to_be_bytes().to_vec();
+    offsets.extend(layer.y.to_be_bytes());
+    put_property(out, PROP_OFFSETS, &offsets);
+    put_property(out, PROP_END, &[]);
+    let hierarchy_pointer = out.len();
+    put_u32(out, 0);
+    put_u32(out, 0); // No layer mask.
+
+    let hierarchy = out.len() as u32;
+    set_u32(out, hierarchy_pointer, hierarchy);
+    put_u32(out, layer.width);
+    put_u32(out, layer.height);
+    put_u32(out, BPP);
+    let level_pointer = out.len();
+    // The first level, then the end of the unused smaller levels.
+    put_u32(out, 0);
+    put_u32(out, 0);
+
+    let level = out.len() as u32;
+    set_u32(out, level_pointer, level);
+    put_u32(out, layer.width);
+    put_u32(out, layer.height);
+    let (across, down) = (layer.width.div_ceil(TILE), layer.height.div_ceil(TILE));
+    let tile_pointers = out.len();
+    out.resize(tile_pointers + 4 * (across * down + 1) as usize, 0);
+    // Tiles run across then down, each holding its pixels row by row.
+    for tile in 0..across * down {
+        let at = out.len() as u32;
+        set_u32(out, tile_pointers + 4 * tile as usize, at);
+        let (left, top) = (tile % across * TILE, tile / across * TILE);
+        let right = (left + TILE).min(layer.width);
+        for y in top..(top + TILE).min(layer.height) {
+            let start = ((y * layer.width + left) * BPP) as usize;
+            let end = ((y * layer.width + right) * BPP) as usize;
+            out.extend(&layer.pixels[start..end]);
+        }
+    }
+}
+
+fn put_property(out: &mut Vec<u8>, kind: u32, payload: &[u8]) {
+    put_u32(out, kind);
+    put_u32(out, payload.len() as u32);
+    out.extend(payload);
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_be_bytes());
+}
+
+fn set_u32(out: &mut [u8], at: usize, value: u32) {
+    out[at..at + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+/// A length counting the closing NUL, then the bytes and the NUL.
+fn put_string(out: &mut Vec<u8>, value: &str) {
+    put_u32(out, value.len() as u32 + 1);
+    out.extend(value.as_bytes());
+    out.push(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointers_reach_layers_and_tiles() {
+        // Wide enough for two tiles across.
+        let (width, height) = (70, 2);
+        let mut pixels = vec![0; (width * height * BPP) as usize];
+        pixels[(width * BPP + 69 * BPP) as usize..].copy_from_slice(&[1, 255]);
+        let image = Image {
+            width,
+            height,
+            colormap: vec![[255, 0, 0], [0, 0, 255]],
+            layers: vec![Layer {
+                name: "Blue".to_string(),
+                x: 0,
+                y: 0,
+                width,
+                height,
+                pixels,
+            }],
+        };
+        let bytes = image.to_bytes();
+        let u32_at = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        assert_eq!(&bytes[..14], MAGIC);
+        assert_eq!((u32_at(14), u32_at(18), u32_at(22)), (70, 2, INDEXED));
+        // The colormap property: type, length, count, colors.
+        assert_eq!((u32_at(26), u32_at(30), u32_at(34)), (PROP_COLORMAP, 10, 2));
+        assert_eq!(&bytes[38..44], &[255, 0, 0, 0, 0, 255]);
+
+        let pointers = 44 + 9 + 8;
+        let layer = u32_at(pointers) as usize;
+        assert_eq!((u32_at(pointers + 4), u32_at(pointers + 8)), (0, 0));
+        assert_eq!(u32_at(layer + 8), INDEXED_ALPHA);
+        assert_eq!(&bytes[layer + 16..layer + 21], b"Blue\0");
+        // Opacity, visibility, and offsets properties, then the end.
+        let hierarchy = u32_at(layer + 21 + 12 + 12 + 16 + 8) as usize;
+        assert_eq!(u32_at(hierarchy + 8), BPP);
+        let level = u32_at(hierarchy + 12) as usize;
+        let (first, second) = (u32_at(level + 8) as usize, u32_at(level + 12) as usize);
+        assert_eq!(u32_at(level + 16), 0);
+        assert_eq!(second - first, (64 * 2 * BPP) as usize);
+        assert_eq!(bytes.len() - second, (6 * 2 * BPP) as usize);
+        assert_eq!(&bytes[bytes.len() - 2..], &[1, 255]);
+    }
+}
@@ -0,0 +1,356 @@
+//! A writer for simple Excel workbooks (`.xlsx`).
+//!
+//! A workbook is a zip archive of SpreadsheetML parts. Only what filled,
+//! labelled cells need is written: one worksheet part per sheet with inline
+//! strings, and a stylesheet with a fill per distinct cell color. Text on a
+//! dark fill is drawn white.
+
+use crate::zip;
+use std::fmt::Write as _;
+use std::fs;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Empty,
+    Text(String),
+    Number(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub value: Value,
+    pub fill: Option<[u8; 3]>,
+    pub bold: bool,
+}
+
+impl Cell {
+    pub fn empty() -> Cell {
+        Cell {
+            value: Value::Empty,
+            fill: None,
+            bold: false,
+        }
+    }
+
+    pub fn text(text: impl Into<String>) -> Cell {
+        Cell {
+            value: Value::Text(text.into()),
+            fill: None,
+            bold: false,
+        }
+    }
+
+    pub fn number(number: impl Into<f64>) -> Cell {
+        Cell {
+            value: Value::Number(number.into()),
+            fill: None,
+            bold: false,
+        }
+    }
+
+    pub fn filled(mut self, rgb: [u8; 3]) -> Cell {
+        self.fill = Some(rgb);
+        self
+    }
+
+    pub fn bold(mut self) -> Cell {
+        self.bold = true;
+        self
+    }
+}
+
+/// Rows of cells from the top. `column_widths` are in Excel's character
+/// units; columns past the end use the last width given.
+#[derive(Debug, Clone, Default)]
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<Vec<Cell>>,
+    pub column_widths: Vec<f32>,
+    /// Row height in points, when not left to Excel.
+    pub row_height: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Workbook {
+    pub sheets: Vec<Sheet>,
+}
+
+/// A cell's look: its fill, and whether the text is bold and white.
+type Style = (Option<[u8; 3]>, bool, bool);
+
+impl Workbook {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut styles: Vec<Style> = vec![(None, false, false)];
+        let mut sheets = Vec::new();
+        for sheet in &self.sheets {
+            sheets.push(worksheet(sheet, &mut styles));
+        }
+
+        let mut parts = vec![
+            ("[Content_Types].xml".to_string(), self.content_types()),
+            ("_rels/.rels".to_string(), ROOT_RELS.to_string()),
+            ("xl/workbook.xml".to_string(), self.workbook()),
+            (
+                "xl/_rels/workbook.xml.rels".to_string(),
+                self.workbook_rels(),
+            ),
+            ("xl/styles.xml".to_string(), stylesheet(&styles)),
+        ];
+        for (index, xml) in sheets.into_iter().enumerate() {
+            parts.push((format!("xl/worksheets/sheet{}.xml", index + 1), xml));
+        }
+        zip::write(&parts)
+    }
+
+    pub fn save(&self, path: &str) {
+        fs::write(path, self.to_bytes()).expect("Unable to write Excel workbook.");
+    }
+
+    fn content_types(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+            r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+            r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+            r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+            r#"<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+        ));
+        for index in 1..=self.sheets.len() {
+            let _ = write!(
+                xml,
+                r#"<Override PartName="/xl/worksheets/sheet{index}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+            );
+        }
+        xml.push_str("</Types>");
+        xml
+    }
+
+    fn workbook(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+        ));
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            let _ = write!(
+                xml,
+                r#"<sheet name="{}" sheetId="{id}" r:id="rId{id}"/>"#,
+                escape(&sheet_name(&sheet.name)),
+                id = index + 1
+            );
+        }
+        xml.push_str("</sheets></workbook>");
+        xml
+    }
+
+    fn workbook_rels(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+        ));
+        for id in 1..=self.sheets.len() {
+            let _ = write!(
+                xml,
+                r#"<Relationship Id="rId{id}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{id}.xml"/>"#
+            );
+        }
+        let _ = write!(
+            xml,
+            r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#,
+            self.sheets.len() + 1
+        );
+        xml.push_str("</Relationships>");
+        xml
+    }
+}
+
+const ROOT_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+    r#"</Relationships>"#,
+);
+
+/// Writes a sheet's part, adding any new cell looks to `styles`.
+fn worksheet(sheet: &Sheet, styles: &mut Vec<Style>) -> String {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+    ));
+    if let Some(height) = sheet.row_height {
+        let _ = write!(
+            xml,
+            r#"<sheetFormatPr defaultRowHeight="{height}" customHeight="1"/>"#
+        );
+    }
+    let columns = sheet.rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns > 0 && !sheet.column_widths.is_empty() {
+        xml.push_str("<cols>");
+        for column in 0..columns {
+            let width = sheet.column_widths[column.min(sheet.column_widths.len() - 1)];
+            let _ = write!(
+                xml,
+                r#"<col min="{n}" max="{n}" width="{width}" customWidth="1"/>"#,
+                n = column + 1
+            );
+        }
+        xml.push_str("</cols>");
+    }
+    xml.push_str("<sheetData>");
+    for (row, cells) in sheet.rows.iter().enumerate() {
+        let _ = write!(xml, r#"<row r="{}""#, row + 1);
+        if let Some(height) = sheet.row_height {
+            let _ = write!(xml, r#" ht="{height}" customHeight="1""#);
+        }
+        xml.push('>');
+        for (column, cell) in cells.iter().enumerate() {
+            let style = (cell.fill, cell.bold, cell.fill.is_some_and(is_dark));
+            let index = match styles.iter().position(|&known| known == style) {
+                Some(index) => index,
+                None => {
+                    styles.push(style);
+                    styles.len() - 1
+                }
+            };
+            let reference = format!("{}{}", column_name(column), row + 1);
+            match &cell.value {
+                Value::Empty => {
+                    let _ = write!(xml, r#"<c r="{reference}" s="{index}"/>"#);
+                }
+                Value::Text(text) => {
+                    let _ = write!(
+                        xml,
+                        r#"<c r="{reference}" s="{index}" t="inlineStr"><is><t>{}</t></is></c>"#,
+                        escape(text)
+                    );
+                }
+                Value::Number(number) => {
+                    let _ = write!(xml, r#"<c r="{reference}" s="{index}"><v>{number}</v></c>"#);
+                }
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Fonts are regular and bold, each in black and white; fills start with
+/// the two Excel reserves.
+fn stylesheet(styles: &[Style]) -> String {
+    let fills: Vec<[u8; 3]> = styles.iter().filter_map(|(fill, ..)| *fill).collect();
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+        r#"<fonts count="4">"#,
+        r#"<font><sz val="11"/><color rgb="FF000000"/><name val="Calibri"/></font>"#,
+        r#"<font><sz val="11"/><color rgb="FFFFFFFF"/><name val="Calibri"/></font>"#,
+        r#"<font><b/><sz val="11"/><color rgb="FF000000"/><name val="Calibri"/></font>"#,
+        r#"<font><b/><sz val="11"/><color rgb="FFFFFFFF"/><name val="Calibri"/></font>"#,
+        r#"</fonts>"#,
+    ));
+    let _ = write!(
+        xml,
+        r#"<fills count="{}"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill>"#,
+        fills.len() + 2
+    );
+    for [r, g, b] in &fills {
+        let _ = write!(
+            xml,
+            r#"<fill><patternFill patternType="solid"><fgColor rgb="FF{r:02X}{g:02X}{b:02X}"/><bgColor indexed="64"/></patternFill></fill>"#
+        );
+    }
+    xml.push_str(concat!(
+        r#"</fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders>"#,
+        r#"<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>"#,
+    ));
+    let _ = write!(xml, r#"<cellXfs count="{}">"#, styles.len());
+    let mut fill_id = 2;
+    for &(fill, bold, white) in styles {
+        let font_id = usize::from(bold) * 2 + usize::from(white);
+        let fill = if fill.is_some() {
+            fill_id += 1;
+            fill_id - 1
+        } else {
+            0
+        };
+        let _ = write!(
+            xml,
+            r#"<xf numFmtId="0" fontId="{font_id}" fillId="{fill}" borderId="0" xfId="0" applyFont="1" applyFill="1" applyAlignment="1"><alignment horizontal="center" vertical="center"/></xf>"#
+        );
+    }
+    xml.push_str(concat!(
+        r#"</cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles>"#,
+        r#"</styleSheet>"#,
+    ));
+    xml
+}
+
+/// Whether white text reads better than black on `rgb`.
+fn is_dark([r, g, b]: [u8; 3]) -> bool {
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    luma < 128.0
+}
+
+/// `A`, `B`, ..., `Z`, `AA`, ... for a zero-based column.
+fn column_name(column: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = column + 1;
+    while n > 0 {
+        n -= 1;
+        name.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("Column names are ASCII.")
+}
+
+/// Excel refuses sheet names over 31 characters or with `[]:*?/\`.
+fn sheet_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !"[]:*?/\\".contains(*c))
+        .take(31)
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_styles_and_archive() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(47), "AV");
+
+        let red = Cell::text("Re").filled([200, 0, 0]);
+        let sheet = Sheet {
+            name: "Mosaic".to_string(),
+            rows: vec![vec![red.clone(), red, Cell::number(3).bold()]],
+            ..Sheet::default()
+        };
+        let mut styles = vec![(None, false, false)];
+        let xml = worksheet(&sheet, &mut styles);
+        assert_eq!(styles.len(), 3);
+        assert!(xml.contains(r#"<c r="B1" s="1" t="inlineStr"><is><t>Re</t></is></c>"#));
+        assert!(xml.contains(r#"<c r="C1" s="2"><v>3</v></c>"#));
+
+        let bytes = Workbook {
+            sheets: vec![sheet],
+        }
+        .to_bytes();
+        assert_eq!(&bytes[..4], b"PK\x03\x04");
+        // The end record counts six parts: four fixed, styles, one sheet.
+        let end = bytes.len() - 22;
+        assert_eq!(&bytes[end..end + 4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([bytes[end + 10], bytes[end + 11]]), 6);
+    }
+}
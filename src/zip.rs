@@ -0,0 +1,203 @@
+//! Just enough of the zip format for the archives this program writes and
+//! reads back: workbooks, project bundles, and OpenRaster images.
+//!
+//! Entries are deflated, except the `mimetype` that formats like OpenRaster
+//! store first, or stored when reading archives made elsewhere.
+//! Zip64, encryption, and archives spanning disks are not supported.
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::{Read, Write};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const DIRECTORY_ENTRY: u32 = 0x02014b50;
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+/// The end record without its comment.
+const END_LENGTH: usize = 22;
+
+/// Packs `parts` into a zip archive, each deflated.
+pub fn write<C: AsRef<[u8]>>(parts: &[(String, C)]) -> Vec<u8> {
+    pack(None, parts)
+}
+
+/// Like `write`, led by an entry named `mimetype` holding `mimetype` and
+/// stored uncompressed, so readers can tell the format from its first
+/// bytes.
+pub fn write_with_mimetype<C: AsRef<[u8]>>(mimetype: &str, parts: &[(String, C)]) -> Vec<u8> {
+    pack(Some(mimetype), parts)
+}
+
+fn pack<C: AsRef<[u8]>>(mimetype: Option<&str>, parts: &[(String, C)]) -> Vec<u8> {
+    // 1980-01-01 00:00, the earliest DOS timestamp.
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    let lead = mimetype.map(|mimetype| ("mimetype", mimetype.as_bytes(), false));
+    let rest = parts
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.as_ref(), true));
+    let mut entries: u16 = 0;
+    for (name, content, deflate) in lead.into_iter().chain(rest) {
+        entries += 1;
+        let mut crc = Crc::new();
+        crc.update(content);
+        let compressed = if deflate {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(content)
+                .expect("Writing to memory cannot fail.");
+            encoder.finish().expect("Writing to memory cannot fail.")
+        } else {
+            content.to_vec()
+        };
+
+        let offset = archive.len() as u32;
+        // Fields shared by the local header and the directory entry, from
+        // the version needed to the name length.
+        let mut common = Vec::new();
+        common.extend(20u16.to_le_bytes());
+        common.extend(0x0800u16.to_le_bytes()); // Names are UTF-8.
+        common.extend(if deflate { 8u16 } else { 0 }.to_le_bytes()); // Deflate or store.
+        common.extend(DOS_TIME.to_le_bytes());
+        common.extend(DOS_DATE.to_le_bytes());
+        common.extend(crc.sum().to_le_bytes());
+        common.extend((compressed.len() as u32).to_le_bytes());
+        common.extend((content.len() as u32).to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+
+        archive.extend(LOCAL_HEADER.to_le_bytes());
+        archive.extend(&common);
+        archive.extend(0u16.to_le_bytes()); // No extra field.
+        archive.extend(name.as_bytes());
+        archive.extend(&compressed);
+
+        directory.extend(DIRECTORY_ENTRY.to_le_bytes());
+        directory.extend(20u16.to_le_bytes()); // Made by.
+        directory.extend(&common);
+        directory.extend([0; 12]); // Extra, comment, disk, and attributes.
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let directory_offset = archive.len() as u32;
+    archive.extend(&directory);
+    archive.extend(END_OF_DIRECTORY.to_le_bytes());
+    archive.extend([0; 4]); // This disk and the directory's.
+    archive.extend(entries.to_le_bytes());
+    archive.extend(entries.to_le_bytes());
+    archive.extend((directory.len() as u32).to_le_bytes());
+    archive.extend(directory_offset.to_le_bytes());
+    archive.extend(0u16.to_le_bytes()); // No comment.
+    archive
+}
+
+/// Every file in `archive` with its contents, in directory order.
+pub fn read(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let u16_at = |at: usize| -> Result<usize, String> {
+        archive
+            .get(at..at + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            .ok_or_else(|| "truncated zip archive".to_string())
+    };
+    let u32_at = |at: usize| -> Result<u32, String> {
+        archive
+            .get(at..at + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| "truncated zip archive".to_string())
+    };
+    // The end record sits last, before a comment of up to 64 KiB.
+    let end = (0..=archive.len().saturating_sub(END_LENGTH))
+        .rev()
+        .take(u16::MAX as usize + 1)
+        .find(|&at| u32_at(at) == Ok(END_OF_DIRECTORY))
+        .ok_or_else(|| "not a zip archive".to_string())?;
+    let entries = u16_at(end + 10)?;
+    let mut at = u32_at(end + 16)? as usize;
+
+    let mut files = Vec::with_capacity(entries);
+    for _ in 0..entries {
+        if u32_at(at)? != DIRECTORY_ENTRY {
+            return Err("damaged zip directory".to_string());
+        }
+        let method = u16_at(at + 10)?;
+        let crc = u32_at(at + 16)?;
+        let compressed = u32_at(at + 20)? as usize;
+        let size = u32_at(at + 24)? as usize;
+        let name_length = u16_at(at + 28)?;
+        let skipped = u16_at(at + 30)? + u16_at(at + 32)?;
+        let local = u32_at(at + 42)? as usize;
+        let name = archive
+            .get(at + 46..at + 46 + name_length)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .ok_or_else(|| "truncated zip archive".to_string())?;
+        at += 46 + name_length + skipped;
+
+        if u32_at(local)? != LOCAL_HEADER {
+            return Err(format!("damaged zip entry {name}"));
+        }
+        let start = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let data = archive
+            .get(start..start + compressed)
+            .ok_or_else(|| format!("truncated zip entry {name}"))?;
+        let content = match method {
+            0 => data.to_vec(),
+            8 => {
+                let mut content = Vec::with_capacity(size);
+                DeflateDecoder::new(data)
+                    .read_to_end(&mut content)
+                    .map_err(|err| format!("damaged zip entry {name}: {err}"))?;
+                content
+            }
+            _ => {
+                return Err(format!(
+                    "zip entry {name} uses unsupported compression {method}"
+                ))
+            }
+        };
+        let mut check = Crc::new();
+        check.update(&content);
+        if content.len() != size || check.sum() != crc {
+            return Err(format!("zip entry {name} fails its checksum"));
+        }
+        files.push((name, content));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_read_back() {
+        let parts = vec![
+            ("manifest.json".to_string(), b"{}".to_vec()),
+            ("picture.png".to_string(), (0..=255).collect::<Vec<u8>>()),
+            ("empty".to_string(), Vec::new()),
+        ];
+        let archive = write(&parts);
+        assert_eq!(read(&archive), Ok(parts));
+
+        let mut damaged = archive.clone();
+        // Inside the first entry's compressed data.
+        damaged[44] ^= 0xff;
+        assert!(read(&damaged).is_err());
+        assert!(read(b"PK").is_err());
+    }
+
+    #[test]
+    fn mimetypes_lead_uncompressed() {
+        let parts = vec![("stack.xml".to_string(), b"<image/>".to_vec())];
+        let archive = write_with_mimetype("image/openraster", &parts);
+        // The name starts after the 30-byte local header, the content
+        // right after the name.
+        assert_eq!(&archive[30..54], b"mimetypeimage/openraster");
+        let files = read(&archive).unwrap();
+        assert_eq!(
+            files[0],
+            ("mimetype".to_string(), b"image/openraster".to_vec())
+        );
+        assert_eq!(files[1..], parts);
+    }
+}
@@ -0,0 +1,234 @@
+//! Zooming the window onto the tiles of one color.
+//!
+//! The `search-color` key, `/` by default, starts a search typed into the
+//! title bar. `Enter` zooms until every tile of the named color fits in
+//! the panes, as near their centroid as the grid's edges allow, easing
+//! there over `FRAMES` frames; an empty search zooms back out and
+//! `Escape` cancels. `--interactive-zoom-to-color` zooms the same way once
+//! the mosaic is solved.
+
+use crate::{assign, idle_loop_mode, Model, CLICK_TITLE_HOLD, X_SIZE, Y_SIZE};
+use nannou::prelude::*;
+use std::time::Instant;
+
+/// Frames a zoom takes to ease from one view to the next.
+const FRAMES: u32 = 30;
+/// The closest the view zooms, about four tiles across.
+const MAX_ZOOM: f32 = 12.0;
+/// Share of the pane the color's tiles are zoomed to fill, leaving a
+/// margin around them.
+const FILL: f32 = 0.9;
+
+/// A `Model::zoom` and `Model::pan`: the magnification, 1.0 showing the
+/// whole grid, and the point of the grid at the middle of each pane, as
+/// fractions of its width and height from the bottom left.
+pub type View = (f32, Vec2);
+
+/// The view of the whole grid.
+pub fn whole() -> View {
+    (1.0, vec2(0.5, 0.5))
+}
+
+/// A zoom on its way from one view to another.
+pub struct Animation {
+    from: View,
+    to: View,
+    frame: u32,
+}
+
+/// The search being typed into the title bar.
+pub struct Search {
+    query: String,
+    /// The character of the key that opened the search is still to come
+    /// and is not part of it.
+    opening: bool,
+}
+
+/// `grid`, a pane's whole mosaic, scaled and moved to the model's view.
+pub fn apply(grid: Rect, model: &Model) -> Rect {
+    let wh = grid.wh() * model.zoom;
+    let bottom_left = grid.xy() - model.pan * wh;
+    Rect::from_corners(bottom_left, bottom_left + wh)
+}
+
+/// Starts a search, unless the mosaic is still solving.
+pub fn start_search(app: &App, model: &mut Model) {
+    if model.progress.is_some() {
+        log::info!("Still solving; try again once the mosaic is complete.");
+        return;
+    }
+    model.color_search = Some(Search {
+        query: String::new(),
+        opening: true,
+    });
+    show_search(app, model);
+}
+
+/// Handles `key` if a search is being typed, returning whether it was.
+pub fn key(app: &App, model: &mut Model, key: Key) -> bool {
+    let search = match &mut model.color_search {
+        Some(search) => search,
+        None => return false,
+    };
+    match key {
+        Key::Return | Key::NumpadEnter => {
+            let query = search.query.trim().to_string();
+            model.color_search = None;
+            zoom_to_color(app, model, &query);
+        }
+        Key::Escape => {
+            model.color_search = None;
+            app.main_window().set_title("Search cancelled.");
+        }
+        Key::Back => {
+            search.query.pop();
+            show_search(app, model);
+        }
+        _ => {}
+    }
+    true
+}
+
+/// Adds a typed character to the search, if one is being typed.
+pub fn character(app: &App, model: &mut Model, typed: char) {
+    let search = match &mut model.color_search {
+        Some(search) => search,
+        None => return,
+    };
+    if std::mem::take(&mut search.opening) || typed.is_control() {
+        return;
+    }
+    search.query.push(typed);
+    show_search(app, model);
+}
+
+fn show_search(app: &App, model: &Model) {
+    if let Some(search) = &model.color_search {
+        app.main_window()
+            .set_title(&format!("Search: {}_", search.query));
+    }
+}
+
+/// Eases towards the view of the color named `name`, or back to the whole
+/// grid when it is empty. Names match regardless of case, and failing an
+/// exact match the first color whose name contains `name` is taken.
+pub fn zoom_to_color(app: &App, model: &mut Model, name: &str) {
+    let message = if name.is_empty() {
+        start(app, model, whole());
+        "Showing the whole mosaic.".to_string()
+    } else {
+        let wanted = name.to_lowercase();
+        let colors = &model.palette.colors;
+        let found = colors
+            .iter()
+            .position(|config| config.name.to_lowercase() == wanted)
+            .or_else(|| {
+                colors
+                    .iter()
+                    .position(|config| config.name.to_lowercase().contains(&wanted))
+            });
+        match found {
+            None => format!("No palette color is named '{name}'."),
+            Some(index) => {
+                let name = model.palette.colors[index].name.clone();
+                match fit(&model.assignment, index) {
+                    None => format!("No tiles are {name}."),
+                    Some(view) => {
+                        start(app, model, view);
+                        let tiles = model.assignment.iter().filter(|&&a| a == index).count();
+                        format!("Zoomed to {tiles} tiles of {name}.")
+                    }
+                }
+            }
+        }
+    };
+    log::info!("{message}");
+    app.main_window().set_title(&message);
+    model.click_title_until = Some(Instant::now() + CLICK_TITLE_HOLD);
+}
+
+/// The view that fits every tile assigned `index`, centred on their
+/// centroid where the grid's edges allow, or `None` if there are none.
+fn fit(assignment: &[usize], index: usize) -> Option<View> {
+    let tiles: Vec<Vec2> = (0..assignment.len())
+        .filter(|&tile| assignment[tile] == index && index != assign::HOLE)
+        .map(|tile| {
+            vec2(
+                (tile as u64 % X_SIZE) as f32 + 0.5,
+                (tile as u64 / X_SIZE) as f32 + 0.5,
+            ) / vec2(X_SIZE as f32, Y_SIZE as f32)
+        })
+        .collect();
+    if tiles.is_empty() {
+        return None;
+    }
+    let centroid = tiles.iter().sum::<Vec2>() / tiles.len() as f32;
+    let half_tile = vec2(0.5 / X_SIZE as f32, 0.5 / Y_SIZE as f32);
+    // Twice the furthest any tile's outer edge is from the centroid, so
+    // that they fit with the centroid in the middle.
+    let span = tiles.iter().fold(Vec2::ZERO, |span, tile| {
+        span.max(((*tile - centroid).abs() + half_tile) * 2.0)
+    });
+    let zoom = (FILL / span.max_element()).clamp(1.0, MAX_ZOOM);
+    // The view is 1 / zoom of the grid; keeping it inside the grid still
+    // keeps the tiles in view, as they are inside it too.
+    let half_view = 0.5 / zoom;
+    let pan = centroid.clamp(Vec2::splat(half_view), Vec2::splat(1.0 - half_view));
+    Some((zoom, pan))
+}
+
+fn start(app: &App, model: &mut Model, to: View) {
+    model.zoom_animation = Some(Animation {
+        from: (model.zoom, model.pan),
+        to,
+        frame: 0,
+    });
+    app.set_loop_mode(LoopMode::refresh_sync());
+}
+
+/// Steps a zoom in progress, returning to the idle loop once it arrives.
+pub fn tick(app: &App, model: &mut Model) {
+    let animation = match &mut model.zoom_animation {
+        Some(animation) => animation,
+        None => return,
+    };
+    animation.frame += 1;
+    let t = ease_in_out(animation.frame as f32 / FRAMES as f32);
+    let ((from_zoom, from_pan), (to_zoom, to_pan)) = (animation.from, animation.to);
+    model.zoom = from_zoom + (to_zoom - from_zoom) * t;
+    model.pan = from_pan.lerp(to_pan, t);
+    if animation.frame >= FRAMES {
+        model.zoom_animation = None;
+        app.set_loop_mode(idle_loop_mode(model));
+    }
+}
+
+/// Slow at both ends and fastest halfway, for `t` from 0 to 1.
+fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_a_color_and_stays_inside_the_grid() {
+        let mut assignment = vec![0; (X_SIZE * Y_SIZE) as usize];
+        // A 4 x 2 block against the left edge, two rows up.
+        for y in 2..4 {
+            for x in 0..4 {
+                assignment[(y * X_SIZE + x) as usize] = 1;
+            }
+        }
+        let (zoom, pan) = fit(&assignment, 1).unwrap();
+        // Four tiles are a twelfth of the grid across.
+        assert!((zoom - FILL * 12.0).abs() < 1e-4);
+        let half_view = 0.5 / zoom;
+        assert!((pan.x - half_view).abs() < 1e-6);
+        assert!((pan.y - 3.0 / Y_SIZE as f32).abs() < 1e-6);
+        assert_eq!(fit(&assignment, 0).unwrap().0, 1.0);
+        assert!(fit(&assignment, 2).is_none());
+    }
+}